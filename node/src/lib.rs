@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use srch::Expression;
+
+/// A compiled text expression
+#[napi(js_name = "Expression")]
+pub struct JsExpression {
+	expression: Arc<Expression>
+}
+
+#[napi]
+impl JsExpression {
+
+	#[napi(constructor)]
+	pub fn new(source: String) -> Result<Self> {
+		let expression = Expression::new(&source).map_err(|err| Error::from_reason(err.to_string()))?;
+		Ok(Self { expression: Arc::new(expression) })
+	}
+
+	#[napi]
+	pub fn matches(&self, input: String) -> bool {
+		self.expression.matches(input)
+	}
+
+	#[napi]
+	pub fn explain(&self) -> String {
+		self.expression.explain()
+	}
+
+	#[napi]
+	pub fn matches_all(&self, inputs: Vec<String>) -> Vec<bool> {
+		inputs.iter().map(|input| self.expression.matches(input)).collect()
+	}
+
+	/// Like `matchesAll`, but matches on the libuv thread pool and returns a
+	/// promise
+	#[napi(ts_return_type = "Promise<boolean[]>")]
+	pub fn matches_all_async(&self, inputs: Vec<String>) -> AsyncTask<MatchesAll> {
+		AsyncTask::new(MatchesAll { expression: Arc::clone(&self.expression), inputs })
+	}
+
+}
+
+/// Compiles an expression, throws if the source is invalid
+#[napi]
+pub fn compile(source: String) -> Result<JsExpression> {
+	JsExpression::new(source)
+}
+
+pub struct MatchesAll {
+	expression: Arc<Expression>,
+	inputs: Vec<String>
+}
+
+impl Task for MatchesAll {
+	type Output = Vec<bool>;
+	type JsValue = Vec<bool>;
+
+	fn compute(&mut self) -> Result<Self::Output> {
+		Ok(self.inputs.iter().map(|input| self.expression.matches(input)).collect())
+	}
+
+	fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+		Ok(output)
+	}
+}
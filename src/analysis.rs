@@ -0,0 +1,1218 @@
+use std::fmt;
+
+use crate::logical_operator::LogicalOperator;
+use crate::optimizer::query_cost;
+use crate::parser::AST;
+use crate::query::{Query, Times, ValueRange};
+use crate::registry;
+use crate::runtime;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FindingKind {
+	/// A clause can never match
+	Contradiction,
+	/// The expression matches every input
+	Tautology,
+	/// A query or clause doesn't change the result of the expression
+	Redundancy
+}
+
+/// A problem found while analysing an expression
+#[derive(Clone, Debug, PartialEq)]
+pub struct Finding {
+	pub kind: FindingKind,
+	pub message: String
+}
+
+impl Finding {
+
+	fn new(kind: FindingKind, message: String) -> Self {
+		Self { kind, message }
+	}
+
+}
+
+impl fmt::Display for FindingKind {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Contradiction => write!(f, "contradiction"),
+			Self::Tautology => write!(f, "tautology"),
+			Self::Redundancy => write!(f, "redundancy")
+		}
+	}
+}
+
+impl fmt::Display for Finding {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {}", self.kind, self.message)
+	}
+}
+
+/// How the inputs matched by a new version of an expression relate to the
+/// inputs matched by the old version
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+	/// Both versions match the same inputs
+	Equivalent,
+	/// The new version matches a strict subset of the inputs of the old one
+	Narrower,
+	/// The new version matches a strict superset of the inputs of the old one
+	Broader,
+	/// Each version matches inputs the other doesn't
+	Incomparable,
+	/// The relation couldn't be decided
+	Unknown
+}
+
+impl fmt::Display for Relation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Equivalent => write!(f, "equivalent"),
+			Self::Narrower => write!(f, "strictly narrower"),
+			Self::Broader => write!(f, "strictly broader"),
+			Self::Incomparable => write!(f, "incomparable"),
+			Self::Unknown => write!(f, "unknown")
+		}
+	}
+}
+
+/// The result of comparing two versions of an expression, see
+/// [`Expression::compare`](crate::Expression::compare)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+	pub relation: Relation,
+	/// An input only matched by the old version
+	pub removed: Option<String>,
+	/// An input only matched by the new version
+	pub added: Option<String>
+}
+
+impl Comparison {
+
+	/// Decides the relation from the counterexamples found and whether each
+	/// direction was proven
+	pub(crate) fn new(removed: Option<String>, added: Option<String>, old_entails_new: bool, new_entails_old: bool) -> Self {
+		let decide = |counterexample: &Option<String>, proven: bool| match counterexample {
+			Some(_) => Some(false),
+			None if proven => Some(true),
+			None => None
+		};
+
+		let relation = match (decide(&added, new_entails_old), decide(&removed, old_entails_new)) {
+			(Some(true), Some(true)) => Relation::Equivalent,
+			(Some(true), Some(false)) => Relation::Narrower,
+			(Some(false), Some(true)) => Relation::Broader,
+			(Some(false), Some(false)) => Relation::Incomparable,
+			_ => Relation::Unknown
+		};
+
+		Self { relation, removed, added }
+	}
+
+}
+
+/// A query of a clause, which has to fail if it is negated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Literal<'a> {
+	pub query: &'a Query,
+	pub negated: bool,
+	/// The position of the query in the expression, counting from the left
+	pub index: usize
+}
+
+impl Literal<'_> {
+
+	pub fn exec(&self, input: &str, unicode: bool) -> bool {
+		self.query.exec_with(input, unicode) != self.negated
+	}
+
+}
+
+impl fmt::Display for Literal<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.negated {
+			write!(f, "not {}", self.query)
+		} else {
+			write!(f, "{}", self.query)
+		}
+	}
+}
+
+/// The most clauses an ast is split into. Distributing conjunctions over
+/// disjunctions grows the clauses exponentially, e.g. for the negation of an
+/// `or` of conjunctions, so larger asts aren't split at all
+pub const MAX_CLAUSES: usize = 256;
+
+/// Splits an ast into its clauses, i.e. converts it into a disjunction of
+/// conjunctions of (negated) queries. Parsed expressions are already in this
+/// form since `and` binds stronger than `or`, combined expressions and
+/// negations are distributed. `None` if there'd be more than [`MAX_CLAUSES`]
+/// clauses
+pub fn clauses(ast: &AST) -> Option<Vec<Vec<Literal<'_>>>> {
+	fn normalize<'a>(ast: &'a AST, negated: bool, index: &mut usize) -> Option<Vec<Vec<Literal<'a>>>> {
+		match ast {
+			AST::Query(query) => {
+				*index += 1;
+				Some(vec![vec![Literal { query, negated, index: *index - 1 }]])
+			},
+			AST::Not(ast) => normalize(ast, !negated, index),
+			AST::BinaryExpression { left, operator, right } => {
+				let left = normalize(left, negated, index)?;
+				let right = normalize(right, negated, index)?;
+
+				// a negated conjunction is a disjunction of the negated operands and vice versa
+				if (*operator == LogicalOperator::Or) != negated {
+					if left.len() + right.len() > MAX_CLAUSES {
+						return None;
+					}
+
+					Some(left.into_iter().chain(right).collect())
+				} else {
+					if left.len().saturating_mul(right.len()) > MAX_CLAUSES {
+						return None;
+					}
+
+					Some(left.iter().flat_map(|a| {
+						right.iter().map(move |b| a.iter().chain(b).copied().collect())
+					}).collect())
+				}
+			}
+		}
+	}
+
+	normalize(ast, false, &mut 0)
+}
+
+fn is_class(query: &Query) -> bool {
+	matches!(
+		query,
+		Query::Numeric | Query::Alpha | Query::Alphanumeric | Query::Uppercase | Query::Lowercase | Query::Whitespace | Query::Special
+	)
+}
+
+fn is_tautology(query: &Query) -> bool {
+	match query {
+		Query::Starts(arg) | Query::Ends(arg) | Query::Contains(arg)
+		| Query::IStarts(arg) | Query::IEnds(arg) | Query::IContains(arg) => arg.is_empty(),
+		Query::LengthRange(range) => range.bounds() == (0, None),
+		Query::ContainsTimes(_, times) => times.bounds() == (0, None),
+		Query::WordsRange(range) => range.bounds() == (0, None),
+		_ => false
+	}
+}
+
+/// A literal which has to be a substring of every matching input
+fn required_substring(query: &Query) -> Option<&str> {
+	match query {
+		Query::Starts(arg) | Query::Ends(arg) | Query::Contains(arg) | Query::Equals(arg) => Some(arg),
+		Query::ContainsTimes(arg, times) if times.bounds().0 > 0 => Some(arg),
+		_ => None
+	}
+}
+
+/// The case-insensitive query matching at least the strings a string query
+/// matches, e.g. `istarts "foo"` for `starts "Foo"`
+fn fold_case(query: &Query) -> Option<Query> {
+	match query {
+		Query::Starts(arg) => Some(Query::IStarts(arg.to_lowercase().into())),
+		Query::Ends(arg) => Some(Query::IEnds(arg.to_lowercase().into())),
+		Query::Contains(arg) => Some(Query::IContains(arg.to_lowercase().into())),
+		Query::Equals(arg) => Some(Query::IEquals(arg.to_lowercase().into())),
+		_ => None
+	}
+}
+
+fn is_case_insensitive(query: &Query) -> bool {
+	matches!(query, Query::IStarts(_) | Query::IEnds(_) | Query::IContains(_) | Query::IEquals(_))
+}
+
+type Bounds = (u64, Option<u64>);
+
+/// Whether the range of counts `inner` lies in `outer`, both bounds included
+fn is_within((min, max): Bounds, (outer_min, outer_max): Bounds) -> bool {
+	outer_min <= min && outer_max.is_none_or(|outer_max| max.is_some_and(|max| max <= outer_max))
+}
+
+/// Whether no count lies in both ranges
+fn is_disjoint((min, max): Bounds, (other_min, other_max): Bounds) -> bool {
+	max.is_some_and(|max| max < other_min) || other_max.is_some_and(|other_max| other_max < min)
+}
+
+/// Whether every string matching `a` also matches `b`
+fn implies(a: &Query, b: &Query) -> bool {
+	if a == b || is_tautology(b) {
+		return true;
+	}
+
+	// Every string matching a case-sensitive query matches its folded query
+	if let (Some(folded), true) = (fold_case(a), is_case_insensitive(b)) {
+		return implies(&folded, b);
+	}
+
+	match (a, b) {
+		(Query::Equals(arg), b) => b.exec(arg),
+		(Query::Starts(x), Query::Starts(y)) | (Query::IStarts(x), Query::IStarts(y)) => x.starts_with(&**y),
+		(Query::Ends(x), Query::Ends(y)) | (Query::IEnds(x), Query::IEnds(y)) => x.ends_with(&**y),
+		(Query::Starts(x), Query::Contains(y))
+		| (Query::Ends(x), Query::Contains(y))
+		| (Query::Contains(x), Query::Contains(y))
+		| (Query::IStarts(x), Query::IContains(y))
+		| (Query::IEnds(x), Query::IContains(y))
+		| (Query::IContains(x), Query::IContains(y)) => x.contains(&**y),
+		(Query::IEquals(arg), b) if is_case_insensitive(b) => b.exec(arg),
+		(Query::Numeric, Query::Alphanumeric) | (Query::Alpha, Query::Alphanumeric) => true,
+		(Query::Uppercase, Query::Alpha | Query::Alphanumeric) | (Query::Lowercase, Query::Alpha | Query::Alphanumeric) => true,
+		(Query::ContainsTimes(x, times), Query::Contains(y)) if times.bounds().0 > 0 => x.contains(&**y),
+		(Query::ContainsTimes(x, times), Query::ContainsTimes(y, other)) if x == y => is_within(times.bounds(), other.bounds()),
+		(Query::Value(ValueRange::Over(x)), Query::Value(ValueRange::Over(y))) => x >= y,
+		(Query::Value(ValueRange::Under(x)), Query::Value(ValueRange::Under(y))) => x <= y,
+		(Query::Value(ValueRange::Equals(x)), Query::Value(ValueRange::Over(y))) => x > y,
+		(Query::Value(ValueRange::Equals(x)), Query::Value(ValueRange::Under(y))) => x < y,
+		(a, b) => match (a.length_bounds(), b.length_bounds()) {
+			(Some(bounds), Some(other)) => is_within(bounds, other),
+			_ => match (a.word_bounds(), b.word_bounds()) {
+				(Some(bounds), Some(other)) => is_within(bounds, other),
+				_ => false
+			}
+		}
+	}
+}
+
+/// Whether no string can match both `a` and `b`
+fn contradicts(a: &Query, b: &Query) -> bool {
+	if let (Some(folded), true) = (fold_case(a), is_case_insensitive(b)) {
+		return contradicts(&folded, b);
+	}
+
+	if let (true, Some(folded)) = (is_case_insensitive(a), fold_case(b)) {
+		return contradicts(a, &folded);
+	}
+
+	match (a, b) {
+		(Query::IEquals(arg), other) | (other, Query::IEquals(arg)) if is_case_insensitive(other) => !other.exec(arg),
+		(Query::IStarts(x), Query::IStarts(y)) => !x.starts_with(&**y) && !y.starts_with(&**x),
+		(Query::IEnds(x), Query::IEnds(y)) => !x.ends_with(&**y) && !y.ends_with(&**x),
+		(Query::Equals(arg), other) | (other, Query::Equals(arg)) => !other.exec(arg),
+		(Query::Starts(x), Query::Starts(y)) => !x.starts_with(&**y) && !y.starts_with(&**x),
+		(Query::Ends(x), Query::Ends(y)) => !x.ends_with(&**y) && !y.ends_with(&**x),
+		(Query::ContainsTimes(x, times), Query::ContainsTimes(y, other)) if x == y => is_disjoint(times.bounds(), other.bounds()),
+		// An input containing `x` contains every substring of it at least once
+		(Query::ContainsTimes(y, Times::AtMost(0) | Times::Exactly(0)), other)
+		| (other, Query::ContainsTimes(y, Times::AtMost(0) | Times::Exactly(0))) => {
+			required_substring(other).is_some_and(|x| x.contains(&**y))
+		},
+		(Query::Value(ValueRange::Equals(x)), Query::Value(range)) | (Query::Value(range), Query::Value(ValueRange::Equals(x))) => {
+			!range.contains(&x.to_string())
+		},
+		(Query::Value(ValueRange::Over(x)), Query::Value(ValueRange::Under(y)))
+		| (Query::Value(ValueRange::Under(y)), Query::Value(ValueRange::Over(x))) => x >= y,
+		// Every number has a digit
+		(class, Query::Value(_)) | (Query::Value(_), class) if is_class(class) => {
+			!matches!(class, Query::Numeric | Query::Alphanumeric)
+		},
+		// Whitespace has no words, the other classes no whitespace
+		(class, words) | (words, class) if is_class(class) && words.word_bounds().is_some() => {
+			let most = if *class == Query::Whitespace { 0 } else { 1 };
+			words.word_bounds().is_some_and(|(min, _)| min > most)
+		},
+		(words, other) | (other, words) if words.word_bounds().is_some() => {
+			match (words.word_bounds(), other.word_bounds(), required_substring(other)) {
+				(Some(bounds), Some(other), _) => is_disjoint(bounds, other),
+				// The words of a substring are words of the input as well
+				(Some((_, Some(max))), None, Some(arg)) => arg.split_ascii_whitespace().count() as u64 > max,
+				_ => false
+			}
+		},
+		(class, other) | (other, class) if is_class(class) => match required_substring(other) {
+			Some(arg) => !class.exec(arg),
+			None => false
+		},
+		(a, b) => match (a.length_bounds(), b.length_bounds()) {
+			(Some(bounds), Some(other)) => is_disjoint(bounds, other),
+			(Some((_, Some(max))), None) => required_substring(b).is_some_and(|arg| arg.len() as u64 > max),
+			(None, Some((_, Some(max)))) => required_substring(a).is_some_and(|arg| arg.len() as u64 > max),
+			_ => false
+		}
+	}
+}
+
+/// Whether every string matching `a` also matches `b`
+fn literal_implies(a: &Literal, b: &Literal) -> bool {
+	match (a.negated, b.negated) {
+		(false, false) => implies(a.query, b.query),
+		(true, true) => implies(b.query, a.query),
+		(false, true) => contradicts(a.query, b.query),
+		(true, false) => is_tautology(b.query)
+	}
+}
+
+/// Whether no string can match both `a` and `b`
+fn literal_contradicts(a: &Literal, b: &Literal) -> bool {
+	match (a.negated, b.negated) {
+		(false, false) => contradicts(a.query, b.query),
+		(false, true) => implies(a.query, b.query),
+		(true, false) => implies(b.query, a.query),
+		(true, true) => false
+	}
+}
+
+/// The queries of a clause which have to match
+fn positive<'a>(clause: &[Literal<'a>]) -> Vec<&'a Query> {
+	clause.iter().filter(|literal| !literal.negated).map(|literal| literal.query).collect()
+}
+
+/// Character classes without a common character can only match the empty
+/// string together
+fn has_disjoint_classes(clause: &[&Query]) -> bool {
+	let classes = clause.iter().filter(|query| is_class(query)).collect::<Vec<_>>();
+
+	classes.iter().any(|a| {
+		classes.iter().any(|b| match (a, b) {
+			(Query::Special, other) | (other, Query::Special) => !matches!(other, Query::Special),
+			(Query::Whitespace, other) | (other, Query::Whitespace) => !matches!(other, Query::Whitespace),
+			(Query::Numeric, Query::Alpha | Query::Uppercase | Query::Lowercase)
+			| (Query::Alpha | Query::Uppercase | Query::Lowercase, Query::Numeric) => true,
+			(Query::Uppercase, Query::Lowercase) | (Query::Lowercase, Query::Uppercase) => true,
+			_ => false
+		})
+	})
+}
+
+fn requires_content(query: &Query) -> bool {
+	match query {
+		Query::Length(_) | Query::LengthRange(_) => query.length_bounds().is_some_and(|(min, _)| min > 0),
+		Query::Value(_) => true,
+		Query::Words(_) | Query::WordsRange(_) => query.word_bounds().is_some_and(|(min, _)| min > 0),
+		query => matches!(required_substring(query), Some(arg) if !arg.is_empty())
+	}
+}
+
+fn clause_source(clause: &[Literal]) -> String {
+	clause.iter().map(|literal| literal.to_string()).collect::<Vec<_>>().join(" and ")
+}
+
+fn find_contradiction(clause: &[Literal]) -> Option<String> {
+	if let Some(literal) = clause.iter().find(|literal| literal.negated && is_tautology(literal.query)) {
+		return Some(format!("`{}` never matches", literal));
+	}
+
+	for (i, a) in clause.iter().enumerate() {
+		for b in clause.iter().skip(i + 1) {
+			if literal_contradicts(a, b) {
+				return Some(format!("`{}` and `{}` can never match together", a, b));
+			}
+		}
+	}
+
+	let queries = positive(clause);
+
+	if has_disjoint_classes(&queries) {
+		if let Some(query) = queries.iter().find(|query| requires_content(query)) {
+			return Some(format!("`{}` can only match an empty string, but `{}` requires content", clause_source(clause), query));
+		}
+	}
+
+	None
+}
+
+/// Whether every input matching clause `a` also matches clause `b`
+fn covers(a: &[Literal], b: &[Literal]) -> bool {
+	b.iter().all(|literal| a.iter().any(|other| literal_implies(other, literal)))
+}
+
+/// Analyses an ast for contradictions, tautologies and redundant queries
+pub fn lint(ast: &AST) -> Vec<Finding> {
+	// asts with too many clauses aren't analysed
+	let clauses = match clauses(ast) {
+		Some(clauses) => clauses,
+		None => return Vec::new()
+	};
+
+	let mut findings = Vec::new();
+	let mut satisfiable = Vec::new();
+
+	for clause in clauses.iter() {
+		if let Some(reason) = find_contradiction(clause) {
+			findings.push(Finding::new(FindingKind::Contradiction, format!("`{}` can never match: {}", clause_source(clause), reason)));
+			continue;
+		}
+
+		satisfiable.push(clause);
+
+		if clause.iter().all(|literal| !literal.negated && is_tautology(literal.query)) {
+			findings.push(Finding::new(FindingKind::Tautology, format!("`{}` matches every input", clause_source(clause))));
+			continue;
+		}
+
+		for (i, a) in clause.iter().enumerate() {
+			for b in clause.iter().skip(i + 1) {
+				let redundant = match (literal_implies(a, b), literal_implies(b, a)) {
+					(true, _) => Some((b, a)),
+					(false, true) => Some((a, b)),
+					(false, false) => None
+				};
+
+				if let Some((redundant, by)) = redundant {
+					findings.push(Finding::new(FindingKind::Redundancy, format!("`{}` is redundant, it is implied by `{}`", redundant, by)));
+				}
+			}
+		}
+	}
+
+	// a clause implying another clause never changes the result of the disjunction
+	for (i, a) in satisfiable.iter().enumerate() {
+		for (j, b) in satisfiable.iter().enumerate() {
+			// equivalent clauses are only reported once
+			if i != j && covers(a, b) && (!covers(b, a) || i > j) {
+				findings.push(Finding::new(FindingKind::Redundancy, format!("clause `{}` is redundant, it is covered by `{}`", clause_source(a), clause_source(b))));
+				break;
+			}
+		}
+	}
+
+	findings
+}
+
+/// The registered description of a custom query, its keyword if it isn't
+/// registered
+fn custom_description(name: &str) -> String {
+	registry::description(name).unwrap_or_else(|| format!("@{}", name))
+}
+
+/// Describes the numbers a value comparison matches, e.g. "numbers over 5"
+fn numbers(range: &ValueRange, plural: bool) -> String {
+	let numbers = if plural { "numbers" } else { "a number" };
+
+	match range {
+		ValueRange::Over(n) => format!("{} over {}", numbers, n),
+		ValueRange::Under(n) => format!("{} under {}", numbers, n),
+		ValueRange::Equals(n) => format!("the number {}", n)
+	}
+}
+
+/// Describes what a single query requires of a matching input
+fn explain_query(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("starts with \"{}\"", arg),
+		Query::Ends(arg) => format!("ends with \"{}\"", arg),
+		Query::Contains(arg) => format!("contains \"{}\"", arg),
+		Query::ContainsTimes(arg, times) => format!("contains \"{}\" {}", arg, times),
+		Query::Equals(arg) => format!("is exactly \"{}\"", arg),
+		Query::IStarts(arg) => format!("starts with \"{}\" ignoring case", arg),
+		Query::IEnds(arg) => format!("ends with \"{}\" ignoring case", arg),
+		Query::IContains(arg) => format!("contains \"{}\" ignoring case", arg),
+		Query::IEquals(arg) => format!("is \"{}\" ignoring case", arg),
+		Query::Length(len) => format!("is {} bytes long", len),
+		Query::LengthRange(range) => format!("is {} bytes long", range),
+		Query::Words(words) => format!("has {} words", words),
+		Query::WordsRange(range) => format!("has {} words", range),
+		Query::Value(range) => format!("is {}", numbers(range, false)),
+		Query::Numeric => "consists only of digits".to_string(),
+		Query::Alpha => "consists only of letters".to_string(),
+		Query::Alphanumeric => "consists only of letters and digits".to_string(),
+		Query::Uppercase => "consists only of uppercase letters".to_string(),
+		Query::Lowercase => "consists only of lowercase letters".to_string(),
+		Query::Whitespace => "consists only of whitespace".to_string(),
+		Query::Special => "consists only of special characters".to_string(),
+		Query::Pattern(pattern) => format!("is {}", pattern.description()),
+		Query::Custom(name) => format!("is {}", custom_description(name))
+	}
+}
+
+/// Describes what a negated query requires of a matching input
+fn explain_negated_query(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("doesn't start with \"{}\"", arg),
+		Query::Ends(arg) => format!("doesn't end with \"{}\"", arg),
+		Query::Contains(arg) => format!("doesn't contain \"{}\"", arg),
+		Query::ContainsTimes(arg, times) => format!("doesn't contain \"{}\" {}", arg, times),
+		Query::Equals(arg) => format!("isn't exactly \"{}\"", arg),
+		Query::IStarts(arg) => format!("doesn't start with \"{}\" ignoring case", arg),
+		Query::IEnds(arg) => format!("doesn't end with \"{}\" ignoring case", arg),
+		Query::IContains(arg) => format!("doesn't contain \"{}\" ignoring case", arg),
+		Query::IEquals(arg) => format!("isn't \"{}\" ignoring case", arg),
+		Query::Length(len) => format!("isn't {} bytes long", len),
+		Query::LengthRange(range) => format!("isn't {} bytes long", range),
+		Query::Words(words) => format!("doesn't have {} words", words),
+		Query::WordsRange(range) => format!("doesn't have {} words", range),
+		Query::Value(range) => format!("isn't {}", numbers(range, false)),
+		Query::Numeric => "doesn't consist only of digits".to_string(),
+		Query::Alpha => "doesn't consist only of letters".to_string(),
+		Query::Alphanumeric => "doesn't consist only of letters and digits".to_string(),
+		Query::Uppercase => "doesn't consist only of uppercase letters".to_string(),
+		Query::Lowercase => "doesn't consist only of lowercase letters".to_string(),
+		Query::Whitespace => "doesn't consist only of whitespace".to_string(),
+		Query::Special => "doesn't consist only of special characters".to_string(),
+		Query::Pattern(pattern) => format!("isn't {}", pattern.description()),
+		Query::Custom(name) => format!("isn't {}", custom_description(name))
+	}
+}
+
+fn explain_literal(literal: &Literal) -> String {
+	if literal.negated {
+		explain_negated_query(literal.query)
+	} else {
+		explain_query(literal.query)
+	}
+}
+
+/// Describes an ast in plain english, one line per clause
+pub fn explain(ast: &AST) -> String {
+	let clauses = match clauses(ast) {
+		Some(clauses) => clauses,
+		None => return format!("matches if the input matches an expression of more than {} clauses, which can't be explained", MAX_CLAUSES)
+	};
+
+	clauses.iter().map(|clause| {
+		clause.iter().map(explain_literal).collect::<Vec<_>>().join(" and ")
+	}).enumerate().map(|(i, clause)| {
+		if i == 0 { format!("matches if the input {}", clause) } else { format!("or if it {}", clause) }
+	}).collect::<Vec<_>>().join("\n")
+}
+
+/// Describes what a query requires of matching strings, in plural form
+fn describe_query(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("start with \"{}\"", arg),
+		Query::Ends(arg) => format!("end with \"{}\"", arg),
+		Query::Contains(arg) => format!("contain \"{}\"", arg),
+		Query::ContainsTimes(arg, times) => format!("contain \"{}\" {}", arg, times),
+		Query::Equals(arg) => format!("are exactly \"{}\"", arg),
+		Query::IStarts(arg) => format!("start with \"{}\" ignoring case", arg),
+		Query::IEnds(arg) => format!("end with \"{}\" ignoring case", arg),
+		Query::IContains(arg) => format!("contain \"{}\" ignoring case", arg),
+		Query::IEquals(arg) => format!("are \"{}\" ignoring case", arg),
+		Query::Length(len) => format!("are exactly {} bytes long", len),
+		Query::LengthRange(range) => format!("are {} bytes long", range),
+		Query::Words(words) => format!("have exactly {} words", words),
+		Query::WordsRange(range) => format!("have {} words", range),
+		Query::Value(range) => format!("are {}", numbers(range, true)),
+		Query::Numeric => "consist only of digits".to_string(),
+		Query::Alpha => "consist only of letters".to_string(),
+		Query::Alphanumeric => "consist only of letters and digits".to_string(),
+		Query::Uppercase => "consist only of uppercase letters".to_string(),
+		Query::Lowercase => "consist only of lowercase letters".to_string(),
+		Query::Whitespace => "consist only of whitespace".to_string(),
+		Query::Special => "consist only of special characters".to_string(),
+		Query::Pattern(pattern) => format!("are {}", pattern.description()),
+		Query::Custom(name) => format!("are {}", custom_description(name))
+	}
+}
+
+/// Describes what a negated query requires of matching strings, in plural form
+fn describe_negated_query(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("don't start with \"{}\"", arg),
+		Query::Ends(arg) => format!("don't end with \"{}\"", arg),
+		Query::Contains(arg) => format!("don't contain \"{}\"", arg),
+		Query::ContainsTimes(arg, times) => format!("don't contain \"{}\" {}", arg, times),
+		Query::Equals(arg) => format!("aren't exactly \"{}\"", arg),
+		Query::IStarts(arg) => format!("don't start with \"{}\" ignoring case", arg),
+		Query::IEnds(arg) => format!("don't end with \"{}\" ignoring case", arg),
+		Query::IContains(arg) => format!("don't contain \"{}\" ignoring case", arg),
+		Query::IEquals(arg) => format!("aren't \"{}\" ignoring case", arg),
+		Query::Length(len) => format!("aren't exactly {} bytes long", len),
+		Query::LengthRange(range) => format!("aren't {} bytes long", range),
+		Query::Words(words) => format!("don't have exactly {} words", words),
+		Query::WordsRange(range) => format!("don't have {} words", range),
+		Query::Value(range) => format!("aren't {}", numbers(range, true)),
+		Query::Numeric => "don't consist only of digits".to_string(),
+		Query::Alpha => "don't consist only of letters".to_string(),
+		Query::Alphanumeric => "don't consist only of letters and digits".to_string(),
+		Query::Uppercase => "don't consist only of uppercase letters".to_string(),
+		Query::Lowercase => "don't consist only of lowercase letters".to_string(),
+		Query::Whitespace => "don't consist only of whitespace".to_string(),
+		Query::Special => "don't consist only of special characters".to_string(),
+		Query::Pattern(pattern) => format!("aren't {}", pattern.description()),
+		Query::Custom(name) => format!("aren't {}", custom_description(name))
+	}
+}
+
+/// Describes an ast as a single sentence, the clauses are joined using "or"
+pub fn describe(ast: &AST) -> String {
+	let clauses = match clauses(ast) {
+		Some(clauses) => clauses,
+		None => return format!("matches strings of an expression of more than {} clauses, which can't be described", MAX_CLAUSES)
+	};
+
+	let clauses = clauses.iter().map(|clause| {
+		clause.iter().map(|literal| {
+			if literal.negated { describe_negated_query(literal.query) } else { describe_query(literal.query) }
+		}).collect::<Vec<_>>().join(" and ")
+	}).collect::<Vec<_>>();
+
+	format!("matches strings that {}", clauses.join(", or that "))
+}
+
+/// The literals of an ast which matches exactly the inputs containing one of
+/// them, i.e. a `contains` query or an `or` of such queries
+pub fn literals(ast: &AST) -> Option<Vec<&str>> {
+	match ast {
+		AST::Query(Query::Contains(arg)) => Some(vec![arg]),
+		AST::BinaryExpression { left, operator: LogicalOperator::Or, right } => {
+			let mut found = literals(left)?;
+			found.extend(literals(right)?);
+			Some(found)
+		},
+		_ => None
+	}
+}
+
+/// Whether every input matching `a` provably matches `b`, i.e. every clause
+/// of `a` can't match or is covered by a clause of `b`. A result of `false`
+/// doesn't prove the opposite, see [`examples`] for counterexamples
+pub fn entails(a: &AST, b: &AST) -> bool {
+	let (left, right) = match (clauses(a), clauses(b)) {
+		(Some(left), Some(right)) => (left, right),
+		_ => return false
+	};
+
+	left.iter().all(|clause| {
+		find_contradiction(clause).is_some() || right.iter().any(|other| covers(clause, other))
+	})
+}
+
+/// The parts of an input built by [`example`] which aren't fixed by a query
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Fillers {
+	/// Used if no character class is required
+	pub filler: String,
+	pub digits: String,
+	pub letters: String,
+	pub special: String
+}
+
+/// Builds an input which likely matches every query of a clause, negated
+/// queries are ignored so the result still has to be checked
+pub(crate) fn example(clause: &[&Query], fillers: &Fillers) -> String {
+	let mut prefix = String::new();
+	let mut suffix = String::new();
+	let mut middle = String::new();
+	let mut min = 0;
+	let mut max = None;
+	let mut filler = fillers.filler.clone();
+
+	for query in clause {
+		match query {
+			Query::Equals(arg) | Query::IEquals(arg) => return arg.to_string(),
+			Query::Pattern(pattern) => return pattern.example().to_string(),
+			Query::Value(ValueRange::Over(n)) => return n.saturating_add(1).to_string(),
+			Query::Value(ValueRange::Under(0)) => return "-1".to_string(),
+			Query::Value(ValueRange::Under(n)) => return (n - 1).to_string(),
+			Query::Value(ValueRange::Equals(n)) => return n.to_string(),
+			Query::Starts(arg) | Query::IStarts(arg) => prefix = arg.to_string(),
+			Query::Ends(arg) | Query::IEnds(arg) => suffix = arg.to_string(),
+			Query::Contains(arg) | Query::IContains(arg) => middle.push_str(arg),
+			Query::ContainsTimes(arg, times) => middle.push_str(&arg.repeat(times.bounds().0 as usize)),
+			Query::Length(_) | Query::LengthRange(_) => {
+				let (at_least, at_most) = query.length_bounds().unwrap_or((0, None));
+
+				min = min.max(at_least as usize);
+				max = match (max, at_most) {
+					(Some(max), Some(at_most)) => Some(usize::min(max, at_most as usize)),
+					(max, at_most) => max.or(at_most.map(|at_most| at_most as usize))
+				};
+			},
+			Query::Words(_) | Query::WordsRange(_) => {
+				let (words, _) = query.word_bounds().unwrap_or((0, None));
+				filler = vec!["a"; words as usize].join(" ");
+			},
+			Query::Numeric => filler = fillers.digits.clone(),
+			Query::Alpha => filler = fillers.letters.clone(),
+			Query::Alphanumeric => filler = fillers.letters.clone() + &fillers.digits,
+			Query::Uppercase => filler = fillers.letters.to_uppercase(),
+			Query::Lowercase => filler = fillers.letters.to_lowercase(),
+			Query::Whitespace => filler = " ".to_string(),
+			Query::Special => filler = fillers.special.clone(),
+			// Custom queries don't provide an example
+			Query::Custom(_) => {}
+		}
+	}
+
+	let natural = prefix.len() + middle.len() + filler.len() + suffix.len();
+	let length = match max {
+		Some(max) => Some(natural.min(max).max(min)),
+		None if natural < min => Some(min),
+		None => None
+	};
+
+	if let Some(length) = length {
+		let fixed = prefix.len() + middle.len() + suffix.len();
+		let padding = filler.chars().next().unwrap_or('a');
+
+		filler = filler.chars().take(length.saturating_sub(fixed)).collect();
+
+		while fixed + filler.len() < length {
+			filler.push(padding);
+		}
+	}
+
+	prefix + &middle + &filler + &suffix
+}
+
+/// Inputs which match the clauses of an ast or nearly do, used to find
+/// inputs on which two expressions disagree
+pub fn examples(ast: &AST) -> Vec<String> {
+	let fillers = [
+		Fillers::default(),
+		Fillers { filler: "a1".into(), digits: "1".into(), letters: "a".into(), special: "!".into() },
+		Fillers { filler: "x 7".into(), digits: "12345".into(), letters: "abcDE".into(), special: "#-.@".into() }
+	];
+
+	let mut examples = vec![String::new(), " ".to_string()];
+
+	for clause in clauses(ast).unwrap_or_default() {
+		let queries = positive(&clause);
+
+		for fillers in fillers.iter() {
+			let example = example(&queries, fillers);
+
+			// near misses, e.g. one byte too long or missing the last character
+			let mut shorter = example.clone();
+			shorter.pop();
+
+			examples.extend([format!("{}x", example), format!("1{}", example), shorter, example]);
+		}
+
+		for literal in clause.iter() {
+			if let Some(arg) = required_substring(literal.query) {
+				examples.push(arg.to_string());
+			}
+		}
+	}
+
+	examples.sort();
+	examples.dedup();
+
+	// non-empty examples first, as they are more telling counterexamples
+	examples.sort_by_key(|example| example.trim().is_empty());
+	examples
+}
+
+/// How close `input` comes to matching, the fraction of the queries of the
+/// closest clause it satisfies. Queries are weighted by their estimated cost,
+/// so satisfying an expensive query like a named pattern counts for more
+/// than satisfying a `length`. Asts with too many clauses only score `0.0`
+/// or `1.0`
+pub fn score(ast: &AST, input: &str, unicode: bool) -> f32 {
+	let clauses = match clauses(ast) {
+		Some(clauses) => clauses,
+		None => return if runtime::run(ast, input, unicode) { 1.0 } else { 0.0 }
+	};
+
+	clauses.iter().map(|clause| {
+		let total = clause.iter().map(|literal| query_cost(literal.query)).sum::<u32>();
+		let satisfied = clause.iter()
+			.filter(|literal| literal.exec(input, unicode))
+			.map(|literal| query_cost(literal.query))
+			.sum::<u32>();
+
+		satisfied as f32 / total as f32
+	}).fold(0.0, f32::max)
+}
+
+/// Lists the clauses of an ast together with the result of every query for
+/// `input`, one line per clause. Asts with too many clauses are traced as a
+/// whole in a single line
+pub fn trace(ast: &AST, input: &str, unicode: bool) -> String {
+	let clauses = match clauses(ast) {
+		Some(clauses) => clauses,
+		None => {
+			let (sign, verdict) = if runtime::run(ast, input, unicode) { ("+", "matches") } else { ("-", "fails") };
+			return format!("{} the expression {}, it has more than {} clauses to trace", sign, verdict, MAX_CLAUSES);
+		}
+	};
+
+	clauses.iter().map(|clause| {
+		let matches = clause.iter().all(|literal| literal.exec(input, unicode));
+		let queries = clause.iter().map(|literal| {
+			format!("`{}` {}", literal, if literal.exec(input, unicode) { "matches" } else { "fails" })
+		}).collect::<Vec<_>>().join(", ");
+
+		format!("{} {}", if matches { "+" } else { "-" }, queries)
+	}).collect::<Vec<_>>().join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::{clauses, describe, entails, examples, explain, lint, literals, score, trace, FindingKind, Relation, MAX_CLAUSES};
+	use crate::into_ast;
+
+	fn kinds(source: &str) -> Vec<FindingKind> {
+		lint(&into_ast(source).unwrap()).iter().map(|finding| finding.kind).collect()
+	}
+
+	macro_rules! lint_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (source, expected): (&str, Vec<FindingKind>) = $value;
+					pretty_assertions::assert_eq!(kinds(source), expected);
+				}
+			)*
+		}
+	}
+
+	mod it_accepts_sound_expressions {
+		use super::*;
+
+		lint_tests! {
+			single_query: ("numeric", vec![]),
+			numeric_value: ("numeric and value over 1000", vec![]),
+			words_and_length: ("words 2 and length 3", vec![]),
+			counts_of_different_literals: ("contains \"a\" exactly 1 time and contains \"b\" exactly 0 times", vec![]),
+			numeric_and_length: ("numeric and length 5", vec![]),
+			starts_and_ends: ("starts \"foo\" and ends \"bar\" or length 9", vec![]),
+			overlapping_starts: ("starts \"ab\" or starts \"cd\"", vec![]),
+		}
+	}
+
+	mod it_finds_contradictions {
+		use super::*;
+
+		lint_tests! {
+			different_lengths: ("length 3 and length 5", vec![FindingKind::Contradiction]),
+			different_equals: ("equals \"a\" and equals \"b\"", vec![FindingKind::Contradiction]),
+			equals_with_wrong_length: ("equals \"abc\" and length 5", vec![FindingKind::Contradiction]),
+			different_starts: ("starts \"foo\" and starts \"bar\"", vec![FindingKind::Contradiction]),
+			literal_longer_than_length: ("contains \"foobar\" and length 3", vec![FindingKind::Contradiction]),
+			literal_outside_of_class: ("numeric and starts \"a\"", vec![FindingKind::Contradiction]),
+			disjoint_classes: ("numeric and alpha and length 3", vec![FindingKind::Contradiction]),
+			only_one_clause: ("length 3 and length 5 or numeric", vec![FindingKind::Contradiction]),
+			disjoint_length_ranges: ("length over 5 and length at most 5", vec![FindingKind::Contradiction]),
+			length_outside_of_range: ("length 3 and length between 4 and 8", vec![FindingKind::Contradiction]),
+			different_case_insensitive_starts: ("istarts \"foo\" and istarts \"bar\"", vec![FindingKind::Contradiction]),
+			equals_outside_of_case_insensitive_query: ("equals \"Foo\" and iends \"x\"", vec![FindingKind::Contradiction]),
+			literal_longer_than_range: ("starts \"foobar\" and length under 6", vec![FindingKind::Contradiction]),
+			disjoint_cases: ("uppercase and lowercase and length 1", vec![FindingKind::Contradiction]),
+			whitespace_and_letters: ("whitespace and alpha and length over 0", vec![FindingKind::Contradiction]),
+			literal_of_other_case: ("uppercase and starts \"a\"", vec![FindingKind::Contradiction]),
+			disjoint_values: ("value over 10 and value under 5", vec![FindingKind::Contradiction]),
+			value_outside_of_range: ("value equals 3 and value over 3", vec![FindingKind::Contradiction]),
+			value_of_letters: ("alpha and value over 1", vec![FindingKind::Contradiction]),
+			disjoint_word_counts: ("words over 5 and words 3", vec![FindingKind::Contradiction]),
+			words_of_a_class: ("numeric and words 2", vec![FindingKind::Contradiction]),
+			substring_with_more_words: ("contains \"a b c\" and words at most 2", vec![FindingKind::Contradiction]),
+			disjoint_counts: ("contains \",\" exactly 2 times and contains \",\" at least 3 times", vec![FindingKind::Contradiction]),
+			contained_but_not_counted: ("contains \"ab\" and contains \"a\" at most 0 times", vec![FindingKind::Contradiction]),
+		}
+	}
+
+	mod it_finds_tautologies {
+		use super::*;
+
+		lint_tests! {
+			empty_contains: ("contains \"\"", vec![FindingKind::Tautology]),
+			unbounded_length: ("length at least 0", vec![FindingKind::Tautology]),
+			empty_starts_in_disjunction: ("numeric or starts \"\"", vec![FindingKind::Tautology, FindingKind::Redundancy]),
+		}
+	}
+
+	mod it_finds_redundancies {
+		use super::*;
+
+		lint_tests! {
+			duplicate_query: ("numeric and numeric", vec![FindingKind::Redundancy]),
+			implied_contains: ("contains \"ab\" and contains \"a\"", vec![FindingKind::Redundancy]),
+			implied_class: ("numeric and alphanumeric", vec![FindingKind::Redundancy]),
+			duplicate_clause: ("numeric or numeric", vec![FindingKind::Redundancy]),
+			covered_clause: ("numeric or numeric and length 5", vec![FindingKind::Redundancy]),
+			implied_case_insensitive_query: ("starts \"Foo\" and istarts \"fo\"", vec![FindingKind::Redundancy]),
+			implied_length_range: ("length 5 and length over 2", vec![FindingKind::Redundancy]),
+			nested_length_ranges: ("length between 2 and 4 and length under 8", vec![FindingKind::Redundancy]),
+			implied_letters: ("uppercase and alpha", vec![FindingKind::Redundancy]),
+			implied_value: ("value over 100 and value over 10", vec![FindingKind::Redundancy]),
+			implied_word_count: ("words 3 and words between 2 and 4", vec![FindingKind::Redundancy]),
+			implied_count: ("contains \",\" exactly 2 times and contains \",\" at most 5 times", vec![FindingKind::Redundancy]),
+			count_implies_contains: ("contains \"ab\" at least 2 times and contains \"a\"", vec![FindingKind::Redundancy]),
+		}
+	}
+
+	mod it_explains_expressions {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn single_clause() {
+			assert_eq!(
+				explain(&into_ast("numeric and length 5").unwrap()),
+				"matches if the input consists only of digits and is 5 bytes long"
+			);
+		}
+
+		#[test]
+		fn multiple_clauses() {
+			assert_eq!(
+				explain(&into_ast("starts \"a\" or @ipv4").unwrap()),
+				"matches if the input starts with \"a\"\nor if it is an ipv4 address like \"192.168.0.1\""
+			);
+		}
+	}
+
+	mod it_describes_expressions {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn single_clause() {
+			assert_eq!(
+				describe(&into_ast("starts \"foo\" and length 5").unwrap()),
+				"matches strings that start with \"foo\" and are exactly 5 bytes long"
+			);
+		}
+
+		#[test]
+		fn multiple_clauses() {
+			assert_eq!(
+				describe(&into_ast("numeric or @email or ends \"!\"").unwrap()),
+				"matches strings that consist only of digits, or that are an email address like \"jane@example.com\", or that end with \"!\""
+			);
+		}
+
+		#[test]
+		fn case_insensitive_queries() {
+			assert_eq!(
+				describe(&into_ast("icontains \"Error\"").unwrap()),
+				"matches strings that contain \"error\" ignoring case"
+			);
+		}
+
+		#[test]
+		fn length_ranges() {
+			assert_eq!(
+				describe(&into_ast("length between 2 and 8 or length over 20").unwrap()),
+				"matches strings that are between 2 and 8 bytes long, or that are over 20 bytes long"
+			);
+		}
+
+		#[test]
+		fn word_counts() {
+			assert_eq!(
+				describe(&into_ast("words 3 or words over 10").unwrap()),
+				"matches strings that have exactly 3 words, or that have over 10 words"
+			);
+		}
+
+		#[test]
+		fn quantified_contains() {
+			assert_eq!(
+				describe(&into_ast("contains \",\" exactly 2 times").unwrap()),
+				"matches strings that contain \",\" exactly 2 times"
+			);
+		}
+
+		#[test]
+		fn values() {
+			assert_eq!(
+				describe(&into_ast("value over 10 or value equals 0").unwrap()),
+				"matches strings that are numbers over 10, or that are the number 0"
+			);
+		}
+
+		#[test]
+		fn negated_queries() {
+			let ast = crate::parser::AST::Not(Box::new(into_ast("contains \"a\" or length 2").unwrap()));
+			assert_eq!(describe(&ast), "matches strings that don't contain \"a\" and aren't exactly 2 bytes long");
+		}
+	}
+
+	mod it_extracts_literals {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn alternatives_of_substrings() {
+			let ast = into_ast("contains \"error\" or contains \"warn\" or contains \"fatal\"").unwrap();
+			assert_eq!(literals(&ast), Some(vec!["error", "warn", "fatal"]));
+		}
+
+		#[test]
+		fn other_queries_and_operators() {
+			assert_eq!(literals(&into_ast("contains \"a\" and contains \"b\"").unwrap()), None);
+			assert_eq!(literals(&into_ast("contains \"a\" or starts \"b\"").unwrap()), None);
+
+			let ast = crate::parser::AST::Not(Box::new(into_ast("contains \"a\"").unwrap()));
+			assert_eq!(literals(&ast), None);
+		}
+	}
+
+	mod it_compares_expressions {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		fn entailed(a: &str, b: &str) -> bool {
+			entails(&into_ast(a).unwrap(), &into_ast(b).unwrap())
+		}
+
+		#[test]
+		fn proves_narrower_clauses() {
+			assert_eq!(entailed("starts \"ab\" and length 3", "starts \"a\""), true);
+			assert_eq!(entailed("numeric", "numeric or alpha"), true);
+			assert_eq!(entailed("numeric and alpha and length 2", "equals \"x\""), true);
+			assert_eq!(entailed("starts \"a\"", "starts \"ab\""), false);
+			assert_eq!(entailed("numeric or alpha", "numeric"), false);
+			assert_eq!(entailed("length 5", "length at least 5"), true);
+			assert_eq!(entailed("length between 3 and 4", "length over 2 and length under 5"), true);
+			assert_eq!(entailed("length at least 5", "length between 5 and 9"), false);
+		}
+
+		fn relation(old: &str, new: &str) -> Relation {
+			let old = crate::Expression::new(old).unwrap();
+			old.compare(&crate::Expression::new(new).unwrap()).relation
+		}
+
+		#[test]
+		fn decides_relations() {
+			assert_eq!(relation("numeric or alpha", "alpha or numeric"), Relation::Equivalent);
+			assert_eq!(relation("starts \"a\"", "starts \"ab\""), Relation::Narrower);
+			assert_eq!(relation("numeric and length 5", "numeric"), Relation::Broader);
+			assert_eq!(relation("starts \"a\"", "ends \"b\""), Relation::Incomparable);
+			assert_eq!(relation("@email", "contains \"@\" and contains \".\""), Relation::Unknown);
+		}
+
+		#[test]
+		fn doesnt_compare_expressions_with_different_options() {
+			let options = crate::MatchOptions { trim: true, ..crate::MatchOptions::default() };
+			let old = crate::Expression::new("numeric").unwrap();
+
+			assert_eq!(old.compare(&crate::Expression::new_with("numeric", options).unwrap()).relation, Relation::Unknown);
+		}
+
+		#[test]
+		fn finds_examples_of_every_clause() {
+			let examples = examples(&into_ast("numeric and length 3 or starts \"ab\"").unwrap());
+
+			assert!(examples.contains(&"111".to_string()));
+			assert!(examples.contains(&"1111".to_string()));
+			assert!(examples.contains(&"ab".to_string()));
+		}
+
+		#[test]
+		fn finds_examples_within_length_ranges() {
+			let examples = examples(&into_ast("numeric and length over 4 or alpha and length under 2").unwrap());
+
+			assert!(examples.contains(&"11111".to_string()));
+			assert!(examples.contains(&"a".to_string()));
+		}
+	}
+
+	mod it_scores_inputs {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		fn scored(source: &str, input: &str) -> f32 {
+			score(&into_ast(source).unwrap(), input, false)
+		}
+
+		#[test]
+		fn weights_queries_by_cost() {
+			assert_eq!(scored("numeric and length 3", "123"), 1.0);
+			assert_eq!(scored("numeric and length 3", "12"), 0.8);
+			assert_eq!(scored("numeric and length 3", "abc"), 0.2);
+			assert_eq!(scored("numeric and length 3", "ab"), 0.0);
+		}
+
+		#[test]
+		fn uses_the_closest_clause() {
+			assert_eq!(scored("equals \"x\" or starts \"a\" and ends \"b\"", "ab"), 1.0);
+			assert_eq!(scored("equals \"x\" or starts \"a\" and ends \"b\"", "ac"), 0.5);
+			assert_eq!(scored("not numeric", "12"), 0.0);
+		}
+	}
+
+	mod it_traces_inputs {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn lists_query_results_per_clause() {
+			assert_eq!(
+				trace(&into_ast("numeric and length 3 or starts \"1\"").unwrap(), "12a", false),
+				"- `numeric` fails, `length 3` matches\n+ `starts \"1\"` matches"
+			);
+		}
+	}
+
+	mod it_normalizes_combined_expressions {
+		use super::*;
+		use crate::logical_operator::LogicalOperator;
+		use crate::parser::AST;
+		use pretty_assertions::assert_eq;
+
+		fn not(source: &str) -> AST {
+			AST::Not(Box::new(into_ast(source).unwrap()))
+		}
+
+		fn and(left: AST, right: AST) -> AST {
+			AST::BinaryExpression { left: Box::new(left), operator: LogicalOperator::And, right: Box::new(right) }
+		}
+
+		#[test]
+		fn distributes_conjunctions() {
+			assert_eq!(
+				explain(&and(into_ast("starts \"a\" or numeric").unwrap(), into_ast("length 3").unwrap())),
+				"matches if the input starts with \"a\" and is 3 bytes long\nor if it consists only of digits and is 3 bytes long"
+			);
+		}
+
+		#[test]
+		fn negates_conjunctions() {
+			assert_eq!(
+				explain(&not("numeric and length 3")),
+				"matches if the input doesn't consist only of digits\nor if it isn't 3 bytes long"
+			);
+		}
+
+		#[test]
+		fn traces_negated_queries() {
+			assert_eq!(trace(&not("numeric"), "123", false), "- `not numeric` fails");
+		}
+
+		#[test]
+		fn finds_contradicting_negations() {
+			let findings = lint(&and(into_ast("equals \"abc\"").unwrap(), not("starts \"a\"")));
+			assert_eq!(findings.iter().map(|finding| finding.kind).collect::<Vec<_>>(), vec![FindingKind::Contradiction]);
+		}
+
+		#[test]
+		fn finds_redundant_negations() {
+			let findings = lint(&and(into_ast("starts \"a\"").unwrap(), not("starts \"b\"")));
+			assert_eq!(findings.iter().map(|finding| finding.kind).collect::<Vec<_>>(), vec![FindingKind::Redundancy]);
+		}
+	}
+
+	mod it_bounds_the_clauses {
+		use super::*;
+		use crate::parser::AST;
+		use pretty_assertions::assert_eq;
+
+		fn complement_of_alternatives(n: usize) -> AST {
+			let source = (0..n).map(|i| format!("starts \"x{}\" and ends \"y\"", i)).collect::<Vec<_>>().join(" or ");
+			AST::Not(Box::new(into_ast(&source).unwrap()))
+		}
+
+		#[test]
+		fn splits_asts_up_to_the_bound() {
+			// every negated alternative doubles the clauses
+			let ast = complement_of_alternatives(8);
+			assert_eq!(clauses(&ast).map(|clauses| clauses.len()), Some(MAX_CLAUSES));
+			assert_eq!(clauses(&complement_of_alternatives(9)), None);
+		}
+
+		#[test]
+		fn analyses_large_complements_without_splitting_them() {
+			let ast = complement_of_alternatives(40);
+
+			assert_eq!(clauses(&ast), None);
+			assert_eq!(lint(&ast), vec![]);
+			assert_eq!(entails(&ast, &ast), false);
+			assert_eq!(examples(&ast), vec![String::new(), " ".to_string()]);
+			assert_eq!(score(&ast, "x3 z", false), 1.0);
+			assert_eq!(score(&ast, "x3 y", false), 0.0);
+			assert!(trace(&ast, "x3 z", false).starts_with("+ "));
+			assert!(trace(&ast, "x3 y", false).starts_with("- "));
+		}
+	}
+}
@@ -0,0 +1,106 @@
+//! Support for [`assert_srch_matches!`](crate::assert_srch_matches) and
+//! [`assert_srch_rejects!`](crate::assert_srch_rejects)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::Expression;
+
+thread_local! {
+    /// Expressions are compiled once per thread at their first use
+    static EXPRESSIONS: RefCell<HashMap<String, Rc<Expression>>> = RefCell::new(HashMap::new());
+}
+
+fn compile(source: &str) -> Rc<Expression> {
+    EXPRESSIONS.with(|expressions| {
+        expressions
+            .borrow_mut()
+            .entry(source.to_string())
+            .or_insert_with(|| match Expression::new(source) {
+                Ok(expression) => Rc::new(expression),
+                Err(err) => panic!("invalid expression `{}`: {}", source, err),
+            })
+            .clone()
+    })
+}
+
+#[track_caller]
+pub fn assert(source: &str, input: &str, expected: bool) {
+    let expression = compile(source);
+
+    if expression.matches(input) == expected {
+        return;
+    }
+
+    panic!(
+        "assertion failed: `{:?}` {} `{}`\n\n{}\n\n{}",
+        input,
+        if expected {
+            "doesn't match"
+        } else {
+            "unexpectedly matches"
+        },
+        source,
+        expression.trace(input),
+        expression.explain()
+    );
+}
+
+/// Asserts that an expression matches a value. On failure the message shows
+/// which queries of the expression failed
+///
+/// ```rust
+/// srch::assert_srch_matches!("numeric and length 5", "12345");
+/// ```
+#[macro_export]
+macro_rules! assert_srch_matches {
+    ($source:expr, $value:expr $(,)?) => {
+        $crate::assertions::assert(
+            ::std::convert::AsRef::<str>::as_ref(&$source),
+            ::std::convert::AsRef::<str>::as_ref(&$value),
+            true,
+        )
+    };
+}
+
+/// Asserts that an expression doesn't match a value
+///
+/// ```rust
+/// srch::assert_srch_rejects!("numeric and length 5", "1234a");
+/// ```
+#[macro_export]
+macro_rules! assert_srch_rejects {
+    ($source:expr, $value:expr $(,)?) => {
+        $crate::assertions::assert(
+            ::std::convert::AsRef::<str>::as_ref(&$source),
+            ::std::convert::AsRef::<str>::as_ref(&$value),
+            false,
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn passes_on_expected_results() {
+        crate::assert_srch_matches!("numeric", "123");
+        crate::assert_srch_rejects!("numeric", String::from("abc"));
+    }
+
+    #[test]
+    fn fails_with_a_trace() {
+        let err =
+            std::panic::catch_unwind(|| crate::assert_srch_matches!("numeric or alpha", "12a"))
+                .unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<String>().unwrap(),
+            "assertion failed: `\"12a\"` doesn't match `numeric or alpha`\n\n\
+             - `numeric` fails\n- `alpha` fails\n\n\
+             matches if the input consists only of digits\nor if it consists only of letters"
+        );
+    }
+}
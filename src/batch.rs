@@ -0,0 +1,235 @@
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::{Expression, ExpressionSet};
+
+enum Matcher<'e> {
+    Expression(&'e Expression),
+    Set(&'e ExpressionSet),
+}
+
+impl Matcher<'_> {
+    fn matches(&self, input: &str) -> bool {
+        match self {
+            Matcher::Expression(expr) => expr.matches(input),
+            Matcher::Set(set) => set.matches(input),
+        }
+    }
+}
+
+/// Where a [`BatchRunner`] stopped in a corpus, passed to the next run to
+/// resume after the last matched or inspected input. It is written as a
+/// plain string, so it can be handed to clients, e.g. of an http api
+///
+/// ```rust
+/// let token: srch::Continuation = "42".parse().unwrap();
+/// assert_eq!(token.offset(), 42);
+/// assert_eq!(token.to_string(), "42");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Continuation {
+    offset: usize,
+}
+
+impl Continuation {
+    /// The index of the first input of the corpus which wasn't inspected
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for Continuation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.offset)
+    }
+}
+
+impl FromStr for Continuation {
+    type Err = ParseIntError;
+
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            offset: token.parse()?,
+        })
+    }
+}
+
+/// The results of a single run of a [`BatchRunner`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Batch {
+    /// The indices of the matching inputs of the corpus, in order
+    pub matches: Vec<usize>,
+    /// The number of inputs inspected by this run
+    pub inspected: usize,
+    /// Resumes the run, `None` if the end of the corpus was reached
+    pub continuation: Option<Continuation>,
+}
+
+impl Batch {
+    /// Whether the rest of the corpus was inspected
+    pub fn is_complete(&self) -> bool {
+        self.continuation.is_none()
+    }
+}
+
+/// Matches an expression or a set against a corpus of inputs within a
+/// budget of time or results, e.g. to answer a request of a server or keep
+/// an interactive tool responsive on huge inputs. A run which exhausts its
+/// budget returns the results so far and a [`Continuation`] to resume from.
+/// At least one input is inspected by every run, so a run always makes
+/// progress
+///
+/// ```rust
+/// use srch::{BatchRunner, Expression};
+///
+/// let expr = Expression::new("numeric").unwrap();
+/// let corpus = ["1", "a", "2", "b", "3"];
+/// let runner = BatchRunner::new(&expr).max_results(2);
+///
+/// let first = runner.run(&corpus, None);
+/// assert_eq!(first.matches, vec![0, 2]);
+///
+/// let second = runner.run(&corpus, first.continuation);
+/// assert_eq!(second.matches, vec![4]);
+/// assert!(second.is_complete());
+/// ```
+pub struct BatchRunner<'e> {
+    matcher: Matcher<'e>,
+    time_budget: Option<Duration>,
+    max_results: Option<usize>,
+}
+
+impl<'e> BatchRunner<'e> {
+    pub fn new(expression: &'e Expression) -> Self {
+        Self::with_matcher(Matcher::Expression(expression))
+    }
+
+    /// Runs a set of expressions, inputs match according to its policy
+    pub fn from_set(set: &'e ExpressionSet) -> Self {
+        Self::with_matcher(Matcher::Set(set))
+    }
+
+    fn with_matcher(matcher: Matcher<'e>) -> Self {
+        Self {
+            matcher,
+            time_budget: None,
+            max_results: None,
+        }
+    }
+
+    /// Stops a run once it took longer than `budget`, the time is checked
+    /// after every input
+    pub fn time_budget(mut self, budget: Duration) -> Self {
+        self.time_budget = Some(budget);
+        self
+    }
+
+    /// Stops a run once `max` inputs matched
+    pub fn max_results(mut self, max: usize) -> Self {
+        self.max_results = Some(max);
+        self
+    }
+
+    /// Matches the inputs of `corpus` starting at `continuation`, or at the
+    /// first input if it is `None`
+    pub fn run<S: AsRef<str>>(&self, corpus: &[S], continuation: Option<Continuation>) -> Batch {
+        let start = continuation.map_or(0, |continuation| continuation.offset);
+        let deadline = self.time_budget.map(|budget| Instant::now() + budget);
+
+        let mut batch = Batch {
+            matches: vec![],
+            inspected: 0,
+            continuation: None,
+        };
+
+        for (index, input) in corpus.iter().enumerate().skip(start) {
+            batch.inspected += 1;
+
+            if self.matcher.matches(input.as_ref()) {
+                batch.matches.push(index);
+            }
+
+            let exhausted = self
+                .max_results
+                .is_some_and(|max| batch.matches.len() >= max)
+                || deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+            if exhausted && index + 1 < corpus.len() {
+                batch.continuation = Some(Continuation { offset: index + 1 });
+                break;
+            }
+        }
+
+        batch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchRunner, Continuation};
+    use crate::{Expression, ExpressionSet, Policy};
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn resumes_until_the_corpus_is_complete() {
+        let expr = Expression::new("numeric").unwrap();
+        let corpus = (0..10)
+            .map(|n| {
+                if n % 3 == 0 {
+                    n.to_string()
+                } else {
+                    "x".to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let runner = BatchRunner::new(&expr).max_results(1);
+        let mut continuation = None;
+        let mut matches = vec![];
+        let mut runs = 0;
+
+        loop {
+            let batch = runner.run(&corpus, continuation);
+            matches.extend(batch.matches);
+            runs += 1;
+
+            match batch.continuation {
+                Some(next) => continuation = Some(next),
+                None => break,
+            }
+        }
+
+        assert_eq!(matches, vec![0, 3, 6, 9]);
+        assert_eq!(runs, 4);
+    }
+
+    #[test]
+    fn stops_when_the_time_is_up() {
+        let set = ExpressionSet::new(["numeric", "alpha"], Policy::Any).unwrap();
+        let corpus = ["1", "a", "-"];
+
+        let batch = BatchRunner::from_set(&set)
+            .time_budget(Duration::ZERO)
+            .run(&corpus, None);
+
+        assert_eq!(batch.matches, vec![0]);
+        assert_eq!(batch.inspected, 1);
+        assert_eq!(batch.continuation, Some(Continuation { offset: 1 }));
+    }
+
+    #[test]
+    fn runs_without_a_budget() {
+        let expr = Expression::new("length 1").unwrap();
+        let corpus = ["a", "bc", "d"];
+
+        let batch = BatchRunner::new(&expr).run(&corpus, Some("1".parse().unwrap()));
+
+        assert_eq!(batch.matches, vec![2]);
+        assert_eq!(batch.inspected, 2);
+        assert!(batch.is_complete());
+        assert!("next".parse::<Continuation>().is_err());
+    }
+}
@@ -0,0 +1,290 @@
+//! Standardized workloads for measuring the throughput of expressions. They
+//! are used by `srch bench` and can be used by applications to check that an
+//! upgrade of srch doesn't slow down their own rule sets
+//!
+//! ```rust
+//! use srch::bench::{self, CorpusKind};
+//!
+//! let corpus = bench::generate(CorpusKind::Logs, 1_000, 42);
+//! let expr = srch::Expression::new("contains \"error\"").unwrap();
+//!
+//! let measurement = bench::measure("errors", &expr, &corpus, 3);
+//! assert_eq!(measurement.records, 1_000);
+//! ```
+
+use std::time::{Duration, Instant};
+
+use crate::rng::Rng;
+use crate::{Expression, Result};
+
+/// The seed of the corpora generated for the standard workloads
+pub const SEED: u64 = 0x5eed;
+
+/// The kind of records of a generated corpus
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorpusKind {
+    /// Log lines like `2024-01-07T12:03:59 ERROR disk error on /dev/sda1 in 12ms`
+    Logs,
+    /// Single words made of digits, letters and special characters
+    Words,
+    /// Values like numbers, email addresses, urls, uuids and ipv4 addresses
+    Values,
+}
+
+impl CorpusKind {
+    pub const ALL: [CorpusKind; 3] = [CorpusKind::Logs, CorpusKind::Words, CorpusKind::Values];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CorpusKind::Logs => "logs",
+            CorpusKind::Words => "words",
+            CorpusKind::Values => "values",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|kind| kind.name() == name)
+    }
+}
+
+const DIGITS: &[u8] = b"0123456789";
+const LETTERS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const SPECIAL: &[u8] = b"!#$%&*+-./:;=?@_~";
+const HEX: &[u8] = b"0123456789abcdef";
+
+fn log_line(rng: &mut Rng) -> String {
+    let level = rng.pick(&["INFO", "INFO", "INFO", "DEBUG", "WARN", "ERROR"]);
+
+    let message = match rng.below(5) {
+        0 => "request served".to_string(),
+        1 => format!("cache miss for key user:{}", rng.below(10_000)),
+        2 => format!(
+            "connection timeout to 10.0.{}.{}",
+            rng.below(256),
+            rng.below(256)
+        ),
+        3 => format!("disk error on /dev/sda{}", rng.below(4)),
+        _ => format!("retrying job {}", rng.string(HEX, 8)),
+    };
+
+    format!(
+        "2024-01-{:02}T{:02}:{:02}:{:02} {} {} in {}ms",
+        rng.below(31) + 1,
+        rng.below(24),
+        rng.below(60),
+        rng.below(60),
+        level,
+        message,
+        rng.below(1_000)
+    )
+}
+
+fn word(rng: &mut Rng) -> String {
+    let len = rng.below(12) + 1;
+
+    match rng.below(4) {
+        0 => rng.string(DIGITS, len),
+        1 => rng.string(LETTERS, len),
+        2 => rng.string(SPECIAL, len),
+        _ => rng.string(&[DIGITS, LETTERS, SPECIAL].concat(), len),
+    }
+}
+
+fn value(rng: &mut Rng) -> String {
+    match rng.below(6) {
+        0 => rng.below(1_000_000).to_string(),
+        1 => format!("{}@example.com", rng.string(LETTERS, 6).to_lowercase()),
+        2 => format!(
+            "https://example.com/{}",
+            rng.string(LETTERS, 8).to_lowercase()
+        ),
+        3 => format!(
+            "{}-{}-{}-{}-{}",
+            rng.string(HEX, 8),
+            rng.string(HEX, 4),
+            rng.string(HEX, 4),
+            rng.string(HEX, 4),
+            rng.string(HEX, 12)
+        ),
+        4 => format!(
+            "{}.{}.{}.{}",
+            rng.below(256),
+            rng.below(256),
+            rng.below(256),
+            rng.below(256)
+        ),
+        _ => word(rng),
+    }
+}
+
+/// Generates `records` records of a kind, the same seed always generates the
+/// same corpus
+pub fn generate(kind: CorpusKind, records: usize, seed: u64) -> Vec<String> {
+    let mut rng = Rng::new(seed);
+
+    let record = match kind {
+        CorpusKind::Logs => log_line,
+        CorpusKind::Words => word,
+        CorpusKind::Values => value,
+    };
+
+    (0..records).map(|_| record(&mut rng)).collect()
+}
+
+/// A named expression together with the kind of corpus it is measured against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Workload {
+    pub name: &'static str,
+    pub expression: &'static str,
+    pub corpus: CorpusKind,
+}
+
+/// Representative expressions, from a single substring check up to
+/// alternatives of named patterns
+pub const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "substring",
+        expression: "contains \"error\"",
+        corpus: CorpusKind::Logs,
+    },
+    Workload {
+        name: "affixes",
+        expression: "starts \"2024-01-1\" and ends \"0ms\"",
+        corpus: CorpusKind::Logs,
+    },
+    Workload {
+        name: "alternatives",
+        expression: "contains \"ERROR\" or contains \"WARN\" or contains \"timeout\"",
+        corpus: CorpusKind::Logs,
+    },
+    Workload {
+        name: "classes",
+        expression: "numeric and length 5 or alpha and length 3 or special",
+        corpus: CorpusKind::Words,
+    },
+    Workload {
+        name: "patterns",
+        expression: "@email or @url or @uuid or @ipv4",
+        corpus: CorpusKind::Values,
+    },
+];
+
+impl Workload {
+    /// Measures the expression against a generated corpus of `records`
+    /// records, see [`measure`]
+    pub fn run(&self, records: usize, iterations: usize) -> Result<Measurement> {
+        let expr = Expression::new(self.expression)?;
+        let corpus = generate(self.corpus, records, SEED);
+
+        Ok(measure(self.name, &expr, &corpus, iterations))
+    }
+}
+
+/// The fastest of several runs of an expression over a corpus
+#[derive(Clone, Debug, PartialEq)]
+pub struct Measurement {
+    pub name: String,
+    pub records: usize,
+    pub bytes: usize,
+    pub matches: usize,
+    pub elapsed: Duration,
+}
+
+impl Measurement {
+    fn per_second(&self, n: usize) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            seconds if seconds > 0.0 => n as f64 / seconds,
+            _ => f64::INFINITY,
+        }
+    }
+
+    pub fn records_per_second(&self) -> f64 {
+        self.per_second(self.records)
+    }
+
+    pub fn bytes_per_second(&self) -> f64 {
+        self.per_second(self.bytes)
+    }
+
+    /// Whether fewer records per second were matched than in `baseline`,
+    /// allowing for a relative `tolerance` (e.g. `0.1` for 10%) as timings
+    /// are never exact
+    pub fn regressed(&self, baseline: &Measurement, tolerance: f64) -> bool {
+        self.records_per_second() * (1.0 + tolerance) < baseline.records_per_second()
+    }
+}
+
+/// Matches every record of a corpus `iterations` times (at least once) and
+/// keeps the fastest run, which is the one least disturbed by other processes
+pub fn measure(name: &str, expr: &Expression, corpus: &[String], iterations: usize) -> Measurement {
+    let mut fastest = Duration::MAX;
+    let mut matches = 0;
+
+    for _ in 0..iterations.max(1) {
+        let started = Instant::now();
+        matches = corpus.iter().filter(|record| expr.matches(record)).count();
+        fastest = fastest.min(started.elapsed());
+    }
+
+    Measurement {
+        name: name.to_string(),
+        records: corpus.len(),
+        bytes: corpus.iter().map(String::len).sum(),
+        matches,
+        elapsed: fastest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate, measure, CorpusKind, Measurement, WORKLOADS};
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+    use std::time::Duration;
+
+    #[test]
+    fn generates_deterministic_corpora() {
+        for kind in CorpusKind::ALL.iter().copied() {
+            assert_eq!(generate(kind, 50, 7), generate(kind, 50, 7));
+            assert!(generate(kind, 50, 7) != generate(kind, 50, 8));
+        }
+    }
+
+    #[test]
+    fn workloads_match_part_of_their_corpus() {
+        for workload in WORKLOADS {
+            let measurement = workload.run(1_000, 1).unwrap();
+
+            assert_eq!(measurement.records, 1_000);
+            assert!(measurement.matches > 0, "{} matches nothing", workload.name);
+            assert!(
+                measurement.matches < 1_000,
+                "{} matches everything",
+                workload.name
+            );
+        }
+    }
+
+    #[test]
+    fn measures_matches_and_bytes() {
+        let corpus = vec!["12".to_string(), "ab".to_string(), "345".to_string()];
+        let measurement = measure("digits", &Expression::new("numeric").unwrap(), &corpus, 2);
+
+        assert_eq!(measurement.matches, 2);
+        assert_eq!(measurement.bytes, 7);
+    }
+
+    #[test]
+    fn detects_regressions() {
+        let measurement = |millis| Measurement {
+            name: "substring".to_string(),
+            records: 1_000,
+            bytes: 10_000,
+            matches: 10,
+            elapsed: Duration::from_millis(millis),
+        };
+
+        assert_eq!(measurement(105).regressed(&measurement(100), 0.1), false);
+        assert_eq!(measurement(150).regressed(&measurement(100), 0.1), true);
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::Expression;
+
+#[derive(Default)]
+struct Cache {
+    /// The results by the hash of their input, the input is kept to detect
+    /// hash collisions
+    results: HashMap<u64, (Box<str>, bool)>,
+    /// The hashes in insertion order, the oldest result is evicted first
+    order: VecDeque<u64>,
+}
+
+/// An expression which remembers the results of the last `capacity`
+/// distinct inputs, for inputs which repeat a lot like deduplicated log
+/// streams
+///
+/// ```rust
+/// let expr = srch::Expression::new("contains \"error\"").unwrap().cached(1024);
+///
+/// assert!(expr.matches("disk error"));
+/// assert!(expr.matches("disk error"));
+/// assert_eq!(expr.len(), 1);
+/// ```
+pub struct CachedExpression {
+    expression: Expression,
+    capacity: usize,
+    cache: Mutex<Cache>,
+}
+
+impl CachedExpression {
+    pub fn new(expression: Expression, capacity: usize) -> Self {
+        Self {
+            expression,
+            capacity,
+            cache: Mutex::new(Cache::default()),
+        }
+    }
+
+    pub fn matches(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+
+        let mut hasher = DefaultHasher::new();
+        input.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if let Some((cached, result)) = self.lock().results.get(&hash) {
+            if &**cached == input {
+                return *result;
+            }
+        }
+
+        // Evaluated without holding the lock, so other threads can look up
+        // their inputs in the meantime
+        let result = self.expression.matches(input);
+
+        if self.capacity == 0 {
+            return result;
+        }
+
+        let mut cache = self.lock();
+        let replaced = cache.results.insert(hash, (input.into(), result));
+
+        if replaced.is_none() {
+            cache.order.push_back(hash);
+        }
+
+        while cache.order.len() > self.capacity {
+            if let Some(oldest) = cache.order.pop_front() {
+                cache.results.remove(&oldest);
+            }
+        }
+
+        result
+    }
+
+    /// The number of cached results
+    pub fn len(&self) -> usize {
+        self.lock().results.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        let mut cache = self.lock();
+        cache.results.clear();
+        cache.order.clear();
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn into_inner(self) -> Expression {
+        self.expression
+    }
+
+    /// A poisoned cache is still usable, every entry is a complete result
+    fn lock(&self) -> MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn caches_results() {
+        let expr = Expression::new("numeric").unwrap().cached(8);
+
+        assert_eq!(expr.matches("123"), true);
+        assert_eq!(expr.matches("abc"), false);
+        assert_eq!(expr.matches("123"), true);
+        assert_eq!(expr.len(), 2);
+    }
+
+    #[test]
+    fn evicts_the_oldest_results() {
+        let expr = Expression::new("numeric").unwrap().cached(2);
+
+        for input in ["1", "2", "3", "2"].iter() {
+            expr.matches(input);
+        }
+
+        assert_eq!(expr.len(), 2);
+        assert_eq!(expr.matches("1"), true);
+        assert_eq!(expr.len(), 2);
+    }
+
+    #[test]
+    fn disables_caching_without_capacity() {
+        let expr = Expression::new("numeric").unwrap().cached(0);
+
+        assert_eq!(expr.matches("1"), true);
+        assert_eq!(expr.is_empty(), true);
+    }
+
+    #[test]
+    fn shares_results_between_threads() {
+        let expr = Arc::new(Expression::new("numeric").unwrap().cached(8));
+
+        let threads = (0..4)
+            .map(|_| {
+                let expr = Arc::clone(&expr);
+                thread::spawn(move || (expr.matches("123"), expr.matches("abc")))
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), (true, false));
+        }
+
+        assert_eq!(expr.len(), 2);
+    }
+}
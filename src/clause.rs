@@ -0,0 +1,173 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::analysis::{self, Literal};
+use crate::lexer::{self, Token};
+use crate::options::MatchOptions;
+use crate::parser::AST;
+use crate::query::Query;
+
+/// A query of a [`Clause`], which has to fail if it is negated
+#[derive(Clone, Debug, PartialEq)]
+pub struct Condition {
+    query: Query,
+    negated: bool,
+    span: Option<Range<usize>>,
+    options: MatchOptions,
+}
+
+impl Condition {
+    pub fn matches(&self, input: impl AsRef<str>) -> bool {
+        let input = self.options.prepare(input.as_ref());
+        self.query.exec_with(&input, self.options.unicode) != self.negated
+    }
+
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+
+    /// The byte range of the query in the source of the expression, only
+    /// known for expressions compiled from a source
+    pub fn span(&self) -> Option<Range<usize>> {
+        self.span.clone()
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.negated {
+            write!(f, "not {}", self.query)
+        } else {
+            write!(f, "{}", self.query)
+        }
+    }
+}
+
+/// A top-level alternative of an expression, which matches if all of its
+/// conditions match. An expression matches if any of its clauses matches
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clause {
+    conditions: Vec<Condition>,
+}
+
+impl Clause {
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    pub fn matches(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+        self.conditions
+            .iter()
+            .all(|condition| condition.matches(input))
+    }
+}
+
+impl fmt::Display for Clause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let conditions = self
+            .conditions
+            .iter()
+            .map(|condition| condition.to_string())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", conditions.join(" and "))
+    }
+}
+
+/// Splits an ast into its clauses, `source` is used to find the span of
+/// every query. `None` if the ast has too many clauses
+pub fn clauses(ast: &AST, source: Option<&str>, options: MatchOptions) -> Option<Vec<Clause>> {
+    let spans = source
+        .and_then(|source| lexer::lex_spanned(source).ok())
+        .map(|tokens| {
+            tokens
+                .into_iter()
+                .filter(|(token, _)| matches!(token, Token::Query(_)))
+                .map(|(_, span)| span)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let condition = |literal: &Literal| Condition {
+        query: literal.query.clone(),
+        negated: literal.negated,
+        span: spans.get(literal.index).cloned(),
+        options,
+    };
+
+    let clauses = analysis::clauses(ast)?
+        .iter()
+        .map(|clause| Clause {
+            conditions: clause.iter().map(condition).collect(),
+        })
+        .collect();
+
+    Some(clauses)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn splits_top_level_alternatives() {
+        let expr = Expression::new("numeric and length 3 or starts \"a\"").unwrap();
+        let clauses = expr.clauses().unwrap();
+
+        assert_eq!(
+            clauses
+                .iter()
+                .map(|clause| clause.to_string())
+                .collect::<Vec<_>>(),
+            vec!["numeric and length 3", "starts \"a\""]
+        );
+        assert_eq!(clauses[0].matches("123"), true);
+        assert_eq!(clauses[1].matches("123"), false);
+    }
+
+    #[test]
+    fn locates_conditions() {
+        let source = "numeric and length 3 or  @email";
+        let expr = Expression::new(source).unwrap();
+
+        let spans = expr
+            .clauses()
+            .unwrap()
+            .iter()
+            .flat_map(|clause| clause.conditions().to_vec())
+            .map(|condition| &source[condition.span().unwrap()])
+            .collect::<Vec<_>>();
+
+        assert_eq!(spans, vec!["numeric", "length 3", "@email"]);
+    }
+
+    #[test]
+    fn combined_expressions_have_no_spans() {
+        let expr = Expression::new("numeric")
+            .unwrap()
+            .and(Expression::new("length 3").unwrap())
+            .unwrap()
+            .negate();
+
+        let clauses = expr.clauses().unwrap();
+
+        assert_eq!(clauses.len(), 2);
+        assert_eq!(clauses[0].conditions()[0].is_negated(), true);
+        assert_eq!(clauses[0].conditions()[0].span(), None);
+        assert_eq!(clauses[1].to_string(), "not length 3");
+    }
+
+    #[test]
+    fn doesnt_split_expressions_with_too_many_clauses() {
+        let alternatives = (0..18)
+            .map(|i| format!("starts \"x{}\" and ends \"y\"", i))
+            .collect::<Vec<_>>()
+            .join(" or ");
+
+        let expr = Expression::new(&alternatives).unwrap();
+        assert_eq!(expr.clauses().map(|clauses| clauses.len()), Some(18));
+        assert_eq!(expr.complement().clauses(), None);
+    }
+}
@@ -0,0 +1,96 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use icu_collator::{Collator, CollatorOptions};
+use icu_locid::Locale;
+
+use crate::i18n;
+
+/// Compares strings the way people of a locale expect them to be ordered,
+/// e.g. `ä` next to `a` in German but after `z` in Swedish, instead of by
+/// their bytes
+///
+/// ```rust
+/// let collation = srch::Collation::new("de").unwrap();
+///
+/// let mut words = vec!["zebra", "Äpfel", "apfel"];
+/// words.sort_by(|a, b| collation.compare(a, b));
+///
+/// assert_eq!(words, vec!["apfel", "Äpfel", "zebra"]);
+/// ```
+pub struct Collation {
+    collator: Collator,
+}
+
+impl Collation {
+    /// Creates the collation of a locale given as a BCP 47 language tag like
+    /// `de` or `sv-SE`. Locales without specific rules use the root
+    /// collation, which already orders most scripts sensibly
+    pub fn new(locale: &str) -> Result<Self, InvalidLocale> {
+        let invalid = || InvalidLocale(locale.to_string());
+        let parsed = locale.parse::<Locale>().map_err(|_| invalid())?;
+
+        let collator =
+            Collator::try_new(&(&parsed).into(), CollatorOptions::new()).map_err(|_| invalid())?;
+
+        Ok(Self { collator })
+    }
+
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        self.collator.compare(a, b)
+    }
+}
+
+impl fmt::Debug for Collation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Collation").finish_non_exhaustive()
+    }
+}
+
+/// The error of a locale which can't be parsed or has no collation data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidLocale(pub String);
+
+impl fmt::Display for InvalidLocale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", i18n::message("collation.locale", &self.0))
+    }
+}
+
+impl std::error::Error for InvalidLocale {}
+
+#[cfg(test)]
+mod tests {
+    use super::{Collation, InvalidLocale};
+    use pretty_assertions::assert_eq;
+
+    fn sorted<'a>(locale: &str, mut words: Vec<&'a str>) -> Vec<&'a str> {
+        let collation = Collation::new(locale).unwrap();
+        words.sort_by(|a, b| collation.compare(a, b));
+        words
+    }
+
+    #[test]
+    fn follows_the_rules_of_the_locale() {
+        let words = vec!["zebra", "äpfel", "apfel"];
+
+        assert_eq!(sorted("de", words.clone()), vec!["apfel", "äpfel", "zebra"]);
+        assert_eq!(sorted("sv", words), vec!["apfel", "zebra", "äpfel"]);
+    }
+
+    #[test]
+    fn ignores_case_before_letters() {
+        assert_eq!(
+            sorted("en", vec!["b", "B", "a", "A"]),
+            vec!["a", "A", "b", "B"]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_locales() {
+        assert_eq!(
+            Collation::new("not a locale").unwrap_err(),
+            InvalidLocale("not a locale".to_string())
+        );
+    }
+}
@@ -0,0 +1,644 @@
+use crate::logical_operator::LogicalOperator;
+use crate::parser::AST;
+use crate::pattern::Pattern;
+use crate::query::{LengthRange, Query, Times, ValueRange};
+
+const REGEX_META_CHARS: &str = "\\.+*?()|[]{}^$#&-~";
+
+fn escape(literal: &str) -> String {
+	let mut escaped = String::with_capacity(literal.len());
+
+	for c in literal.chars() {
+		if REGEX_META_CHARS.contains(c) {
+			escaped.push('\\');
+		}
+
+		escaped.push(c);
+	}
+
+	escaped
+}
+
+fn pattern_to_regex(pattern: &Pattern) -> &'static str {
+	match pattern {
+		Pattern::Email => "[A-Za-z0-9!#$%&'*+/=?^_`{|}~.-]+@(?:[A-Za-z0-9](?:[A-Za-z0-9-]{0,61}[A-Za-z0-9])?\\.)+[A-Za-z]{2,}",
+		Pattern::Url => "[A-Za-z][A-Za-z0-9+.-]*://[^\\s/?#]+[^\\s]*",
+		Pattern::Uuid => "[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12}",
+		Pattern::Ipv4 => "(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])(?:\\.(?:25[0-5]|2[0-4][0-9]|1[0-9]{2}|[1-9]?[0-9])){3}"
+	}
+}
+
+/// The character set of a class query, matching a single character
+fn class_to_regex(query: &Query) -> Option<&'static str> {
+	match query {
+		Query::Numeric => Some("[0-9]"),
+		Query::Alpha => Some("[A-Za-z]"),
+		Query::Alphanumeric => Some("[A-Za-z0-9]"),
+		Query::Uppercase => Some("[A-Z]"),
+		Query::Lowercase => Some("[a-z]"),
+		Query::Whitespace => Some("[ \\t\\n\\f\\r]"),
+		Query::Special => Some("[!-/:-@\\[-`{-~]"),
+		_ => None
+	}
+}
+
+/// The query a case-insensitive query runs on the lowercased input
+fn on_lowercased(query: &Query) -> Option<Query> {
+	match query {
+		Query::IStarts(arg) => Some(Query::Starts(arg.clone())),
+		Query::IEnds(arg) => Some(Query::Ends(arg.clone())),
+		Query::IContains(arg) => Some(Query::Contains(arg.clone())),
+		Query::IEquals(arg) => Some(Query::Equals(arg.clone())),
+		_ => None
+	}
+}
+
+/// The repetition of a character matching the lengths of a `length` query
+fn length_repetition(query: &Query) -> Option<String> {
+	match query.length_bounds()? {
+		(min, Some(max)) if min == max => Some(format!("{{{}}}", min)),
+		(min, Some(max)) => Some(format!("{{{},{}}}", min, max)),
+		(min, None) => Some(format!("{{{},}}", min))
+	}
+}
+
+/// Converts the bounds of a `words` query into a pattern, words are runs of
+/// characters which aren't whitespace
+fn words_to_regex((min, max): (u64, Option<u64>)) -> String {
+	const SPACE: &str = "[ \\t\\n\\f\\r]";
+	const WORD: &str = "[^ \\t\\n\\f\\r]+";
+
+	// The words following the first one
+	let rest = match (min.saturating_sub(1), max.map(|max| max.saturating_sub(1))) {
+		(min, Some(max)) if min == max => format!("{{{}}}", min),
+		(min, Some(max)) => format!("{{{},{}}}", min, max),
+		(min, None) => format!("{{{},}}", min)
+	};
+
+	let words = format!("{}(?:{}+{}){}", WORD, SPACE, WORD, rest);
+
+	match (min, max) {
+		(_, Some(0)) => format!("{}*", SPACE),
+		(0, _) => format!("{}*(?:{}{}*)?", SPACE, words, SPACE),
+		_ => format!("{}*{}{}*", SPACE, words, SPACE)
+	}
+}
+
+/// A decimal number like `-12.50`, the inputs a `value` query compares
+const NUMBER: &str = "-?[0-9]+(?:\\.[0-9]+)?";
+
+/// The digits of non-negative integers without leading zeros which are
+/// greater than `n`, built digit by digit like `n` written as a range
+fn integers_over(n: u64) -> Vec<String> {
+	let digits = n.to_string();
+	let mut alternatives = vec![format!("[1-9][0-9]{{{},}}", digits.len())];
+
+	for (i, digit) in digits.bytes().enumerate() {
+		if digit < b'9' {
+			let rest = digits.len() - i - 1;
+			alternatives.push(format!("{}[{}-9][0-9]{{{}}}", &digits[..i], (digit + 1) as char, rest));
+		}
+	}
+
+	alternatives
+}
+
+/// The digits of positive integers without leading zeros which are less
+/// than `n`
+fn integers_under(n: u64) -> Vec<String> {
+	let digits = n.to_string();
+	let mut alternatives = Vec::new();
+
+	if digits.len() > 1 {
+		alternatives.push(format!("[1-9][0-9]{{0,{}}}", digits.len() - 2));
+	}
+
+	for (i, digit) in digits.bytes().enumerate() {
+		let lowest = if i == 0 { b'1' } else { b'0' };
+
+		if digit > lowest {
+			let rest = digits.len() - i - 1;
+			alternatives.push(format!("{}[{}-{}][0-9]{{{}}}", &digits[..i], lowest as char, (digit - 1) as char, rest));
+		}
+	}
+
+	alternatives
+}
+
+/// Converts a value comparison into a pattern matching the decimal numbers
+/// it matches, the integer part is compared digit by digit
+fn value_to_regex(range: &ValueRange) -> String {
+	const ZERO: &str = "-?0+(?:\\.0+)?";
+	const FRACTION: &str = "(?:\\.[0-9]+)?";
+	const NONZERO_FRACTION: &str = "\\.[0-9]*[1-9][0-9]*";
+
+	let integer = match range.bound() {
+		0 => "0+".to_string(),
+		n => format!("0*{}", n)
+	};
+
+	let alternatives = match *range {
+		ValueRange::Equals(0) => vec![ZERO.to_string()],
+		ValueRange::Equals(_) => vec![format!("{}(?:\\.0+)?", integer)],
+		ValueRange::Over(n) => vec![
+			format!("0*(?:{}){}", integers_over(n).join("|"), FRACTION),
+			format!("{}{}", integer, NONZERO_FRACTION)
+		],
+		ValueRange::Under(n) => {
+			let mut alternatives = vec![format!("-(?:0*[1-9][0-9]*{}|0+{})", FRACTION, NONZERO_FRACTION)];
+
+			if n > 0 {
+				alternatives.push(ZERO.to_string());
+				alternatives.push(format!("0+{}", NONZERO_FRACTION));
+			}
+
+			let under = integers_under(n);
+
+			if !under.is_empty() {
+				alternatives.push(format!("0*(?:{}){}", under.join("|"), FRACTION));
+			}
+
+			alternatives
+		}
+	};
+
+	format!("(?:{})", alternatives.join("|"))
+}
+
+/// Converts a quantified `contains` into a pattern, an occurrence is the
+/// shortest text ending with the argument so occurrences can't overlap
+fn times_to_regex(arg: &str, times: &Times) -> String {
+	let occurrence = format!("(?:.*?{})", escape(arg));
+	let (min, max) = times.bounds();
+
+	let at_most = max.map(|max| format!("(?!{}{{{}}})", occurrence, max.saturating_add(1))).unwrap_or_default();
+	let at_least = match min {
+		0 => String::new(),
+		min => format!("{}{{{}}}", occurrence, min)
+	};
+
+	format!("{}{}.*", at_most, at_least)
+}
+
+/// Converts a query into a pattern matching the whole tested string
+fn query_to_regex(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("{}.*", escape(arg)),
+		Query::Ends(arg) => format!(".*{}", escape(arg)),
+		Query::Contains(arg) => format!(".*{}.*", escape(arg)),
+		Query::ContainsTimes(arg, times) => times_to_regex(arg, times),
+		Query::Equals(arg) => escape(arg),
+		Query::IStarts(_) | Query::IEnds(_) | Query::IContains(_) | Query::IEquals(_) => {
+			format!("(?i:{})", on_lowercased(query).map(|query| query_to_regex(&query)).unwrap_or_default())
+		},
+		Query::Length(_) | Query::LengthRange(_) => format!(".{}", length_repetition(query).unwrap_or_default()),
+		Query::Value(range) => value_to_regex(range),
+		Query::Words(_) | Query::WordsRange(_) => words_to_regex(query.word_bounds().unwrap_or((0, None))),
+		Query::Numeric | Query::Alpha | Query::Alphanumeric | Query::Uppercase | Query::Lowercase | Query::Whitespace | Query::Special => {
+			format!("{}*", class_to_regex(query).unwrap_or_default())
+		},
+		Query::Pattern(pattern) => pattern_to_regex(pattern).to_string(),
+		// Custom queries run code, which no regular expression can express
+		Query::Custom(_) => "[^\\s\\S]".to_string(),
+	}
+}
+
+/// Converts a class and a length query into a single repetition, e.g.
+/// `numeric and length 5` into `[0-9]{5}`
+fn class_with_length(left: &AST, right: &AST) -> Option<String> {
+	match (left, right) {
+		(AST::Query(left), AST::Query(right)) => {
+			let (length, class) = match left.length_bounds() {
+				Some(_) => (left, right),
+				None => (right, left)
+			};
+
+			Some(format!("{}{}", class_to_regex(class)?, length_repetition(length)?))
+		},
+		_ => None
+	}
+}
+
+/// Converts an ast into a pattern matching the whole tested string, a
+/// conjunction is expressed using a lookahead since regular expressions
+/// lack an intersection operator. Lookaheads aren't supported by every
+/// regex engine, so they are avoided where possible
+fn ast_to_regex(ast: &AST) -> String {
+	match ast {
+		AST::Query(query) => query_to_regex(query),
+		AST::Not(ast) => format!("(?!(?:{})$).*", ast_to_regex(ast)),
+		AST::BinaryExpression {
+			left,
+			operator: LogicalOperator::Or,
+			right,
+		} => {
+			format!("(?:{}|{})", ast_to_regex(left), ast_to_regex(right))
+		}
+		AST::BinaryExpression {
+			left,
+			operator: LogicalOperator::And,
+			right,
+		} => match class_with_length(left, right) {
+			Some(regex) => regex,
+			None => format!("(?=(?:{})$)(?:{})", ast_to_regex(left), ast_to_regex(right))
+		}
+	}
+}
+
+/// Converts an ast into an anchored regular expression, see
+/// [`Expression::to_regex_string`](../struct.Expression.html#method.to_regex_string)
+pub fn to_regex(ast: &AST) -> String {
+	format!("^(?:{})$", ast_to_regex(ast))
+}
+
+/// The sql dialects predicates can be generated for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SqlDialect {
+	Postgres,
+	Sqlite
+}
+
+fn sql_string(literal: &str) -> String {
+	format!("'{}'", literal.replace('\'', "''"))
+}
+
+fn like_pattern(literal: &str) -> String {
+	let mut escaped = String::with_capacity(literal.len());
+
+	for c in literal.chars() {
+		if matches!(c, '\\' | '%' | '_') {
+			escaped.push('\\');
+		}
+
+		escaped.push(c);
+	}
+
+	escaped
+}
+
+/// The characters a string mustn't contain to match a class query, as a
+/// character set for sqlite's GLOB
+fn glob_complement(query: &Query) -> Option<&'static str> {
+	match query {
+		Query::Numeric => Some("[^0-9]"),
+		Query::Alpha => Some("[^A-Za-z]"),
+		Query::Alphanumeric => Some("[^A-Za-z0-9]"),
+		Query::Uppercase => Some("[^A-Z]"),
+		Query::Lowercase => Some("[^a-z]"),
+		// GLOB doesn't support escapes, so the characters are written as is
+		Query::Whitespace => Some("[^ \t\n\u{c}\r]"),
+		Query::Special => Some("[^!-/:-@[-`{-~]"),
+		_ => None
+	}
+}
+
+/// Compares a length with the bounds of a range, in sql or javascript
+fn compare_length(length: &str, range: &LengthRange, and: &str) -> String {
+	match range {
+		LengthRange::Over(n) => format!("{} > {}", length, n),
+		LengthRange::Under(n) => format!("{} < {}", length, n),
+		LengthRange::AtLeast(n) => format!("{} >= {}", length, n),
+		LengthRange::AtMost(n) => format!("{} <= {}", length, n),
+		LengthRange::Between(min, max) => format!("({} >= {} {} {} <= {})", length, min, and, length, max)
+	}
+}
+
+/// Compares the number of occurrences of a quantified `contains`, in sql or
+/// javascript
+fn compare_count(count: &str, times: &Times, equals: &str) -> String {
+	match times {
+		Times::AtLeast(n) => format!("{} >= {}", count, n),
+		Times::AtMost(n) => format!("{} <= {}", count, n),
+		Times::Exactly(n) => format!("{} {} {}", count, equals, n)
+	}
+}
+
+/// The operator comparing a number with the bound of a value comparison, in
+/// sql or javascript
+fn value_operator(range: &ValueRange, equals: &str) -> String {
+	match range {
+		ValueRange::Over(n) => format!("> {}", n),
+		ValueRange::Under(n) => format!("< {}", n),
+		ValueRange::Equals(n) => format!("{} {}", equals, n)
+	}
+}
+
+fn query_to_sql(query: &Query, column: &str, dialect: SqlDialect) -> String {
+	use SqlDialect::*;
+
+	// Case-insensitive queries compare the lowercased column, sqlite's `lower`
+	// only lowercases ascii letters
+	if let Some(query) = on_lowercased(query) {
+		return query_to_sql(&query, &format!("lower({})", column), dialect);
+	}
+
+	match (query, dialect) {
+		(Query::Starts(arg), _) | (Query::Ends(arg), _) if arg.is_empty() => "1 = 1".to_string(),
+		// Custom queries call a function of the same name in the database
+		(Query::Custom(name), _) => format!("{}({})", name, column),
+		(Query::Starts(arg), Postgres) => {
+			format!("{} LIKE {} ESCAPE '\\'", column, sql_string(&format!("{}%", like_pattern(arg))))
+		},
+		(Query::Starts(arg), Sqlite) => {
+			format!("substr({}, 1, {}) = {}", column, arg.chars().count(), sql_string(arg))
+		},
+		(Query::Ends(arg), Postgres) => {
+			format!("{} LIKE {} ESCAPE '\\'", column, sql_string(&format!("%{}", like_pattern(arg))))
+		},
+		(Query::Ends(arg), Sqlite) => {
+			format!("substr({}, -{}) = {}", column, arg.chars().count(), sql_string(arg))
+		},
+		(Query::Contains(arg), Postgres) => format!("strpos({}, {}) > 0", column, sql_string(arg)),
+		(Query::Contains(arg), Sqlite) => format!("instr({}, {}) > 0", column, sql_string(arg)),
+		// `replace` removes the occurrences without overlapping, like rust
+		(Query::ContainsTimes(arg, times), _) => compare_count(
+			&format!("(length({}) - length(replace({}, {}, ''))) / {}", column, column, sql_string(arg), arg.chars().count()),
+			times,
+			"="
+		),
+		(Query::Equals(arg), _) => format!("{} = {}", column, sql_string(arg)),
+		(Query::Length(len), Postgres) => format!("octet_length({}) = {}", column, len),
+		(Query::Length(len), Sqlite) => format!("length(CAST({} AS BLOB)) = {}", column, len),
+		(Query::LengthRange(range), Postgres) => compare_length(&format!("octet_length({})", column), range, "AND"),
+		(Query::LengthRange(range), Sqlite) => compare_length(&format!("length(CAST({} AS BLOB))", column), range, "AND"),
+		// Postgres doesn't short-circuit AND, the cast has to be guarded
+		(Query::Value(range), Postgres) => format!(
+			"CASE WHEN {} ~ {} THEN CAST({} AS numeric) {} ELSE false END",
+			column, sql_string(&format!("^{}$", NUMBER)), column, value_operator(range, "=")
+		),
+		(Query::Value(range), Sqlite) => format!(
+			"({} REGEXP {} AND CAST({} AS REAL) {})",
+			column, sql_string(&format!("^{}$", NUMBER)), column, value_operator(range, "=")
+		),
+		(Query::Words(_) | Query::WordsRange(_), Postgres) => {
+			format!("{} ~ {}", column, sql_string(&format!("^(?:{})$", query_to_regex(query))))
+		},
+		(Query::Words(_) | Query::WordsRange(_), Sqlite) => {
+			format!("{} REGEXP {}", column, sql_string(&format!("^(?:{})$", query_to_regex(query))))
+		},
+		(Query::Pattern(pattern), Postgres) => {
+			format!("{} ~ {}", column, sql_string(&format!("^(?:{})$", pattern_to_regex(pattern))))
+		},
+		(Query::Pattern(pattern), Sqlite) => {
+			format!("{} REGEXP {}", column, sql_string(&format!("^(?:{})$", pattern_to_regex(pattern))))
+		},
+		(query, Postgres) => format!("{} ~ {}", column, sql_string(&format!("^{}*$", class_to_regex(query).unwrap_or_default()))),
+		(query, Sqlite) => {
+			format!("{} NOT GLOB {}", column, sql_string(&format!("*{}*", glob_complement(query).unwrap_or_default())))
+		}
+	}
+}
+
+fn ast_to_sql(ast: &AST, column: &str, dialect: SqlDialect) -> String {
+	match ast {
+		AST::Query(query) => query_to_sql(query, column, dialect),
+		AST::Not(ast) => format!("NOT ({})", ast_to_sql(ast, column, dialect)),
+		AST::BinaryExpression { left, operator, right } => {
+			let operator = match operator {
+				LogicalOperator::And => "AND",
+				LogicalOperator::Or => "OR"
+			};
+
+			format!("({} {} {})", ast_to_sql(left, column, dialect), operator, ast_to_sql(right, column, dialect))
+		}
+	}
+}
+
+/// Converts an ast into a sql predicate, see
+/// [`Expression::to_sql_predicate`](../struct.Expression.html#method.to_sql_predicate)
+pub fn to_sql(ast: &AST, column: &str, dialect: SqlDialect) -> String {
+	ast_to_sql(ast, column, dialect)
+}
+
+fn js_string(literal: &str) -> String {
+	let mut escaped = String::with_capacity(literal.len() + 2);
+	escaped.push('"');
+
+	for c in literal.chars() {
+		match c {
+			'"' => escaped.push_str("\\\""),
+			'\\' => escaped.push_str("\\\\"),
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+				escaped.push_str(&format!("\\u{{{:x}}}", c as u32))
+			},
+			c => escaped.push(c)
+		}
+	}
+
+	escaped.push('"');
+	escaped
+}
+
+fn js_regex(pattern: &str) -> String {
+	format!("/^{}$/", pattern.replace('/', "\\/"))
+}
+
+fn query_to_js(query: &Query) -> String {
+	match query {
+		Query::Starts(arg) => format!("input.startsWith({})", js_string(arg)),
+		Query::Ends(arg) => format!("input.endsWith({})", js_string(arg)),
+		Query::Contains(arg) => format!("input.includes({})", js_string(arg)),
+		Query::ContainsTimes(arg, times) => {
+			compare_count(&format!("input.split({}).length - 1", js_string(arg)), times, "===")
+		},
+		Query::Equals(arg) => format!("input === {}", js_string(arg)),
+		Query::IStarts(arg) => format!("input.toLowerCase().startsWith({})", js_string(arg)),
+		Query::IEnds(arg) => format!("input.toLowerCase().endsWith({})", js_string(arg)),
+		Query::IContains(arg) => format!("input.toLowerCase().includes({})", js_string(arg)),
+		Query::IEquals(arg) => format!("input.toLowerCase() === {}", js_string(arg)),
+		// The length is measured in bytes like in rust, not in utf-16 code units
+		Query::Length(len) => format!("new TextEncoder().encode(input).length === {}", len),
+		Query::LengthRange(range) => compare_length("new TextEncoder().encode(input).length", range, "&&"),
+		Query::Value(range) => format!("({}.test(input) && Number(input) {})", js_regex(NUMBER), value_operator(range, "===")),
+		Query::Words(_) | Query::WordsRange(_) => format!("{}.test(input)", js_regex(&format!("(?:{})", query_to_regex(query)))),
+		Query::Pattern(pattern) => {
+			format!("{}.test(input)", js_regex(&format!("(?:{})", pattern_to_regex(pattern))))
+		},
+		// Custom queries call a function of the same name in scope
+		Query::Custom(name) => format!("{}(input)", name),
+		query => format!("{}.test(input)", js_regex(&format!("{}*", class_to_regex(query).unwrap_or_default())))
+	}
+}
+
+fn ast_to_js(ast: &AST) -> String {
+	match ast {
+		AST::Query(query) => query_to_js(query),
+		AST::Not(ast) => format!("!({})", ast_to_js(ast)),
+		AST::BinaryExpression { left, operator, right } => {
+			let operator = match operator {
+				LogicalOperator::And => "&&",
+				LogicalOperator::Or => "||"
+			};
+
+			format!("({} {} {})", ast_to_js(left), operator, ast_to_js(right))
+		}
+	}
+}
+
+/// Converts an ast into a javascript arrow function, see
+/// [`Expression::to_js`](../struct.Expression.html#method.to_js)
+pub fn to_js(ast: &AST) -> String {
+	format!("(input) => {}", ast_to_js(ast))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{to_js, to_regex, to_sql, SqlDialect};
+	use crate::into_ast;
+
+	macro_rules! to_regex_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (source, expected) = $value;
+					pretty_assertions::assert_eq!(to_regex(&into_ast(source).unwrap()), expected);
+				}
+			)*
+		}
+	}
+
+	mod it_converts_queries {
+		use super::*;
+
+		to_regex_tests! {
+			starts: ("starts \"foo\"", "^(?:foo.*)$"),
+			ends: ("ends \"foo\"", "^(?:.*foo)$"),
+			contains: ("contains \"foo\"", "^(?:.*foo.*)$"),
+			equals: ("equals \"foo\"", "^(?:foo)$"),
+			length: ("length 5", "^(?:.{5})$"),
+			numeric: ("numeric", "^(?:[0-9]*)$"),
+			alpha: ("alpha", "^(?:[A-Za-z]*)$"),
+			alphanumeric: ("alphanumeric", "^(?:[A-Za-z0-9]*)$"),
+			special: ("special", "^(?:[!-/:-@\\[-`{-~]*)$"),
+			uppercase: ("uppercase and length 3", "^(?:[A-Z]{3})$"),
+			whitespace: ("whitespace", "^(?:[ \\t\\n\\f\\r]*)$"),
+			pattern: ("@uuid", "^(?:[0-9A-Fa-f]{8}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{4}-[0-9A-Fa-f]{12})$"),
+			escapes_literals: ("contains \"a.b*c\"", "^(?:.*a\\.b\\*c.*)$"),
+			contains_times: ("contains \",\" exactly 2 times", "^(?:(?!(?:.*?,){3})(?:.*?,){2}.*)$"),
+			contains_at_most: ("contains \"a\" at most 1 time", "^(?:(?!(?:.*?a){2}).*)$"),
+			words: ("words 0 or words between 2 and 3", "^(?:(?:[ \\t\\n\\f\\r]*|[ \\t\\n\\f\\r]*[^ \\t\\n\\f\\r]+(?:[ \\t\\n\\f\\r]+[^ \\t\\n\\f\\r]+){1,2}[ \\t\\n\\f\\r]*))$"),
+			value_equals: ("value equals 5", "^(?:(?:0*5(?:\\.0+)?))$"),
+			value_over: ("value over 9", "^(?:(?:0*(?:[1-9][0-9]{1,})(?:\\.[0-9]+)?|0*9\\.[0-9]*[1-9][0-9]*))$"),
+			value_under: ("value under 0", "^(?:(?:-(?:0*[1-9][0-9]*(?:\\.[0-9]+)?|0+\\.[0-9]*[1-9][0-9]*)))$"),
+		}
+	}
+
+	mod it_converts_operators {
+		use super::*;
+
+		to_regex_tests! {
+			or: ("numeric or alpha", "^(?:(?:[0-9]*|[A-Za-z]*))$"),
+			and: ("starts \"a\" and length 5", "^(?:(?=(?:a.*)$)(?:.{5}))$"),
+			and_chain: ("starts \"a\" and ends \"b\" and alpha", "^(?:(?=(?:a.*)$)(?:(?=(?:.*b)$)(?:[A-Za-z]*)))$"),
+			class_with_length: ("numeric and length 5", "^(?:[0-9]{5})$"),
+			length_with_class: ("length 3 and alpha", "^(?:[A-Za-z]{3})$"),
+			length_range: ("length between 2 and 4", "^(?:.{2,4})$"),
+			ignoring_case: ("istarts \"Ab\"", "^(?:(?i:ab.*))$"),
+			class_with_length_range: ("numeric and length over 4", "^(?:[0-9]{5,})$"),
+		}
+	}
+
+	macro_rules! to_sql_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (source, dialect, expected) = $value;
+					pretty_assertions::assert_eq!(to_sql(&into_ast(source).unwrap(), "name", dialect), expected);
+				}
+			)*
+		}
+	}
+
+	mod it_converts_to_postgres {
+		use super::*;
+
+		to_sql_tests! {
+			starts: ("starts \"a_b\"", SqlDialect::Postgres, "name LIKE 'a\\_b%' ESCAPE '\\'"),
+			ends: ("ends \"it's\"", SqlDialect::Postgres, "name LIKE '%it''s' ESCAPE '\\'"),
+			contains: ("contains \"%\"", SqlDialect::Postgres, "strpos(name, '%') > 0"),
+			length: ("length 5", SqlDialect::Postgres, "octet_length(name) = 5"),
+			ignoring_case: ("icontains \"Err\"", SqlDialect::Postgres, "strpos(lower(name), 'err') > 0"),
+			length_range: ("length between 2 and 8", SqlDialect::Postgres, "(octet_length(name) >= 2 AND octet_length(name) <= 8)"),
+			numeric: ("numeric", SqlDialect::Postgres, "name ~ '^[0-9]*$'"),
+			contains_times: ("contains \"ab\" at least 2 times", SqlDialect::Postgres, "(length(name) - length(replace(name, 'ab', ''))) / 2 >= 2"),
+			value: ("value over 9", SqlDialect::Postgres, "CASE WHEN name ~ '^-?[0-9]+(?:\\.[0-9]+)?$' THEN CAST(name AS numeric) > 9 ELSE false END"),
+			operators: ("equals \"a\" or alpha and length 2", SqlDialect::Postgres, "(name = 'a' OR (name ~ '^[A-Za-z]*$' AND octet_length(name) = 2))"),
+		}
+	}
+
+	mod it_converts_to_sqlite {
+		use super::*;
+
+		to_sql_tests! {
+			starts: ("starts \"ab\"", SqlDialect::Sqlite, "substr(name, 1, 2) = 'ab'"),
+			ends: ("ends \"ab\"", SqlDialect::Sqlite, "substr(name, -2) = 'ab'"),
+			ends_empty: ("ends \"\"", SqlDialect::Sqlite, "1 = 1"),
+			contains: ("contains \"ab\"", SqlDialect::Sqlite, "instr(name, 'ab') > 0"),
+			length: ("length 5", SqlDialect::Sqlite, "length(CAST(name AS BLOB)) = 5"),
+			length_range: ("length under 5", SqlDialect::Sqlite, "length(CAST(name AS BLOB)) < 5"),
+			numeric: ("numeric", SqlDialect::Sqlite, "name NOT GLOB '*[^0-9]*'"),
+			words: ("words under 2", SqlDialect::Sqlite, "name REGEXP '^(?:[ \\t\\n\\f\\r]*(?:[^ \\t\\n\\f\\r]+(?:[ \\t\\n\\f\\r]+[^ \\t\\n\\f\\r]+){0}[ \\t\\n\\f\\r]*)?)$'"),
+			value: ("value under 10", SqlDialect::Sqlite, "(name REGEXP '^-?[0-9]+(?:\\.[0-9]+)?$' AND CAST(name AS REAL) < 10)"),
+		}
+	}
+
+	macro_rules! to_js_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (source, expected) = $value;
+					pretty_assertions::assert_eq!(to_js(&into_ast(source).unwrap()), expected);
+				}
+			)*
+		}
+	}
+
+	mod it_converts_to_js {
+		use super::*;
+
+		to_js_tests! {
+			starts: ("starts \"a\\\"", "(input) => input.startsWith(\"a\\\\\")"),
+			equals: ("equals \"a\"", "(input) => input === \"a\""),
+			length: ("length 5", "(input) => new TextEncoder().encode(input).length === 5"),
+			length_range: ("length at most 5", "(input) => new TextEncoder().encode(input).length <= 5"),
+			ignoring_case: ("iequals \"OK\"", "(input) => input.toLowerCase() === \"ok\""),
+			special: ("special", "(input) => /^[!-\\/:-@\\[-`{-~]*$/.test(input)"),
+			contains_times: ("contains \",\" exactly 2 times", "(input) => input.split(\",\").length - 1 === 2"),
+			value: ("value equals 5", "(input) => (/^-?[0-9]+(?:\\.[0-9]+)?$/.test(input) && Number(input) === 5)"),
+			operators: ("ends \"a\" or numeric and contains \"1\"", "(input) => (input.endsWith(\"a\") || (/^[0-9]*$/.test(input) && input.includes(\"1\")))"),
+		}
+	}
+
+	mod it_converts_negations {
+		use super::*;
+		use crate::parser::AST;
+		use pretty_assertions::assert_eq;
+
+		fn negated(source: &str) -> AST {
+			AST::Not(Box::new(into_ast(source).unwrap()))
+		}
+
+		#[test]
+		fn to_regex_using_a_negative_lookahead() {
+			assert_eq!(to_regex(&negated("starts \"a\"")), "^(?:(?!(?:a.*)$).*)$");
+		}
+
+		#[test]
+		fn to_sql() {
+			assert_eq!(
+				super::to_sql(&negated("equals \"a\" or length 2"), "name", SqlDialect::Postgres),
+				"NOT ((name = 'a' OR octet_length(name) = 2))"
+			);
+		}
+
+		#[test]
+		fn to_js() {
+			assert_eq!(super::to_js(&negated("equals \"a\"")), "(input) => !(input === \"a\")");
+		}
+	}
+}
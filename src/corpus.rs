@@ -0,0 +1,301 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::{i18n, Document, Error, Expression};
+
+/// How many lines of a document have to match an expression
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quantifier {
+    /// At least one line, written `any line`
+    Any,
+    /// Every line, written `every line`
+    Every,
+    /// Not a single line, written `no line`
+    No,
+}
+
+impl Quantifier {
+    pub fn as_keyword(&self) -> &'static str {
+        match self {
+            Quantifier::Any => "any line",
+            Quantifier::Every => "every line",
+            Quantifier::No => "no line",
+        }
+    }
+
+    /// Splits a leading quantifier like `any line` off a text
+    fn strip(text: &str) -> Option<(Self, &str)> {
+        let mut words = text.trim_start().splitn(3, char::is_whitespace);
+
+        let quantifier = match words.next()? {
+            "any" => Quantifier::Any,
+            "every" => Quantifier::Every,
+            "no" => Quantifier::No,
+            _ => return None,
+        };
+
+        match (words.next()?, words.next()) {
+            ("line", Some(rest)) => Some((quantifier, rest)),
+            _ => None,
+        }
+    }
+}
+
+/// The error of a [`DocumentRule`] which can't be parsed
+#[derive(Clone, Debug)]
+pub enum DocumentRuleError {
+    /// A condition doesn't start with a quantifier
+    Quantifier(String),
+    /// The expression of a condition is invalid
+    Expression(Error),
+}
+
+impl fmt::Display for DocumentRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DocumentRuleError::Quantifier(condition) => {
+                write!(f, "{}", i18n::message("corpus.quantifier", condition))
+            }
+            DocumentRuleError::Expression(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for DocumentRuleError {}
+
+impl From<Error> for DocumentRuleError {
+    fn from(err: Error) -> Self {
+        DocumentRuleError::Expression(err)
+    }
+}
+
+/// A rule over the lines of a whole document, every condition of the rule
+/// has to hold
+///
+/// ```rust
+/// use srch::DocumentRule;
+///
+/// let rule = DocumentRule::parse("any line equals \"BEGIN\" and any line equals \"END\"").unwrap();
+///
+/// assert!(rule.matches("BEGIN\nstep 1\nEND\n"));
+/// assert!(!rule.matches("BEGIN\nstep 1\n"));
+/// ```
+#[derive(Clone, Default)]
+pub struct DocumentRule {
+    conditions: Vec<(Quantifier, Expression)>,
+}
+
+impl DocumentRule {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses conditions of the form `<quantifier> line <expression>` joined
+    /// using `and`, e.g. `any line equals "BEGIN" and no line contains "TODO"`.
+    /// An `and` which isn't followed by a quantifier belongs to the expression
+    pub fn parse(source: &str) -> Result<Self, DocumentRuleError> {
+        let mut conditions = vec![];
+        let mut start = 0;
+        let mut in_string = false;
+
+        for (i, c) in source.char_indices() {
+            match c {
+                '"' => in_string = !in_string,
+                c if c.is_whitespace() && !in_string => {
+                    let rest = source[i..].trim_start();
+
+                    if let Some(next) = rest.strip_prefix("and") {
+                        if next.starts_with(char::is_whitespace)
+                            && Quantifier::strip(next).is_some()
+                        {
+                            conditions.push(&source[start..i]);
+                            start = source.len() - next.len();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        conditions.push(&source[start..]);
+
+        let mut rule = Self::new();
+
+        for condition in conditions {
+            let (quantifier, expression) = Quantifier::strip(condition)
+                .ok_or_else(|| DocumentRuleError::Quantifier(condition.trim().to_string()))?;
+
+            rule = rule.with(quantifier, Expression::new(expression)?);
+        }
+
+        Ok(rule)
+    }
+
+    /// Adds the condition that `quantifier` lines match `expression`
+    pub fn with(mut self, quantifier: Quantifier, expression: Expression) -> Self {
+        self.conditions.push((quantifier, expression));
+        self
+    }
+
+    /// Adds the condition that at least one line matches `expression`
+    pub fn any(self, expression: Expression) -> Self {
+        self.with(Quantifier::Any, expression)
+    }
+
+    /// Adds the condition that every line matches `expression`
+    pub fn every(self, expression: Expression) -> Self {
+        self.with(Quantifier::Every, expression)
+    }
+
+    /// Adds the condition that no line matches `expression`
+    pub fn none(self, expression: Expression) -> Self {
+        self.with(Quantifier::No, expression)
+    }
+
+    /// Whether the lines of `text` satisfy every condition, the lines are
+    /// read once and only until the result is known
+    pub fn matches(&self, text: &str) -> bool {
+        // Whether a line matching the expression of a condition was seen
+        let mut seen = vec![false; self.conditions.len()];
+
+        for line in Document::new(text).lines() {
+            for ((quantifier, expression), seen) in self.conditions.iter().zip(seen.iter_mut()) {
+                match quantifier {
+                    Quantifier::Any if !*seen => *seen = expression.matches(line),
+                    Quantifier::Every if !expression.matches(line) => return false,
+                    Quantifier::No if expression.matches(line) => return false,
+                    _ => {}
+                }
+            }
+        }
+
+        self.conditions
+            .iter()
+            .zip(seen)
+            .all(|((quantifier, _), seen)| *quantifier != Quantifier::Any || seen)
+    }
+}
+
+/// A set of named documents, e.g. the files of a config tree, which are
+/// matched against [`DocumentRule`]s one document at a time
+///
+/// ```rust
+/// use srch::{Corpus, DocumentRule};
+///
+/// let mut corpus = Corpus::new();
+/// corpus.add("a.conf", "BEGIN\nEND\n");
+/// corpus.add("b.conf", "BEGIN\n");
+///
+/// let rule = DocumentRule::parse("any line equals \"BEGIN\" and no line equals \"END\"").unwrap();
+/// assert_eq!(corpus.matching(&rule), vec!["b.conf"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Corpus {
+    documents: Vec<(String, String)>,
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads every file into a corpus, the documents are named by their path
+    pub fn from_paths<I, P>(paths: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut corpus = Self::new();
+
+        for path in paths {
+            let path = path.as_ref();
+            corpus.add(path.display().to_string(), fs::read_to_string(path)?);
+        }
+
+        Ok(corpus)
+    }
+
+    pub fn add(&mut self, name: impl Into<String>, text: impl Into<String>) {
+        self.documents.push((name.into(), text.into()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// The names of the documents satisfying `rule`, in the order they were
+    /// added
+    pub fn matching(&self, rule: &DocumentRule) -> Vec<&str> {
+        self.documents
+            .iter()
+            .filter(|(_, text)| rule.matches(text))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentRule, DocumentRuleError, Quantifier};
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    fn rule(source: &str) -> DocumentRule {
+        DocumentRule::parse(source).unwrap()
+    }
+
+    #[test]
+    fn evaluates_quantifiers() {
+        let text = "port 80\nhost localhost\n";
+
+        assert_eq!(rule("any line ends \"80\"").matches(text), true);
+        assert_eq!(rule("every line contains \" \"").matches(text), true);
+        assert_eq!(rule("every line starts \"port\"").matches(text), false);
+        assert_eq!(rule("no line contains \"TODO\"").matches(text), true);
+        assert_eq!(rule("no line starts \"host\"").matches(text), false);
+    }
+
+    #[test]
+    fn handles_empty_documents() {
+        assert_eq!(rule("any line numeric").matches(""), false);
+        assert_eq!(rule("every line numeric").matches(""), true);
+        assert_eq!(rule("no line numeric").matches(""), true);
+    }
+
+    #[test]
+    fn keeps_and_of_expressions() {
+        let rule = rule("any line numeric and length 2 and no line equals \"and no line x\"");
+
+        assert_eq!(rule.matches("12\n"), true);
+        assert_eq!(rule.matches("123\n"), false);
+        assert_eq!(rule.matches("12\nand no line x\n"), false);
+    }
+
+    #[test]
+    fn builds_rules() {
+        let rule = DocumentRule::new()
+            .any(Expression::new("equals \"BEGIN\"").unwrap())
+            .with(Quantifier::Every, Expression::new("alpha").unwrap());
+
+        assert_eq!(rule.matches("BEGIN\nEND\n"), true);
+        assert_eq!(rule.matches("BEGIN\n1\n"), false);
+    }
+
+    #[test]
+    fn requires_quantifiers() {
+        assert!(matches!(
+            DocumentRule::parse("numeric"),
+            Err(DocumentRuleError::Quantifier(condition)) if condition == "numeric"
+        ));
+        assert!(matches!(
+            DocumentRule::parse("any line numeric and some line alpha"),
+            Err(DocumentRuleError::Expression(_))
+        ));
+    }
+}
@@ -0,0 +1,253 @@
+use std::fmt;
+use std::ops::Range;
+
+use miette::{Diagnostic, LabeledSpan, SourceCode};
+
+use crate::error::Error;
+use crate::lexer::{self, Token};
+use crate::parser;
+use crate::pattern::Pattern;
+
+fn code(error: &Error) -> &'static str {
+    match error {
+        Error::LexicalError(err) => match err {
+            lexer::Error::UnknownSyntax => "srch::unknown_syntax",
+            lexer::Error::InternalError => "srch::unknown_keyword",
+            lexer::Error::ToManyArguments => "srch::too_many_arguments",
+            lexer::Error::NoLeadingZeros => "srch::leading_zeros",
+            lexer::Error::UnclosedString => "srch::unclosed_string",
+            lexer::Error::ExpectedString => "srch::expected_string",
+            lexer::Error::ExpectedInteger => "srch::expected_integer",
+            lexer::Error::ExpectedQuery => "srch::expected_query",
+            lexer::Error::ExpectedOperator => "srch::expected_operator",
+            lexer::Error::UnknownPattern(_) => "srch::unknown_pattern",
+            lexer::Error::EmptyRange => "srch::empty_range",
+        },
+        Error::ParserError(err) => match err {
+            parser::Error::ExpectedQuery => "srch::expected_query",
+            parser::Error::ExpectedOperator => "srch::expected_operator",
+            parser::Error::EmptyExpression => "srch::empty_expression",
+            parser::Error::ExpressionTooComplex(_) => "srch::too_complex",
+            parser::Error::InternalError => "srch::internal_error",
+        },
+        Error::LimitExceeded(_) => "srch::limit_exceeded",
+        Error::IncompatibleOptions => "srch::incompatible_options",
+    }
+}
+
+fn help(error: &Error) -> Option<String> {
+    let help = match error {
+        Error::LexicalError(lexer::Error::InternalError) => {
+            "queries are `starts`, `ends`, `contains`, `equals`, their case-insensitive \
+             variants like `icontains`, `length`, `words`, `value`, `numeric`, `alpha`, \
+             `alphanumeric`, `uppercase`, `lowercase`, `whitespace`, `special` and named \
+             patterns, they are combined using `and` and `or`"
+                .to_string()
+        }
+        Error::LexicalError(lexer::Error::NoLeadingZeros) => "remove the leading zeros".to_string(),
+        Error::LexicalError(lexer::Error::UnclosedString) => "add a closing `\"`".to_string(),
+        Error::LexicalError(lexer::Error::ExpectedString) => {
+            "strings are enclosed in double quotes, e.g. `starts \"foo\"`".to_string()
+        }
+        Error::LexicalError(lexer::Error::ExpectedInteger) => {
+            "lengths are positive integers or ranges, e.g. `length 5` or `length at least 5`"
+                .to_string()
+        }
+        Error::LexicalError(lexer::Error::EmptyRange) => {
+            "the shorter length comes first, e.g. `length between 2 and 8`".to_string()
+        }
+        Error::LexicalError(lexer::Error::UnknownPattern(_)) => format!(
+            "the available patterns are {}",
+            Pattern::ALL
+                .iter()
+                .map(|pattern| format!("`{}`", pattern.as_keyword()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Error::ParserError(parser::Error::ExpectedQuery) => {
+            "operators have to be followed by a query, e.g. `numeric and length 5`".to_string()
+        }
+        Error::ParserError(parser::Error::ExpectedOperator) => {
+            "queries have to be combined using `and` or `or`".to_string()
+        }
+        Error::ParserError(parser::Error::EmptyExpression) => {
+            "an expression consists of at least one query, e.g. `numeric`".to_string()
+        }
+        Error::ParserError(parser::Error::ExpressionTooComplex(_)) => {
+            "split the expression into several expressions, e.g. the rules of a rule set"
+                .to_string()
+        }
+        _ => return None,
+    };
+
+    Some(help)
+}
+
+impl Diagnostic for Error {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(code(self)))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        help(self).map(|help| Box::new(help) as Box<dyn fmt::Display>)
+    }
+}
+
+/// Finds the part of `source` which caused `error`
+fn locate(error: &Error, source: &str) -> Range<usize> {
+    let tokens = match lexer::lex_spanned(source) {
+        Ok(tokens) => tokens,
+        Err((_, span)) => return span,
+    };
+
+    if let Error::LexicalError(_)
+    | Error::ParserError(parser::Error::EmptyExpression)
+    | Error::ParserError(parser::Error::ExpressionTooComplex(_))
+    | Error::LimitExceeded(_)
+    | Error::IncompatibleOptions = error
+    {
+        return 0..source.len();
+    }
+
+    // Queries and operators have to alternate, starting and ending with a
+    // query. A trailing operator is misplaced as well. A `not` belongs to the
+    // query following it
+    let tokens = tokens
+        .into_iter()
+        .filter(|(token, _)| *token != Token::Not)
+        .collect::<Vec<_>>();
+
+    let misplaced = tokens
+        .iter()
+        .enumerate()
+        .find(|(i, (token, _))| matches!(token, Token::Query(_)) != (i % 2 == 0));
+
+    match (misplaced, tokens.last()) {
+        (Some((_, (_, span))), _) => span.clone(),
+        (None, Some((_, span))) => span.clone(),
+        (None, None) => 0..source.len(),
+    }
+}
+
+/// An [`Error`] together with the source of the expression, so the part of
+/// the expression which caused the error can be shown
+///
+/// ```rust
+/// let source = "numeric and";
+/// let err = srch::Expression::new(source).err().unwrap().with_source(source);
+///
+/// println!("{:?}", miette::Report::new(err));
+/// ```
+#[derive(Debug)]
+pub struct SourceError {
+    error: Error,
+    source: String,
+    span: Range<usize>,
+}
+
+impl SourceError {
+    pub fn error(&self) -> &Error {
+        &self.error
+    }
+
+    /// The byte range of the source which caused the error
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+}
+
+impl Error {
+    /// Attaches the source of the expression which caused the error
+    pub fn with_source(self, source: &str) -> SourceError {
+        let span = locate(&self, source);
+
+        SourceError {
+            error: self,
+            source: source.to_string(),
+            span,
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl Diagnostic for SourceError {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = match &self.error {
+            Error::LexicalError(err) => err.to_string(),
+            Error::ParserError(err) => err.to_string(),
+            Error::LimitExceeded(err) => err.to_string(),
+            Error::IncompatibleOptions => self.error.to_string(),
+        };
+
+        Some(Box::new(std::iter::once(LabeledSpan::new_with_span(
+            Some(label),
+            self.span.clone(),
+        ))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::Diagnostic;
+
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    fn diagnose(source: &str) -> (String, std::ops::Range<usize>) {
+        let err = Expression::new(source).err().unwrap().with_source(source);
+        let code = err.code().unwrap().to_string();
+        (code, err.span())
+    }
+
+    #[test]
+    fn locates_lexical_errors() {
+        assert_eq!(
+            diagnose("numeric and foo"),
+            ("srch::unknown_keyword".to_string(), 12..15)
+        );
+        assert_eq!(
+            diagnose("starts \"abc"),
+            ("srch::unclosed_string".to_string(), 0..11)
+        );
+        assert_eq!(
+            diagnose("@nope"),
+            ("srch::unknown_pattern".to_string(), 0..5)
+        );
+    }
+
+    #[test]
+    fn locates_parser_errors() {
+        assert_eq!(
+            diagnose("numeric alpha"),
+            ("srch::expected_operator".to_string(), 8..13)
+        );
+        assert_eq!(
+            diagnose("numeric and"),
+            ("srch::expected_query".to_string(), 8..11)
+        );
+        assert_eq!(
+            diagnose("and numeric"),
+            ("srch::expected_query".to_string(), 0..3)
+        );
+        assert_eq!(diagnose("  "), ("srch::empty_expression".to_string(), 0..2));
+    }
+}
@@ -0,0 +1,283 @@
+use std::ops::Range;
+
+use crate::Expression;
+
+/// A text which is split into the records the cli matches expressions
+/// against, e.g. lines or words
+///
+/// ```rust
+/// use srch::{Document, Expression};
+///
+/// let document = Document::new("id 12345\nname jane\n");
+/// let expr = Expression::new("numeric").unwrap();
+///
+/// assert_eq!(document.words().filter(&expr).collect::<Vec<_>>(), vec!["12345"]);
+/// assert_eq!(document.lines().reject(&expr).count(), 2);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Document<'a> {
+    text: &'a str,
+}
+
+impl<'a> Document<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    /// The lines of the text without their line terminators (`\n` or
+    /// `\r\n`), like the `line` mode
+    pub fn lines(&self) -> Records<'a> {
+        Records::new(self.text.lines())
+    }
+
+    /// The words of the text, which are separated by whitespace, like the
+    /// `word` mode
+    pub fn words(&self) -> Records<'a> {
+        self.words_with(Tokenizer::default())
+    }
+
+    /// The words of the text split by a [`Tokenizer`], like the `word` mode
+    /// with the tokenizer flags
+    ///
+    /// ```rust
+    /// use srch::{Document, Tokenizer};
+    ///
+    /// let tokenizer = Tokenizer {
+    ///     strip_punctuation: true,
+    ///     ..Tokenizer::default()
+    /// };
+    ///
+    /// let document = Document::new("error: disk full (sda)");
+    /// assert_eq!(
+    ///     document.words_with(tokenizer).collect::<Vec<_>>(),
+    ///     vec!["error", "disk", "full", "sda"]
+    /// );
+    /// ```
+    pub fn words_with(&self, tokenizer: Tokenizer) -> Records<'a> {
+        Records::new(tokenizer.split(self.text).into_iter())
+    }
+
+    /// The records of the text separated by `delimiter`, like the `delim`
+    /// mode. A trailing line terminator of the last record is removed and an
+    /// empty last record is skipped
+    pub fn records(&self, delimiter: &'a str) -> Records<'a> {
+        let mut records = self.text.split(delimiter).collect::<Vec<_>>();
+
+        if let Some(last) = records.pop() {
+            let last = last
+                .strip_suffix('\n')
+                .map(|last| last.strip_suffix('\r').unwrap_or(last))
+                .unwrap_or(last);
+
+            if !last.is_empty() {
+                records.push(last);
+            }
+        }
+
+        Records::new(records.into_iter())
+    }
+}
+
+/// How a text is split into words, see [`Document::words_with`]. The
+/// default splits at ascii whitespace like [`Document::words`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tokenizer {
+    /// Splits at any unicode whitespace, e.g. non-breaking spaces, instead of
+    /// ascii whitespace only
+    pub unicode_whitespace: bool,
+    /// Removes the characters which are neither letters nor digits from the
+    /// start and end of every word, so "error:" becomes "error". Words made
+    /// only of such characters are dropped
+    pub strip_punctuation: bool,
+    /// Keeps the text between the words as tokens of their own, so the
+    /// tokens add up to the whole text
+    pub keep_delimiters: bool,
+}
+
+impl Tokenizer {
+    fn is_whitespace(&self, c: char) -> bool {
+        match self.unicode_whitespace {
+            true => c.is_whitespace(),
+            false => c.is_ascii_whitespace(),
+        }
+    }
+
+    /// The byte ranges of the words of a text, never including delimiters
+    pub fn word_spans(&self, text: &str) -> Vec<Range<usize>> {
+        let mut spans = vec![];
+        let mut start = None;
+
+        for (i, c) in text
+            .char_indices()
+            .chain(std::iter::once((text.len(), ' ')))
+        {
+            match (self.is_whitespace(c), start) {
+                (true, Some(word)) => {
+                    spans.push(word..i);
+                    start = None;
+                }
+                (false, None) => start = Some(i),
+                _ => {}
+            }
+        }
+
+        if !self.strip_punctuation {
+            return spans;
+        }
+
+        let is_punctuation = |c: char| !c.is_alphanumeric();
+
+        spans
+            .into_iter()
+            .map(|span| {
+                let word = &text[span.clone()];
+                let start = span.start + word.len() - word.trim_start_matches(is_punctuation).len();
+                let end = span.end - (word.len() - word.trim_end_matches(is_punctuation).len());
+                start..end.max(start)
+            })
+            .filter(|span| !span.is_empty())
+            .collect()
+    }
+
+    /// The words of a text, with the delimiters between them if
+    /// `keep_delimiters` is set
+    pub fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        let spans = self.word_spans(text);
+
+        if !self.keep_delimiters {
+            return spans.into_iter().map(|span| &text[span]).collect();
+        }
+
+        let mut tokens = Vec::with_capacity(spans.len() * 2 + 1);
+        let mut end = 0;
+
+        for span in spans {
+            if span.start > end {
+                tokens.push(&text[end..span.start]);
+            }
+
+            end = span.end;
+            tokens.push(&text[span]);
+        }
+
+        if end < text.len() {
+            tokens.push(&text[end..]);
+        }
+
+        tokens
+    }
+}
+
+/// The records of a [`Document`]
+pub struct Records<'a> {
+    iter: Box<dyn Iterator<Item = &'a str> + 'a>,
+}
+
+impl<'a> Records<'a> {
+    fn new(iter: impl Iterator<Item = &'a str> + 'a) -> Self {
+        Self {
+            iter: Box::new(iter),
+        }
+    }
+
+    /// Keeps only the records matching `expression`, like `srch for`
+    pub fn filter(self, expression: &'a Expression) -> Records<'a> {
+        Records::new(self.iter.filter(move |record| expression.matches(record)))
+    }
+
+    /// Keeps only the records not matching `expression`, like `srch not`
+    pub fn reject(self, expression: &'a Expression) -> Records<'a> {
+        Records::new(self.iter.filter(move |record| !expression.matches(record)))
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Document, Tokenizer};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn splits_lines() {
+        let document = Document::new("a\r\nb\n\nc\n");
+        assert_eq!(
+            document.lines().collect::<Vec<_>>(),
+            vec!["a", "b", "", "c"]
+        );
+    }
+
+    #[test]
+    fn splits_words() {
+        let document = Document::new("  a b\tc\n d ");
+        assert_eq!(
+            document.words().collect::<Vec<_>>(),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn splits_words_using_tokenizers() {
+        let document = Document::new("\"error:\"\u{a0}disk -- full.");
+        let words = |tokenizer| document.words_with(tokenizer).collect::<Vec<_>>();
+
+        let unicode = Tokenizer {
+            unicode_whitespace: true,
+            ..Tokenizer::default()
+        };
+
+        let stripped = Tokenizer {
+            strip_punctuation: true,
+            ..unicode
+        };
+
+        let delimited = Tokenizer {
+            keep_delimiters: true,
+            ..stripped
+        };
+
+        assert_eq!(
+            words(Tokenizer::default()),
+            vec!["\"error:\"\u{a0}disk", "--", "full."]
+        );
+        assert_eq!(words(unicode), vec!["\"error:\"", "disk", "--", "full."]);
+        assert_eq!(words(stripped), vec!["error", "disk", "full"]);
+        assert_eq!(
+            words(delimited),
+            vec!["\"", "error", ":\"\u{a0}", "disk", " -- ", "full", "."]
+        );
+    }
+
+    #[test]
+    fn splits_records() {
+        let document = Document::new("a;;b;c\n");
+        assert_eq!(
+            document.records(";").collect::<Vec<_>>(),
+            vec!["a", "", "b", "c"]
+        );
+
+        let document = Document::new("a\0b\0");
+        assert_eq!(document.records("\0").collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn filters_and_rejects_records() {
+        let expr = crate::Expression::new("starts \"a\"").unwrap();
+        let document = Document::new("ab,ba,ac");
+
+        assert_eq!(
+            document.records(",").filter(&expr).collect::<Vec<_>>(),
+            vec!["ab", "ac"]
+        );
+        assert_eq!(
+            document.records(",").reject(&expr).collect::<Vec<_>>(),
+            vec!["ba"]
+        );
+    }
+}
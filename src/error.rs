@@ -1,4 +1,7 @@
-use crate::{lexer, parser};
+use std::fmt;
+
+use crate::limits::LimitError;
+use crate::{i18n, lexer, parser};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -6,6 +9,12 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     LexicalError(lexer::Error),
     ParserError(parser::Error),
+    /// The expression exceeds the limits it was compiled with, see
+    /// [`Expression::new_untrusted`](crate::Expression::new_untrusted)
+    LimitExceeded(LimitError),
+    /// Expressions matched with different [`MatchOptions`](crate::MatchOptions)
+    /// can't be combined, see [`Expression::and`](crate::Expression::and)
+    IncompatibleOptions,
 }
 
 impl From<lexer::Error> for Error {
@@ -19,3 +28,28 @@ impl From<parser::Error> for Error {
         Error::ParserError(err)
     }
 }
+
+impl From<LimitError> for Error {
+    fn from(err: LimitError) -> Self {
+        Error::LimitExceeded(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::LexicalError(err) => {
+                write!(f, "{}", i18n::message("error.lexical", &err.to_string()))
+            }
+            Error::ParserError(err) => {
+                write!(f, "{}", i18n::message("error.syntax", &err.to_string()))
+            }
+            Error::LimitExceeded(err) => {
+                write!(f, "{}", i18n::message("error.limit", &err.to_string()))
+            }
+            Error::IncompatibleOptions => write!(f, "{}", i18n::message("error.options", "")),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
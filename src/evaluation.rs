@@ -0,0 +1,314 @@
+use std::ops::Range;
+
+use crate::logical_operator::LogicalOperator;
+use crate::options::MatchOptions;
+use crate::parser::AST;
+use crate::query::Query;
+
+/// The result of matching an expression against an input, including the
+/// verdict of every node of the expression and the parts of the input which
+/// made it match
+///
+/// ```rust
+/// let expr = srch::Expression::new("contains \"err\" and length 9").unwrap();
+/// let result = expr.evaluate("an error!");
+///
+/// assert!(result.matched);
+/// assert_eq!(result.evidence(), vec![0..9]);
+/// assert_eq!(result.root.children[0].evidence, vec![3..6]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Match {
+    pub input: String,
+    pub matched: bool,
+    pub root: Verdict,
+}
+
+impl Match {
+    /// The byte ranges of the input which made the expression match, empty
+    /// if it doesn't match
+    pub fn evidence(&self) -> Vec<Range<usize>> {
+        self.root.evidence.clone()
+    }
+
+    /// Serializes the result as a single line of json, in the same layout as
+    /// its `serde` implementation
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"input\":{},\"matched\":{},\"root\":{}}}",
+            json_string(&self.input),
+            self.matched,
+            self.root.to_json()
+        )
+    }
+}
+
+/// The verdict of a single node of an expression, `node` is the query or
+/// operator (`and`, `or` or `not`) of the node
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Verdict {
+    pub node: String,
+    pub matched: bool,
+    /// The sorted, non-overlapping byte ranges of the input which made the
+    /// node match. Negated nodes have no evidence, as the absence of a match
+    /// can't be located
+    pub evidence: Vec<Range<usize>>,
+    pub children: Vec<Verdict>,
+}
+
+impl Verdict {
+    /// Combines the verdicts of the operands of `operator`
+    pub(crate) fn combine(operator: LogicalOperator, children: Vec<Verdict>) -> Self {
+        let (node, matched) = match operator {
+            LogicalOperator::And => ("and", children.iter().all(|child| child.matched)),
+            LogicalOperator::Or => ("or", children.iter().any(|child| child.matched)),
+        };
+
+        let evidence = match matched {
+            true => merge(
+                children
+                    .iter()
+                    .filter(|child| child.matched)
+                    .flat_map(|child| child.evidence.iter().cloned())
+                    .collect(),
+            ),
+            false => vec![],
+        };
+
+        Self {
+            node: node.to_string(),
+            matched,
+            evidence,
+            children,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let evidence = self
+            .evidence
+            .iter()
+            .map(|span| format!("{{\"start\":{},\"end\":{}}}", span.start, span.end))
+            .collect::<Vec<_>>();
+
+        let children = self
+            .children
+            .iter()
+            .map(Verdict::to_json)
+            .collect::<Vec<_>>();
+
+        format!(
+            "{{\"node\":{},\"matched\":{},\"evidence\":[{}],\"children\":[{}]}}",
+            json_string(&self.node),
+            self.matched,
+            evidence.join(","),
+            children.join(",")
+        )
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+/// Sorts spans and merges the overlapping ones
+fn merge(mut spans: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    spans.sort_by_key(|span| (span.start, span.end));
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(spans.len());
+
+    for span in spans {
+        match merged.last_mut() {
+            Some(last) if span.start <= last.end => last.end = last.end.max(span.end),
+            _ => merged.push(span),
+        }
+    }
+
+    merged
+}
+
+/// The parts of an input a matching query looked at
+fn locate(query: &Query, input: &str) -> Vec<Range<usize>> {
+    let span = match query {
+        Query::Contains(arg) | Query::ContainsTimes(arg, _) if !arg.is_empty() => {
+            return input
+                .match_indices(&**arg)
+                .map(|(start, found)| start..start + found.len())
+                .collect()
+        }
+        Query::Starts(arg) => 0..arg.len(),
+        Query::Ends(arg) => input.len() - arg.len()..input.len(),
+        Query::Contains(_) => 0..0,
+        // Lowercasing ascii keeps the positions of the characters
+        Query::IContains(arg) if !arg.is_empty() && input.is_ascii() => {
+            return input
+                .to_lowercase()
+                .match_indices(&**arg)
+                .map(|(start, found)| start..start + found.len())
+                .collect()
+        }
+        Query::IStarts(arg) if input.is_ascii() => 0..arg.len(),
+        Query::IEnds(arg) if input.is_ascii() => input.len() - arg.len()..input.len(),
+        _ => 0..input.len(),
+    };
+
+    vec![span]
+}
+
+/// Evaluates every node of an ast, spans are shifted by `offset` into the
+/// original input
+fn verdict(ast: &AST, input: &str, unicode: bool, offset: usize) -> Verdict {
+    match ast {
+        AST::Query(query) => {
+            let matched = query.exec_with(input, unicode);
+            let evidence = match matched {
+                true => locate(query, input)
+                    .into_iter()
+                    .map(|span| span.start + offset..span.end + offset)
+                    .collect(),
+                false => vec![],
+            };
+
+            Verdict {
+                node: query.to_string(),
+                matched,
+                evidence,
+                children: vec![],
+            }
+        }
+        AST::Not(ast) => {
+            let child = verdict(ast, input, unicode, offset);
+
+            Verdict {
+                node: "not".to_string(),
+                matched: !child.matched,
+                evidence: vec![],
+                children: vec![child],
+            }
+        }
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => Verdict::combine(
+            *operator,
+            vec![
+                verdict(left, input, unicode, offset),
+                verdict(right, input, unicode, offset),
+            ],
+        ),
+    }
+}
+
+/// Evaluates every node of an ast without short-circuiting, so the verdict
+/// of every node is known
+pub fn evaluate(ast: &AST, input: &str, options: MatchOptions) -> Match {
+    let prepared = options.prepare(input);
+
+    // Trimming removes a prefix, the spans are shifted back so they point
+    // into the input as given
+    let offset = match options.trim {
+        true => input.len() - input.trim_start().len(),
+        false => 0,
+    };
+
+    let root = verdict(ast, &prepared, options.unicode, offset);
+
+    Match {
+        input: input.to_string(),
+        matched: root.matched,
+        root,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, MatchOptions};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn collects_evidence_of_matching_alternatives() {
+        let expr = Expression::new("contains \"a\" or ends \"z\" or numeric").unwrap();
+        let result = expr.evaluate("banana");
+
+        assert_eq!(result.matched, true);
+        assert_eq!(result.evidence(), vec![1..2, 3..4, 5..6]);
+        assert_eq!(result.root.children[1].matched, false);
+    }
+
+    #[test]
+    fn failing_expressions_have_no_evidence() {
+        let expr = Expression::new("starts \"a\" and length 2").unwrap();
+        let result = expr.evaluate("abc");
+
+        assert_eq!(result.matched, false);
+        assert!(result.evidence().is_empty());
+        assert_eq!(result.root.children[0].evidence, vec![0..1]);
+    }
+
+    #[test]
+    fn negated_nodes_have_no_evidence() {
+        let expr = Expression::new("numeric").unwrap().negate();
+        let result = expr.evaluate("abc");
+
+        assert_eq!(result.matched, true);
+        assert_eq!(result.root.node, "not");
+        assert!(result.evidence().is_empty());
+    }
+
+    #[test]
+    fn locates_evidence_in_trimmed_inputs() {
+        let options = MatchOptions {
+            trim: true,
+            ..MatchOptions::default()
+        };
+
+        let expr = Expression::new_with("ends \"b\"", options).unwrap();
+        assert_eq!(expr.evaluate("  ab  ").evidence(), vec![3..4]);
+    }
+
+    #[test]
+    fn serializes_as_json() {
+        let expr = Expression::new("contains \"1\" or numeric").unwrap();
+        let json = expr.evaluate("\"1").to_json();
+
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"input":"\"1","matched":true,"root":{"node":"or","matched":true,"evidence":[{"start":1,"end":2}],"children":["#,
+                r#"{"node":"contains \"1\"","matched":true,"evidence":[{"start":1,"end":2}],"children":[]},"#,
+                r#"{"node":"numeric","matched":false,"evidence":[],"children":[]}]}}"#
+            )
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn to_json_matches_serde() {
+        let numeric = Expression::new("numeric").unwrap();
+        let result = Expression::new("contains \"a\"")
+            .unwrap()
+            .and(numeric.negate())
+            .unwrap()
+            .evaluate("a\tb\u{1}");
+
+        assert_eq!(serde_json::to_string(&result).unwrap(), result.to_json());
+    }
+}
@@ -0,0 +1,340 @@
+use std::ops::Range;
+
+use crate::error::{Error, Result};
+use crate::evaluation::{Match, Verdict};
+use crate::logical_operator::LogicalOperator;
+use crate::scan;
+use crate::{Expression, QueryStats};
+
+/// Determines when an [`ExpressionSet`] matches an input
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Policy {
+    /// At least one expression of the set has to match
+    #[default]
+    Any,
+    /// Every expression of the set has to match
+    All,
+}
+
+/// A set of expressions which are matched together according to a [`Policy`]
+pub struct ExpressionSet {
+    expressions: Vec<Expression>,
+    policy: Policy,
+}
+
+impl ExpressionSet {
+    pub fn new<I, S>(sources: I, policy: Policy) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::compile(sources, policy).map_err(|mut errors| errors.remove(0).1)
+    }
+
+    /// Compiles every source like [`ExpressionSet::new`], but reports every
+    /// invalid source together with its index instead of only the first one.
+    /// With the `parallel` feature the sources are compiled on all cores
+    ///
+    /// ```rust
+    /// let errors = srch::ExpressionSet::compile(["numeric", "length", "and"], srch::Policy::Any)
+    ///     .err()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 2]);
+    /// ```
+    pub fn compile<I, S>(
+        sources: I,
+        policy: Policy,
+    ) -> std::result::Result<Self, Vec<(usize, Error)>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let sources = sources
+            .into_iter()
+            .map(|source| source.as_ref().to_string())
+            .collect::<Vec<_>>();
+
+        let mut expressions = Vec::with_capacity(sources.len());
+        let mut errors = vec![];
+
+        for (index, compiled) in crate::compile_all(&sources).into_iter().enumerate() {
+            match compiled {
+                Ok(expression) => expressions.push(expression),
+                Err(error) => errors.push((index, error)),
+            }
+        }
+
+        match errors.is_empty() {
+            true => Ok(Self {
+                expressions,
+                policy,
+            }),
+            false => Err(errors),
+        }
+    }
+
+    pub fn policy(&self) -> Policy {
+        self.policy
+    }
+
+    pub fn len(&self) -> usize {
+        self.expressions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expressions.is_empty()
+    }
+
+    pub fn matches(&self, input: impl AsRef<str>) -> bool {
+        let input = input.as_ref();
+
+        match self.policy {
+            Policy::Any => self.expressions.iter().any(|expr| expr.matches(input)),
+            Policy::All => self.expressions.iter().all(|expr| expr.matches(input)),
+        }
+    }
+
+    /// The indices of the expressions matching `input`, in the order they
+    /// were given
+    ///
+    /// ```rust
+    /// let set = srch::ExpressionSet::new(["numeric", "alpha", "length 3"], srch::Policy::Any).unwrap();
+    /// assert_eq!(set.matching("123"), vec![0, 2]);
+    /// ```
+    pub fn matching(&self, input: impl AsRef<str>) -> Vec<usize> {
+        let input = input.as_ref();
+
+        self.expressions
+            .iter()
+            .enumerate()
+            .filter(|(_, expr)| expr.matches(input))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// The set matching exactly the inputs this set doesn't match, every
+    /// expression is complemented and the policy is flipped
+    ///
+    /// ```rust
+    /// let set = srch::ExpressionSet::new(["numeric", "alpha"], srch::Policy::Any).unwrap();
+    /// let complement = set.complement();
+    ///
+    /// assert_eq!(complement.policy(), srch::Policy::All);
+    /// assert!(complement.matches("a1"));
+    /// assert!(!complement.matches("123"));
+    /// ```
+    pub fn complement(&self) -> Self {
+        let policy = match self.policy {
+            Policy::Any => Policy::All,
+            Policy::All => Policy::Any,
+        };
+
+        Self {
+            expressions: self
+                .expressions
+                .iter()
+                .map(Expression::complement)
+                .collect(),
+            policy,
+        }
+    }
+
+    /// Counts how often every query of every expression is evaluated and
+    /// matches, see [`Expression::with_stats`]
+    pub fn with_stats(self) -> Self {
+        Self {
+            expressions: self
+                .expressions
+                .into_iter()
+                .map(Expression::with_stats)
+                .collect(),
+            ..self
+        }
+    }
+
+    /// The stats of the queries of every expression, in the order the
+    /// expressions were given
+    pub fn stats(&self) -> Vec<Vec<QueryStats>> {
+        self.expressions.iter().map(Expression::stats).collect()
+    }
+
+    /// The substrings of a set which only checks for substrings, see
+    /// [`Expression::literals`]. Every expression of a set matching any
+    /// expression has to be literal only, a set matching all expressions has
+    /// to consist of a single one
+    pub fn literals(&self) -> Option<Vec<&str>> {
+        match (self.policy, self.expressions.len()) {
+            (Policy::All, len) if len > 1 => None,
+            _ => self.expressions.iter().map(Expression::literals).try_fold(
+                vec![],
+                |mut all, literals| {
+                    all.extend(literals?);
+                    Some(all)
+                },
+            ),
+        }
+    }
+
+    /// Finds the parts of `input` matched by the set, see
+    /// [`Expression::scan`]
+    pub fn scan(&self, input: &str) -> Vec<Range<usize>> {
+        let max = self
+            .expressions
+            .iter()
+            .map(|expr| scan::max_window(expr.runtime.ast()))
+            .try_fold(0, |max, len| len.map(|len| max.max(len)));
+
+        // A window matching any expression contains an anchor of one of
+        // them, a window matching all of them the anchors of each
+        let anchors = match self.policy {
+            Policy::Any => self
+                .expressions
+                .iter()
+                .map(|expr| expr.anchors(input))
+                .try_fold(vec![], |mut all, anchors| {
+                    all.extend(anchors?);
+                    Some(all)
+                }),
+            Policy::All => self.expressions.iter().find_map(|expr| expr.anchors(input)),
+        };
+
+        scan::scan(input, max, anchors.as_deref(), |window| {
+            self.matches(window)
+        })
+    }
+
+    /// How close `input` comes to matching, see [`Expression::score`]. A set
+    /// matching any expression scores like its closest expression, a set
+    /// matching all expressions by the average of their scores
+    pub fn score(&self, input: &str) -> f32 {
+        let scores = self.expressions.iter().map(|expr| expr.score(input));
+
+        match self.policy {
+            Policy::Any => scores.fold(0.0, f32::max),
+            Policy::All => scores.sum::<f32>() / self.expressions.len().max(1) as f32,
+        }
+    }
+
+    /// Evaluates every expression of the set, a set of multiple expressions
+    /// is evaluated like the expressions combined using `or` (for
+    /// [`Policy::Any`]) or `and` (for [`Policy::All`])
+    pub fn evaluate(&self, input: &str) -> Match {
+        if let [expr] = self.expressions.as_slice() {
+            return expr.evaluate(input);
+        }
+
+        let operator = match self.policy {
+            Policy::Any => LogicalOperator::Or,
+            Policy::All => LogicalOperator::And,
+        };
+
+        let children = self
+            .expressions
+            .iter()
+            .map(|expr| expr.evaluate(input).root)
+            .collect();
+
+        let root = Verdict::combine(operator, children);
+
+        Match {
+            input: input.to_string(),
+            matched: root.matched,
+            root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{ExpressionSet, Policy};
+    use crate::parser::AST;
+    use crate::query::Query;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn shares_literals_between_expressions() {
+        let set =
+            ExpressionSet::new(["starts \"ab\"", "length 3 or ends \"ab\""], Policy::Any).unwrap();
+
+        let first = match set.expressions[0].runtime.ast() {
+            AST::Query(Query::Starts(arg)) => arg.clone(),
+            ast => panic!("unexpected ast {:?}", ast),
+        };
+
+        let second = match set.expressions[1].runtime.ast() {
+            AST::BinaryExpression { right, .. } => match &**right {
+                AST::Query(Query::Ends(arg)) => arg.clone(),
+                ast => panic!("unexpected ast {:?}", ast),
+            },
+            ast => panic!("unexpected ast {:?}", ast),
+        };
+
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn any_matches_if_one_expression_matches() {
+        let set = ExpressionSet::new(["numeric", "alpha"], Policy::Any).unwrap();
+        assert_eq!(set.matches("123"), true);
+        assert_eq!(set.matches("abc"), true);
+        assert_eq!(set.matches("1bc"), false);
+    }
+
+    #[test]
+    fn all_matches_if_every_expression_matches() {
+        let set = ExpressionSet::new(["numeric", "length 3"], Policy::All).unwrap();
+        assert_eq!(set.matches("123"), true);
+        assert_eq!(set.matches("1234"), false);
+    }
+
+    #[test]
+    fn evaluates_every_expression() {
+        let set = ExpressionSet::new(["numeric", "length 3"], Policy::All).unwrap();
+        let result = set.evaluate("1234");
+
+        assert_eq!(result.matched, false);
+        assert_eq!(result.root.node, "and");
+        assert_eq!(
+            result
+                .root
+                .children
+                .iter()
+                .map(|verdict| verdict.matched)
+                .collect::<Vec<_>>(),
+            vec![true, false]
+        );
+    }
+
+    #[test]
+    fn collects_literals_of_every_expression() {
+        let set = ExpressionSet::new(
+            ["contains \"a\"", "contains \"b\" or contains \"c\""],
+            Policy::Any,
+        )
+        .unwrap();
+        assert_eq!(set.literals(), Some(vec!["a", "b", "c"]));
+
+        let set = ExpressionSet::new(["contains \"a\"", "numeric"], Policy::Any).unwrap();
+        assert_eq!(set.literals(), None);
+
+        let set = ExpressionSet::new(["contains \"a\"", "contains \"b\""], Policy::All).unwrap();
+        assert_eq!(set.literals(), None);
+    }
+
+    #[test]
+    fn scores_by_policy() {
+        let any = ExpressionSet::new(["numeric and length 3", "alpha"], Policy::Any).unwrap();
+        assert_eq!(any.score("12"), 0.8);
+
+        let all = ExpressionSet::new(["numeric", "length 3"], Policy::All).unwrap();
+        assert_eq!(all.score("12"), 0.5);
+    }
+
+    #[test]
+    fn fails_on_invalid_expression() {
+        assert!(ExpressionSet::new(["numeric", "and"], Policy::Any).is_err());
+    }
+}
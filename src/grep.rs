@@ -0,0 +1,77 @@
+//! Matching of whole lines for [`grep-searcher`](https://crates.io/crates/grep-searcher)
+
+use grep_matcher::{Match, Matcher, NoCaptures, NoError};
+
+use crate::Expression;
+
+/// A [`Matcher`] which matches whole lines using a predicate, usually an
+/// expression (see [`matcher`]). Since there is no fast path for finding
+/// candidate lines, searchers pass it one line at a time
+pub struct LineMatcher<F> {
+    is_match: F,
+}
+
+impl<F: Fn(&str) -> bool> LineMatcher<F> {
+    pub fn new(is_match: F) -> Self {
+        Self { is_match }
+    }
+}
+
+impl<F: Fn(&str) -> bool> Matcher for LineMatcher<F> {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        let line = &haystack[at..];
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+
+        match (self.is_match)(&String::from_utf8_lossy(line)) {
+            true => Ok(Some(Match::new(at, at + line.len()))),
+            false => Ok(None),
+        }
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+}
+
+/// Creates a [`LineMatcher`] for an expression
+///
+/// ```rust
+/// use grep_searcher::{sinks::UTF8, Searcher};
+///
+/// let expr = srch::Expression::new("numeric").unwrap();
+/// let mut lines = Vec::new();
+///
+/// Searcher::new()
+///     .search_slice(srch::grep::matcher(&expr), b"123\nabc\n456\n", UTF8(|n, line| {
+///         lines.push((n, line.trim_end().to_string()));
+///         Ok(true)
+///     }))
+///     .unwrap();
+///
+/// assert_eq!(lines, vec![(1, "123".to_string()), (3, "456".to_string())]);
+/// ```
+pub fn matcher(expression: &Expression) -> LineMatcher<impl Fn(&str) -> bool + '_> {
+    LineMatcher::new(move |line: &str| expression.matches(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_matcher::{Match, Matcher};
+
+    use super::matcher;
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn matches_whole_lines_without_terminator() {
+        let expr = Expression::new("alpha").unwrap();
+        let matcher = matcher(&expr);
+
+        assert_eq!(matcher.find(b"abc\r\n").unwrap(), Some(Match::new(0, 3)));
+        assert_eq!(matcher.find(b"ab1\n").unwrap(), None);
+    }
+}
@@ -0,0 +1,245 @@
+//! Translations of the messages of [`Error`](crate::Error). Messages are
+//! looked up by key in the active [`Catalog`], keys missing in the catalog
+//! fall back to english
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//!
+//! let mut catalog = HashMap::new();
+//! catalog.insert("parser.expected_query".to_string(), "query expected".to_string());
+//!
+//! srch::i18n::set_catalog(catalog);
+//! let err = srch::Expression::new("numeric and").err().unwrap();
+//! assert_eq!(err.to_string(), "syntax error: query expected");
+//!
+//! srch::i18n::reset_catalog();
+//! ```
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// A source of translated message templates. Templates can contain the
+/// placeholder `{0}`, which is replaced by the argument of the message
+pub trait Catalog: Send + Sync {
+    fn template(&self, key: &str) -> Option<&str>;
+}
+
+impl Catalog for HashMap<String, String> {
+    fn template(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+/// A catalog compiled into srch
+pub struct Builtin(&'static [(&'static str, &'static str)]);
+
+impl Catalog for Builtin {
+    fn template(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, template)| *template)
+    }
+}
+
+pub const ENGLISH: Builtin = Builtin(&[
+    ("error.lexical", "lexical error: {0}"),
+    ("error.syntax", "syntax error: {0}"),
+    ("error.limit", "limit exceeded: {0}"),
+    (
+        "error.options",
+        "expressions with different options can't be combined",
+    ),
+    ("lexer.unknown_syntax", "unknown syntax"),
+    ("lexer.unknown_keyword", "unknown keyword"),
+    ("lexer.too_many_arguments", "too many arguments"),
+    (
+        "lexer.leading_zeros",
+        "integers must not have leading zeros",
+    ),
+    ("lexer.unclosed_string", "unclosed string"),
+    ("lexer.expected_string", "expected a string"),
+    ("lexer.expected_integer", "expected an integer"),
+    ("lexer.expected_query", "expected a query"),
+    ("lexer.expected_operator", "expected an operator"),
+    ("lexer.unknown_pattern", "unknown pattern \"@{0}\""),
+    ("lexer.empty_range", "no length is in the range"),
+    ("parser.expected_query", "expected a query"),
+    ("parser.expected_operator", "expected an operator"),
+    ("parser.empty_expression", "empty expression"),
+    (
+        "parser.too_complex",
+        "the expression is nested deeper than {0} levels",
+    ),
+    ("parser.internal_error", "internal error"),
+    (
+        "limits.source_length",
+        "the expression is longer than {0} bytes",
+    ),
+    ("limits.queries", "the expression has more than {0} queries"),
+    (
+        "limits.depth",
+        "the expression is nested deeper than {0} levels",
+    ),
+    (
+        "limits.argument_length",
+        "an argument is longer than {0} bytes",
+    ),
+    ("limits.pattern", "the pattern \"{0}\" isn't allowed"),
+    ("limits.out_of_fuel", "the expression ran out of fuel"),
+    ("collation.locale", "\"{0}\" isn't a supported locale"),
+    (
+        "corpus.quantifier",
+        "expected \"any line\", \"every line\" or \"no line\" before \"{0}\"",
+    ),
+    (
+        "registry.invalid_name",
+        "\"{0}\" isn't a lowercase name like \"ticket_id\"",
+    ),
+    ("registry.taken", "the name \"{0}\" is already taken"),
+    (
+        "plugin.abi",
+        "the plugin was built for version {0} of the plugin abi",
+    ),
+    ("plugin.failed", "the plugin failed to register its queries"),
+]);
+
+pub const GERMAN: Builtin = Builtin(&[
+    ("error.lexical", "lexikalischer Fehler: {0}"),
+    ("error.syntax", "Syntaxfehler: {0}"),
+    ("error.limit", "Limit überschritten: {0}"),
+    (
+        "error.options",
+        "Ausdrücke mit verschiedenen Optionen können nicht kombiniert werden",
+    ),
+    ("lexer.unknown_syntax", "unbekannte Syntax"),
+    ("lexer.unknown_keyword", "unbekanntes Schlüsselwort"),
+    ("lexer.too_many_arguments", "zu viele Argumente"),
+    (
+        "lexer.leading_zeros",
+        "Zahlen dürfen keine führenden Nullen haben",
+    ),
+    ("lexer.unclosed_string", "nicht geschlossene Zeichenkette"),
+    ("lexer.expected_string", "Zeichenkette erwartet"),
+    ("lexer.expected_integer", "Zahl erwartet"),
+    ("lexer.expected_query", "Abfrage erwartet"),
+    ("lexer.expected_operator", "Operator erwartet"),
+    ("lexer.unknown_pattern", "unbekanntes Muster \"@{0}\""),
+    ("lexer.empty_range", "keine Länge liegt im Bereich"),
+    ("parser.expected_query", "Abfrage erwartet"),
+    ("parser.expected_operator", "Operator erwartet"),
+    ("parser.empty_expression", "leerer Ausdruck"),
+    (
+        "parser.too_complex",
+        "der Ausdruck ist tiefer als {0} Ebenen verschachtelt",
+    ),
+    ("parser.internal_error", "interner Fehler"),
+    (
+        "limits.source_length",
+        "der Ausdruck ist länger als {0} Bytes",
+    ),
+    ("limits.queries", "der Ausdruck hat mehr als {0} Abfragen"),
+    (
+        "limits.depth",
+        "der Ausdruck ist tiefer als {0} Ebenen verschachtelt",
+    ),
+    (
+        "limits.argument_length",
+        "ein Argument ist länger als {0} Bytes",
+    ),
+    ("limits.pattern", "das Muster \"{0}\" ist nicht erlaubt"),
+    (
+        "limits.out_of_fuel",
+        "dem Ausdruck ist der Treibstoff ausgegangen",
+    ),
+    ("collation.locale", "\"{0}\" ist keine unterstützte Locale"),
+    (
+        "corpus.quantifier",
+        "\"any line\", \"every line\" oder \"no line\" vor \"{0}\" erwartet",
+    ),
+    (
+        "registry.invalid_name",
+        "\"{0}\" ist kein kleingeschriebener Name wie \"ticket_id\"",
+    ),
+    ("registry.taken", "der Name \"{0}\" ist bereits vergeben"),
+    (
+        "plugin.abi",
+        "das Plugin wurde für Version {0} der Plugin-ABI gebaut",
+    ),
+    (
+        "plugin.failed",
+        "das Plugin konnte seine Abfragen nicht registrieren",
+    ),
+]);
+
+static CATALOG: RwLock<Option<Box<dyn Catalog>>> = RwLock::new(None);
+
+/// Replaces the catalog used for all messages
+pub fn set_catalog(catalog: impl Catalog + 'static) {
+    *CATALOG.write().unwrap_or_else(|err| err.into_inner()) = Some(Box::new(catalog));
+}
+
+/// Switches back to english messages
+pub fn reset_catalog() {
+    *CATALOG.write().unwrap_or_else(|err| err.into_inner()) = None;
+}
+
+/// Returns the builtin catalog of a language like `de` or `de_DE.UTF-8`
+pub fn builtin(language: &str) -> Option<Builtin> {
+    match language.split(['_', '-', '.']).next() {
+        Some("en") => Some(ENGLISH),
+        Some("de") => Some(GERMAN),
+        _ => None,
+    }
+}
+
+fn format(catalog: &dyn Catalog, key: &str, arg: &str) -> String {
+    let template = catalog
+        .template(key)
+        .or_else(|| ENGLISH.template(key))
+        .unwrap_or(key);
+
+    template.replace("{0}", arg)
+}
+
+/// Formats the message `key` using the active catalog
+pub(crate) fn message(key: &str, arg: &str) -> String {
+    let catalog = CATALOG.read().unwrap_or_else(|err| err.into_inner());
+
+    match catalog.as_deref() {
+        Some(catalog) => format(catalog, key, arg),
+        None => format(&ENGLISH, key, arg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{builtin, format, GERMAN};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn formats_arguments() {
+        assert_eq!(
+            format(&GERMAN, "lexer.unknown_pattern", "foo"),
+            "unbekanntes Muster \"@foo\""
+        );
+    }
+
+    #[test]
+    fn falls_back_to_english() {
+        let catalog = HashMap::new();
+        assert_eq!(
+            format(&catalog, "parser.empty_expression", ""),
+            "empty expression"
+        );
+    }
+
+    #[test]
+    fn finds_builtin_catalogs_by_locale() {
+        assert!(builtin("de_DE.UTF-8").is_some());
+        assert!(builtin("en").is_some());
+        assert!(builtin("C").is_none());
+    }
+}
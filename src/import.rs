@@ -0,0 +1,318 @@
+use std::fmt;
+
+use crate::Expression;
+
+/// A construct of a regular expression which has no equivalent text
+/// expression
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnsupportedFeature {
+	/// The part of the regular expression which couldn't be converted
+	pub construct: String,
+	/// The byte offset of the construct in the regular expression
+	pub offset: usize,
+	pub reason: &'static str
+}
+
+impl UnsupportedFeature {
+
+	fn new(construct: &str, offset: usize, reason: &'static str) -> Self {
+		Self { construct: construct.to_string(), offset, reason }
+	}
+
+}
+
+impl fmt::Display for UnsupportedFeature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "can't convert `{}` at offset {}: {}", self.construct, self.offset, self.reason)
+	}
+}
+
+impl std::error::Error for UnsupportedFeature {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Item {
+	Literal(char),
+	Any,
+	Class(&'static str)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Quantifier {
+	One,
+	Star,
+	Exact(u64)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Atom {
+	item: Item,
+	quantifier: Quantifier,
+	offset: usize
+}
+
+/// Maps the supported character classes onto class queries
+fn class(set: &str) -> Option<&'static str> {
+	match set {
+		"0-9" | "\\d" => Some("numeric"),
+		"A-Za-z" | "a-zA-Z" => Some("alpha"),
+		"A-Za-z0-9" | "a-zA-Z0-9" | "0-9A-Za-z" | "0-9a-zA-Z" => Some("alphanumeric"),
+		"A-Z" => Some("uppercase"),
+		"a-z" => Some("lowercase"),
+		"\\s" => Some("whitespace"),
+		_ => None
+	}
+}
+
+fn parse_quantifier(
+	pattern: &str,
+	offset: usize
+) -> Result<(Quantifier, usize), UnsupportedFeature> {
+	let rest = &pattern[offset..];
+
+	let (quantifier, len) = match rest.chars().next() {
+		Some('*') => (Quantifier::Star, 1),
+		Some('+') => return Err(UnsupportedFeature::new(
+			"+", offset, "at least one repetition can't be expressed, only `*` and `{n}` are supported"
+		)),
+		Some('?') => return Err(UnsupportedFeature::new("?", offset, "optional items can't be expressed")),
+		Some('{') => {
+			let end = rest.find('}').ok_or_else(|| {
+				UnsupportedFeature::new(rest, offset, "unterminated repetition")
+			})?;
+
+			match rest[1..end].parse() {
+				Ok(n) => (Quantifier::Exact(n), end + 1),
+				Err(_) => return Err(UnsupportedFeature::new(
+					&rest[..=end], offset, "only exact repetitions like `{5}` are supported"
+				))
+			}
+		},
+		_ => (Quantifier::One, 0)
+	};
+
+	// Lazy and possessive quantifiers
+	match rest[len..].chars().next() {
+		Some(c @ '?') | Some(c @ '+') if len > 0 => Err(UnsupportedFeature::new(
+			&rest[..=len], offset, if c == '?' { "lazy quantifiers are not supported" } else { "possessive quantifiers are not supported" }
+		)),
+		_ => Ok((quantifier, len))
+	}
+}
+
+/// Parses a branch (without anchors) of a regular expression into atoms
+fn parse_atoms(pattern: &str, start: usize, end: usize) -> Result<Vec<Atom>, UnsupportedFeature> {
+	let mut atoms = Vec::new();
+	let mut offset = start;
+
+	while offset < end {
+		let rest = &pattern[offset..end];
+		let c = rest.chars().next().unwrap_or_default();
+
+		let (item, len) = match c {
+			'.' => (Item::Any, 1),
+			'[' => {
+				let close = rest.find(']').ok_or_else(|| {
+					UnsupportedFeature::new(rest, offset, "unterminated character class")
+				})?;
+
+				match class(&rest[1..close]) {
+					Some(query) => (Item::Class(query), close + 1),
+					None => return Err(UnsupportedFeature::new(
+						&rest[..=close], offset, "only the classes [0-9], [A-Za-z], [A-Za-z0-9], [A-Z] and [a-z] are supported"
+					))
+				}
+			},
+			'\\' => match rest[1..].chars().next() {
+				Some('d') => (Item::Class("numeric"), 2),
+				Some('s') => (Item::Class("whitespace"), 2),
+				Some(escaped) if !escaped.is_alphanumeric() => (Item::Literal(escaped), 1 + escaped.len_utf8()),
+				Some(escaped) => return Err(UnsupportedFeature::new(
+					&rest[..1 + escaped.len_utf8()], offset, "escape sequence has no equivalent query"
+				)),
+				None => return Err(UnsupportedFeature::new("\\", offset, "trailing backslash"))
+			},
+			'(' | ')' => return Err(UnsupportedFeature::new(&rest[..1], offset, "groups are not supported")),
+			'^' | '$' => return Err(UnsupportedFeature::new(
+				&rest[..1], offset, "anchors are only supported at the start and end"
+			)),
+			'*' | '+' | '?' | '{' => return Err(UnsupportedFeature::new(
+				&rest[..1], offset, "quantifier without an item to repeat"
+			)),
+			c => (Item::Literal(c), c.len_utf8())
+		};
+
+		let (quantifier, quantifier_len) = parse_quantifier(&pattern[..end], offset + len)?;
+
+		atoms.push(Atom { item, quantifier, offset });
+		offset += len + quantifier_len;
+	}
+
+	Ok(atoms)
+}
+
+/// Converts a single alternative of a regular expression
+fn branch_to_source(pattern: &str, start: usize, end: usize) -> Result<String, UnsupportedFeature> {
+	let branch = &pattern[start..end];
+	let mut from = start;
+	let mut to = end;
+
+	let mut anchored_start = branch.starts_with('^');
+	let mut anchored_end = branch.ends_with('$') && !branch.ends_with("\\$");
+
+	if anchored_start {
+		from += 1;
+	}
+
+	if anchored_end && to > from {
+		to -= 1;
+	}
+
+	let mut atoms = parse_atoms(pattern, from, to)?;
+
+	let is_any_star = |atom: Option<&Atom>| matches!(
+		atom, Some(Atom { item: Item::Any, quantifier: Quantifier::Star, .. })
+	);
+
+	// Leading and trailing `.*` remove the anchor of that side
+	while is_any_star(atoms.first()) {
+		atoms.remove(0);
+		anchored_start = false;
+	}
+
+	while is_any_star(atoms.last()) {
+		atoms.pop();
+		anchored_end = false;
+	}
+
+	let literal = atoms.iter().map(|atom| match atom {
+		Atom { item: Item::Literal(c), quantifier: Quantifier::One, .. } => Some(*c),
+		_ => None
+	}).collect::<Option<String>>();
+
+	let unsupported = |reason| UnsupportedFeature::new(branch, start, reason);
+
+	match (atoms.as_slice(), literal) {
+		([], _) => Err(unsupported("an empty pattern has no equivalent query")),
+		(_, Some(literal)) if literal.contains('"') => {
+			Err(unsupported("literals containing double quotes can't be expressed"))
+		},
+		(_, Some(literal)) => Ok(match (anchored_start, anchored_end) {
+			(true, true) => format!("equals \"{}\"", literal),
+			(true, false) => format!("starts \"{}\"", literal),
+			(false, true) => format!("ends \"{}\"", literal),
+			(false, false) => format!("contains \"{}\"", literal)
+		}),
+		([atom], None) if anchored_start && anchored_end => match (atom.item, atom.quantifier) {
+			(Item::Class(query), Quantifier::Star) => Ok(query.to_string()),
+			(Item::Class(query), Quantifier::One) => Ok(format!("{} and length 1", query)),
+			(Item::Class(query), Quantifier::Exact(n)) => Ok(format!("{} and length {}", query, n)),
+			(Item::Any, Quantifier::One) => Ok("length 1".to_string()),
+			(Item::Any, Quantifier::Exact(n)) => Ok(format!("length {}", n)),
+			_ => Err(unsupported("repeated literals can't be expressed"))
+		},
+		([_], None) => Err(unsupported("classes and lengths are only supported if anchored with ^ and $")),
+		_ => Err(unsupported("only literals, a single class or `.{n}` can be converted"))
+	}
+}
+
+/// Converts a regular expression into the source of an equivalent text
+/// expression, see [`from_regex`](fn.from_regex.html)
+pub fn regex_to_source(pattern: &str) -> Result<String, UnsupportedFeature> {
+	let mut branches = Vec::new();
+	let mut start = 0;
+	let mut escaped = false;
+
+	for (i, c) in pattern.char_indices() {
+		match (c, escaped) {
+			('\\', false) => escaped = true,
+			('|', false) => {
+				branches.push(branch_to_source(pattern, start, i)?);
+				start = i + 1;
+			},
+			_ => escaped = false
+		}
+	}
+
+	branches.push(branch_to_source(pattern, start, pattern.len())?);
+
+	Ok(branches.join(" or "))
+}
+
+/// Converts the representable subset of regular expressions (anchors,
+/// literals, the classes `[0-9]`, `[A-Za-z]`, `[A-Za-z0-9]` and exact
+/// repetitions) into a text expression
+///
+/// ```rust
+/// let expr = srch::from_regex("^[0-9]{5}$").unwrap();
+/// assert!(expr.matches("12345"));
+///
+/// let err = srch::from_regex("^(foo)+$").err().unwrap();
+/// assert_eq!(err.construct, "(");
+/// ```
+pub fn from_regex(pattern: &str) -> Result<Expression, UnsupportedFeature> {
+	let source = regex_to_source(pattern)?;
+	Ok(Expression::new(&source).expect("converted regular expressions are valid expressions"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::regex_to_source;
+
+	macro_rules! from_regex_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (pattern, expected) = $value;
+					pretty_assertions::assert_eq!(regex_to_source(pattern).map_err(|err| err.construct), expected);
+				}
+			)*
+		}
+	}
+
+	mod it_converts_literals {
+		use super::*;
+
+		from_regex_tests! {
+			equals: ("^foo$", Ok("equals \"foo\"".to_string())),
+			starts: ("^foo", Ok("starts \"foo\"".to_string())),
+			starts_any: ("^foo.*$", Ok("starts \"foo\"".to_string())),
+			ends: ("foo$", Ok("ends \"foo\"".to_string())),
+			contains: ("foo", Ok("contains \"foo\"".to_string())),
+			contains_any: ("^.*foo.*$", Ok("contains \"foo\"".to_string())),
+			escaped: ("^a\\.b$", Ok("equals \"a.b\"".to_string())),
+			alternation: ("^foo$|bar", Ok("equals \"foo\" or contains \"bar\"".to_string())),
+		}
+	}
+
+	mod it_converts_classes {
+		use super::*;
+
+		from_regex_tests! {
+			numeric: ("^[0-9]*$", Ok("numeric".to_string())),
+			digits: ("^\\d{5}$", Ok("numeric and length 5".to_string())),
+			alpha: ("^[a-zA-Z]{3}$", Ok("alpha and length 3".to_string())),
+			alphanumeric: ("^[A-Za-z0-9]$", Ok("alphanumeric and length 1".to_string())),
+			uppercase: ("^[A-Z]*$", Ok("uppercase".to_string())),
+			lowercase: ("^[a-z]{4}$", Ok("lowercase and length 4".to_string())),
+			whitespace: ("^\\s*$", Ok("whitespace".to_string())),
+			length: ("^.{8}$", Ok("length 8".to_string())),
+		}
+	}
+
+	mod it_reports_unsupported_constructs {
+		use super::*;
+
+		from_regex_tests! {
+			groups: ("^(foo)$", Err("(".to_string())),
+			plus: ("^[0-9]+$", Err("+".to_string())),
+			ranges: ("^.{2,5}$", Err("{2,5}".to_string())),
+			lazy: ("^a.*?b$", Err("*?".to_string())),
+			negated_classes: ("^[^0-9]*$", Err("[^0-9]".to_string())),
+			word_escapes: ("\\w", Err("\\w".to_string())),
+			unanchored_classes: ("[0-9]*", Err("[0-9]*".to_string())),
+			mixed: ("^a[0-9]$", Err("^a[0-9]$".to_string())),
+			empty: ("", Err("".to_string())),
+		}
+	}
+}
@@ -0,0 +1,319 @@
+use std::ops::Range;
+
+use crate::analysis;
+use crate::query::Query;
+use crate::{Expression, Tokenizer};
+
+/// The character classes every character of a record belongs to
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Classes {
+    numeric: bool,
+    alpha: bool,
+    alphanumeric: bool,
+    special: bool,
+}
+
+impl Classes {
+    fn of(text: &str) -> Self {
+        Self {
+            numeric: Query::Numeric.exec(text),
+            alpha: Query::Alpha.exec(text),
+            alphanumeric: Query::Alphanumeric.exec(text),
+            special: Query::Special.exec(text),
+        }
+    }
+
+    /// Whether a record of these classes belongs to every class of `other`
+    fn contains(&self, other: &Classes) -> bool {
+        (self.numeric || !other.numeric)
+            && (self.alpha || !other.alpha)
+            && (self.alphanumeric || !other.alphanumeric)
+            && (self.special || !other.special)
+    }
+}
+
+/// A line or word of the indexed text together with its summary
+#[derive(Clone, Debug, PartialEq)]
+struct Entry {
+    /// The line the record is on, counting from 1
+    line: usize,
+    range: Range<usize>,
+    classes: Classes,
+}
+
+impl Entry {
+    fn new(text: &str, line: usize, range: Range<usize>) -> Self {
+        Self {
+            line,
+            classes: Classes::of(&text[range.clone()]),
+            range,
+        }
+    }
+}
+
+/// The `length` and class queries a record has to satisfy to match a clause
+#[derive(Debug, Default)]
+struct Requirement {
+    /// The bounds of the lengths of the `length` queries
+    lengths: Vec<(u64, Option<u64>)>,
+    classes: Classes,
+}
+
+impl Requirement {
+    fn admits(&self, entry: &Entry) -> bool {
+        let len = entry.range.len() as u64;
+        self.lengths
+            .iter()
+            .all(|(min, max)| *min <= len && max.is_none_or(|max| len <= max))
+            && entry.classes.contains(&self.classes)
+    }
+}
+
+/// Skips the records which can't match an expression using their summaries,
+/// only the remaining records are matched against the expression
+struct Prefilter {
+    /// The requirements of every clause, `None` if the summaries can't be
+    /// used because the expression normalizes its input or has too many
+    /// clauses
+    clauses: Option<Vec<Requirement>>,
+}
+
+impl Prefilter {
+    fn new(expr: &Expression) -> Self {
+        let options = expr.runtime.options();
+
+        // The summaries describe the raw ascii input
+        if options.ignore_case || options.trim || options.unicode {
+            return Self { clauses: None };
+        }
+
+        let clauses = analysis::clauses(expr.runtime.ast()).map(|clauses| {
+            clauses
+                .iter()
+                .map(|clause| {
+                    let mut requirement = Requirement::default();
+
+                    for literal in clause.iter().filter(|literal| !literal.negated) {
+                        match literal.query {
+                            Query::Length(_) | Query::LengthRange(_) => {
+                                requirement.lengths.extend(literal.query.length_bounds())
+                            }
+                            Query::Numeric => requirement.classes.numeric = true,
+                            Query::Alpha => requirement.classes.alpha = true,
+                            Query::Alphanumeric => requirement.classes.alphanumeric = true,
+                            Query::Special => requirement.classes.special = true,
+                            _ => {}
+                        }
+                    }
+
+                    requirement
+                })
+                .collect()
+        });
+
+        Self { clauses }
+    }
+
+    fn admits(&self, entry: &Entry) -> bool {
+        match &self.clauses {
+            Some(clauses) => clauses.iter().any(|clause| clause.admits(entry)),
+            None => true,
+        }
+    }
+}
+
+/// A text split into lines and words once, so many expressions can be
+/// matched against it without splitting it again, e.g. in a repl or server.
+/// The length and character classes of every record are stored as well, so
+/// records which can't match an expression are skipped without running it
+///
+/// Text can be appended while the index is used, e.g. the lines of a growing
+/// log file. An unterminated last line is continued by the appended text
+///
+/// ```rust
+/// use srch::{Expression, Index};
+///
+/// let mut index = Index::new();
+/// index.push("id 12345\nname jane\nzip 5");
+/// index.push("4321\n");
+///
+/// let zip = Expression::new("numeric and length 5").unwrap();
+/// assert_eq!(index.words(&zip), vec![(1, "12345"), (3, "54321")]);
+///
+/// let name = Expression::new("starts \"name\"").unwrap();
+/// assert_eq!(index.lines(&name), vec![(2, "name jane")]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Index {
+    text: String,
+    lines: Vec<Entry>,
+    words: Vec<Entry>,
+}
+
+impl Index {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends text to the index, only the new lines are split
+    pub fn push(&mut self, text: &str) {
+        let start = match self.lines.last() {
+            Some(last) if !self.text.ends_with('\n') => {
+                let line = last.line;
+
+                while self.words.last().is_some_and(|word| word.line == line) {
+                    self.words.pop();
+                }
+
+                self.lines.pop().map_or(0, |last| last.range.start)
+            }
+            _ => self.text.len(),
+        };
+
+        self.text.push_str(text);
+
+        let mut offset = start;
+
+        for raw in self.text[start..].split_inclusive('\n') {
+            let content = raw
+                .strip_suffix('\n')
+                .map(|line| line.strip_suffix('\r').unwrap_or(line))
+                .unwrap_or(raw);
+
+            let line = self.lines.len() + 1;
+            let range = offset..offset + content.len();
+
+            for span in Tokenizer::default().word_spans(content) {
+                let span = range.start + span.start..range.start + span.end;
+                self.words.push(Entry::new(&self.text, line, span));
+            }
+
+            self.lines.push(Entry::new(&self.text, line, range));
+            offset += raw.len();
+        }
+    }
+
+    /// The number of lines
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// The whole indexed text
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn matching<'a>(&'a self, entries: &[Entry], expr: &Expression) -> Vec<(usize, &'a str)> {
+        let prefilter = Prefilter::new(expr);
+
+        entries
+            .iter()
+            .filter(|entry| prefilter.admits(entry))
+            .map(|entry| (entry.line, &self.text[entry.range.clone()]))
+            .filter(|(_, text)| expr.matches(text))
+            .collect()
+    }
+
+    /// The lines matching `expr` together with their line numbers, like the
+    /// `line` mode
+    pub fn lines(&self, expr: &Expression) -> Vec<(usize, &str)> {
+        self.matching(&self.lines, expr)
+    }
+
+    /// The words matching `expr` together with the numbers of their lines,
+    /// like the `word` mode
+    pub fn words(&self, expr: &Expression) -> Vec<(usize, &str)> {
+        self.matching(&self.words, expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Index;
+    use crate::{Expression, MatchOptions};
+    use pretty_assertions::assert_eq;
+
+    fn index(text: &str) -> Index {
+        let mut index = Index::new();
+        index.push(text);
+        index
+    }
+
+    fn lines<'a>(index: &'a Index, source: &str) -> Vec<(usize, &'a str)> {
+        index.lines(&Expression::new(source).unwrap())
+    }
+
+    #[test]
+    fn splits_lines_and_words() {
+        let index = index("a 1\r\n\nb  22 c");
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(lines(&index, "length 0"), vec![(2, "")]);
+
+        let words = index.words(&Expression::new("numeric or alpha").unwrap());
+
+        assert_eq!(
+            words,
+            vec![(1, "a"), (1, "1"), (3, "b"), (3, "22"), (3, "c")]
+        );
+    }
+
+    #[test]
+    fn continues_unterminated_lines() {
+        let mut incremental = Index::new();
+
+        for part in ["12", "3\n45", "\n", "6"].iter() {
+            incremental.push(part);
+        }
+
+        assert_eq!(incremental, index("123\n45\n6"));
+        assert_eq!(
+            lines(&incremental, "numeric and length 3"),
+            vec![(1, "123")]
+        );
+    }
+
+    #[test]
+    fn matches_like_the_expression() {
+        let index = index("12345\nabcde\n12 45\n!?\n");
+
+        for source in [
+            "numeric and length 5",
+            "alpha or special",
+            "not numeric and length 5",
+            "contains \"2\" and alphanumeric or length 2",
+        ]
+        .iter()
+        {
+            let expr = Expression::new(source).unwrap();
+            let expected = index.text().lines().filter(|line| expr.matches(line));
+
+            assert_eq!(
+                index
+                    .lines(&expr)
+                    .into_iter()
+                    .map(|(_, line)| line)
+                    .collect::<Vec<_>>(),
+                expected.collect::<Vec<_>>(),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn doesnt_skip_records_of_normalizing_expressions() {
+        let index = index(" 123 \n");
+        let options = MatchOptions {
+            trim: true,
+            ..MatchOptions::default()
+        };
+
+        let expr = Expression::new_with("numeric and length 3", options).unwrap();
+        assert_eq!(index.lines(&expr), vec![(1, " 123 ")]);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::parser::AST;
+use crate::query::Query;
+
+/// Deduplicates the string literals of queries, identical literals share one
+/// allocation. A lexer interns the literals of its expression, expressions
+/// compiled together are interned again by one shared interner
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Interner {
+    literals: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub(crate) fn intern(&mut self, literal: String) -> Arc<str> {
+        if let Some(interned) = self.literals.get(literal.as_str()) {
+            return interned.clone();
+        }
+
+        let interned: Arc<str> = literal.into();
+        self.literals.insert(interned.clone());
+        interned
+    }
+
+    /// Like [`Interner::intern`], but keeps the allocation of a literal
+    /// which isn't interned yet
+    pub(crate) fn share(&mut self, literal: Arc<str>) -> Arc<str> {
+        if let Some(interned) = self.literals.get(&literal) {
+            return interned.clone();
+        }
+
+        self.literals.insert(literal.clone());
+        literal
+    }
+
+    /// Lowercases a literal, a literal which is already lowercase keeps its
+    /// allocation
+    pub(crate) fn fold(&mut self, literal: Arc<str>) -> Arc<str> {
+        let folded = literal.to_lowercase();
+
+        match *folded == *literal {
+            true => self.share(literal),
+            false => self.intern(folded),
+        }
+    }
+
+    /// Shares the literals of every query of an ast
+    pub(crate) fn intern_ast(&mut self, ast: AST) -> AST {
+        match ast {
+            AST::Query(query) => AST::Query(self.intern_query(query)),
+            AST::Not(ast) => AST::Not(Box::new(self.intern_ast(*ast))),
+            AST::BinaryExpression {
+                left,
+                operator,
+                right,
+            } => AST::BinaryExpression {
+                left: Box::new(self.intern_ast(*left)),
+                operator,
+                right: Box::new(self.intern_ast(*right)),
+            },
+        }
+    }
+
+    fn intern_query(&mut self, query: Query) -> Query {
+        match query {
+            Query::Starts(arg) => Query::Starts(self.share(arg)),
+            Query::Ends(arg) => Query::Ends(self.share(arg)),
+            Query::Contains(arg) => Query::Contains(self.share(arg)),
+            Query::ContainsTimes(arg, times) => Query::ContainsTimes(self.share(arg), times),
+            Query::Equals(arg) => Query::Equals(self.share(arg)),
+            Query::IStarts(arg) => Query::IStarts(self.share(arg)),
+            Query::IEnds(arg) => Query::IEnds(self.share(arg)),
+            Query::IContains(arg) => Query::IContains(self.share(arg)),
+            Query::IEquals(arg) => Query::IEquals(self.share(arg)),
+            query => query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::Interner;
+
+    #[test]
+    fn shares_identical_literals() {
+        let mut interner = Interner::default();
+        let first = interner.intern("ab".to_string());
+
+        assert!(Arc::ptr_eq(&first, &interner.share("ab".into())));
+        assert!(Arc::ptr_eq(&first, &interner.fold("AB".into())));
+        assert!(!Arc::ptr_eq(&first, &interner.intern("b".to_string())));
+    }
+
+    #[test]
+    fn keeps_lowercase_literals() {
+        let mut interner = Interner::default();
+        let literal: Arc<str> = "ab".into();
+
+        assert!(Arc::ptr_eq(&literal, &interner.fold(literal.clone())));
+    }
+}
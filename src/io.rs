@@ -0,0 +1,206 @@
+//! Adapters to filter lines of readers and writers by an expression. With
+//! the `tokio` feature there are async equivalents for async readers and
+//! streams
+
+use std::io::{self, BufRead, Lines, Write};
+
+use crate::Expression;
+
+#[cfg(feature = "tokio")]
+mod stream;
+
+#[cfg(feature = "tokio")]
+pub use stream::{AsyncMatchingLines, FilterStreamExt, MatchingStream};
+
+/// An iterator over the lines of a reader which match an expression, the
+/// line terminators are removed like by [`BufRead::lines`]
+///
+/// ```rust
+/// use srch::io::MatchingLines;
+///
+/// let expr = srch::Expression::new("numeric").unwrap();
+/// let input = "123\nabc\n456\n".as_bytes();
+///
+/// let lines = MatchingLines::new(input, &expr).collect::<std::io::Result<Vec<_>>>().unwrap();
+/// assert_eq!(lines, vec!["123", "456"]);
+/// ```
+pub struct MatchingLines<'e, R> {
+    lines: Lines<R>,
+    expression: &'e Expression,
+}
+
+impl<'e, R: BufRead> MatchingLines<'e, R> {
+    pub fn new(reader: R, expression: &'e Expression) -> Self {
+        Self {
+            lines: reader.lines(),
+            expression,
+        }
+    }
+}
+
+impl<'e, R: BufRead> Iterator for MatchingLines<'e, R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(line) if !self.expression.matches(&line) => continue,
+                result => return Some(result),
+            }
+        }
+    }
+}
+
+/// A writer which passes only the lines matching an expression on to the
+/// inner writer. Incomplete lines are buffered until their line terminator
+/// is written, the last line is written on [`into_inner`](Self::into_inner)
+/// or when the writer is dropped
+///
+/// ```rust
+/// use std::io::Write;
+/// use srch::io::FilterWriter;
+///
+/// let expr = srch::Expression::new("numeric").unwrap();
+/// let mut writer = FilterWriter::new(Vec::new(), &expr);
+///
+/// write!(writer, "123\nabc\n45").unwrap();
+/// write!(writer, "6").unwrap();
+///
+/// assert_eq!(writer.into_inner().unwrap(), b"123\n456");
+/// ```
+pub struct FilterWriter<'e, W: Write> {
+    inner: Option<W>,
+    expression: &'e Expression,
+    line: Vec<u8>,
+}
+
+impl<'e, W: Write> FilterWriter<'e, W> {
+    pub fn new(inner: W, expression: &'e Expression) -> Self {
+        Self {
+            inner: Some(inner),
+            expression,
+            line: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.inner
+            .as_ref()
+            .expect("the inner writer is only taken on into_inner")
+    }
+
+    /// Writes the buffered incomplete line if it matches and returns the
+    /// inner writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.write_incomplete_line()?;
+        Ok(self
+            .inner
+            .take()
+            .expect("the inner writer is only taken once"))
+    }
+
+    /// Writes `line` (including its terminator) to the inner writer if the
+    /// line matches
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let content = line.strip_suffix(b"\n").unwrap_or(line);
+        let content = content.strip_suffix(b"\r").unwrap_or(content);
+
+        if !self.expression.matches(String::from_utf8_lossy(content)) {
+            return Ok(());
+        }
+
+        match self.inner.as_mut() {
+            Some(inner) => inner.write_all(line),
+            None => Ok(()),
+        }
+    }
+
+    fn write_incomplete_line(&mut self) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+
+        let line = std::mem::take(&mut self.line);
+        self.write_line(&line)
+    }
+}
+
+impl<'e, W: Write> Write for FilterWriter<'e, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+
+        while let Some(end) = rest.iter().position(|byte| *byte == b'\n') {
+            let (line, remainder) = rest.split_at(end + 1);
+
+            if self.line.is_empty() {
+                self.write_line(line)?;
+            } else {
+                let mut buffered = std::mem::take(&mut self.line);
+                buffered.extend_from_slice(line);
+                self.write_line(&buffered)?;
+            }
+
+            rest = remainder;
+        }
+
+        self.line.extend_from_slice(rest);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'e, W: Write> Drop for FilterWriter<'e, W> {
+    fn drop(&mut self) {
+        let _ = self.write_incomplete_line();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::{FilterWriter, MatchingLines};
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn matching_lines_removes_line_terminators() {
+        let expr = Expression::new("alpha").unwrap();
+        let lines = MatchingLines::new("abc\r\n123\r\ndef".as_bytes(), &expr)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines, vec!["abc", "def"]);
+    }
+
+    #[test]
+    fn filter_writer_keeps_line_terminators() {
+        let expr = Expression::new("alpha").unwrap();
+        let mut writer = FilterWriter::new(Vec::new(), &expr);
+
+        writer.write_all(b"abc\r\n123\r\nd").unwrap();
+        writer.write_all(b"ef\n").unwrap();
+
+        assert_eq!(writer.into_inner().unwrap(), b"abc\r\ndef\n");
+    }
+
+    #[test]
+    fn filter_writer_writes_the_last_line_on_drop() {
+        let expr = Expression::new("alpha").unwrap();
+        let mut output = Vec::new();
+
+        {
+            let mut writer = FilterWriter::new(&mut output, &expr);
+            writer.write_all(b"123\nabc").unwrap();
+        }
+
+        assert_eq!(output, b"abc");
+    }
+}
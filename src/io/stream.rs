@@ -0,0 +1,151 @@
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+use crate::Expression;
+
+/// A stream yielding only the items of the inner stream which match an
+/// expression, see [`FilterStreamExt::srch_filter`]
+pub struct MatchingStream<'e, S> {
+    stream: S,
+    expression: &'e Expression,
+}
+
+impl<'e, S> Stream for MatchingStream<'e, S>
+where
+    S: Stream + Unpin,
+    S::Item: AsRef<str>,
+{
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        loop {
+            match Pin::new(&mut self.stream).poll_next(cx) {
+                Poll::Ready(Some(item)) if !self.expression.matches(item.as_ref()) => continue,
+                poll => return poll,
+            }
+        }
+    }
+}
+
+/// Adds [`srch_filter`](Self::srch_filter) to every stream
+pub trait FilterStreamExt: Stream + Sized {
+    /// Filters the items of the stream by an expression
+    fn srch_filter(self, expression: &Expression) -> MatchingStream<'_, Self> {
+        MatchingStream {
+            stream: self,
+            expression,
+        }
+    }
+}
+
+impl<S: Stream> FilterStreamExt for S {}
+
+/// The async equivalent of [`MatchingLines`](super::MatchingLines), decodes
+/// the lines of an async reader and yields those matching an expression
+///
+/// ```rust
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// use srch::io::AsyncMatchingLines;
+///
+/// let expr = srch::Expression::new("numeric").unwrap();
+/// let mut lines = AsyncMatchingLines::new("123\nabc\n456".as_bytes(), &expr);
+///
+/// assert_eq!(lines.next_line().await.unwrap(), Some("123".to_string()));
+/// assert_eq!(lines.next_line().await.unwrap(), Some("456".to_string()));
+/// assert_eq!(lines.next_line().await.unwrap(), None);
+/// # });
+/// ```
+pub struct AsyncMatchingLines<'e, R> {
+    lines: Lines<R>,
+    expression: &'e Expression,
+}
+
+impl<'e, R: AsyncBufRead + Unpin> AsyncMatchingLines<'e, R> {
+    pub fn new(reader: R, expression: &'e Expression) -> Self {
+        Self::from_lines(reader.lines(), expression)
+    }
+
+    pub fn from_lines(lines: Lines<R>, expression: &'e Expression) -> Self {
+        Self { lines, expression }
+    }
+
+    /// Returns the next matching line, or `None` if the reader is exhausted
+    pub async fn next_line(&mut self) -> io::Result<Option<String>> {
+        loop {
+            match self.lines.next_line().await? {
+                Some(line) if !self.expression.matches(&line) => continue,
+                line => return Ok(line),
+            }
+        }
+    }
+}
+
+impl<'e, R: AsyncBufRead + Unpin> Stream for AsyncMatchingLines<'e, R> {
+    type Item = io::Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match Pin::new(&mut self.lines).poll_next_line(cx) {
+                Poll::Ready(Ok(Some(line))) if !self.expression.matches(&line) => continue,
+                Poll::Ready(result) => return Poll::Ready(result.transpose()),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+    use std::pin::Pin;
+
+    use futures_core::Stream;
+
+    use super::{AsyncMatchingLines, FilterStreamExt};
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    async fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+        let mut items = Vec::new();
+
+        while let Some(item) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            items.push(item);
+        }
+
+        items
+    }
+
+    #[tokio::test]
+    async fn filters_streams() {
+        let expr = Expression::new("alpha").unwrap();
+        let lines = AsyncMatchingLines::new("abc\n123\ndef\n".as_bytes(), &expr);
+
+        let lines = collect(lines).await.into_iter().map(Result::unwrap);
+        assert_eq!(lines.collect::<Vec<_>>(), vec!["abc", "def"]);
+    }
+
+    #[tokio::test]
+    async fn filters_streams_with_srch_filter() {
+        struct Items(Vec<&'static str>);
+
+        impl Stream for Items {
+            type Item = &'static str;
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _: &mut std::task::Context<'_>,
+            ) -> std::task::Poll<Option<Self::Item>> {
+                std::task::Poll::Ready(self.0.pop())
+            }
+        }
+
+        let expr = Expression::new("numeric").unwrap();
+        let items = collect(Items(vec!["1", "a", "2"]).srch_filter(&expr)).await;
+
+        assert_eq!(items, vec!["2", "1"]);
+    }
+}
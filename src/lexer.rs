@@ -1,12 +1,21 @@
+use std::fmt;
 use std::iter::Peekable;
+use std::ops::Range;
+use std::str::Chars;
+use std::sync::Arc;
 
-use crate::query::Query;
+use crate::i18n;
+use crate::interner::Interner;
+use crate::query::{LengthRange, Query, Times, ValueRange};
 use crate::logical_operator::LogicalOperator;
+use crate::pattern::Pattern;
+use crate::registry;
 
 
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
 	UnknownSyntax,
 	InternalError,
@@ -16,18 +25,45 @@ pub enum Error {
 	ExpectedString,
 	ExpectedInteger,
 	ExpectedQuery,
-	ExpectedOperator
+	ExpectedOperator,
+	UnknownPattern(String),
+	/// A range of lengths no length is in, e.g. `length between 5 and 2`
+	EmptyRange
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let message = match self {
+			Self::UnknownSyntax => i18n::message("lexer.unknown_syntax", ""),
+			Self::InternalError => i18n::message("lexer.unknown_keyword", ""),
+			Self::ToManyArguments => i18n::message("lexer.too_many_arguments", ""),
+			Self::NoLeadingZeros => i18n::message("lexer.leading_zeros", ""),
+			Self::UnclosedString => i18n::message("lexer.unclosed_string", ""),
+			Self::ExpectedString => i18n::message("lexer.expected_string", ""),
+			Self::ExpectedInteger => i18n::message("lexer.expected_integer", ""),
+			Self::ExpectedQuery => i18n::message("lexer.expected_query", ""),
+			Self::ExpectedOperator => i18n::message("lexer.expected_operator", ""),
+			Self::UnknownPattern(name) => i18n::message("lexer.unknown_pattern", name),
+			Self::EmptyRange => i18n::message("lexer.empty_range", "")
+		};
+
+		write!(f, "{}", message)
+	}
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Token {
 	Query(Query),
-	LogicalOperator(LogicalOperator)
+	LogicalOperator(LogicalOperator),
+	/// Negates the following query
+	Not
 }
 
 #[derive(Clone)]
 pub struct Lexer<I: Iterator<Item = char> + Clone> {
 	iter: Peekable<I>,
+	/// The string literals read so far, identical literals share one allocation
+	literals: Interner
 }
 
 impl<I: Iterator<Item = char> + Clone> Lexer<I> {
@@ -35,6 +71,7 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 	fn new(input: I) -> Self {
 		Self {
 			iter: input.peekable(),
+			literals: Interner::default()
 		}
 	}
 
@@ -44,11 +81,20 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 				Some(c) if c.is_ascii_whitespace() => {
 					self.iter.next();
 				}
+				Some('#') => self.skip_comment(),
 				_ => break,
 			}
 		}
 	}
 
+	fn skip_comment(&mut self) {
+		for c in &mut self.iter {
+			if c == '\n' {
+				break;
+			}
+		}
+	}
+
 	fn peek(&mut self) -> Option<char> {
 		self.trim();
 		self.iter.peek().cloned()
@@ -89,9 +135,18 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 		Ok(Some(seq))
 	}
 
-	fn expect_string(&mut self) -> Result<String> {
+	fn expect_string(&mut self) -> Result<Arc<str>> {
+		match self.read_string()? {
+			Some(s) => Ok(self.literals.intern(s)),
+			None => Err(Error::ExpectedString)
+		}
+	}
+
+	/// Reads the argument of a case-insensitive query, which is lowercased
+	/// once instead of on every match
+	fn expect_folded_string(&mut self) -> Result<Arc<str>> {
 		match self.read_string()? {
-			Some(s) => Ok(s),
+			Some(s) => Ok(self.literals.intern(s.to_lowercase())),
 			None => Err(Error::ExpectedString)
 		}
 	}
@@ -144,6 +199,105 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 		}
 	}
 
+	/// Reads the argument of `length` or `words`, an integer or a range like
+	/// `over 5`, `at least 5` or `between 2 and 8`
+	fn expect_range(&mut self, exact: fn(u64) -> Query, in_range: fn(LengthRange) -> Query) -> Result<Query> {
+		match self.peek() {
+			Some(c) if !c.is_ascii_digit() => {},
+			_ => return Ok(exact(self.expect_integer()?))
+		}
+
+		let range = match self.expect_keyword()?.as_str() {
+			"over" => LengthRange::Over(self.expect_integer()?),
+			"under" => LengthRange::Under(self.expect_integer()?),
+			"at" => {
+				self.trim();
+
+				match self.expect_keyword()?.as_str() {
+					"least" => LengthRange::AtLeast(self.expect_integer()?),
+					"most" => LengthRange::AtMost(self.expect_integer()?),
+					_ => return Err(Error::UnknownSyntax)
+				}
+			},
+			"between" => {
+				let min = self.expect_integer()?;
+				self.trim();
+
+				if self.expect_keyword()? != "and" {
+					return Err(Error::UnknownSyntax);
+				}
+
+				LengthRange::Between(min, self.expect_integer()?)
+			},
+			_ => return Err(Error::ExpectedInteger)
+		};
+
+		match range.is_empty() {
+			true => Err(Error::EmptyRange),
+			false => Ok(in_range(range))
+		}
+	}
+
+	/// Reads the argument of `value`, a comparison like `over 100`
+	fn expect_value(&mut self) -> Result<Query> {
+		self.trim();
+
+		let range = match self.expect_keyword()?.as_str() {
+			"over" => ValueRange::Over(self.expect_integer()?),
+			"under" => ValueRange::Under(self.expect_integer()?),
+			"equals" => ValueRange::Equals(self.expect_integer()?),
+			_ => return Err(Error::UnknownSyntax)
+		};
+
+		Ok(Query::Value(range))
+	}
+
+	/// Reads the quantifier which may follow the argument of `contains`, e.g.
+	/// `at least 3 times` or `exactly 1 time`
+	fn read_times(&mut self) -> Result<Option<Times>> {
+		self.trim();
+
+		let mut lookahead = self.iter.clone();
+		let keyword = std::iter::from_fn(|| lookahead.next_if(|c| !c.is_ascii_whitespace())).collect::<String>();
+
+		let times = match keyword.as_str() {
+			"exactly" => {
+				self.expect_keyword()?;
+				Times::Exactly(self.expect_integer()?)
+			},
+			"at" => {
+				self.expect_keyword()?;
+				self.trim();
+
+				match self.expect_keyword()?.as_str() {
+					"least" => Times::AtLeast(self.expect_integer()?),
+					"most" => Times::AtMost(self.expect_integer()?),
+					_ => return Err(Error::UnknownSyntax)
+				}
+			},
+			_ => return Ok(None)
+		};
+
+		self.trim();
+
+		match self.expect_keyword()?.as_str() {
+			"time" | "times" => Ok(Some(times)),
+			_ => Err(Error::UnknownSyntax)
+		}
+	}
+
+	/// Reads the argument of `contains` and its quantifier, occurrences of an
+	/// empty string can't be counted
+	fn expect_contains(&mut self) -> Result<Query> {
+		let arg = self.expect_string()?;
+
+		match self.read_times()? {
+			Some(_) if arg.is_empty() => Err(Error::UnknownSyntax),
+			Some(times) => Ok(Query::ContainsTimes(arg, times)),
+			None => Ok(Query::Contains(arg))
+		}
+	}
+
 	fn expect_keyword(&mut self) -> Result<String> {
 		let mut keyword = String::new();
 
@@ -159,23 +313,40 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 		Ok(keyword)
 	}
 
-	fn query_from_keyword(&mut self, keyword: &String) -> Result<Option<Query>> {
-		match keyword.as_str() {
+	fn query_from_keyword(&mut self, keyword: &str) -> Result<Option<Query>> {
+		if let Some(name) = keyword.strip_prefix('@') {
+			return match Pattern::from_name(name) {
+				Some(pattern) => Ok(Some(Query::Pattern(pattern))),
+				None if registry::is_registered(name) => Ok(Some(Query::Custom(name.into()))),
+				None => Err(Error::UnknownPattern(name.to_string()))
+			};
+		}
+
+		match keyword {
 			"starts" => Ok(Some(Query::Starts(self.expect_string()?))),
 			"ends" => Ok(Some(Query::Ends(self.expect_string()?))),
-			"contains" => Ok(Some(Query::Contains(self.expect_string()?))),
+			"contains" => Ok(Some(self.expect_contains()?)),
 			"equals" => Ok(Some(Query::Equals(self.expect_string()?))),
-			"length" => Ok(Some(Query::Length(self.expect_integer()?))),
+			"istarts" => Ok(Some(Query::IStarts(self.expect_folded_string()?))),
+			"iends" => Ok(Some(Query::IEnds(self.expect_folded_string()?))),
+			"icontains" => Ok(Some(Query::IContains(self.expect_folded_string()?))),
+			"iequals" => Ok(Some(Query::IEquals(self.expect_folded_string()?))),
+			"length" => Ok(Some(self.expect_range(Query::Length, Query::LengthRange)?)),
+			"words" => Ok(Some(self.expect_range(Query::Words, Query::WordsRange)?)),
+			"value" => Ok(Some(self.expect_value()?)),
 			"numeric" => Ok(Some(Query::Numeric)),
 			"alpha" => Ok(Some(Query::Alpha)),
 			"alphanumeric" => Ok(Some(Query::Alphanumeric)),
+			"uppercase" => Ok(Some(Query::Uppercase)),
+			"lowercase" => Ok(Some(Query::Lowercase)),
+			"whitespace" => Ok(Some(Query::Whitespace)),
 			"special" => Ok(Some(Query::Special)),
 			_ => Ok(None)
 		}
 	}
 
-	fn operator_from_keyword(&mut self, keyword: &String) -> Result<Option<LogicalOperator>> {
-		match keyword.as_str() {
+	fn operator_from_keyword(&mut self, keyword: &str) -> Result<Option<LogicalOperator>> {
+		match keyword {
 			"and" => Ok(Some(LogicalOperator::And)),
 			"or" => Ok(Some(LogicalOperator::Or)),
 			_ => Ok(None)
@@ -194,13 +365,40 @@ impl<I: Iterator<Item = char> + Clone> Lexer<I> {
 			return Ok(Some(Token::Query(query)));
 		} else if let Some(operator) = self.operator_from_keyword(&keyword)? {
 			return Ok(Some(Token::LogicalOperator(operator)));
+		} else if keyword == "not" {
+			return Ok(Some(Token::Not));
 		}
 
 		Err(Error::InternalError)
 	}
 }
 
-pub fn lex(expr: &String) -> Result<Vec<Token>> {
+/// A byte range of the lexed source
+pub type Span = Range<usize>;
+
+/// Lexes an expression like [`lex`], but keeps the span of every token. On
+/// failure the span covers the token the error occured in
+pub fn lex_spanned(expr: &str) -> std::result::Result<Vec<(Token, Span)>, (Error, Span)> {
+	fn offset(expr: &str, lexer: &Lexer<Chars>) -> usize {
+		expr.len() - lexer.iter.clone().map(char::len_utf8).sum::<usize>()
+	}
+
+	let mut lexer = Lexer::new(expr.chars());
+	let mut tokens = Vec::new();
+
+	loop {
+		lexer.trim();
+		let start = offset(expr, &lexer);
+
+		match lexer.next() {
+			Ok(Some(token)) => tokens.push((token, start..offset(expr, &lexer))),
+			Ok(None) => return Ok(tokens),
+			Err(err) => return Err((err, start..offset(expr, &lexer).max(start)))
+		}
+	}
+}
+
+pub fn lex(expr: &str) -> Result<Vec<Token>> {
 	let mut lexer = Lexer::new(expr.chars());
 	let mut tokens: Vec<Token> = Vec::new();
 
@@ -214,9 +412,10 @@ pub fn lex(expr: &String) -> Result<Vec<Token>> {
 
 #[cfg(test)]
 mod tests {
-	use super::{lex, Token};
+	use super::{lex, Error, Token};
 	use crate::query::Query;
 	use crate::logical_operator::LogicalOperator;
+	use crate::pattern::Pattern;
 
 	macro_rules! lexer_tests {
 		($($name:ident: $value:expr,)*) => {
@@ -237,25 +436,25 @@ mod tests {
 			starts: (
 				"starts \"foo\"",
 				vec![
-					Token::Query(Query::Starts("foo".to_string()))
+					Token::Query(Query::Starts("foo".into()))
 				]
 			),
 			ends: (
 				"ends \"foo\"",
 				vec![
-					Token::Query(Query::Ends("foo".to_string()))
+					Token::Query(Query::Ends("foo".into()))
 				]
 			),
 			contains: (
 				"contains \"foo\"",
 				vec![
-					Token::Query(Query::Contains("foo".to_string()))
+					Token::Query(Query::Contains("foo".into()))
 				]
 			),
 			equals: (
 				"equals \"foo\"",
 				vec![
-					Token::Query(Query::Equals("foo".to_string()))
+					Token::Query(Query::Equals("foo".into()))
 				]
 			),
 			length: (
@@ -288,6 +487,30 @@ mod tests {
 					Token::Query(Query::Special)
 				]
 			),
+			uppercase: (
+				"uppercase",
+				vec![
+					Token::Query(Query::Uppercase)
+				]
+			),
+			lowercase: (
+				"lowercase",
+				vec![
+					Token::Query(Query::Lowercase)
+				]
+			),
+			whitespace: (
+				"whitespace",
+				vec![
+					Token::Query(Query::Whitespace)
+				]
+			),
+			pattern: (
+				"@email",
+				vec![
+					Token::Query(Query::Pattern(Pattern::Email))
+				]
+			),
 		}
 	}
 
@@ -323,6 +546,16 @@ mod tests {
 					Token::LogicalOperator(LogicalOperator::Or)
 				]
 			),
+			not: (
+				"not numeric and not length 5",
+				vec![
+					Token::Not,
+					Token::Query(Query::Numeric),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Not,
+					Token::Query(Query::Length(5))
+				]
+			),
 		}
 	}
 
@@ -333,55 +566,55 @@ mod tests {
 			starts_and_ends: (
 				"starts \"baz\" and ends \"bar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
-					Token::Query(Query::Ends("bar".to_string()))
+					Token::Query(Query::Ends("bar".into()))
 				]
 			),
 			starts_or_ends: (
 				"starts \"baz\" or ends \"bar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
-					Token::Query(Query::Ends("bar".to_string()))
+					Token::Query(Query::Ends("bar".into()))
 				]
 			),
 			starts_and_contains: (
 				"starts \"baz\" and contains \"bar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
-					Token::Query(Query::Contains("bar".to_string()))
+					Token::Query(Query::Contains("bar".into()))
 				]
 			),
 			starts_or_contains: (
 				"starts \"baz\" or contains \"bar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
-					Token::Query(Query::Contains("bar".to_string()))
+					Token::Query(Query::Contains("bar".into()))
 				]
 			),
 			starts_and_equals: (
 				"starts \"baz\" and equals \"bazbar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
-					Token::Query(Query::Equals("bazbar".to_string()))
+					Token::Query(Query::Equals("bazbar".into()))
 				]
 			),
 			starts_or_equals: (
 				"starts \"baz\" or equals \"bazbar\"",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
-					Token::Query(Query::Equals("bazbar".to_string()))
+					Token::Query(Query::Equals("bazbar".into()))
 				]
 			),
 			starts_and_length: (
 				"starts \"baz\" and length 10",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
 					Token::Query(Query::Length(10))
 				]
@@ -389,7 +622,7 @@ mod tests {
 			starts_or_length: (
 				"starts \"baz\" or length 12130",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Length(12130))
 				]
@@ -397,7 +630,7 @@ mod tests {
 			starts_and_numeric: (
 				"starts \"baz\" and numeric",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
 					Token::Query(Query::Numeric)
 				]
@@ -405,7 +638,7 @@ mod tests {
 			starts_or_numeric: (
 				"starts \"baz\" or numeric",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Numeric)
 				]
@@ -413,7 +646,7 @@ mod tests {
 			starts_and_alpha: (
 				"starts \"baz\" and alpha",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
 					Token::Query(Query::Alpha)
 				]
@@ -421,7 +654,7 @@ mod tests {
 			starts_or_alpha: (
 				"starts \"baz\" or alpha",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Alpha)
 				]
@@ -429,7 +662,7 @@ mod tests {
 			starts_and_alphanumeric: (
 				"starts \"baz\" and alphanumeric",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
 					Token::Query(Query::Alphanumeric)
 				]
@@ -437,7 +670,7 @@ mod tests {
 			starts_or_alphanumeric: (
 				"starts \"baz\" or alphanumeric",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Alphanumeric)
 				]
@@ -445,7 +678,7 @@ mod tests {
 			starts_and_special: (
 				"starts \"baz\" and special",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
 					Token::Query(Query::Special)
 				]
@@ -453,7 +686,7 @@ mod tests {
 			starts_or_special: (
 				"starts \"baz\" or special",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Special)
 				]
@@ -468,9 +701,9 @@ mod tests {
 			starts_and_ends_or_length_or_special: (
 				"starts \"baz\" and ends \"bar\" or length 123 or special",
 				vec![
-					Token::Query(Query::Starts("baz".to_string())),
+					Token::Query(Query::Starts("baz".into())),
 					Token::LogicalOperator(LogicalOperator::And),
-					Token::Query(Query::Ends("bar".to_string())),
+					Token::Query(Query::Ends("bar".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Length(123)),
 					Token::LogicalOperator(LogicalOperator::Or),
@@ -495,7 +728,7 @@ mod tests {
 			begins_with_multiple_whitespaces_and_query_with_string: (
 				"    starts \"foo\" or alpha",
 				vec![
-					Token::Query(Query::Starts("foo".to_string())),
+					Token::Query(Query::Starts("foo".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Alpha),
 				]
@@ -521,7 +754,7 @@ mod tests {
 				vec![
 					Token::Query(Query::Numeric),
 					Token::LogicalOperator(LogicalOperator::Or),
-					Token::Query(Query::Starts("foo".to_string())),
+					Token::Query(Query::Starts("foo".into())),
 				]
 			),
 			ends_with_multiple_whitespacess_and_query_with_integer: (
@@ -551,7 +784,7 @@ mod tests {
 			has_multiple_whitespaces_between_query_with_string_and_operator: (
 				"starts \"foo\"      or      alpha",
 				vec![
-					Token::Query(Query::Starts("foo".to_string())),
+					Token::Query(Query::Starts("foo".into())),
 					Token::LogicalOperator(LogicalOperator::Or),
 					Token::Query(Query::Alpha),
 				]
@@ -566,4 +799,240 @@ mod tests {
 			),
 		}
 	}
+
+	mod it_ignores_comments {
+		use super::*;
+
+		lexer_tests! {
+			only_comment: (
+				"# nothing to see here",
+				vec![]
+			),
+			trailing_comment: (
+				"numeric # digits only",
+				vec![
+					Token::Query(Query::Numeric),
+				]
+			),
+			comment_between_lines: (
+				"numeric\n# digits only\nor alpha",
+				vec![
+					Token::Query(Query::Numeric),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::Alpha),
+				]
+			),
+			hash_inside_string: (
+				"contains \"#\" # a hash",
+				vec![
+					Token::Query(Query::Contains("#".into())),
+				]
+			),
+		}
+	}
+
+	mod it_parses_patterns {
+		use super::*;
+
+		lexer_tests! {
+			pattern_and_query: (
+				"@uuid or @ipv4 and length 9",
+				vec![
+					Token::Query(Query::Pattern(Pattern::Uuid)),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::Pattern(Pattern::Ipv4)),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::Length(9)),
+				]
+			),
+		}
+
+		#[test]
+		fn unknown_pattern() {
+			match lex("@unknown") {
+				Err(Error::UnknownPattern(name)) => pretty_assertions::assert_eq!(name, "unknown"),
+				result => panic!("expected an unknown pattern error, got {:?}", result)
+			}
+		}
+	}
+
+	mod it_parses_length_ranges {
+		use super::*;
+		use crate::query::LengthRange;
+
+		lexer_tests! {
+			over: (
+				"length over 5",
+				vec![
+					Token::Query(Query::LengthRange(LengthRange::Over(5)))
+				]
+			),
+			under: (
+				"length under 5",
+				vec![
+					Token::Query(Query::LengthRange(LengthRange::Under(5)))
+				]
+			),
+			at_least_and_at_most: (
+				"length at least 2 and length  at  most 8",
+				vec![
+					Token::Query(Query::LengthRange(LengthRange::AtLeast(2))),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::LengthRange(LengthRange::AtMost(8))),
+				]
+			),
+			between: (
+				"length between 2 and 8 and numeric",
+				vec![
+					Token::Query(Query::LengthRange(LengthRange::Between(2, 8))),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::Numeric),
+				]
+			),
+		}
+
+		#[test]
+		fn rejects_malformed_ranges() {
+			assert!(matches!(lex("length over"), Err(Error::ExpectedInteger)));
+			assert!(matches!(lex("length above 5"), Err(Error::ExpectedInteger)));
+			assert!(matches!(lex("length at 5"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("length between 2 or 8"), Err(Error::UnknownSyntax)));
+		}
+
+		#[test]
+		fn rejects_empty_ranges() {
+			assert!(matches!(lex("length under 0"), Err(Error::EmptyRange)));
+			assert!(matches!(lex("length between 8 and 2"), Err(Error::EmptyRange)));
+			assert!(lex("length between 2 and 2").is_ok());
+		}
+	}
+
+	mod it_parses_value_comparisons {
+		use super::*;
+		use crate::query::ValueRange;
+
+		lexer_tests! {
+			comparisons: (
+				"numeric and value over 1000 or value  under 0 or value equals 42",
+				vec![
+					Token::Query(Query::Numeric),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::Value(ValueRange::Over(1000))),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::Value(ValueRange::Under(0))),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::Value(ValueRange::Equals(42))),
+				]
+			),
+		}
+
+		#[test]
+		fn rejects_malformed_comparisons() {
+			assert!(matches!(lex("value"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("value 5"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("value above 5"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("value over"), Err(Error::ExpectedInteger)));
+			assert!(matches!(lex("value over -5"), Err(Error::ExpectedInteger)));
+		}
+	}
+
+	mod it_parses_word_counts {
+		use super::*;
+		use crate::query::LengthRange;
+
+		lexer_tests! {
+			counts: (
+				"words 3 or words over 5 and words between 1 and 2",
+				vec![
+					Token::Query(Query::Words(3)),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::WordsRange(LengthRange::Over(5))),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::WordsRange(LengthRange::Between(1, 2))),
+				]
+			),
+		}
+
+		#[test]
+		fn rejects_empty_ranges() {
+			assert!(matches!(lex("words under 0"), Err(Error::EmptyRange)));
+			assert!(matches!(lex("words"), Err(Error::ExpectedInteger)));
+		}
+	}
+
+	mod it_parses_quantified_contains {
+		use super::*;
+		use crate::query::Times;
+
+		lexer_tests! {
+			quantifiers: (
+				"contains \",\" exactly 2 times and contains \"a\" at least 1 time or contains \"b\" at most 3 times",
+				vec![
+					Token::Query(Query::ContainsTimes(",".into(), Times::Exactly(2))),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::ContainsTimes("a".into(), Times::AtLeast(1))),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::ContainsTimes("b".into(), Times::AtMost(3))),
+				]
+			),
+			without_quantifier: (
+				"contains \"a\" and length 2",
+				vec![
+					Token::Query(Query::Contains("a".into())),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Query(Query::Length(2)),
+				]
+			),
+		}
+
+		#[test]
+		fn rejects_malformed_quantifiers() {
+			assert!(matches!(lex("contains \"a\" exactly 2"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("contains \"a\" at 2 times"), Err(Error::UnknownSyntax)));
+			assert!(matches!(lex("contains \"a\" exactly two times"), Err(Error::ExpectedInteger)));
+			assert!(matches!(lex("contains \"\" at least 1 time"), Err(Error::UnknownSyntax)));
+		}
+	}
+
+	mod it_parses_case_insensitive_queries {
+		use super::*;
+
+		lexer_tests! {
+			lowercases_the_arguments: (
+				"istarts \"Foo\" or iends \"BAR\" or icontains \"Baz\" or iequals \"ÄÖ\"",
+				vec![
+					Token::Query(Query::IStarts("foo".into())),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::IEnds("bar".into())),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::IContains("baz".into())),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Query(Query::IEquals("äö".into())),
+				]
+			),
+		}
+
+		#[test]
+		fn requires_a_string() {
+			assert!(matches!(lex("icontains 5"), Err(Error::ExpectedString)));
+		}
+	}
+
+	mod it_interns_literals {
+		use super::*;
+		use std::sync::Arc;
+
+		#[test]
+		fn identical_literals_share_their_allocation() {
+			let tokens = lex("starts \"ab\" or ends \"ab\" or contains \"b\"").unwrap();
+
+			let literals = tokens.iter().filter_map(|token| match token {
+				Token::Query(Query::Starts(arg)) | Token::Query(Query::Ends(arg)) | Token::Query(Query::Contains(arg)) => Some(arg),
+				_ => None
+			}).collect::<Vec<_>>();
+
+			assert!(Arc::ptr_eq(literals[0], literals[1]));
+			assert!(!Arc::ptr_eq(literals[0], literals[2]));
+		}
+	}
 }
@@ -28,6 +28,46 @@
 //! ```rust
 //! let expr = srch::Expression::new(&"contains \"@\" and contains \".com\"".to_owned()).unwrap();
 //! assert!(expr.matches("foo@baz.com"));
+//! ```
+//!
+//! ## Everything but errors
+//!
+//! ```rust
+//! let expr = srch::Expression::new("not contains \"error\" and not length 0").unwrap();
+//! assert!(expr.matches("all good"));
+//! assert!(!expr.matches("error: disk full"));
+//! ```
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature enabled, compiling expressions emits
+//! [`tracing`](https://crates.io/crates/tracing) events.
+//!
+//! # Plugins
+//!
+//! Custom queries are registered using [`register_query`] and written like
+//! the built-in patterns, e.g. `@ticket_id`. With the `plugins` feature they
+//! can be loaded from shared libraries using `Plugin::load`, which implement
+//! a small versioned C abi.
+//!
+//! # WebAssembly
+//!
+//! The `srch-wasm` crate in `wasm/` exports `compile(source)`,
+//! `matches(handle, input)` and `explain(handle)` using
+//! [`wasm-bindgen`](https://crates.io/crates/wasm-bindgen), an npm package can
+//! be built with `wasm-pack build` in that directory.
+//!
+//! # Python
+//!
+//! The `python` feature builds a python module using [`pyo3`](https://pyo3.rs).
+//! Batch matching with `matches_all` and `filter` releases the GIL:
+//!
+//! ```python
+//! import srch
+//!
+//! expr = srch.Expression("numeric and length 5")
+//! df[expr.matches_all(df["zip"].tolist())]
+//! ```
 
 // ```
 //
@@ -80,35 +120,624 @@
 //
 // A lot cleaner, right? :) So now we know how we can use performant reusable text expressions!
 
+mod analysis;
+#[cfg(feature = "assertions")]
+#[doc(hidden)]
+pub mod assertions;
+mod batch;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod cache;
+mod clause;
+#[cfg(feature = "collation")]
+mod collation;
+mod convert;
+mod corpus;
+#[cfg(feature = "fancy-errors")]
+mod diagnostic;
+mod document;
 mod error;
+mod evaluation;
+mod expression_set;
+#[cfg(feature = "grep")]
+pub mod grep;
+pub mod i18n;
+mod import;
+mod index;
+mod interner;
+pub mod io;
 mod lexer;
+mod limits;
 mod logical_operator;
+mod optimizer;
+mod options;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod parser;
+mod pattern;
+pub mod pipeline;
+#[cfg(feature = "plugins")]
+mod plugin;
+#[cfg(feature = "polars")]
+mod polars;
+#[cfg(feature = "python")]
+mod python;
 mod query;
+mod registry;
+mod rng;
+mod rule;
+mod rule_set;
 mod runtime;
+mod scan;
+#[cfg(feature = "serde")]
+mod serialization;
+mod stats;
+#[cfg(any(test, feature = "proptest"))]
+pub mod strategies;
+#[doc(hidden)]
+pub mod testing;
+#[cfg(feature = "serde")]
+mod validated;
+#[cfg(feature = "validator")]
+mod validation;
+#[cfg(feature = "notify")]
+mod watch;
 
-pub use error::Result;
+pub use analysis::{Comparison, Finding, FindingKind, Relation, MAX_CLAUSES};
+pub use batch::{Batch, BatchRunner, Continuation};
+pub use cache::CachedExpression;
+pub use clause::{Clause, Condition};
+#[cfg(feature = "collation")]
+pub use collation::{Collation, InvalidLocale};
+pub use convert::SqlDialect;
+pub use corpus::{Corpus, DocumentRule, DocumentRuleError, Quantifier};
+#[cfg(feature = "fancy-errors")]
+pub use diagnostic::SourceError;
+pub use document::{Document, Records, Tokenizer};
+pub use error::{Error, Result};
+pub use evaluation::{Match, Verdict};
+pub use expression_set::{ExpressionSet, Policy};
+pub use import::{from_regex, regex_to_source, UnsupportedFeature};
+pub use index::Index;
+pub use limits::{LimitError, Limits, OutOfFuel};
+pub use options::MatchOptions;
+pub use parser::MAX_DEPTH;
+#[cfg(feature = "parallel")]
+pub use parallel::ParallelFilterExt;
+pub use pattern::Pattern;
+#[cfg(feature = "plugins")]
+pub use plugin::{Plugin, PluginError, PluginQuery, Registrar, PLUGIN_ABI_VERSION};
+#[cfg(feature = "polars")]
+pub use self::polars::SrchExprExt;
+pub use registry::{register_query, registered_queries, RegistryError};
+pub use rule::Rule;
+pub use rule_set::{
+    InvalidRule, Migrations, NamedRule, RuleChange, RuleSet, RuleSetError, Selector, Severity,
+};
 pub use runtime::Runtime;
+pub use stats::QueryStats;
+#[cfg(feature = "serde")]
+pub use validated::Validated;
+#[cfg(feature = "validator")]
+pub use validation::{validate, validate_with};
+#[cfg(feature = "notify")]
+pub use watch::{WatchError, WatchedExpression};
 
-pub fn into_ast(source: &String) -> Result<parser::AST> {
+use std::sync::Arc;
+
+use logical_operator::LogicalOperator;
+
+pub fn into_ast(source: &str) -> Result<parser::AST> {
     let tokens = lexer::lex(source)?;
+
+    #[cfg(feature = "tracing")]
+    tracing::trace!(tokens = tokens.len(), "lexed expression");
+
     let ast = parser::parse(tokens)?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(source, "compiled expression");
+
     Ok(ast)
 }
 
+/// Compiles every source, on all cores with the `parallel` feature, so large
+/// rule sets load quickly and every invalid source is known at once. The
+/// literals of all sources are interned together, so identical literals of
+/// different sources share one allocation
+pub(crate) fn compile_all(sources: &[String]) -> Vec<Result<Expression>> {
+    #[cfg(feature = "parallel")]
+    use rayon::prelude::*;
+
+    #[cfg(feature = "parallel")]
+    let asts = sources
+        .par_iter()
+        .map(|source| into_ast(source))
+        .collect::<Vec<_>>();
+
+    #[cfg(not(feature = "parallel"))]
+    let asts = sources
+        .iter()
+        .map(|source| into_ast(source))
+        .collect::<Vec<_>>();
+
+    let mut interner = interner::Interner::default();
+    let asts = asts
+        .into_iter()
+        .map(|ast| ast.map(|ast| interner.intern_ast(ast)))
+        .zip(sources);
+
+    let compile = |(ast, source): (Result<parser::AST>, &String)| {
+        ast.map(|ast| Expression {
+            runtime: Runtime::new(ast),
+            source: Some(source.as_str().into()),
+        })
+    };
+
+    #[cfg(feature = "parallel")]
+    let compiled = asts.collect::<Vec<_>>().into_par_iter().map(compile).collect();
+
+    #[cfg(not(feature = "parallel"))]
+    let compiled = asts.map(compile).collect();
+
+    compiled
+}
+
+/// A compiled expression, clones share the compiled program
+#[derive(Clone)]
 pub struct Expression {
     runtime: Runtime,
+    /// The source the expression was compiled from, combined and
+    /// deserialized expressions have none
+    source: Option<Arc<str>>,
 }
 
 impl Expression {
-    pub fn new(source: &String) -> Result<Self> {
-        let ast = into_ast(source.into())?;
+    pub fn new(source: &str) -> Result<Self> {
+        let ast = into_ast(source)?;
         let runtime = Runtime::new(ast);
 
-        Ok(Self { runtime })
+        Ok(Self {
+            runtime,
+            source: Some(source.into()),
+        })
     }
 
     pub fn matches(&self, input: impl AsRef<str>) -> bool {
         self.runtime.run(input.as_ref())
     }
+
+    /// Matches the part of `input` starting at the byte `offset`, for
+    /// scanners iterating over a buffer. Offsets past the end or inside of a
+    /// character never match
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("starts \"id=\" and ends \";\"").unwrap();
+    /// let buffer = "name=jane;id=42;";
+    ///
+    /// assert!(expr.matches_at(buffer, 10));
+    /// assert!(!expr.matches_at(buffer, 0));
+    /// assert!(!expr.matches_at(buffer, 100));
+    /// ```
+    pub fn matches_at(&self, input: &str, offset: usize) -> bool {
+        match input.get(offset..) {
+            Some(rest) => self.runtime.run(rest),
+            None => false,
+        }
+    }
+
+    /// Finds the parts of `input` matched by the expression, e.g. every run of
+    /// five digits for `numeric and length 5`. The windows are searched from
+    /// left to right, the longest window starting at a position is taken and
+    /// windows never overlap. Empty windows are never reported
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric and length 5").unwrap();
+    /// let input = "zip 12345, tel 0123456789";
+    ///
+    /// let windows = expr.scan(input);
+    /// assert_eq!(windows, vec![4..9, 15..20, 20..25]);
+    /// assert_eq!(&input[windows[0].clone()], "12345");
+    /// ```
+    pub fn scan(&self, input: &str) -> Vec<std::ops::Range<usize>> {
+        let max = scan::max_window(self.runtime.ast());
+        let anchors = self.anchors(input);
+
+        scan::scan(input, max, anchors.as_deref(), |window| {
+            self.matches(window)
+        })
+    }
+
+    /// The literals a window of `input` has to contain to match, see
+    /// [`scan::anchors`]. Trimmed or lowercased windows may not contain the
+    /// literals where the input does, so there are none with these options
+    pub(crate) fn anchors(&self, input: &str) -> Option<Vec<scan::Anchor>> {
+        let options = self.runtime.options();
+
+        match options.trim || options.ignore_case {
+            true => None,
+            false => scan::anchors(self.runtime.ast(), input),
+        }
+    }
+
+    /// Compiles an expression with options, which are applied without any
+    /// overhead per query. The conversions into other formats like
+    /// [`to_regex_string`](#method.to_regex_string) ignore the options
+    ///
+    /// ```rust
+    /// use srch::{Expression, MatchOptions};
+    ///
+    /// let options = MatchOptions {
+    ///     ignore_case: true,
+    ///     trim: true,
+    ///     ..MatchOptions::default()
+    /// };
+    ///
+    /// let expr = Expression::new_with("starts \"Error\"", options).unwrap();
+    /// assert!(expr.matches("  ERROR: disk full"));
+    /// ```
+    pub fn new_with(source: &str, options: MatchOptions) -> Result<Self> {
+        let ast = into_ast(source)?;
+        let runtime = Runtime::with_options(ast, options);
+
+        Ok(Self {
+            runtime,
+            source: Some(source.into()),
+        })
+    }
+
+    /// Compiles an expression written by an untrusted user, e.g. the
+    /// customer of a service. Sources exceeding the [`Limits`] are rejected
+    /// with [`Error::LimitExceeded`] and every match is bounded by the fuel
+    /// of the limits
+    ///
+    /// ```rust
+    /// use srch::{Expression, Limits};
+    ///
+    /// let expr = Expression::new_untrusted("contains \"error\"", Limits::default()).unwrap();
+    /// assert!(expr.matches("disk error"));
+    ///
+    /// assert!(Expression::new_untrusted("@email", Limits::default()).is_err());
+    /// ```
+    pub fn new_untrusted(source: &str, limits: Limits) -> Result<Self> {
+        limits::check_source(source, &limits)?;
+
+        let ast = into_ast(source)?;
+        limits::check_ast(&ast, &limits)?;
+
+        Ok(Self {
+            runtime: Runtime::new(ast).with_fuel(limits.fuel),
+            source: Some(source.into()),
+        })
+    }
+
+    /// Matches `input` unless the match runs out of fuel, see
+    /// [`Limits::fuel`]. [`matches`](#method.matches) treats inputs which
+    /// use up the fuel as not matching
+    pub fn try_matches(&self, input: impl AsRef<str>) -> std::result::Result<bool, OutOfFuel> {
+        self.runtime.try_run(input.as_ref())
+    }
+
+    /// Wraps the expression in a cache of the results of the last `capacity`
+    /// distinct inputs, see [`CachedExpression`]
+    pub fn cached(self, capacity: usize) -> CachedExpression {
+        CachedExpression::new(self, capacity)
+    }
+
+    /// The options are compiled into both expressions, so only expressions
+    /// with the same options can be combined
+    fn combine(self, operator: LogicalOperator, other: Expression) -> Result<Self> {
+        if self.runtime.options() != other.runtime.options() {
+            return Err(Error::IncompatibleOptions);
+        }
+
+        Ok(Self {
+            runtime: self.runtime.combine(operator, other.runtime)?,
+            source: None,
+        })
+    }
+
+    /// Combines two expressions into one matching inputs matched by both.
+    /// Long chains of combined expressions are rebalanced, combining fails
+    /// with `ExpressionTooComplex` if the result would still be nested deeper
+    /// than [`MAX_DEPTH`], and with [`Error::IncompatibleOptions`] if the
+    /// expressions are matched with different [`MatchOptions`]
+    ///
+    /// ```rust
+    /// let user = srch::Expression::new("starts \"a\" or starts \"b\"").unwrap();
+    /// let guard = srch::Expression::new("length 3").unwrap();
+    ///
+    /// let expr = user.and(guard).unwrap();
+    /// assert!(expr.matches("bcd"));
+    /// assert!(!expr.matches("bcde"));
+    /// ```
+    pub fn and(self, other: Expression) -> Result<Self> {
+        self.combine(LogicalOperator::And, other)
+    }
+
+    /// Combines two expressions into one matching inputs matched by either,
+    /// see [`and`](#method.and)
+    pub fn or(self, other: Expression) -> Result<Self> {
+        self.combine(LogicalOperator::Or, other)
+    }
+
+    /// Inverts the expression, so it matches exactly the inputs it didn't
+    /// match before
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric").unwrap().negate();
+    /// assert!(expr.matches("abc"));
+    /// assert!(!expr.matches("123"));
+    /// ```
+    pub fn negate(self) -> Self {
+        self.complement()
+    }
+
+    /// The expression matching exactly the inputs this one doesn't match,
+    /// like `srch not`. The options and stats of the expression are kept
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric").unwrap();
+    /// let complement = expr.complement();
+    ///
+    /// assert!(complement.matches("abc"));
+    /// assert!(!complement.matches("123"));
+    /// assert!(complement.complement().matches("123"));
+    /// ```
+    pub fn complement(&self) -> Self {
+        Self {
+            runtime: self.runtime.complement(),
+            source: None,
+        }
+    }
+
+    /// Splits the expression into its top-level alternatives, e.g. to render
+    /// it as a checklist or to check which alternative matched. Each clause
+    /// lists its conditions together with their location in the source.
+    /// `None` if the expression would be split into more than
+    /// [`MAX_CLAUSES`] clauses, e.g. the complement of many alternatives
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric and length 5 or @email").unwrap();
+    ///
+    /// let clauses = expr.clauses().unwrap();
+    /// assert_eq!(clauses.len(), 2);
+    /// assert!(clauses[1].matches("jane@example.com"));
+    ///
+    /// let length = &clauses[0].conditions()[1];
+    /// assert_eq!(length.to_string(), "length 5");
+    /// assert_eq!(length.span(), Some(12..20));
+    /// ```
+    pub fn clauses(&self) -> Option<Vec<Clause>> {
+        clause::clauses(
+            self.runtime.ast(),
+            self.source.as_deref(),
+            self.runtime.options(),
+        )
+    }
+
+    /// Counts how often every query is evaluated and how often it matches
+    /// from now on, see [`stats`](#method.stats). Clones share the counters,
+    /// combining expressions drops them
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("contains \"a\" and length 3")
+    ///     .unwrap()
+    ///     .with_stats();
+    ///
+    /// for input in ["abc", "abcd", "xyz"].iter() {
+    ///     expr.matches(input);
+    /// }
+    ///
+    /// // `length 3` is cheaper, so it's evaluated first
+    /// let stats = expr.stats();
+    /// assert_eq!((stats[0].evaluated, stats[0].matched), (2, 1));
+    /// assert_eq!((stats[1].evaluated, stats[1].matched), (3, 2));
+    /// ```
+    pub fn with_stats(mut self) -> Self {
+        self.runtime.count();
+        self
+    }
+
+    /// The counters of every query in the order they are written, empty
+    /// unless they were enabled using [`with_stats`](#method.with_stats)
+    pub fn stats(&self) -> Vec<QueryStats> {
+        self.runtime.stats()
+    }
+
+    pub fn reset_stats(&self) {
+        self.runtime.reset_stats()
+    }
+
+    /// Analyses the expression for contradicting, tautological and redundant
+    /// queries
+    pub fn lint(&self) -> Vec<Finding> {
+        analysis::lint(self.runtime.ast())
+    }
+
+    /// Compares the expression with a new version of it, e.g. to review a
+    /// changed rule. The relation is proven where possible, otherwise inputs
+    /// on which the versions disagree are searched for and returned as
+    /// counterexamples
+    ///
+    /// ```rust
+    /// use srch::{Expression, Relation};
+    ///
+    /// let old = Expression::new("numeric and length 5").unwrap();
+    /// let new = Expression::new("numeric").unwrap();
+    ///
+    /// let comparison = old.compare(&new);
+    /// assert_eq!(comparison.relation, Relation::Broader);
+    /// assert_eq!(comparison.added.as_deref(), Some("1111"));
+    /// ```
+    pub fn compare(&self, new: &Expression) -> Comparison {
+        let (old_ast, new_ast) = (self.runtime.ast(), new.runtime.ast());
+
+        let mut removed = None;
+        let mut added = None;
+
+        for input in analysis::examples(old_ast)
+            .into_iter()
+            .chain(analysis::examples(new_ast))
+        {
+            match (self.matches(&input), new.matches(&input)) {
+                (true, false) if removed.is_none() => removed = Some(input),
+                (false, true) if added.is_none() => added = Some(input),
+                _ => {}
+            }
+        }
+
+        // The asts can only be compared if the inputs are normalized the same way
+        let comparable = self.runtime.options() == new.runtime.options();
+
+        Comparison::new(
+            removed,
+            added,
+            comparable && analysis::entails(old_ast, new_ast),
+            comparable && analysis::entails(new_ast, old_ast),
+        )
+    }
+
+    /// Describes the expression in plain english, one line per clause
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric and length 5").unwrap();
+    /// assert_eq!(expr.explain(), "matches if the input consists only of digits and is 5 bytes long");
+    /// ```
+    pub fn explain(&self) -> String {
+        analysis::explain(self.runtime.ast())
+    }
+
+    /// Whether the expression only checks for substrings, i.e. matches
+    /// exactly the inputs containing one of its [`literals`](Self::literals)
+    pub fn is_literal_only(&self) -> bool {
+        self.literals().is_some()
+    }
+
+    /// The substrings of an expression which only checks for substrings, so
+    /// applications can look for them using a dedicated substring search.
+    /// Expressions ignoring case or trimming the input have none, as their
+    /// inputs are normalized before matching
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("contains \"error\" or contains \"fatal\"").unwrap();
+    /// assert_eq!(expr.literals(), Some(vec!["error", "fatal"]));
+    ///
+    /// let expr = srch::Expression::new("contains \"error\" and length 5").unwrap();
+    /// assert_eq!(expr.is_literal_only(), false);
+    /// ```
+    pub fn literals(&self) -> Option<Vec<&str>> {
+        let options = self.runtime.options();
+
+        if options.ignore_case || options.trim {
+            return None;
+        }
+
+        analysis::literals(self.runtime.ast())
+    }
+
+    /// Describes the expression as a single sentence, e.g. to show a rule to
+    /// the end users of an application
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("starts \"foo\" and length 5").unwrap();
+    ///
+    /// assert_eq!(
+    ///     expr.describe(),
+    ///     "matches strings that start with \"foo\" and are exactly 5 bytes long"
+    /// );
+    /// ```
+    pub fn describe(&self) -> String {
+        analysis::describe(self.runtime.ast())
+    }
+
+    /// Shows why the expression matches `input` or not, one line per clause
+    /// starting with `+` if the clause matches and `-` otherwise
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric and length 3").unwrap();
+    /// assert_eq!(expr.trace("12a"), "- `numeric` fails, `length 3` matches");
+    /// ```
+    pub fn trace(&self, input: &str) -> String {
+        let options = self.runtime.options();
+        analysis::trace(self.runtime.ast(), &options.prepare(input), options.unicode)
+    }
+
+    /// How close `input` comes to matching, from `0.0` if no query of any
+    /// clause is satisfied to `1.0` for a match. It is the fraction of the
+    /// queries of the closest clause which are satisfied, weighted by their
+    /// estimated cost. Near misses can be ranked by it, e.g. if an exact
+    /// filter matches nothing
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("starts \"ERROR\" and contains \"disk\"").unwrap();
+    ///
+    /// assert_eq!(expr.score("ERROR disk full"), 1.0);
+    /// assert!(expr.score("WARN disk full") > expr.score("WARN cpu hot"));
+    /// ```
+    pub fn score(&self, input: &str) -> f32 {
+        let options = self.runtime.options();
+        analysis::score(self.runtime.ast(), &options.prepare(input), options.unicode)
+    }
+
+    /// Matches `input` and records the verdict of every node together with
+    /// the parts of the input which made it match, see [`Match`]. Unlike
+    /// [`matches`](#method.matches) every node is evaluated
+    pub fn evaluate(&self, input: &str) -> Match {
+        evaluation::evaluate(self.runtime.ast(), input, self.runtime.options())
+    }
+
+    /// Converts the expression into an equivalent anchored regular expression,
+    /// so it can be used by tools which only accept regular expressions.
+    /// Conjunctions which can't be merged are expressed using lookaheads
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric and length 5").unwrap();
+    /// assert_eq!(expr.to_regex_string(), "^(?:[0-9]{5})$");
+    ///
+    /// let expr = srch::Expression::new("starts \"a\" or equals \"b.c\"").unwrap();
+    /// assert_eq!(expr.to_regex_string(), "^(?:(?:a.*|b\\.c))$");
+    /// ```
+    pub fn to_regex_string(&self) -> String {
+        convert::to_regex(self.runtime.ast())
+    }
+
+    /// Converts the expression into a sql predicate on `column`, which can be
+    /// used in a WHERE clause. The column is inserted as is, so it can be any
+    /// sql expression. For sqlite the named patterns use the `REGEXP`
+    /// operator, which requires a `regexp` function to be registered
+    ///
+    /// ```rust
+    /// use srch::SqlDialect;
+    ///
+    /// let expr = srch::Expression::new("starts \"a\" and length 3").unwrap();
+    ///
+    /// assert_eq!(
+    ///     expr.to_sql_predicate("name", SqlDialect::Postgres),
+    ///     "(name LIKE 'a%' ESCAPE '\\' AND octet_length(name) = 3)"
+    /// );
+    /// assert_eq!(
+    ///     expr.to_sql_predicate("name", SqlDialect::Sqlite),
+    ///     "(substr(name, 1, 1) = 'a' AND length(CAST(name AS BLOB)) = 3)"
+    /// );
+    /// ```
+    pub fn to_sql_predicate(&self, column: &str, dialect: SqlDialect) -> String {
+        convert::to_sql(self.runtime.ast(), column, dialect)
+    }
+
+    /// Converts the expression into a self-contained javascript arrow function
+    /// taking the tested string and returning whether it matches, so the same
+    /// rule can be checked in the browser
+    ///
+    /// ```rust
+    /// let expr = srch::Expression::new("numeric or starts \"+\"").unwrap();
+    ///
+    /// assert_eq!(
+    ///     expr.to_js(),
+    ///     r#"(input) => (/^[0-9]*$/.test(input) || input.startsWith("+"))"#
+    /// );
+    /// ```
+    pub fn to_js(&self) -> String {
+        convert::to_js(self.runtime.ast())
+    }
 }
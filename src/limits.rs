@@ -0,0 +1,203 @@
+use std::fmt;
+
+use crate::i18n;
+use crate::parser::{self, AST};
+use crate::query::Query;
+
+/// Limits of expressions written by untrusted users, e.g. the customers of a
+/// service, see [`Expression::new_untrusted`](crate::Expression::new_untrusted)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// The maximum length of the source in bytes, checked before the source
+    /// is compiled
+    pub max_source_length: usize,
+    /// The maximum number of queries
+    pub max_queries: usize,
+    /// The maximum nesting depth of the syntax tree
+    pub max_depth: usize,
+    /// The maximum length of a string argument in bytes
+    pub max_argument_length: usize,
+    /// Allows named patterns like `@email` and custom queries, which are the
+    /// most expensive queries
+    pub allow_patterns: bool,
+    /// The fuel a single match may use up, inputs which need more don't
+    /// match. A query costs its estimated cost for every started 64 bytes of
+    /// the input
+    pub fuel: Option<u64>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_source_length: 4096,
+            max_queries: 64,
+            max_depth: 64,
+            max_argument_length: 256,
+            allow_patterns: false,
+            fuel: Some(100_000),
+        }
+    }
+}
+
+/// The limit an expression exceeds, see [`Limits`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum LimitError {
+    SourceLength(usize),
+    Queries(usize),
+    Depth(usize),
+    ArgumentLength(usize),
+    /// The expression uses a named pattern, which aren't allowed
+    Pattern(String),
+}
+
+impl fmt::Display for LimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Self::SourceLength(max) => i18n::message("limits.source_length", &max.to_string()),
+            Self::Queries(max) => i18n::message("limits.queries", &max.to_string()),
+            Self::Depth(max) => i18n::message("limits.depth", &max.to_string()),
+            Self::ArgumentLength(max) => i18n::message("limits.argument_length", &max.to_string()),
+            Self::Pattern(name) => i18n::message("limits.pattern", name),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// The error of a match which ran out of fuel, see [`Limits::fuel`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfFuel;
+
+impl fmt::Display for OutOfFuel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", i18n::message("limits.out_of_fuel", ""))
+    }
+}
+
+impl std::error::Error for OutOfFuel {}
+
+/// Checks the limits which don't require compiling the source
+pub fn check_source(source: &str, limits: &Limits) -> Result<(), LimitError> {
+    match source.len() > limits.max_source_length {
+        true => Err(LimitError::SourceLength(limits.max_source_length)),
+        false => Ok(()),
+    }
+}
+
+fn queries(ast: &AST) -> Vec<&Query> {
+    match ast {
+        AST::Query(query) => vec![query],
+        AST::Not(ast) => queries(ast),
+        AST::BinaryExpression { left, right, .. } => {
+            let mut all = queries(left);
+            all.extend(queries(right));
+            all
+        }
+    }
+}
+
+/// Checks the limits of a compiled source
+pub fn check_ast(ast: &AST, limits: &Limits) -> Result<(), LimitError> {
+    let queries = queries(ast);
+
+    if queries.len() > limits.max_queries {
+        return Err(LimitError::Queries(limits.max_queries));
+    }
+
+    if parser::depth(ast) > limits.max_depth {
+        return Err(LimitError::Depth(limits.max_depth));
+    }
+
+    for query in queries {
+        match query {
+            Query::Starts(arg)
+            | Query::Ends(arg)
+            | Query::Contains(arg)
+            | Query::ContainsTimes(arg, _)
+            | Query::Equals(arg)
+            | Query::IStarts(arg)
+            | Query::IEnds(arg)
+            | Query::IContains(arg)
+            | Query::IEquals(arg)
+                if arg.len() > limits.max_argument_length =>
+            {
+                return Err(LimitError::ArgumentLength(limits.max_argument_length))
+            }
+            Query::Pattern(pattern) if !limits.allow_patterns => {
+                return Err(LimitError::Pattern(pattern.as_keyword().to_string()))
+            }
+            Query::Custom(_) if !limits.allow_patterns => {
+                return Err(LimitError::Pattern(query.to_string()))
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LimitError, Limits};
+    use crate::{Error, Expression};
+    use pretty_assertions::assert_eq;
+
+    fn limit(source: &str, limits: Limits) -> Option<LimitError> {
+        match Expression::new_untrusted(source, limits) {
+            Err(Error::LimitExceeded(err)) => Some(err),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => None,
+        }
+    }
+
+    #[test]
+    fn accepts_expressions_within_the_limits() {
+        assert_eq!(limit("numeric and length 5", Limits::default()), None);
+    }
+
+    #[test]
+    fn rejects_expressions_exceeding_the_limits() {
+        let limits = Limits {
+            max_source_length: 40,
+            max_queries: 3,
+            max_depth: 2,
+            max_argument_length: 4,
+            ..Limits::default()
+        };
+
+        assert_eq!(
+            limit(&"numeric or ".repeat(5), limits),
+            Some(LimitError::SourceLength(40))
+        );
+        assert_eq!(
+            limit("alpha or alpha or alpha or alpha", limits),
+            Some(LimitError::Queries(3))
+        );
+        assert_eq!(
+            limit("alpha or alpha or alpha", limits),
+            Some(LimitError::Depth(2))
+        );
+        assert_eq!(
+            limit("contains \"abcde\"", limits),
+            Some(LimitError::ArgumentLength(4))
+        );
+        assert_eq!(
+            limit("@email", limits),
+            Some(LimitError::Pattern("@email".to_string()))
+        );
+    }
+
+    #[test]
+    fn runs_out_of_fuel() {
+        let limits = Limits {
+            fuel: Some(20),
+            ..Limits::default()
+        };
+
+        let expr = Expression::new_untrusted("contains \"a\" or numeric", limits).unwrap();
+
+        assert_eq!(expr.try_matches("123"), Ok(true));
+        assert!(expr.try_matches("b".repeat(256)).is_err());
+        assert_eq!(expr.matches("b".repeat(256)), false);
+    }
+}
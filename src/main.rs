@@ -35,6 +35,62 @@
 //! foo
 //! ```
 //!
+//! Words are separated by ascii whitespace by default, so punctuation is
+//! part of a word. `--unicode-whitespace` splits at any unicode whitespace
+//! (e.g. non-breaking spaces), `--strip-punctuation` removes everything but
+//! letters and digits from the start and end of words and
+//! `--keep-delimiters` also matches the text between words. The same
+//! options are available as `srch::Tokenizer` for `Document::words_with`.
+//!
+//! ```bash
+//! $ echo 'error: disk full' | srch for 'equals "error"' -w --strip-punctuation
+//! error
+//! ```
+//!
+//! To search prose the modes `sentence` and `paragraph` are available as
+//! well. Paragraphs are separated by blank lines, sentences end with `.`,
+//! `!` or `?` and may span multiple lines. Both modes are supported by `for`
+//! and `not`.
+//!
+//! ```bash
+//! $ srch for 'contains "TODO"' -m paragraph NOTES.md
+//! ```
+//!
+//! The `char` mode executes the expression on every character (grapheme) of
+//! the input, which is mostly useful together with class queries.
+//!
+//! ```bash
+//! $ srch for 'special' -m char -u README.md
+//! ```
+//!
+//! Record oriented data with other separators can be searched using the
+//! `delim` mode together with `--delimiter`, which understands the escapes
+//! `\0`, `\t`, `\n`, `\r` and `\\`. In this mode `{line}` refers to the
+//! number of the record.
+//!
+//! ```bash
+//! $ printf 'foo;bar;baz' | srch for 'starts "b"' -m delim -d ';'
+//! bar
+//! baz
+//! ```
+//!
+//! If `srch` is built with the `yaml` feature, the `yaml` mode searches yaml
+//! documents. The expression is executed on the scalar at the dot separated
+//! path given by `--key` (sequences are indexed by number) and every matching
+//! document is printed.
+//!
+//! ```bash
+//! $ srch for 'ends ":latest"' -m yaml -k spec.template.spec.containers.0.image deploy.yaml
+//! ```
+//!
+//! `-z` / `--null-data` is a shorthand for `-m delim -d '\0'`. Together with
+//! `--print0`, which terminates every printed match with a NUL byte, `srch`
+//! composes with `find -print0` and `xargs -0`.
+//!
+//! ```bash
+//! $ find . -print0 | srch for 'ends ".rs"' -z --print0 | xargs -0 wc -l
+//! ```
+//!
 //! ## Filtering or Ignoring
 //! The commands `for` and `not` work exactly the same. The only
 //! difference is the inverted output is printed if `not` is used.
@@ -49,179 +105,7422 @@
 //! $ srch for <EXPRESSION> [FILE]
 //! $ srch not <EXPRESSION> [FILE]
 //! ```
+//!
+//! Single queries can also be negated inside an expression, which is what
+//! `not` can't express on its own:
+//!
+//! ```bash
+//! $ srch for 'numeric and not length 5' foo.txt
+//! ```
+//!
+//! ## Expression Files
+//! Long expressions can be stored in a file and passed using
+//! `-E/--expr-file PATH` instead of the positional EXPRESSION. Expression
+//! files may span multiple lines and contain comments starting with `#`.
+//!
+//! ```bash
+//! $ cat mails.te
+//! # naive email addresses
+//! contains "@"
+//!     and contains ".com"
+//! $ srch for -E mails.te -m word foo.txt
+//! ```
+//!
+//! If PATH is a directory every file in it holds one expression, and a
+//! record matches if any of them (or with `--all` all of them) match. With
+//! the `parallel` feature large rule directories are compiled on all cores.
+//! Every invalid expression is reported at once, labeled by its file.
+//!
+//! ```bash
+//! $ srch for -E rules/ --all app.log
+//! ```
+//!
+//! ## Multiple Expressions
+//! Instead of writing one giant expression, `-e/--expr` can be repeated to
+//! combine several expressions. By default an item matches if any of the
+//! expressions match, `--all` requires all of them to match. As with
+//! `--expr-file` the positional EXPRESSION is omitted in this case.
+//!
+//! ```bash
+//! $ srch for -e 'numeric' -e 'length 5' --all foo.txt
+//! ```
+//!
+//! `--summary` prints how many records each expression matched to stderr
+//! when done, so a classification run reports the distribution of its
+//! classes alongside the matches.
+//!
+//! ```bash
+//! $ srch for -e '@email' -e '@url' -e 'numeric' contacts.txt --summary > /dev/null
+//! 120 @email
+//!  14 @url
+//!   3 numeric
+//! ```
+//!
+//! ## Named Patterns
+//! Frequently used expressions can be stored in a pattern library at
+//! `~/.config/srch/patterns.toml` (or `$SRCH_PATTERNS`) which maps names to
+//! expressions. Instead of an expression `@name` can be passed to use the
+//! named expression, it can be used inside of an expression as well, e.g.
+//! `@https-errors and length under 200`. Unknown names are reported by
+//! name. `srch patterns list` prints all named patterns.
+//!
+//! All patterns are validated when the library is loaded. Patterns can be
+//! documented using the versioned format:
+//!
+//! ```toml
+//! version = 1
+//!
+//! [rules.container-id]
+//! expression = 'alphanumeric and length 12'
+//! description = 'a short docker container id'
+//! severity = 'info'
+//! tags = ['docker']
+//! ```
+//!
+//! `patterns list` and `which` only use the patterns with one of the tags
+//! given by `--tag` and at least the severity given by `--severity` (`info`,
+//! `warning`, `error` or `critical`), which keeps large libraries usable.
+//!
+//! The built-in patterns `@email`, `@url`, `@uuid` and `@ipv4` are available
+//! out of the box and can be used anywhere inside of an expression.
+//! With the `plugins` feature the shared libraries in
+//! `~/.config/srch/plugins` (or `$SRCH_PLUGINS`) are loaded on startup, the
+//! custom queries they provide can be used like the built-in patterns.
+//!
+//! ```bash
+//! $ cat ~/.config/srch/patterns.toml
+//! https-errors = 'contains "https" and contains "error"'
+//! $ srch for @https-errors access.log
+//! $ srch for '@email and ends ".org"' -m word contacts.txt
+//! ```
+//!
+//! ## Slicing
+//! The match stream can be sliced using `--skip n`, `--head n` and
+//! `--tail n`. `--first` and `--last` are shorthands for `--head 1` and
+//! `--tail 1`. Slicing is done lazily, so `--head` stops reading the input
+//! as soon as enough matches were found.
+//!
+//! ```bash
+//! $ srch for 'numeric' --skip 10 --head 10 foo.txt
+//! ```
+//!
+//! ## grep Compatibility
+//! Some flags are named like their `grep` counterparts: `-i` ignores the
+//! case, `-v` inverts the matches, `-c` prints the number of matches, `-n`
+//! prefixes each match with its line number and `-w` selects the word mode.
+//!
+//! ```bash
+//! $ srch for 'contains "error"' -in app.log
+//! 12:Error: connection refused
+//! ```
+//!
+//! ## Ranking Near Misses
+//! If an exact filter matches nothing, `--rank` shows what almost matched.
+//! Every record is printed with its score instead, the fraction of the
+//! queries of the closest alternative it satisfies, weighted by how
+//! expensive the queries are. The closest records come first, so `--head`
+//! keeps the best candidates.
+//!
+//! ```bash
+//! $ srch for 'starts "ERROR" and contains "disk"' app.log --rank --head 3
+//! 0.71 WARN disk almost full
+//! 0.29 ERROR cpu too hot
+//! 0.00 INFO started
+//! ```
+//!
+//! ## Extracting Matches
+//! `-o/--only-matching` searches every record for the parts matched by the
+//! expression and prints each of them on its own line, like `grep -o`. The
+//! longest part starting at a position is taken and parts never overlap.
+//!
+//! ```bash
+//! $ echo 'zip 12345, tel 0123456789' | srch for 'numeric and length 5' -o
+//! 12345
+//! 01234
+//! 56789
+//! ```
+//!
+//! ## Sorting and De-duplication
+//! Matches can be sorted using `--sort` and de-duplicated using `--unique`
+//! before they are sliced and printed. `--unique` keeps the output streaming
+//! while `--sort` has to read the whole input first.
+//!
+//! ```bash
+//! $ srch for 'contains "@"' -m word --unique --sort mails.txt
+//! ```
+//!
+//! `--sort` orders matches by their bytes, which puts `Äpfel` after `zebra`.
+//! With the `collation` feature `--collate LOCALE` sorts them the way
+//! readers of a locale expect instead, e.g. `--sort --collate de`.
+//!
+//! `--squeeze` prints runs of identical consecutive matches only once,
+//! prefixed with the length of the run like `uniq -c`, which keeps the output
+//! of chatty logs readable while streaming.
+//!
+//! ```bash
+//! $ srch for 'contains "retrying"' --squeeze --follow app.log
+//! ```
+//!
+//! ## Highlighting
+//! `highlight` prints the whole input, but highlights the matching lines (or
+//! words in `word` mode). Use `--color always` to keep the highlighting when
+//! piping into a pager.
+//!
+//! ```bash
+//! $ srch highlight 'contains "ERROR"' --color always app.log | less -R
+//! ```
+//!
+//! ## Replacing
+//! `replace` prints the whole input, but every match (depending on the mode
+//! a line or a word) is replaced with the given replacement string. Instead
+//! of printing the result it can be written to a file using `--output FILE`
+//! or back into the input file using `--in-place`. When editing in place
+//! `--backup SUFFIX` keeps a copy of the original file. Results are written
+//! to a temporary file first and then renamed, so a failing run never leaves
+//! a partially written file behind.
+//!
+//! ```bash
+//! $ srch replace 'numeric and length 5' 12345 -m word -i --backup .orig foo.txt
+//! ```
+//!
+//! The replacement is inserted as is, unless `--references` is given. Then
+//! it can reference the match: `$0` is the whole match and `$1`, `$2`, ...
+//! are the parts of it which made the expression match, in the order they
+//! appear (as shown by `--explain`). These aren't capture groups, the text
+//! expression language has no captures: a part is the prefix, suffix or
+//! substring a `starts`, `ends` or `contains` query found, or the whole
+//! match for queries which look at all of it, like `numeric`. A reference
+//! followed by a digit can be written as `${1}` and a literal dollar as `$$`.
+//! Referencing a part the match doesn't have is an error.
+//!
+//! ```bash
+//! $ echo 'ERROR: disk full' | srch replace 'starts "ERROR: "' '[$1] $0' --references
+//! [ERROR: ] ERROR: disk full
+//! ```
+//!
+//! Instead of the result `--patch` prints a unified diff of the changes to
+//! all input files, which can be reviewed and applied later using
+//! `git apply`.
+//!
+//! ```bash
+//! $ srch replace 'equals "TODO"' 'DONE' --patch notes.md todo.md > done.patch
+//! $ git apply done.patch
+//! ```
+//!
+//! Lines are matched without their line ending, so `ends "foo"` matches
+//! lines of files written on Windows too. `replace` and `highlight` write
+//! every line back with its own line ending, so files with `\r\n` or mixed
+//! line endings keep them, even when creating a patch. Filtered lines end
+//! with `\n` unless `--crlf` is given.
+//!
+//! ## Converting
+//! `convert` translates a text expression into other formats, so patterns
+//! can be reused by tools which don't understand text expressions. Using
+//! `--to-regex` an anchored regular expression is printed. Conjunctions are
+//! expressed using lookaheads where they can't be merged, so the regex engine
+//! may have to support them.
+//!
+//! ```bash
+//! $ srch convert --to-regex 'numeric and length 5'
+//! ^(?:[0-9]{5})$
+//! ```
+//!
+//! `--from-regex` goes the other way and converts simple regular expressions
+//! (anchors, literals, the classes `[0-9]`, `[A-Za-z]` and `[A-Za-z0-9]` and
+//! exact repetitions) into readable text expressions. Unsupported constructs
+//! are reported.
+//!
+//! ```bash
+//! $ srch convert --from-regex '^[0-9]{5}$|^foo'
+//! numeric and length 5 or starts "foo"
+//! ```
+//!
+//! ## Describing Expressions
+//! `describe` renders an expression as a sentence in plain english, so rules
+//! can be shown to people who don't know the syntax, e.g. in reviews.
+//!
+//! ```bash
+//! $ srch describe 'starts "foo" and length 5 or numeric'
+//! matches strings that start with "foo" and are exactly 5 bytes long, or that consist only of digits
+//! ```
+//!
+//! ## Linting
+//! `lint` checks every expression of an expression file (expressions are
+//! separated by blank lines) for syntax errors, contradictions (clauses that
+//! can never match), tautologies (clauses matching everything) and redundant
+//! queries or clauses. If anything was found `srch` exits with a non-zero
+//! status, so it can be used in CI.
+//!
+//! ```bash
+//! $ srch lint rules.te
+//! rules.te:4: warning: contradiction: `length 3 and length 5` can never match: `length 3` and `length 5` can never match together
+//! ```
+//!
+//! ## Comparing Expressions
+//! `diff` compares the old and new version of an expression file, or of a
+//! rule set, and reports whether the new version is equivalent, strictly
+//! narrower, strictly broader or incomparable. The relation is proven where
+//! possible, otherwise `srch` searches for inputs on which the versions
+//! disagree and prints them. Rule sets are compared rule by rule, skipping
+//! unchanged rules. As with `diff(1)` the exit status is 1 if anything
+//! changed.
+//!
+//! ```bash
+//! $ srch diff old.te new.te
+//! strictly broader
+//!   only new.te: "1111"
+//! $ srch diff old.toml new.toml
+//! user-id: added
+//! zip-code: strictly narrower
+//!   only old.toml: "1"
+//! ```
+//!
+//! ## Testing Expressions
+//! `check` runs a test suite, a toml file listing inputs which expressions
+//! have to match or reject. Failing cases are reported and `srch` exits with
+//! a non-zero status.
+//!
+//! ```bash
+//! $ cat tests.toml
+//! [[case]]
+//! name = "five digit numbers"
+//! expression = "numeric and length 5"
+//! matches = ["12345"]
+//! rejects = ["1234", "abcde"]
+//! $ srch check tests.toml
+//! 1 passed, 0 failed
+//! ```
+//!
+//! `sample` bootstraps a test suite from real data: it picks records of a
+//! file which match the expression and records which don't (20 of each by
+//! default, spread over the whole file) and writes them as a single case.
+//! Once the records are reviewed, `check` guards the expression against
+//! regressions.
+//!
+//! ```bash
+//! $ srch sample '@https-errors' access.log --matched 10 --unmatched 30 --out tests.toml
+//! $ srch check tests.toml
+//! ```
+//!
+//! ## Building Expressions
+//! If you are not sure how to write an expression, `wizard` asks a few
+//! questions (should it start with something? how long should it be?) and
+//! prints the resulting expression. Given a sample file the expression is
+//! tested against it before you accept it.
+//!
+//! ```bash
+//! $ srch wizard numbers.txt
+//! ```
+//!
+//! ## Rule Attribution
+//! `which` prints every rule matching the given text. Rules are given with
+//! `-e` or read from files with `-E` (separated by blank lines, reported as
+//! `file:line`). Without any rules all named patterns are tried. If no rule
+//! matches `srch` exits with a non-zero status.
+//!
+//! ```bash
+//! $ srch which 'mara@schulke.xyz'
+//! @email
+//! $ srch which -E rules.txt 'ERROR: disk full'
+//! rules.txt:4
+//! ```
+//!
+//! ## Explaining Matches
+//! `--explain` prints one line of json per record instead of filtering,
+//! containing whether the record matches, the verdict of every query and
+//! operator and the byte ranges of the record which made them match.
+//!
+//! ```bash
+//! $ echo 'abc' | srch for 'contains "b"' --explain
+//! {"input":"abc","matched":true,"root":{"node":"contains \"b\"","matched":true,"evidence":[{"start":1,"end":2}],"children":[]}}
+//! ```
+//!
+//! ## Serving
+//! If `srch` is built with the `server` feature, `serve` exposes a small
+//! json api, so other services can use text expressions without linking
+//! Rust. All endpoints expect a `POST` request with an `expression`:
+//!
+//! - `/compile` checks if the expression is valid
+//! - `/match` tests a single `text`
+//! - `/batch` tests a list of `texts`
+//! - `/explain` tests a single `text` and returns the verdict of every query
+//!   and operator together with the parts of the text which made them match,
+//!   in the same format as `--explain`
+//!
+//! ```bash
+//! $ srch serve --port 8080 &
+//! $ curl -d '{"expression": "numeric", "texts": ["1", "a"]}' localhost:8080/batch
+//! {"matches":[true,false]}
+//! ```
+//!
+//! ## Following Files
+//! Using `-F/--follow` the input file is kept open and new matches are
+//! printed as soon as they are appended to the file, just like `tail -F`.
+//! Truncated and rotated files are detected and read from the start.
+//!
+//! ```bash
+//! $ srch for 'contains "error"' --follow /var/log/app.log
+//! ```
+//!
+//! When srch is built with the `notify` feature, an expression read using
+//! `-E/--expr-file` is recompiled whenever the file changes, so rules can be
+//! edited without restarting `srch`.
+//!
+//! ## Splitting Output
+//! `--rest-to FILE` writes the records which aren't printed to a file while
+//! filtering, so the input can be split in a single pass. `--matches-to FILE`
+//! writes the printed records to a file instead of stdout.
+//!
+//! ```bash
+//! $ srch not 'contains "password"' app.log --matches-to clean.log --rest-to quarantine.log
+//! ```
+//!
+//! ## Executing Commands
+//! `-x/--exec COMMAND` runs a command for every match instead of printing
+//! it. The command supports the same placeholders as `--format` and is
+//! executed directly (not using a shell), so matches are always passed as a
+//! single argument. The match is additionally written to the command's stdin
+//! and available in the `SRCH_FILE`, `SRCH_LINE` and `SRCH_TEXT` environment
+//! variables. If any command fails `srch` exits with a non-zero status.
+//!
+//! ```bash
+//! $ ls | srch for 'ends ".tmp"' --exec 'mv {text} /tmp/quarantine/'
+//! ```
+//!
+//! ## Statistics
+//! `--stats` prints a summary to stderr once the input was processed: the
+//! number of files searched, lines scanned and matches found, as well as the
+//! elapsed time and throughput. This comes in handy when benchmarking
+//! changes to an expression.
+//!
+//! `--stats-detail` shows which queries dominate the cost of an expression:
+//! it prints how often every query was evaluated and how often it matched.
+//! Cheaper queries are evaluated first and `and`/`or` stop as soon as the
+//! result is known, so expensive queries are often evaluated less often
+//! than there are records.
+//!
+//! ```bash
+//! $ srch for 'contains "error" and length 40' app.log --stats-detail > /dev/null
+//!  310 evaluated   12 matched  contains "error"
+//! 9520 evaluated  310 matched  length 40
+//! ```
+//!
+//! ## Benchmarking
+//! If `srch` is built with the `bench` feature, `bench` measures the
+//! throughput of a set of standard workloads (substring checks, affixes,
+//! alternatives, character classes and named patterns) on generated corpora,
+//! so releases can be compared on the same machine. Expressions given using
+//! `-e` are measured instead, against the lines of a file or a generated
+//! corpus (`--corpus logs|words|values`). The same workloads are available
+//! to applications as `srch::bench`.
+//!
+//! ```bash
+//! $ srch bench --records 10000
+//! substring: 412.35 MiB/s, 8493217 records/s, 1969 of 10000 records matched
+//! ...
+//! ```
+//!
+//! ## Progress
+//! When reading a file while stderr is a terminal (and stdout is redirected)
+//! a progress bar is drawn to stderr, so scanning huge files doesn't appear
+//! to hang. It can be suppressed using `--no-progress`.
+//!
+//! The files to search can also be read from a file (or stdin using `-`)
+//! with `--files-from`, one file per line. `--files-from0` expects the names
+//! to be separated by NUL bytes instead, as printed by `find -print0`.
+//!
+//! ```bash
+//! $ find . -name '*.md' -print0 | srch for 'contains "TODO"' --files-from0 -
+//! ```
+//!
+//! If `srch` is built with the `clipboard` feature, `--clipboard` reads the
+//! input from the system clipboard and `--copy` copies the output back to it.
+//!
+//! ```bash
+//! $ srch for '@email' -w -u --clipboard --copy
+//! ```
+//!
+//! ## Remote Files
+//! If `srch` is built with the `http` feature, input files can be given as
+//! `http://` or `https://` urls. The response is searched while it is
+//! downloaded.
+//!
+//! ```bash
+//! $ srch for 'contains "ERROR"' https://example.com/app.log
+//! ```
+//!
+//! ## Huge Records
+//! `--max-record-length BYTES` bounds the memory used per record, so
+//! minified files or corrupted logs with lines of hundreds of megabytes can't
+//! exhaust the memory. Longer records fail the search by default, with
+//! `--overlong skip` they are skipped and with `--overlong truncate` their
+//! first BYTES bytes are matched and printed.
+//!
+//! ```bash
+//! $ srch for 'contains "error"' --max-record-length 65536 --overlong skip app.log
+//! ```
+//!
+//! ## Multiple Files
+//! `for` and `not` accept any number of input files. When searching more
+//! than one file the matches are grouped by file under a heading with the
+//! file name if stdout is a terminal, otherwise each match is prefixed with
+//! its file name. Use `--heading` or `--no-heading` to choose explicitly.
+//!
+//! ```bash
+//! $ srch for 'contains "TODO"' --heading src/lexer.rs src/parser.rs
+//! src/lexer.rs
+//! // TODO: ...
+//!
+//! src/parser.rs
+//! // TODO: ...
+//! ```
+//!
+//! ## Verbose Output
+//! `--verbose` prints which files are read and how long compiling and
+//! searching took to stderr, repeat it for more details. If `srch` is built
+//! with the `tracing` feature the events of the library are printed as well.
+//!
+//! ```bash
+//! $ srch for numeric --verbose --verbose numbers.txt
+//! srch: compiled 1 expression(s) in 4.2µs
+//! srch: reading numbers.txt
+//! srch: searched 4 line(s) of 1 file(s) in 61.3µs
+//! ```
+//!
+//! ## Languages
+//! Error messages about invalid expressions are shown in the language of the
+//! locale (`$LC_ALL`, `$LC_MESSAGES` or `$LANG`), currently english and
+//! german are built in. A custom catalog can be loaded from a toml file
+//! mapping message keys to templates using `$SRCH_CATALOG`.
+//!
+//! ```bash
+//! $ cat catalog.toml
+//! "parser.expected_query" = "a query is missing"
+//! $ SRCH_CATALOG=catalog.toml srch check rules.txt
+//! ```
+//!
+//! ## Diagnosing the Environment
+//! `doctor` checks whether matches can be highlighted in the terminal,
+//! whether the locale has builtin messages and uses UTF-8, and whether the
+//! catalog and the pattern library can be loaded. Every finding is printed
+//! together with a hint how to fix it, the exit code is 1 if srch can't work
+//! as configured.
+//!
+//! ```bash
+//! $ SRCH_PATTERNS=broken.toml srch doctor
+//! ok      terminal: xterm-256color supports colors, matches are highlighted
+//! ok      locale: en_US.UTF-8 has builtin messages
+//! ok      encoding: the locale uses UTF-8
+//! error   patterns: broken.toml: rule "x": syntax error: expected a query, commands using a named pattern fail until it is fixed
+//! ```
+//!
+//! ## Output Formatting
+//! Each match can be formatted using a template passed to `--format`. The
+//! placeholders `{file}`, `{line}` and `{text}` are replaced with the input
+//! file name (`<stdin>` if reading from stdin), the line number and the
+//! matched text. Literal braces are written as `{{` and `}}`.
+//!
+//! ```bash
+//! $ srch for 'contains "error"' --format '{file}:{line}: {text}' app.log
+//! ```
+//!
+//! `--output-format json` prints all matches as a json array of objects with
+//! the keys `file`, `line` and `text`, `--output-format jsonl` prints one such
+//! object per line. `-q/--quiet` prints nothing, the exit status tells
+//! whether anything matched.
+//!
+//! ```bash
+//! $ srch for 'numeric' --output-format jsonl ids.txt
+//! {"file":"ids.txt","line":1,"text":"42"}
+//! ```
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{
+    self, BufRead, BufReader, BufWriter, IsTerminal, Read, Result, Seek, SeekFrom, Write,
+};
+use std::path::{Path, PathBuf};
+use std::process::{Command as Process, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::{App, Arg, ArgGroup, ArgMatches, ErrorKind, ValueHint};
+use unicode_segmentation::UnicodeSegmentation;
+
+use output::OutputSink;
+
+const NAME: &str = env!("CARGO_PKG_NAME");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const AUTHOR: &str = env!("CARGO_PKG_AUTHORS");
+const DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
+
+const STDIN_NAME: &str = "<stdin>";
+const CLIPBOARD_NAME: &str = "<clipboard>";
+const PATTERN_PREFIX: char = '@';
+
+fn fail(message: impl Display) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(1);
+}
+
+/// How often `--verbose` was given
+static VERBOSITY: AtomicU64 = AtomicU64::new(0);
+
+/// Prints a message to stderr if `--verbose` was given at least `level` times
+macro_rules! verbose {
+    ($level:expr, $($arg:tt)*) => {
+        if $crate::VERBOSITY.load(std::sync::atomic::Ordering::Relaxed) >= $level {
+            eprintln!("srch: {}", format_args!($($arg)*));
+        }
+    };
+}
+
+/// Sets the verbosity and, with the `tracing` feature, prints the events of
+/// the library on stderr
+fn init_verbosity(level: u64) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+
+    #[cfg(feature = "tracing")]
+    if level > 0 {
+        let max_level = match level {
+            1 => tracing::Level::INFO,
+            2 => tracing::Level::DEBUG,
+            _ => tracing::Level::TRACE,
+        };
+
+        tracing_subscriber::fmt()
+            .with_max_level(max_level)
+            .with_writer(io::stderr)
+            .init();
+    }
+}
+
+/// Selects the language of error messages, either a toml file of message
+/// templates given by `$SRCH_CATALOG` or the builtin catalog of the locale
+fn init_catalog() {
+    if let Some(path) = env::var_os("SRCH_CATALOG") {
+        let catalog = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|source| parse_catalog(&source));
+
+        match catalog {
+            Ok(catalog) => srch::i18n::set_catalog(catalog),
+            Err(err) => fail(format!(
+                "can't load catalog {}: {}",
+                Path::new(&path).display(),
+                err
+            )),
+        }
+
+        return;
+    }
+
+    let language = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    if let Some(catalog) = language.as_deref().and_then(srch::i18n::builtin) {
+        srch::i18n::set_catalog(catalog);
+    }
+}
+
+fn parse_catalog(source: &str) -> std::result::Result<HashMap<String, String>, String> {
+    let table = match source.parse::<toml::Value>() {
+        Ok(toml::Value::Table(table)) => table,
+        Ok(_) => return Err("expected a table of message templates".to_string()),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    table
+        .into_iter()
+        .map(|(key, template)| match template {
+            toml::Value::String(template) => Ok((key, template)),
+            _ => Err(format!("message \"{}\" is not a string", key)),
+        })
+        .collect()
+}
+
+/// Named expressions from the pattern library, a rule set mapping names to
+/// expressions, e.g. `https-errors = 'contains "https" and contains "error"'`
+#[derive(Default)]
+struct Patterns {
+    rules: srch::RuleSet,
+}
+
+/// `$XDG_CONFIG_HOME/srch`, falling back to `~/.config/srch`
+fn config_dir() -> Option<PathBuf> {
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config.join("srch"))
+}
+
+/// `$SRCH_PLUGINS` or the `plugins` directory of the config directory
+#[cfg(feature = "plugins")]
+fn plugin_dir() -> Option<PathBuf> {
+    match env::var_os("SRCH_PLUGINS") {
+        Some(path) => Some(path.into()),
+        None => Some(config_dir()?.join("plugins")),
+    }
+}
+
+/// Loads the plugins of the plugin directory, so their queries can be used
+/// in every expression
+#[cfg(feature = "plugins")]
+fn init_plugins() {
+    let dir = match plugin_dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+
+    // Safety: the plugin directory is configured by the user, so its
+    // libraries are trusted like the binary itself
+    match unsafe { srch::Plugin::load_dir(&dir) } {
+        Ok(plugins) => {
+            for plugin in plugins {
+                verbose!(
+                    1,
+                    "loaded {} from {}",
+                    plugin.queries().join(", "),
+                    plugin.path().display()
+                );
+            }
+        }
+        Err((path, err)) => fail(format!("can't load plugin {}: {}", path.display(), err)),
+    }
+}
+
+#[cfg(not(feature = "plugins"))]
+fn init_plugins() {}
+
+impl Patterns {
+    /// `$SRCH_PATTERNS` or `$XDG_CONFIG_HOME/srch/patterns.toml`, falling back
+    /// to `~/.config/srch/patterns.toml`
+    fn path() -> Option<PathBuf> {
+        if let Some(path) = env::var_os("SRCH_PATTERNS") {
+            return Some(path.into());
+        }
+
+        Some(config_dir()?.join("patterns.toml"))
+    }
+
+    /// Parses and validates all patterns of the library
+    fn parse(source: &str) -> std::result::Result<Self, String> {
+        let rules = srch::RuleSet::parse(source).map_err(|err| err.to_string())?;
+        Ok(Self { rules })
+    }
+
+    /// Loads the pattern library, a missing library is treated as empty
+    fn load() -> Self {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Self::default(),
+        };
+
+        let source = match fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Self::default(),
+            Err(err) => fail(format!("{}: {}", path.display(), err)),
+        };
+
+        match Self::parse(&source) {
+            Ok(patterns) => patterns,
+            Err(err) => fail(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.rules.get(name).map(|rule| rule.source())
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.rules.iter().map(|rule| (rule.name(), rule.source()))
+    }
+}
+
+/// The selector given by `--tag` and `--severity`, none if neither is given
+fn selector_from_matches(matches: &ArgMatches) -> Option<srch::Selector> {
+    let tags = matches
+        .values_of("tag")
+        .map(|tags| tags.map(String::from).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let min_severity = matches
+        .value_of("severity")
+        .and_then(srch::Severity::from_name);
+
+    match tags.is_empty() && min_severity.is_none() {
+        true => None,
+        false => Some(srch::Selector { tags, min_severity }),
+    }
+}
+
+/// The byte ranges of the names of the `@name` references of an expression
+/// source, references in string literals and comments are skipped
+fn pattern_references(source: &str) -> Vec<std::ops::Range<usize>> {
+    let mut names = Vec::new();
+    let mut chars = source.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '"' => {
+                chars.find(|(_, c)| *c == '"');
+            }
+            '#' => {
+                chars.find(|(_, c)| *c == '\n');
+            }
+            PATTERN_PREFIX => {
+                let end = source[start..]
+                    .find(char::is_whitespace)
+                    .map_or(source.len(), |len| start + len);
+
+                names.push(start + PATTERN_PREFIX.len_utf8()..end);
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+            }
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// The library patterns registered as custom queries, so they can be
+/// referenced inside of other expressions, paired with the name of their
+/// pattern
+static LIBRARY_QUERIES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+/// Makes `@name` usable inside of a larger expression and returns the name
+/// to reference it by. The expression language has no parentheses, so a
+/// library pattern can't be inlined there and is registered as a custom
+/// query matching its expression instead. Custom queries are named like
+/// `five_digits`, so a pattern like `five-digits` is renamed. Patterns
+/// renamed to the same query, like `five-digits` and `five_digits`, can't
+/// both be referenced
+fn register_pattern(patterns: &Patterns, name: &str) -> std::result::Result<String, String> {
+    let rule = match patterns.rules.get(name) {
+        Some(rule) => rule,
+        None if srch::Pattern::from_name(name).is_some() => return Ok(name.to_string()),
+        None if srch::registered_queries()
+            .iter()
+            .any(|(custom, _)| custom == name) =>
+        {
+            return Ok(name.to_string())
+        }
+        None => return Err(format!("unknown pattern \"{}{}\"", PATTERN_PREFIX, name)),
+    };
+
+    let query = name.to_lowercase().replace('-', "_");
+    let mut registered = LIBRARY_QUERIES
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+
+    match registered.iter().find(|(registered, _)| *registered == query) {
+        Some((_, pattern)) if pattern == name => return Ok(query),
+        Some((_, pattern)) => {
+            return Err(format!(
+                "the patterns \"{}{}\" and \"{}{}\" can't both be used inside of an expression, both are referenced as \"{}\"",
+                PATTERN_PREFIX, pattern, PATTERN_PREFIX, name, query
+            ))
+        }
+        None => {}
+    }
+
+    let description = match rule.description() {
+        Some(description) => description.to_string(),
+        None => format!("matched by `{}`", rule.source()),
+    };
+
+    let expr = rule.expression().clone();
+
+    match srch::register_query(&query, &description, move |input| expr.matches(input)) {
+        Ok(()) => {
+            verbose!(2, "using {}{} = {}", PATTERN_PREFIX, name, rule.source());
+            registered.push((query.clone(), name.to_string()));
+            Ok(query)
+        }
+        Err(err) => Err(format!(
+            "the pattern \"{}{}\" can only be used on its own: {}",
+            PATTERN_PREFIX, name, err
+        )),
+    }
+}
+
+/// Resolves the `@name` references of an expression which isn't a single
+/// reference, see [`register_pattern`]
+fn resolve_references(
+    source: String,
+    patterns: &mut Option<Patterns>,
+) -> std::result::Result<String, String> {
+    let references = pattern_references(&source);
+
+    if references.is_empty() {
+        return Ok(source);
+    }
+
+    let patterns = patterns.get_or_insert_with(Patterns::load);
+    let mut resolved = String::with_capacity(source.len());
+    let mut end = 0;
+
+    for name in references {
+        resolved.push_str(&source[end..name.start]);
+        resolved.push_str(&register_pattern(patterns, &source[name.clone()])?);
+        end = name.end;
+    }
+
+    resolved.push_str(&source[end..]);
+    Ok(resolved)
+}
+
+/// Resolves sources of the form `@name` to the named expression from the
+/// pattern library, the library is only loaded if needed. Built-in patterns
+/// and the queries of plugins are left to the expression language unless the
+/// library overrides them. References inside of larger expressions are
+/// resolved using [`register_pattern`]. Fails on the first unknown pattern
+fn resolve_patterns(sources: Vec<String>) -> std::result::Result<Vec<String>, String> {
+    let mut patterns = None;
+
+    sources
+        .into_iter()
+        .map(|source| {
+            let name = match source.trim().strip_prefix(PATTERN_PREFIX) {
+                Some(name) if !name.contains(char::is_whitespace) => name,
+                _ => return resolve_references(source, &mut patterns),
+            };
+
+            let patterns = patterns.get_or_insert_with(Patterns::load);
+
+            match patterns.get(name) {
+                Some(expression) => {
+                    verbose!(2, "using {}{} = {}", PATTERN_PREFIX, name, expression);
+                    Ok(expression.to_string())
+                }
+                None if srch::Pattern::from_name(name).is_some() => Ok(source),
+                None if srch::registered_queries()
+                    .iter()
+                    .any(|(custom, _)| custom == name) =>
+                {
+                    Ok(source)
+                }
+                None => Err(format!("unknown pattern \"{}{}\"", PATTERN_PREFIX, name)),
+            }
+        })
+        .collect()
+}
+
+/// How serious a finding of `srch doctor` is
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Health {
+    Ok,
+    Warning,
+    Error,
+}
+
+/// A finding of `srch doctor` about a part of the environment
+#[derive(Clone, Debug, PartialEq)]
+struct Diagnosis {
+    health: Health,
+    subject: &'static str,
+    message: String,
+}
+
+impl Diagnosis {
+    fn new(health: Health, subject: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            health,
+            subject,
+            message: message.into(),
+        }
+    }
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let health = match self.health {
+            Health::Ok => "ok",
+            Health::Warning => "warning",
+            Health::Error => "error",
+        };
+
+        write!(f, "{:<8}{}: {}", health, self.subject, self.message)
+    }
+}
+
+/// Whether `highlight --color auto` highlights matches
+fn diagnose_terminal(is_terminal: bool, term: Option<&str>) -> Diagnosis {
+    match (is_terminal, term) {
+        (false, _) => Diagnosis::new(
+            Health::Warning,
+            "terminal",
+            "stdout isn't a terminal, so matches aren't highlighted unless --color always is given",
+        ),
+        (true, None) | (true, Some("")) | (true, Some("dumb")) => Diagnosis::new(
+            Health::Warning,
+            "terminal",
+            "$TERM doesn't name a terminal with colors, use --color never if highlighted matches show up as escape codes",
+        ),
+        (true, Some(term)) => Diagnosis::new(
+            Health::Ok,
+            "terminal",
+            format!("{} supports colors, matches are highlighted", term),
+        ),
+    }
+}
+
+/// The language of messages and the encoding of the locale, `locale` is the
+/// first of `$LC_ALL`, `$LC_MESSAGES` and `$LANG` which is set
+fn diagnose_locale(locale: Option<&str>) -> Vec<Diagnosis> {
+    let locale = match locale {
+        Some(locale) if !locale.is_empty() => locale,
+        _ => {
+            return vec![Diagnosis::new(
+                Health::Warning,
+                "locale",
+                "no locale is set, messages are english and the encoding is unknown, set e.g. LANG=en_US.UTF-8",
+            )]
+        }
+    };
+
+    let language = match srch::i18n::builtin(locale) {
+        Some(_) => Diagnosis::new(
+            Health::Ok,
+            "locale",
+            format!("{} has builtin messages", locale),
+        ),
+        None => Diagnosis::new(
+            Health::Warning,
+            "locale",
+            format!(
+                "there are no builtin messages for {}, so they are english unless $SRCH_CATALOG names a translation",
+                locale
+            ),
+        ),
+    };
+
+    let encoding = match locale.split_once('.').map(|(_, encoding)| encoding) {
+        Some(encoding)
+            if encoding.eq_ignore_ascii_case("utf-8") || encoding.eq_ignore_ascii_case("utf8") =>
+        {
+            Diagnosis::new(Health::Ok, "encoding", "the locale uses UTF-8")
+        }
+        Some(encoding) => Diagnosis::new(
+            Health::Warning,
+            "encoding",
+            format!(
+                "the locale uses {}, but srch prints UTF-8, set e.g. LANG={}.UTF-8",
+                encoding,
+                locale.split('.').next().unwrap_or(locale)
+            ),
+        ),
+        None => Diagnosis::new(
+            Health::Warning,
+            "encoding",
+            format!(
+                "{} doesn't name an encoding, non-ascii text may be shown garbled, set e.g. LANG=en_US.UTF-8",
+                locale
+            ),
+        ),
+    };
+
+    vec![language, encoding]
+}
+
+/// Whether the catalog given by `$SRCH_CATALOG` can be loaded, every
+/// command fails if it can't
+fn diagnose_catalog(path: &Path, source: io::Result<String>) -> Diagnosis {
+    match source
+        .map_err(|err| err.to_string())
+        .and_then(|source| parse_catalog(&source))
+    {
+        Ok(catalog) => Diagnosis::new(
+            Health::Ok,
+            "catalog",
+            format!("{} defines {} message(s)", path.display(), catalog.len()),
+        ),
+        Err(err) => Diagnosis::new(
+            Health::Error,
+            "catalog",
+            format!(
+                "{}: {}, every command fails until it is fixed or $SRCH_CATALOG is unset",
+                path.display(),
+                err
+            ),
+        ),
+    }
+}
+
+/// Whether the pattern library can be loaded, commands using a named pattern
+/// fail if it can't
+fn diagnose_patterns(path: &Path, source: io::Result<String>) -> Diagnosis {
+    let source = match source {
+        Ok(source) => source,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Diagnosis::new(
+                Health::Ok,
+                "patterns",
+                format!(
+                    "there is no pattern library at {}, only the built-in patterns are available",
+                    path.display()
+                ),
+            )
+        }
+        Err(err) => {
+            return Diagnosis::new(
+                Health::Error,
+                "patterns",
+                format!("{}: {}", path.display(), err),
+            )
+        }
+    };
+
+    match Patterns::parse(&source) {
+        Ok(patterns) => Diagnosis::new(
+            Health::Ok,
+            "patterns",
+            format!(
+                "{} defines {} pattern(s)",
+                path.display(),
+                patterns.iter().count()
+            ),
+        ),
+        Err(err) => Diagnosis::new(
+            Health::Error,
+            "patterns",
+            format!(
+                "{}: {}, commands using a named pattern fail until it is fixed",
+                path.display(),
+                err
+            ),
+        ),
+    }
+}
+
+/// Whether the plugins can be loaded, every command fails if one can't
+#[cfg(feature = "plugins")]
+fn diagnose_plugins(dir: &Path) -> Diagnosis {
+    // Safety: see init_plugins
+    match unsafe { srch::Plugin::load_dir(dir) } {
+        Ok(plugins) => Diagnosis::new(
+            Health::Ok,
+            "plugins",
+            format!(
+                "{} contains {} plugin(s) providing {} query(s)",
+                dir.display(),
+                plugins.len(),
+                plugins
+                    .iter()
+                    .map(|plugin| plugin.queries().len())
+                    .sum::<usize>()
+            ),
+        ),
+        Err((path, err)) => Diagnosis::new(
+            Health::Error,
+            "plugins",
+            format!(
+                "{}: {}, every command fails until it is fixed or removed",
+                path.display(),
+                err
+            ),
+        ),
+    }
+}
+
+/// Checks the environment srch runs in, see `srch doctor`
+fn diagnose() -> Vec<Diagnosis> {
+    let mut diagnoses = vec![diagnose_terminal(
+        io::stdout().is_terminal(),
+        env::var("TERM").ok().as_deref(),
+    )];
+
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty());
+
+    diagnoses.extend(diagnose_locale(locale.as_deref()));
+
+    if let Some(path) = env::var_os("SRCH_CATALOG") {
+        let path = Path::new(&path);
+        diagnoses.push(diagnose_catalog(path, fs::read_to_string(path)));
+    }
+
+    diagnoses.push(match Patterns::path() {
+        Some(path) => diagnose_patterns(&path, fs::read_to_string(&path)),
+        None => Diagnosis::new(
+            Health::Warning,
+            "patterns",
+            "neither $SRCH_PATTERNS nor $HOME is set, so the pattern library can't be found",
+        ),
+    });
+
+    #[cfg(feature = "plugins")]
+    if let Some(dir) = plugin_dir() {
+        diagnoses.push(diagnose_plugins(&dir));
+    }
+
+    diagnoses
+}
+
+/// A test case of an expression test suite
+#[derive(Clone, Debug, Default, PartialEq)]
+struct TestCase {
+    name: Option<String>,
+    expression: String,
+    matches: Vec<String>,
+    rejects: Vec<String>,
+}
+
+/// A toml file of test cases, listing inputs each expression has to match
+/// or reject:
+///
+/// ```toml
+/// [[case]]
+/// name = "five digit numbers"
+/// expression = "numeric and length 5"
+/// matches = ["12345"]
+/// rejects = ["1234", "abcde"]
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+struct TestSuite {
+    cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn parse(source: &str) -> std::result::Result<Self, String> {
+        fn strings(
+            case: &toml::value::Table,
+            key: &str,
+        ) -> std::result::Result<Vec<String>, String> {
+            match case.get(key) {
+                None => Ok(vec![]),
+                Some(toml::Value::Array(values)) => values
+                    .iter()
+                    .map(|value| match value {
+                        toml::Value::String(value) => Ok(value.clone()),
+                        _ => Err(format!("\"{}\" must only contain strings", key)),
+                    })
+                    .collect(),
+                Some(_) => Err(format!("\"{}\" must be an array of strings", key)),
+            }
+        }
+
+        let suite = source
+            .parse::<toml::Value>()
+            .map_err(|err| err.to_string())?;
+
+        let cases = match suite.get("case") {
+            Some(toml::Value::Array(cases)) => cases,
+            Some(_) => return Err("\"case\" must be an array of tables".to_string()),
+            None => return Ok(Self::default()),
+        };
+
+        let cases = cases
+            .iter()
+            .map(|case| {
+                let case = case
+                    .as_table()
+                    .ok_or_else(|| "\"case\" must be an array of tables".to_string())?;
+
+                let expression = case
+                    .get("expression")
+                    .and_then(|expression| expression.as_str())
+                    .ok_or_else(|| "every case needs an \"expression\"".to_string())?;
+
+                Ok(TestCase {
+                    name: case
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .map(|name| name.to_string()),
+                    expression: expression.to_string(),
+                    matches: strings(case, "matches")?,
+                    rejects: strings(case, "rejects")?,
+                })
+            })
+            .collect::<std::result::Result<Vec<_>, String>>()?;
+
+        Ok(Self { cases })
+    }
+
+    /// Writes the suite in the format read by [`TestSuite::parse`], one
+    /// input per line
+    fn to_toml(&self) -> String {
+        fn strings(values: &[String]) -> String {
+            match values.is_empty() {
+                true => "[]".to_string(),
+                false => format!(
+                    "[\n{}]",
+                    values
+                        .iter()
+                        .map(|value| format!("    {},\n", toml::Value::String(value.clone())))
+                        .collect::<String>()
+                ),
+            }
+        }
+
+        self.cases
+            .iter()
+            .map(|case| {
+                let name = match &case.name {
+                    Some(name) => format!("name = {}\n", toml::Value::String(name.clone())),
+                    None => String::new(),
+                };
+
+                format!(
+                    "[[case]]\n{}expression = {}\nmatches = {}\nrejects = {}\n",
+                    name,
+                    toml::Value::String(case.expression.clone()),
+                    strings(&case.matches),
+                    strings(&case.rejects)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Keeps a uniformly distributed sample of at most `size` distinct records
+/// of a stream (reservoir sampling). The generator is seeded, so sampling the
+/// same input always gives the same sample
+struct Reservoir {
+    size: usize,
+    seen: u64,
+    state: u64,
+    /// The sampled records together with their position in the stream
+    sample: Vec<(usize, String)>,
+}
+
+impl Reservoir {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            seen: 0,
+            state: 0x9e37_79b9_7f4a_7c15,
+            sample: Vec::with_capacity(size),
+        }
+    }
+
+    /// A xorshift generator, sufficient to pick records
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn offer(&mut self, position: usize, record: &str) {
+        if self.size == 0 || self.sample.iter().any(|(_, sampled)| sampled == record) {
+            return;
+        }
+
+        self.seen += 1;
+
+        if self.sample.len() < self.size {
+            self.sample.push((position, record.to_string()));
+            return;
+        }
+
+        let slot = (self.next() % self.seen) as usize;
+
+        if slot < self.size {
+            self.sample[slot] = (position, record.to_string());
+        }
+    }
+
+    /// The sampled records in the order they appeared in the stream
+    fn into_sample(mut self) -> Vec<String> {
+        self.sample.sort_by_key(|(position, _)| *position);
+        self.sample.into_iter().map(|(_, record)| record).collect()
+    }
+}
+
+/// Interactively builds an expression by asking the user simple questions,
+/// every answered question adds one query to the expression
+struct Wizard<R, W> {
+    input: R,
+    prompt: W,
+}
+
+impl<R: BufRead, W: Write> Wizard<R, W> {
+    const CLASSES: [&'static str; 7] = [
+        "numeric",
+        "alpha",
+        "alphanumeric",
+        "uppercase",
+        "lowercase",
+        "whitespace",
+        "special",
+    ];
+
+    fn new(input: R, prompt: W) -> Self {
+        Self { input, prompt }
+    }
+
+    /// Asks a question, an empty answer (or end of input) skips it
+    fn ask(&mut self, question: &str) -> io::Result<Option<String>> {
+        write!(self.prompt, "{} ", question)?;
+        self.prompt.flush()?;
+
+        let mut answer = String::new();
+        self.input.read_line(&mut answer)?;
+
+        let answer = answer.trim_end_matches(['\r', '\n']);
+
+        Ok(match answer.trim().is_empty() {
+            true => None,
+            false => Some(answer.to_string()),
+        })
+    }
+
+    fn ask_text(&mut self, question: &str) -> io::Result<Option<String>> {
+        loop {
+            match self.ask(question)? {
+                Some(text) if text.contains('"') => {
+                    writeln!(self.prompt, "Text can't contain double quotes, try again.")?
+                }
+                text => return Ok(text),
+            }
+        }
+    }
+
+    fn ask_length(&mut self, question: &str) -> io::Result<Option<u64>> {
+        loop {
+            match self.ask(question)? {
+                Some(length) => match length.trim().parse() {
+                    Ok(length) => return Ok(Some(length)),
+                    Err(_) => writeln!(self.prompt, "Please enter a number.")?,
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn ask_class(&mut self, question: &str) -> io::Result<Option<&'static str>> {
+        loop {
+            match self.ask(question)? {
+                Some(class) => match Self::CLASSES.iter().find(|c| **c == class.trim()) {
+                    Some(class) => return Ok(Some(class)),
+                    None => writeln!(
+                        self.prompt,
+                        "Please choose one of: {}.",
+                        Self::CLASSES.join(", ")
+                    )?,
+                },
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Asks a yes / no question, anything but "n" or "no" counts as yes
+    fn confirm(&mut self, question: &str) -> io::Result<bool> {
+        Ok(!matches!(
+            self.ask(question)?
+                .map(|a| a.trim().to_lowercase())
+                .as_deref(),
+            Some("n") | Some("no")
+        ))
+    }
+
+    /// Runs through all questions and returns the resulting expression, or
+    /// `None` if no question was answered
+    fn build(&mut self) -> io::Result<Option<String>> {
+        let mut queries = vec![];
+
+        if let Some(text) =
+            self.ask_text("Should it start with something? (leave empty to skip)")?
+        {
+            queries.push(format!("starts \"{}\"", text));
+        }
+
+        if let Some(text) = self.ask_text("Should it end with something?")? {
+            queries.push(format!("ends \"{}\"", text));
+        }
+
+        if let Some(text) = self.ask_text("Should it contain something?")? {
+            queries.push(format!("contains \"{}\"", text));
+        }
+
+        if let Some(length) = self.ask_length("How long should it be?")? {
+            queries.push(format!("length {}", length));
+        }
+
+        let question = format!(
+            "Should it only consist of certain characters? ({})",
+            Self::CLASSES.join(", ")
+        );
+
+        if let Some(class) = self.ask_class(&question)? {
+            queries.push(class.to_string());
+        }
+
+        Ok(match queries.is_empty() {
+            true => None,
+            false => Some(queries.join(" and ")),
+        })
+    }
+}
+
+/// A single item of the input (a line or a word) together with its origin
+#[derive(Clone, Debug, PartialEq)]
+struct Record {
+    file: Rc<str>,
+    line: usize,
+    text: String,
+    /// The yaml document the text was taken from, printed instead of the text
+    document: Option<Rc<str>>,
+}
+
+impl Record {
+    /// A record which wasn't read from an input, e.g. a rule printed by
+    /// `which`
+    fn message(text: String) -> Self {
+        Self {
+            file: "".into(),
+            line: 0,
+            text,
+            document: None,
+        }
+    }
+}
+
+type Items = Box<dyn Iterator<Item = io::Result<Record>>>;
+
+const FILTER_POSITIONALS: &[&str] = &["expression", "input"];
+const REPLACE_POSITIONALS: &[&str] = &["expression", "replacement", "input"];
+
+/// The positional arguments of a subcommand. If the expression is read from a
+/// file using `--expr-file` the EXPRESSION positional is omitted on the
+/// command line, so every following positional shifts one to the left
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Positionals<'a> {
+    expression: Option<&'a str>,
+    replacement: Option<&'a str>,
+    inputs: Vec<&'a str>,
+}
+
+impl<'a> Positionals<'a> {
+    fn from_matches(matches: &'a ArgMatches, names: &[&str]) -> Self {
+        let mut values = names
+            .iter()
+            .flat_map(|name| matches.values_of(name).into_iter().flatten());
+
+        let omits_expression = matches.is_present("expr-file") || matches.is_present("exprs");
+
+        let expression = match omits_expression {
+            true => None,
+            false => values.next(),
+        };
+
+        let replacement = match names.contains(&"replacement") {
+            true => values.next(),
+            false => None,
+        };
+
+        Self {
+            expression,
+            replacement,
+            inputs: values.collect(),
+        }
+    }
+
+    /// The single input of subcommands which don't support multiple files
+    fn input(&self) -> Option<&'a str> {
+        if let Some(extra) = self.inputs.get(1) {
+            build_cli()
+                .error(
+                    ErrorKind::UnknownArgument,
+                    format!(
+                        "Found argument '{}' which wasn't expected, only a single input <FILE> is supported",
+                        extra
+                    ),
+                )
+                .exit();
+        }
+
+        self.inputs.first().copied()
+    }
+}
+
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A reader which never reaches the end of a file, instead it waits for new
+/// data to be appended (like `tail -F`). If the file is truncated it is read
+/// from the start again and if it is rotated the new file is opened
+struct Follow {
+    path: PathBuf,
+    file: File,
+    position: u64,
+}
+
+impl Follow {
+    fn open(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let file = File::open(&path)?;
+
+        Ok(Self {
+            path,
+            file,
+            position: 0,
+        })
+    }
+
+    #[cfg(unix)]
+    fn is_rotated(&self) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        match (self.file.metadata(), fs::metadata(&self.path)) {
+            (Ok(open), Ok(current)) => open.ino() != current.ino() || open.dev() != current.dev(),
+            _ => false,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn is_rotated(&self) -> bool {
+        false
+    }
+
+    fn reopen(&mut self) -> io::Result<()> {
+        if self.is_rotated() {
+            if let Ok(file) = File::open(&self.path) {
+                self.file = file;
+                self.position = 0;
+            }
+        } else if self.file.metadata()?.len() < self.position {
+            self.file.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+
+            if n > 0 || buf.is_empty() {
+                self.position += n as u64;
+                return Ok(n);
+            }
+
+            self.reopen()?;
+            thread::sleep(FOLLOW_INTERVAL);
+        }
+    }
+}
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const PROGRESS_WIDTH: usize = 30;
+
+/// A reader which draws a progress bar to stderr while reading an input of
+/// known size, the bar is cleared once the reader reaches the end or is
+/// dropped
+struct Progress<R> {
+    inner: R,
+    total: u64,
+    read: u64,
+    drawn: Option<Instant>,
+}
+
+impl<R: Read> Progress<R> {
+    fn new(inner: R, total: u64) -> Self {
+        Self {
+            inner,
+            total,
+            read: 0,
+            drawn: None,
+        }
+    }
+
+    /// Progress is only shown if stderr is a terminal and stdout is not,
+    /// otherwise the bar would be mixed up with the printed matches
+    fn is_enabled() -> bool {
+        io::stderr().is_terminal() && !io::stdout().is_terminal()
+    }
+
+    fn draw(&mut self) {
+        if matches!(self.drawn, Some(drawn) if drawn.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+
+        let ratio = match self.total {
+            0 => 1.0,
+            total => (self.read as f64 / total as f64).min(1.0),
+        };
+
+        let filled = (ratio * PROGRESS_WIDTH as f64) as usize;
+
+        eprint!(
+            "\r[{}{}] {:>3}% {:.1} / {:.1} MiB",
+            "=".repeat(filled),
+            " ".repeat(PROGRESS_WIDTH - filled),
+            (ratio * 100.0) as usize,
+            self.read as f64 / (1024.0 * 1024.0),
+            self.total as f64 / (1024.0 * 1024.0)
+        );
+
+        self.drawn = Some(Instant::now());
+    }
+}
+
+impl<R: Read> Read for Progress<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        self.read += n as u64;
+
+        match n {
+            0 => self.clear(),
+            _ => self.draw(),
+        }
+
+        Ok(n)
+    }
+}
+
+impl<R> Progress<R> {
+    fn clear(&mut self) {
+        if self.drawn.take().is_some() {
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+impl<R> Drop for Progress<R> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+fn is_url(input: &str) -> bool {
+    input.starts_with("http://") || input.starts_with("https://")
+}
+
+/// Fetches a url, the response body is streamed while searching it
+#[cfg(feature = "http")]
+fn fetch(url: &str, progress: bool) -> io::Result<Box<dyn BufRead>> {
+    let response = ureq::get(url).call().map_err(io::Error::other)?;
+
+    let total = response
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok());
+
+    let reader = response.into_reader();
+
+    Ok(match (progress && Progress::<File>::is_enabled(), total) {
+        (true, Some(total)) => Box::new(BufReader::new(Progress::new(reader, total))),
+        _ => Box::new(BufReader::new(reader)),
+    })
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch(url: &str, _: bool) -> io::Result<Box<dyn BufRead>> {
+    fail(format!(
+        "can't fetch {}, srch was built without the \"http\" feature",
+        url
+    ))
+}
+
+fn open_input(
+    input: Option<&str>,
+    follow: bool,
+    progress: bool,
+) -> io::Result<(Rc<str>, Box<dyn BufRead>)> {
+    verbose!(1, "reading {}", input.unwrap_or(STDIN_NAME));
+
+    let input: (Rc<str>, Box<dyn BufRead>) = match (input, follow) {
+        (Some(url), true) if is_url(url) => fail("--follow can't be used with urls"),
+        (Some(url), false) if is_url(url) => (url.into(), fetch(url, progress)?),
+        (Some(path), true) => (path.into(), Box::new(BufReader::new(Follow::open(path)?))),
+        (Some(path), false) => {
+            let file = File::open(path)?;
+
+            match progress && Progress::<File>::is_enabled() {
+                true => {
+                    let total = file.metadata()?.len();
+                    let reader = Progress::new(file, total);
+                    (path.into(), Box::new(BufReader::new(reader)))
+                }
+                false => (path.into(), Box::new(BufReader::new(file))),
+            }
+        }
+        (None, _) => (STDIN_NAME.into(), Box::new(BufReader::new(io::stdin()))),
+    };
+
+    Ok(input)
+}
+
+/// Counters collected while processing the input, printed using `--stats`
+#[derive(Debug, Default)]
+struct Stats {
+    files: Cell<usize>,
+    lines: Cell<usize>,
+    bytes: Cell<u64>,
+    matches: Cell<usize>,
+}
+
+impl Stats {
+    fn print(&self, out: &mut dyn Write, elapsed: Duration) -> io::Result<()> {
+        let seconds = elapsed.as_secs_f64();
+
+        let per_second = |n: f64| match seconds > 0.0 {
+            true => n / seconds,
+            false => 0.0,
+        };
+
+        writeln!(out, "files searched: {}", self.files.get())?;
+        writeln!(out, "lines scanned:  {}", self.lines.get())?;
+        writeln!(out, "matches:        {}", self.matches.get())?;
+        writeln!(out, "elapsed:        {:.3}s", seconds)?;
+        writeln!(
+            out,
+            "throughput:     {:.2} MiB/s, {:.0} lines/s",
+            per_second(self.bytes.get() as f64) / (1024.0 * 1024.0),
+            per_second(self.lines.get() as f64)
+        )
+    }
+}
+
+/// Counts how many records each expression of a set matched for `--summary`
+struct Summary {
+    labels: Vec<String>,
+    expr: Rc<srch::ExpressionSet>,
+    counts: Vec<Cell<usize>>,
+}
+
+impl Summary {
+    fn new(labels: Vec<String>, expr: Rc<srch::ExpressionSet>) -> Self {
+        let counts = vec![Cell::new(0); expr.len()];
+
+        Self {
+            labels,
+            expr,
+            counts,
+        }
+    }
+
+    fn count(&self, text: &str) {
+        for index in self.expr.matching(text) {
+            self.counts[index].set(self.counts[index].get() + 1);
+        }
+    }
+
+    fn print(&self, out: &mut dyn Write) -> io::Result<()> {
+        let width = self
+            .counts
+            .iter()
+            .map(|count| count.get().to_string().len())
+            .max()
+            .unwrap_or_default();
+
+        for (label, count) in self.labels.iter().zip(self.counts.iter()) {
+            writeln!(out, "{:>width$} {}", count.get(), label, width = width)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Prints how often every query was evaluated and matched for
+/// `--stats-detail`, the queries of multiple expressions are grouped by the
+/// label of their expression
+fn print_query_stats(
+    out: &mut dyn Write,
+    labels: &[String],
+    stats: &[Vec<srch::QueryStats>],
+) -> io::Result<()> {
+    let width = stats
+        .iter()
+        .flatten()
+        .map(|query| query.evaluated.to_string().len())
+        .max()
+        .unwrap_or_default();
+
+    for (label, queries) in labels.iter().zip(stats.iter()) {
+        if stats.len() > 1 {
+            writeln!(out, "{}:", label)?;
+        }
+
+        for query in queries {
+            writeln!(
+                out,
+                "{:>width$} evaluated {:>width$} matched  {}",
+                query.evaluated,
+                query.matched,
+                query.query,
+                width = width
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Groups lines into paragraphs, which are separated by blank lines. Each
+/// paragraph keeps the line number of its first line
+struct Paragraphs {
+    lines: Items,
+}
+
+impl Paragraphs {
+    fn new(lines: Items) -> Self {
+        Self { lines }
+    }
+}
+
+impl Iterator for Paragraphs {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut paragraph: Option<Record> = None;
+
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match (&mut paragraph, line.text.trim().is_empty()) {
+                (None, true) => continue,
+                (None, false) => paragraph = Some(line),
+                (Some(_), true) => break,
+                (Some(paragraph), false) => {
+                    paragraph.text.push('\n');
+                    paragraph.text.push_str(&line.text);
+                }
+            }
+        }
+
+        paragraph.map(Ok)
+    }
+}
+
+/// Splits a paragraph into sentences. A sentence ends with `.`, `!` or `?`
+/// followed by whitespace, line breaks inside a sentence are collapsed
+fn sentences(paragraph: &Record) -> Vec<Record> {
+    let text = &paragraph.text;
+    let mut sentences = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let ends_sentence = matches!(c, '.' | '!' | '?')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+
+        if ends_sentence || chars.peek().is_none() {
+            let end = i + c.len_utf8();
+            let sentence = text[start..end].split_whitespace().collect::<Vec<_>>();
+
+            if !sentence.is_empty() {
+                let offset = start + text[start..end].len() - text[start..end].trim_start().len();
+
+                sentences.push(Record {
+                    line: paragraph.line + text[..offset].matches('\n').count(),
+                    text: sentence.join(" "),
+                    ..paragraph.clone()
+                });
+            }
+
+            start = end;
+        }
+    }
+
+    sentences
+}
+
+/// The unit the expression is executed on
+#[derive(Clone, Debug, PartialEq)]
+enum Mode {
+    Line,
+    Word(srch::Tokenizer),
+    Sentence,
+    Paragraph,
+    Char,
+    Delimited(Vec<u8>),
+    Yaml(String),
+}
+
+impl Mode {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        if matches.is_present("null-data") {
+            return Self::Delimited(vec![b'\0']);
+        }
+
+        let tokenizer = srch::Tokenizer {
+            unicode_whitespace: matches.is_present("unicode-whitespace"),
+            strip_punctuation: matches.is_present("strip-punctuation"),
+            keep_delimiters: matches.is_present("keep-delimiters"),
+        };
+
+        if matches.is_present("words") {
+            return Self::Word(tokenizer);
+        }
+
+        match matches.value_of("mode") {
+            Some("word") => Self::Word(tokenizer),
+            Some("sentence") => Self::Sentence,
+            Some("paragraph") => Self::Paragraph,
+            Some("char") => Self::Char,
+            Some("yaml") => Self::Yaml(matches.value_of("key").unwrap_or_default().to_string()),
+            Some("delim") => Self::Delimited(
+                unescape(matches.value_of("delimiter").unwrap_or_default())
+                    .unwrap_or_default()
+                    .into_bytes(),
+            ),
+            Some(_) | None => Self::Line,
+        }
+    }
+}
+
+/// Replaces the escape sequences `\0`, `\t`, `\n`, `\r` and `\\`
+fn unescape(text: &str) -> std::result::Result<String, String> {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('0') => unescaped.push('\0'),
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some('r') => unescaped.push('\r'),
+            Some('\\') => unescaped.push('\\'),
+            Some(c) => return Err(format!("unknown escape sequence \"\\{}\"", c)),
+            None => return Err("trailing backslash".to_string()),
+        }
+    }
+
+    Ok(unescaped)
+}
+
+fn is_delimiter(delimiter: &str) -> std::result::Result<(), String> {
+    match unescape(delimiter)?.is_empty() {
+        true => Err("the delimiter can't be empty".to_string()),
+        false => Ok(()),
+    }
+}
+
+/// The number of bytes read at once while skipping the rest of a record
+/// which is too long
+const DISCARD_CHUNK: u64 = 64 * 1024;
+
+/// What happens to records longer than `--max-record-length`
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Overlong {
+    Skip,
+    Truncate,
+    Error,
+}
+
+/// The maximum length of a record in bytes, longer records are never read
+/// into memory as a whole
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Limit {
+    max: usize,
+    overlong: Overlong,
+}
+
+impl Limit {
+    fn from_matches(matches: &ArgMatches) -> Option<Self> {
+        let max = matches.value_of("max-record-length")?.parse().ok()?;
+
+        let overlong = match matches.value_of("overlong") {
+            Some("skip") => Overlong::Skip,
+            Some("truncate") => Overlong::Truncate,
+            _ => Overlong::Error,
+        };
+
+        Some(Self { max, overlong })
+    }
+}
+
+/// The error of a record longer than `--max-record-length`
+#[derive(Debug)]
+struct RecordTooLong(usize);
+
+impl Display for RecordTooLong {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "record is longer than {} bytes", self.0)
+    }
+}
+
+impl std::error::Error for RecordTooLong {}
+
+fn is_too_long(record: &io::Result<String>) -> bool {
+    match record {
+        Err(err) => err.get_ref().is_some_and(|err| err.is::<RecordTooLong>()),
+        Ok(_) => false,
+    }
+}
+
+/// Splits a reader into records separated by an arbitrary delimiter. A line
+/// break at the very end of the input isn't part of the last record
+struct Delimited<R> {
+    reader: R,
+    delimiter: Vec<u8>,
+    limit: Option<Limit>,
+    done: bool,
+}
+
+impl<R: BufRead> Delimited<R> {
+    fn new(reader: R, delimiter: Vec<u8>) -> Self {
+        Self {
+            reader,
+            delimiter,
+            limit: None,
+            done: false,
+        }
+    }
+
+    /// Never buffers more than `limit` bytes of a record
+    fn with_limit(mut self, limit: Option<Limit>) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let last = self.delimiter[self.delimiter.len() - 1];
+        let mut record = vec![];
+
+        // One byte more than allowed is enough to tell that a record is too
+        // long, the rest of it is discarded without being buffered
+        let budget = match self.limit {
+            Some(limit) => (limit.max + self.delimiter.len() + 1) as u64,
+            None => u64::MAX,
+        };
+
+        loop {
+            let remaining = budget - record.len() as u64;
+
+            if remaining == 0 {
+                self.discard_record()?;
+                return self.overlong(record);
+            }
+
+            if (&mut self.reader)
+                .take(remaining)
+                .read_until(last, &mut record)?
+                == 0
+            {
+                self.done = true;
+
+                if record.ends_with(b"\n") {
+                    record.pop();
+
+                    if record.ends_with(b"\r") {
+                        record.pop();
+                    }
+                }
+
+                return match record.is_empty() {
+                    true => Ok(None),
+                    false => self.checked(record),
+                };
+            }
+
+            if record.ends_with(&self.delimiter) {
+                record.truncate(record.len() - self.delimiter.len());
+
+                // Lines of files written on Windows end with `\r\n`
+                if self.delimiter == b"\n" && record.ends_with(b"\r") {
+                    record.pop();
+                }
+
+                return self.checked(record);
+            }
+        }
+    }
+
+    fn checked(&self, record: Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        match self.limit {
+            Some(limit) if record.len() > limit.max => self.overlong(record),
+            _ => Ok(Some(record)),
+        }
+    }
+
+    fn overlong(&self, mut record: Vec<u8>) -> io::Result<Option<Vec<u8>>> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => return Ok(Some(record)),
+        };
+
+        if limit.overlong != Overlong::Truncate {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                RecordTooLong(limit.max),
+            ));
+        }
+
+        record.truncate(limit.max);
+
+        // A character cut in half is dropped entirely
+        if let Err(err) = std::str::from_utf8(&record) {
+            if err.error_len().is_none() {
+                record.truncate(err.valid_up_to());
+            }
+        }
+
+        Ok(Some(record))
+    }
+
+    /// Skips the rest of the current record
+    fn discard_record(&mut self) -> io::Result<()> {
+        let last = self.delimiter[self.delimiter.len() - 1];
+        let mut tail = vec![];
+
+        loop {
+            if (&mut self.reader)
+                .take(DISCARD_CHUNK)
+                .read_until(last, &mut tail)?
+                == 0
+            {
+                self.done = true;
+                return Ok(());
+            }
+
+            if tail.ends_with(&self.delimiter) {
+                return Ok(());
+            }
+
+            // Only the bytes which could be the start of a delimiter are kept
+            let keep = self.delimiter.len() - 1;
+            tail.drain(..tail.len().saturating_sub(keep));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Delimited<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        match self.read_record() {
+            Ok(record) => record.map(|record| {
+                String::from_utf8(record)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+            }),
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Reads a list of file names separated by `separator`, empty entries are
+/// skipped
+fn read_file_list(reader: impl BufRead, separator: u8) -> io::Result<Vec<String>> {
+    let mut files = vec![];
+
+    for file in reader.split(separator) {
+        let mut file = String::from_utf8(file?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if separator == b'\n' && file.ends_with('\r') {
+            file.pop();
+        }
+
+        if !file.is_empty() {
+            files.push(file);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Reads the files given by `--files-from` or `--files-from0`, if present
+fn read_files_from_matches(matches: &ArgMatches) -> io::Result<Option<Vec<String>>> {
+    let (path, separator) = match (
+        matches.value_of("files-from"),
+        matches.value_of("files-from0"),
+    ) {
+        (Some(path), _) => (path, b'\n'),
+        (_, Some(path)) => (path, b'\0'),
+        (None, None) => return Ok(None),
+    };
+
+    let files = match path {
+        "-" => read_file_list(io::stdin().lock(), separator)?,
+        path => read_file_list(BufReader::new(File::open(path)?), separator)?,
+    };
+
+    verbose!(1, "read {} file name(s) from {}", files.len(), path);
+
+    Ok(Some(files))
+}
+
+/// Reads the scalar at the dot separated `key` of every yaml document,
+/// documents without a scalar at that key are skipped. Sequences are indexed
+/// by number, e.g. `spec.containers.0.image`
+#[cfg(feature = "yaml")]
+fn yaml_records(file: &Rc<str>, source: &str, key: &str) -> io::Result<Vec<Record>> {
+    use serde::Deserialize;
+    use serde_yaml::Value;
+
+    let mut records = vec![];
+
+    for (n, document) in serde_yaml::Deserializer::from_str(source).enumerate() {
+        let document = Value::deserialize(document)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let value = key
+            .split('.')
+            .try_fold(&document, |value, segment| match value {
+                Value::Sequence(values) => {
+                    segment.parse::<usize>().ok().and_then(|i| values.get(i))
+                }
+                value => value.get(segment),
+            });
+
+        let text = match value {
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Number(number)) => number.to_string(),
+            Some(Value::Bool(boolean)) => boolean.to_string(),
+            _ => continue,
+        };
+
+        let yaml = serde_yaml::to_string(&document)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        records.push(Record {
+            file: file.clone(),
+            line: n + 1,
+            text,
+            document: Some(format!("---\n{}", yaml.trim_end()).into()),
+        });
+    }
+
+    Ok(records)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn yaml_records(_: &Rc<str>, _: &str, _: &str) -> io::Result<Vec<Record>> {
+    fail("the yaml mode requires srch to be built with the \"yaml\" feature")
+}
+
+/// Access to the system clipboard. On linux the copied text only outlives
+/// `srch` if a clipboard manager is running
+#[cfg(feature = "clipboard")]
+mod clipboard {
+    use std::io;
+
+    pub fn paste() -> io::Result<String> {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.get_text())
+            .map_err(io::Error::other)
+    }
+
+    pub fn copy(text: String) -> io::Result<()> {
+        arboard::Clipboard::new()
+            .and_then(|mut clipboard| clipboard.set_text(text))
+            .map_err(io::Error::other)
+    }
+}
+
+#[cfg(not(feature = "clipboard"))]
+mod clipboard {
+    use std::io;
+
+    pub fn paste() -> io::Result<String> {
+        super::fail("--clipboard requires srch to be built with the \"clipboard\" feature")
+    }
+
+    pub fn copy(_: String) -> io::Result<()> {
+        super::fail("--copy requires srch to be built with the \"clipboard\" feature")
+    }
+}
+
+/// Searches plain files using grep-searcher, which reads them in large
+/// chunks and stops reading binary files at the first NUL byte
+#[cfg(feature = "grep")]
+mod searcher {
+    use std::collections::VecDeque;
+    use std::io::{self, BufRead};
+    use std::rc::Rc;
+
+    use clap::ArgMatches;
+    use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
+    use srch::grep::LineMatcher;
+
+    use super::{is_url, open_input, Items, Mode, Record, Stats};
+
+    /// Whether the input can be searched using grep-searcher, which only
+    /// supports the line mode on files
+    pub fn is_supported(matches: &ArgMatches, inputs: &[&str]) -> bool {
+        Mode::from_matches(matches) == Mode::Line
+            && !matches.is_present("follow")
+            && !matches.is_present("clipboard")
+            && !matches.is_present("rest-to")
+            && !matches.is_present("summary")
+            && !matches.is_present("max-record-length")
+            && !matches.is_present("only-matching")
+            && !inputs.is_empty()
+            && !inputs.iter().any(|input| is_url(input))
+    }
+
+    /// Lines are searched in chunks of about this many bytes, so `--head` can
+    /// stop reading early and huge files aren't kept in memory
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Collects the matching lines of a chunk
+    struct Chunk<'a> {
+        file: &'a Rc<str>,
+        /// The number of lines before the chunk
+        offset: usize,
+        lines: usize,
+        records: &'a mut VecDeque<io::Result<Record>>,
+        stats: &'a Stats,
+        stopped: bool,
+    }
+
+    impl Chunk<'_> {
+        /// Counts the line and checks that it is valid utf-8 like
+        /// `BufRead::lines`, which is used for all other inputs
+        fn read_line<'b>(&mut self, line: &'b [u8]) -> io::Result<&'b str> {
+            self.lines += 1;
+            self.stats.lines.set(self.stats.lines.get() + 1);
+            self.stats
+                .bytes
+                .set(self.stats.bytes.get() + line.len() as u64);
+
+            std::str::from_utf8(line).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "stream did not contain valid UTF-8",
+                )
+            })
+        }
+
+        fn stop(&mut self, err: io::Error) -> io::Result<bool> {
+            self.records.push_back(Err(err));
+            self.stopped = true;
+            Ok(false)
+        }
+    }
+
+    impl Sink for Chunk<'_> {
+        type Error = io::Error;
+
+        fn matched(&mut self, _: &Searcher, m: &SinkMatch<'_>) -> io::Result<bool> {
+            let text = match self.read_line(m.bytes()) {
+                Ok(text) => text,
+                Err(err) => return self.stop(err),
+            };
+
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+
+            self.records.push_back(Ok(Record {
+                file: self.file.clone(),
+                line: self.offset + m.line_number().unwrap_or_default() as usize,
+                text: text.to_string(),
+                document: None,
+            }));
+
+            Ok(true)
+        }
+
+        fn context(&mut self, _: &Searcher, context: &SinkContext<'_>) -> io::Result<bool> {
+            match self.read_line(context.bytes()) {
+                Ok(_) => Ok(true),
+                Err(err) => self.stop(err),
+            }
+        }
+
+        fn binary_data(&mut self, _: &Searcher, offset: u64) -> io::Result<bool> {
+            verbose!(
+                1,
+                "{} is binary, stopped reading at byte {}",
+                self.file,
+                offset
+            );
+
+            self.stopped = true;
+            Ok(false)
+        }
+    }
+
+    /// The matching lines of a file, searched one chunk at a time
+    struct Records<F> {
+        file: Rc<str>,
+        /// `None` once the file is exhausted or stopped being read
+        reader: Option<Box<dyn BufRead>>,
+        matcher: Rc<LineMatcher<F>>,
+        searcher: Searcher,
+        lines: usize,
+        records: VecDeque<io::Result<Record>>,
+        stats: Rc<Stats>,
+    }
+
+    impl<F: Fn(&str) -> bool> Records<F> {
+        /// Reads whole lines until the chunk is full, a read error is returned
+        /// after the lines read before it
+        fn read_chunk(reader: &mut dyn BufRead) -> (Vec<u8>, bool, Option<io::Error>) {
+            let mut chunk = Vec::new();
+
+            while chunk.len() < CHUNK_SIZE {
+                match reader.read_until(b'\n', &mut chunk) {
+                    Ok(0) => return (chunk, true, None),
+                    Ok(_) => {}
+                    Err(err) => return (chunk, true, Some(err)),
+                }
+            }
+
+            (chunk, false, None)
+        }
+
+        fn search_chunk(&mut self) {
+            let reader = match self.reader.as_mut() {
+                Some(reader) => reader,
+                None => return,
+            };
+
+            let (bytes, exhausted, error) = Self::read_chunk(reader);
+
+            let mut chunk = Chunk {
+                file: &self.file,
+                offset: self.lines,
+                lines: 0,
+                records: &mut self.records,
+                stats: &self.stats,
+                stopped: false,
+            };
+
+            if let Err(err) = self
+                .searcher
+                .search_slice(&*self.matcher, &bytes, &mut chunk)
+            {
+                chunk.records.push_back(Err(err));
+                chunk.stopped = true;
+            }
+
+            self.lines += chunk.lines;
+
+            if chunk.stopped || exhausted {
+                self.reader = None;
+            }
+
+            if let Some(err) = error {
+                self.records.push_back(Err(err));
+            }
+        }
+    }
+
+    impl<F: Fn(&str) -> bool> Iterator for Records<F> {
+        type Item = io::Result<Record>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            loop {
+                if let Some(record) = self.records.pop_front() {
+                    return Some(record);
+                }
+
+                self.reader.as_ref()?;
+                self.search_chunk();
+            }
+        }
+    }
+
+    /// Searches the inputs one after another. The lines which don't match are
+    /// only counted, so the records yielded are the matching lines
+    pub fn search<F>(inputs: &[&str], progress: bool, is_match: F, stats: Rc<Stats>) -> Items
+    where
+        F: Fn(&str) -> bool + 'static,
+    {
+        let matcher = Rc::new(srch::grep::LineMatcher::new(is_match));
+        let inputs = inputs
+            .iter()
+            .map(|input| input.to_string())
+            .collect::<Vec<_>>();
+
+        Box::new(inputs.into_iter().flat_map(move |input| -> Items {
+            let (file, reader) = match open_input(Some(&input), false, progress) {
+                Ok(input) => input,
+                Err(err) => return Box::new(std::iter::once(Err(err))),
+            };
+
+            stats.files.set(stats.files.get() + 1);
+
+            let searcher = SearcherBuilder::new()
+                .binary_detection(BinaryDetection::quit(b'\0'))
+                .line_number(true)
+                .passthru(true)
+                .build();
+
+            Box::new(Records {
+                file,
+                reader: Some(reader),
+                matcher: matcher.clone(),
+                searcher,
+                lines: 0,
+                records: VecDeque::new(),
+                stats: stats.clone(),
+            })
+        }))
+    }
+}
+
+fn read_input_from_matches(
+    matches: &ArgMatches,
+    inputs: &[&str],
+    stats: Rc<Stats>,
+) -> io::Result<Items> {
+    let follow = matches.is_present("follow");
+    let progress = !matches.is_present("no-progress");
+    let mode = Mode::from_matches(matches);
+    let limit = Limit::from_matches(matches);
+
+    if matches.is_present("clipboard") {
+        let text = clipboard::paste()?;
+        let reader = Box::new(io::Cursor::new(text.into_bytes()));
+        return read_records(CLIPBOARD_NAME.into(), reader, &mode, limit, stats);
+    }
+
+    if inputs.is_empty() {
+        return read_input(None, follow, progress, &mode, limit, stats);
+    }
+
+    // Files are opened one after another, once the previous one is exhausted
+    let inputs = inputs
+        .iter()
+        .map(|input| input.to_string())
+        .collect::<Vec<_>>();
+
+    Ok(Box::new(inputs.into_iter().flat_map(
+        move |input| match read_input(Some(&input), follow, progress, &mode, limit, stats.clone()) {
+            Ok(items) => items,
+            Err(err) => Box::new(std::iter::once(Err(err))),
+        },
+    )))
+}
+
+fn read_input(
+    input: Option<&str>,
+    follow: bool,
+    progress: bool,
+    mode: &Mode,
+    limit: Option<Limit>,
+    stats: Rc<Stats>,
+) -> io::Result<Items> {
+    let (file, reader) = open_input(input, follow, progress)?;
+    read_records(file, reader, mode, limit, stats)
+}
+
+fn read_records(
+    file: Rc<str>,
+    reader: Box<dyn BufRead>,
+    mode: &Mode,
+    limit: Option<Limit>,
+    stats: Rc<Stats>,
+) -> io::Result<Items> {
+    stats.files.set(stats.files.get() + 1);
+
+    if let Mode::Yaml(key) = mode {
+        let mut source = String::new();
+        let mut reader = reader;
+        reader.read_to_string(&mut source)?;
+
+        stats.lines.set(stats.lines.get() + source.lines().count());
+        stats.bytes.set(stats.bytes.get() + source.len() as u64);
+
+        let records = yaml_records(&file, &source, key)?;
+        return Ok(Box::new(records.into_iter().map(Ok)));
+    }
+
+    let delimiter = match mode {
+        Mode::Delimited(delimiter) => delimiter.len(),
+        _ => 1,
+    };
+
+    let lines: Box<dyn Iterator<Item = io::Result<String>>> = match (mode, limit) {
+        (Mode::Delimited(delimiter), _) => {
+            Box::new(Delimited::new(reader, delimiter.clone()).with_limit(limit))
+        }
+        (_, Some(_)) => Box::new(Delimited::new(reader, b"\n".to_vec()).with_limit(limit)),
+        (_, None) => Box::new(reader.lines()),
+    };
+
+    // Skipped records still count, so the line numbers stay correct
+    let skip = matches!(limit, Some(limit) if limit.overlong == Overlong::Skip);
+    let lines = lines
+        .enumerate()
+        .filter(move |(_, line)| !(skip && is_too_long(line)));
+
+    let lines = lines.map(move |(n, line)| {
+        if is_too_long(&line) {
+            let err = line.err().map(|err| err.to_string()).unwrap_or_default();
+            let message = format!("{}:{}: {}, see --overlong", file, n + 1, err);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+
+        line.map(|text| {
+            stats.lines.set(stats.lines.get() + 1);
+            stats
+                .bytes
+                .set(stats.bytes.get() + (text.len() + delimiter) as u64);
+
+            Record {
+                file: file.clone(),
+                line: n + 1,
+                text,
+                document: None,
+            }
+        })
+    });
+
+    let items: Items = match mode.clone() {
+        Mode::Line | Mode::Delimited(_) | Mode::Yaml(_) => Box::new(lines),
+        Mode::Paragraph => Box::new(Paragraphs::new(Box::new(lines))),
+        Mode::Sentence => {
+            Box::new(
+                Paragraphs::new(Box::new(lines)).flat_map(|paragraph| match paragraph {
+                    Ok(paragraph) => sentences(&paragraph).into_iter().map(Ok).collect(),
+                    Err(err) => vec![Err(err)],
+                }),
+            )
+        }
+        Mode::Char => Box::new(lines.flat_map(|line| {
+            match line {
+                Ok(line) => line
+                    .text
+                    .graphemes(true)
+                    .map(|x| {
+                        Ok(Record {
+                            text: x.to_string(),
+                            ..line.clone()
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            }
+        })),
+        Mode::Word(tokenizer) => Box::new(lines.flat_map(move |line| {
+            match line {
+                Ok(line) => srch::Document::new(&line.text)
+                    .words_with(tokenizer)
+                    .map(|x| {
+                        Ok(Record {
+                            text: x.to_string(),
+                            ..line.clone()
+                        })
+                    })
+                    .collect::<Vec<_>>(),
+                Err(err) => vec![Err(err)],
+            }
+        })),
+    };
+
+    Ok(items)
+}
+
+/// Describes which part of the match stream should be printed
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Slice {
+    skip: usize,
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+
+impl Slice {
+    fn from_matches(matches: &ArgMatches) -> Self {
+        fn count(matches: &ArgMatches, name: &str) -> Option<usize> {
+            matches.value_of(name).and_then(|n| n.parse().ok())
+        }
+
+        let head = match matches.is_present("first") {
+            true => Some(1),
+            false => count(matches, "head"),
+        };
+
+        let tail = match matches.is_present("last") {
+            true => Some(1),
+            false => count(matches, "tail"),
+        };
+
+        Self {
+            skip: count(matches, "skip").unwrap_or_default(),
+            head,
+            tail,
+        }
+    }
+
+    fn apply(&self, items: Items) -> io::Result<Items> {
+        let items = items.skip(self.skip);
+
+        if let Some(n) = self.head {
+            return Ok(Box::new(items.take(n)));
+        }
+
+        if let Some(n) = self.tail {
+            let mut window = VecDeque::with_capacity(n);
+
+            for item in items {
+                let item = item?;
+
+                if window.len() == n {
+                    window.pop_front();
+                }
+
+                if n > 0 {
+                    window.push_back(item);
+                }
+            }
+
+            return Ok(Box::new(window.into_iter().map(Ok)));
+        }
+
+        Ok(Box::new(items))
+    }
+}
+
+/// Drops every match which has already been seen before, keeps streaming
+fn unique(items: Items) -> Items {
+    let mut seen = HashSet::new();
+
+    Box::new(items.filter(move |item| match item {
+        Ok(item) => seen.insert(item.text.clone()),
+        Err(_) => true,
+    }))
+}
+
+/// Collapses every run of identical matches into its first match, prefixed
+/// with the length of the run like `uniq -c`, keeps streaming
+fn squeeze(items: Items) -> Items {
+    let mut items = items.peekable();
+
+    Box::new(std::iter::from_fn(move || {
+        let mut item = match items.next()? {
+            Ok(item) => item,
+            Err(err) => return Some(Err(err)),
+        };
+
+        let mut count = 1;
+
+        while let Some(Ok(next)) = items.peek() {
+            if next.text != item.text {
+                break;
+            }
+
+            items.next();
+            count += 1;
+        }
+
+        item.text = format!("{:>7} {}", count, item.text);
+        item.document = item
+            .document
+            .map(|document| format!("{:>7} {}", count, document).into());
+
+        Some(Ok(item))
+    }))
+}
+
+/// Sorts all matches, this needs to buffer the whole match stream
+fn sort(items: Items) -> io::Result<Items> {
+    let mut items = items.collect::<io::Result<Vec<_>>>()?;
+
+    items.sort_by(|a, b| a.text.cmp(&b.text));
+
+    Ok(Box::new(items.into_iter().map(Ok)))
+}
+
+#[cfg(feature = "collation")]
+fn sort_collated(items: Items, locale: &str) -> io::Result<Items> {
+    let collation = match srch::Collation::new(locale) {
+        Ok(collation) => collation,
+        Err(err) => fail(err),
+    };
+
+    let mut items = items.collect::<io::Result<Vec<_>>>()?;
+
+    items.sort_by(|a, b| collation.compare(&a.text, &b.text));
+
+    Ok(Box::new(items.into_iter().map(Ok)))
+}
+
+#[cfg(not(feature = "collation"))]
+fn sort_collated(_: Items, _: &str) -> io::Result<Items> {
+    fail("--collate requires srch to be built with the \"collation\" feature")
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Placeholder {
+    Literal(String),
+    File,
+    Line,
+    Text,
+}
+
+/// An output template such as `{file}:{line}: {text}`, literal braces can be
+/// written as `{{` and `}}`
+#[derive(Clone, Debug, PartialEq)]
+struct Template {
+    parts: Vec<Placeholder>,
+}
+
+impl Template {
+    fn parse(source: &str) -> std::result::Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed placeholder \"{{{}\"", name)),
+                        }
+                    }
+
+                    let placeholder = match name.as_str() {
+                        "file" => Placeholder::File,
+                        "line" => Placeholder::Line,
+                        "text" => Placeholder::Text,
+                        _ => return Err(format!("unknown placeholder \"{{{}}}\"", name)),
+                    };
+
+                    if !literal.is_empty() {
+                        parts.push(Placeholder::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(placeholder);
+                }
+                '}' => return Err("unmatched \"}\", use \"}}\" for a literal brace".to_string()),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Placeholder::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    fn render(&self, record: &Record) -> String {
+        let mut rendered = String::new();
+
+        for part in &self.parts {
+            match part {
+                Placeholder::Literal(literal) => rendered.push_str(literal),
+                Placeholder::File => rendered.push_str(&record.file),
+                Placeholder::Line => rendered.push_str(&record.line.to_string()),
+                Placeholder::Text => rendered.push_str(&record.text),
+            }
+        }
+
+        rendered
+    }
+}
+
+fn is_template(template: &str) -> std::result::Result<(), String> {
+    Template::parse(template).map(|_| ())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Fragment {
+    Literal(String),
+    Capture(usize),
+}
+
+/// A replacement such as `[$1]`, references are only expanded if enabled
+/// using `--references`. `$0` is the whole match and `$1`, `$2`, ... are the
+/// evidence spans of the match in order (as shown by `--explain`), not
+/// capture groups. A reference followed by a digit can be written as `${1}`,
+/// a literal dollar as `$$`
+#[derive(Clone, Debug, PartialEq)]
+struct Replacement {
+    parts: Vec<Fragment>,
+}
+
+impl Replacement {
+    fn parse(source: &str, references: bool) -> std::result::Result<Self, String> {
+        if !references {
+            return Ok(Self {
+                parts: vec![Fragment::Literal(source.to_string())],
+            });
+        }
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c != '$' {
+                literal.push(c);
+                continue;
+            }
+
+            let braced = chars.next_if_eq(&'{').is_some();
+            let mut number = String::new();
+
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                number.push(digit);
+            }
+
+            if braced && chars.next() != Some('}') {
+                return Err(format!("unclosed reference \"${{{}\"", number));
+            }
+
+            if number.is_empty() {
+                match (braced, chars.next()) {
+                    (false, Some('$')) => {
+                        literal.push('$');
+                        continue;
+                    }
+                    _ => {
+                        return Err("invalid reference, use \"$$\" for a literal dollar".to_string())
+                    }
+                }
+            }
+
+            let index = number
+                .parse()
+                .map_err(|_| format!("invalid reference \"${}\"", number))?;
+
+            if !literal.is_empty() {
+                parts.push(Fragment::Literal(std::mem::take(&mut literal)));
+            }
+
+            parts.push(Fragment::Capture(index));
+        }
+
+        if !literal.is_empty() {
+            parts.push(Fragment::Literal(literal));
+        }
+
+        Ok(Self { parts })
+    }
+
+    /// Renders the replacement of `text`, fails if a reference points to a
+    /// part the match doesn't have
+    fn render(
+        &self,
+        text: &str,
+        expr: &srch::ExpressionSet,
+    ) -> std::result::Result<String, String> {
+        let captures = self
+            .parts
+            .iter()
+            .any(|part| matches!(part, Fragment::Capture(index) if *index > 0));
+
+        // The parts are only located if the replacement references them
+        let evidence = match captures {
+            true => expr.evaluate(text).evidence(),
+            false => vec![],
+        };
+
+        let mut rendered = String::new();
+
+        for part in &self.parts {
+            match part {
+                Fragment::Literal(literal) => rendered.push_str(literal),
+                Fragment::Capture(0) => rendered.push_str(text),
+                Fragment::Capture(index) => match evidence.get(index - 1) {
+                    Some(span) => rendered.push_str(&text[span.clone()]),
+                    None => {
+                        return Err(format!(
+                        "the replacement references ${}, but the match \"{}\" only has {} parts",
+                        index,
+                        text,
+                        evidence.len()
+                    ))
+                    }
+                },
+            }
+        }
+
+        Ok(rendered)
+    }
+}
+
+/// A command executed for every match, each argument is a [`Template`]. The
+/// command is split into arguments at whitespace, unless quoted using `'` or
+/// `"`, and is executed directly instead of using a shell, so matches can
+/// never be interpreted as shell syntax
+#[derive(Clone, Debug, PartialEq)]
+struct Command {
+    args: Vec<Template>,
+}
+
+impl Command {
+    fn split(source: &str) -> std::result::Result<Vec<String>, String> {
+        let mut args = Vec::new();
+        let mut arg: Option<String> = None;
+        let mut quote = None;
+
+        for c in source.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => arg.get_or_insert_with(String::new).push(c),
+                (None, '\'' | '"') => {
+                    quote = Some(c);
+                    arg.get_or_insert_with(String::new);
+                }
+                (None, c) if c.is_whitespace() => args.extend(arg.take()),
+                (None, c) => arg.get_or_insert_with(String::new).push(c),
+            }
+        }
+
+        if let Some(q) = quote {
+            return Err(format!("unclosed quote {}", q));
+        }
+
+        args.extend(arg);
+
+        Ok(args)
+    }
+
+    fn parse(source: &str) -> std::result::Result<Self, String> {
+        let args = Self::split(source)?
+            .iter()
+            .map(|arg| Template::parse(arg))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        match args.is_empty() {
+            true => Err("empty command".to_string()),
+            false => Ok(Self { args }),
+        }
+    }
+
+    /// Runs the command for a match, the match is additionally passed via
+    /// stdin and the `SRCH_FILE`, `SRCH_LINE` and `SRCH_TEXT` environment
+    /// variables
+    fn run(&self, record: &Record) -> io::Result<bool> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.render(record))
+            .collect::<Vec<_>>();
+
+        let mut child = Process::new(&args[0])
+            .args(&args[1..])
+            .env("SRCH_FILE", &*record.file)
+            .env("SRCH_LINE", record.line.to_string())
+            .env("SRCH_TEXT", &record.text)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", args[0], err)))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // the command is free to ignore its stdin
+            let _ = writeln!(stdin, "{}", record.text);
+        }
+
+        Ok(child.wait()?.success())
+    }
+}
+
+fn is_command(command: &str) -> std::result::Result<(), String> {
+    Command::parse(command).map(|_| ())
+}
+
+/// The formats the records of the subcommands are printed in, every
+/// format is an `OutputSink` so adding one doesn't touch the subcommands
+mod output {
+    use std::io::{self, Write};
+    use std::rc::Rc;
+
+    use clap::ArgMatches;
+
+    use super::{Command, Record, Template};
+
+    /// Receives the records printed by a subcommand in order
+    pub(super) trait OutputSink {
+        fn record(&mut self, record: &Record) -> io::Result<()>;
+
+        /// Called once after the last record, e.g. to print a count
+        fn finish(&mut self) -> io::Result<()>;
+
+        /// Whether the subcommand exits successfully
+        fn succeeded(&self) -> bool {
+            true
+        }
+    }
+
+    /// How records are laid out by the sinks printing them one by one
+    #[derive(Clone, Copy, Debug)]
+    pub(super) struct Layout {
+        /// Print the file name above the records of each file
+        pub(super) heading: bool,
+        /// Prefix every record with its file name
+        pub(super) file_names: bool,
+        pub(super) line_numbers: bool,
+        pub(super) terminator: &'static str,
+    }
+
+    /// One record per line, used by the subcommands without output flags
+    pub(super) const LINES: Layout = Layout {
+        heading: false,
+        file_names: false,
+        line_numbers: false,
+        terminator: "\n",
+    };
+
+    /// Records which end with their own line ending, e.g. the lines of
+    /// `replace` and `highlight`
+    pub(super) const RAW: Layout = Layout {
+        terminator: "",
+        ..LINES
+    };
+
+    /// Picks the sink of the output flags of a filter subcommand
+    pub(super) fn from_matches<'a>(
+        matches: &ArgMatches,
+        out: Box<dyn Write + 'a>,
+        layout: Layout,
+    ) -> Box<dyn OutputSink + 'a> {
+        let template = matches
+            .value_of("format")
+            .and_then(|format| Template::parse(format).ok());
+
+        let command = matches
+            .value_of("exec")
+            .and_then(|command| Command::parse(command).ok());
+
+        match (command, template) {
+            (Some(command), _) => Box::new(Exec::new(command)),
+            _ if matches.is_present("count") => Box::new(Count::new(out)),
+            _ if matches.is_present("quiet") => Box::new(Quiet::default()),
+            (None, Some(template)) => Box::new(Templated::new(out, template, layout)),
+            (None, None) => match matches.value_of("output-format") {
+                Some("json") => Box::new(Json::new(out)),
+                Some("jsonl") => Box::new(JsonLines::new(out)),
+                _ => Box::new(Plain::new(out, layout)),
+            },
+        }
+    }
+
+    /// Prints the file name of a record if it is the first of its file
+    #[derive(Debug, Default)]
+    struct Heading {
+        file: Option<Rc<str>>,
+    }
+
+    impl Heading {
+        fn write(&mut self, out: &mut dyn Write, record: &Record) -> io::Result<()> {
+            if self.file.as_ref() == Some(&record.file) {
+                return Ok(());
+            }
+
+            if self.file.is_some() {
+                writeln!(out)?;
+            }
+
+            writeln!(out, "{}", record.file)?;
+            self.file = Some(record.file.clone());
+
+            Ok(())
+        }
+    }
+
+    /// The text printed for a record, the yaml document it was taken from
+    /// if there is one
+    fn text(record: &Record) -> &str {
+        record.document.as_deref().unwrap_or(&record.text)
+    }
+
+    /// Prints the text of every record, optionally prefixed by its file name
+    /// and line number
+    pub(super) struct Plain<'a> {
+        out: Box<dyn Write + 'a>,
+        layout: Layout,
+        heading: Heading,
+    }
+
+    impl<'a> Plain<'a> {
+        pub(super) fn new(out: Box<dyn Write + 'a>, layout: Layout) -> Self {
+            Self {
+                out,
+                layout,
+                heading: Heading::default(),
+            }
+        }
+    }
+
+    impl OutputSink for Plain<'_> {
+        fn record(&mut self, record: &Record) -> io::Result<()> {
+            if self.layout.heading {
+                self.heading.write(&mut self.out, record)?;
+            }
+
+            if self.layout.file_names {
+                write!(self.out, "{}:", record.file)?;
+            }
+
+            if self.layout.line_numbers {
+                write!(self.out, "{}:", record.line)?;
+            }
+
+            write!(self.out, "{}{}", text(record), self.layout.terminator)
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.out.flush()
+        }
+    }
+
+    /// Prints every record using a `--format` template
+    pub(super) struct Templated<'a> {
+        out: Box<dyn Write + 'a>,
+        template: Template,
+        layout: Layout,
+        heading: Heading,
+    }
+
+    impl<'a> Templated<'a> {
+        pub(super) fn new(out: Box<dyn Write + 'a>, template: Template, layout: Layout) -> Self {
+            Self {
+                out,
+                template,
+                layout,
+                heading: Heading::default(),
+            }
+        }
+    }
+
+    impl OutputSink for Templated<'_> {
+        fn record(&mut self, record: &Record) -> io::Result<()> {
+            if self.layout.heading {
+                self.heading.write(&mut self.out, record)?;
+            }
+
+            write!(
+                self.out,
+                "{}{}",
+                self.template.render(record),
+                self.layout.terminator
+            )
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.out.flush()
+        }
+    }
+
+    fn json_string(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len() + 2);
+        escaped.push('"');
+
+        for c in text.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+
+        escaped.push('"');
+        escaped
+    }
+
+    fn json_record(record: &Record) -> String {
+        format!(
+            "{{\"file\":{},\"line\":{},\"text\":{}}}",
+            json_string(&record.file),
+            record.line,
+            json_string(text(record))
+        )
+    }
+
+    /// Prints all records as a single json array of objects with the keys
+    /// `file`, `line` and `text`
+    pub(super) struct Json<'a> {
+        out: Box<dyn Write + 'a>,
+        empty: bool,
+    }
+
+    impl<'a> Json<'a> {
+        pub(super) fn new(out: Box<dyn Write + 'a>) -> Self {
+            Self { out, empty: true }
+        }
+    }
+
+    impl OutputSink for Json<'_> {
+        fn record(&mut self, record: &Record) -> io::Result<()> {
+            let separator = match self.empty {
+                true => "[",
+                false => ",",
+            };
+
+            self.empty = false;
+            write!(self.out, "{}{}", separator, json_record(record))
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            match self.empty {
+                true => writeln!(self.out, "[]")?,
+                false => writeln!(self.out, "]")?,
+            }
+
+            self.out.flush()
+        }
+    }
+
+    /// Prints every record as a json object on its own line, like
+    /// [`Json`] without the surrounding array
+    pub(super) struct JsonLines<'a> {
+        out: Box<dyn Write + 'a>,
+    }
+
+    impl<'a> JsonLines<'a> {
+        pub(super) fn new(out: Box<dyn Write + 'a>) -> Self {
+            Self { out }
+        }
+    }
+
+    impl OutputSink for JsonLines<'_> {
+        fn record(&mut self, record: &Record) -> io::Result<()> {
+            writeln!(self.out, "{}", json_record(record))
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.out.flush()
+        }
+    }
+
+    /// Prints only the number of records
+    pub(super) struct Count<'a> {
+        out: Box<dyn Write + 'a>,
+        count: usize,
+    }
+
+    impl<'a> Count<'a> {
+        pub(super) fn new(out: Box<dyn Write + 'a>) -> Self {
+            Self { out, count: 0 }
+        }
+    }
+
+    impl OutputSink for Count<'_> {
+        fn record(&mut self, _: &Record) -> io::Result<()> {
+            self.count += 1;
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            writeln!(self.out, "{}", self.count)?;
+            self.out.flush()
+        }
+    }
+
+    /// Prints nothing, the subcommand only succeeds if there was a record
+    #[derive(Debug, Default)]
+    pub(super) struct Quiet {
+        matched: bool,
+    }
+
+    impl OutputSink for Quiet {
+        fn record(&mut self, _: &Record) -> io::Result<()> {
+            self.matched = true;
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn succeeded(&self) -> bool {
+            self.matched
+        }
+    }
+
+    /// Runs an `--exec` command for every record, the subcommand fails if
+    /// any of them failed
+    pub(super) struct Exec {
+        command: Command,
+        failed: bool,
+    }
+
+    impl Exec {
+        pub(super) fn new(command: Command) -> Self {
+            Self {
+                command,
+                failed: false,
+            }
+        }
+    }
+
+    impl OutputSink for Exec {
+        fn record(&mut self, record: &Record) -> io::Result<()> {
+            match self.command.run(record) {
+                Ok(true) => {}
+                Ok(false) => self.failed = true,
+                Err(err) => super::fail(err),
+            }
+
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn succeeded(&self) -> bool {
+            !self.failed
+        }
+    }
+}
+
+fn is_port(port: &str) -> std::result::Result<(), String> {
+    match port.parse::<u16>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("\"{}\" is not a valid port", port)),
+    }
+}
+
+fn is_count(n: &str) -> std::result::Result<(), String> {
+    match n.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("\"{}\" is not a valid count", n)),
+    }
+}
+
+// TODO: Add a "split" command to split text at certain chars
+
+// TODO: Add an "extract" command printing only captured fragments, this needs
+// captures in the text expression language first
+
+fn build_cli() -> App<'static> {
+    fn build_subcommand(name: &'static str, descr: &'static str) -> App<'static> {
+        App::new(name)
+            .version(VERSION)
+            .author(AUTHOR)
+            .about(descr)
+            .arg(
+                Arg::new("mode")
+                    .short('m')
+                    .long("mode")
+                    .help("Sets the operation mode")
+                    .takes_value(true)
+                    .value_name("MODE")
+                    .default_value("line")
+                    .possible_values(["line", "word", "sentence", "paragraph", "char", "delim", "yaml"]),
+            )
+            .arg(
+                Arg::new("words")
+                    .short('w')
+                    .long("words")
+                    .help("Use the word mode (same as --mode word)")
+                    .conflicts_with("mode"),
+            )
+            .arg(
+                Arg::new("unicode-whitespace")
+                    .long("unicode-whitespace")
+                    .help("Split words at any unicode whitespace instead of ascii whitespace only"),
+            )
+            .arg(
+                Arg::new("strip-punctuation")
+                    .long("strip-punctuation")
+                    .help("Remove leading and trailing characters which are neither letters nor digits from words"),
+            )
+            .arg(
+                Arg::new("keep-delimiters")
+                    .long("keep-delimiters")
+                    .help("Keep the text between words as records of their own in the word mode"),
+            )
+            .arg(
+                Arg::new("null-data")
+                    .short('z')
+                    .long("null-data")
+                    .help("Use NUL instead of newline as the record separator of the input")
+                    .conflicts_with_all(&["mode", "delimiter"]),
+            )
+            .arg(
+                Arg::new("crlf")
+                    .long("crlf")
+                    .help("End filtered lines with \\r\\n, e.g. for files written on Windows. replace and highlight always keep the line endings of the input"),
+            )
+            .arg(
+                Arg::new("delimiter")
+                    .short('d')
+                    .long("delimiter")
+                    .help("The record separator of the delim mode, supports \\0, \\t, \\n, \\r and \\\\")
+                    .takes_value(true)
+                    .value_name("DELIMITER")
+                    .validator(is_delimiter)
+                    .required_if_eq("mode", "delim"),
+            )
+            .arg(
+                Arg::new("key")
+                    .short('k')
+                    .long("key")
+                    .help("The dot separated path of the value the yaml mode matches against")
+                    .takes_value(true)
+                    .value_name("PATH")
+                    .required_if_eq("mode", "yaml"),
+            )
+            .arg(
+                Arg::new("expression")
+                    .help("The text expression used to determine matches")
+                    .takes_value(true)
+                    .value_name("EXPRESSION")
+                    .value_hint(ValueHint::Other)
+                    .required_unless_present_any(["expr-file", "exprs"])
+                    .index(1),
+            )
+            .arg(
+                Arg::new("exprs")
+                    .short('e')
+                    .long("expr")
+                    .help("Use the given text expression, can be repeated")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .value_name("EXPRESSION")
+                    .value_hint(ValueHint::Other),
+            )
+            .arg(
+                Arg::new("all")
+                    .long("all")
+                    .help("Require all expressions to match instead of any"),
+            )
+            .arg(
+                Arg::new("no-progress")
+                    .long("no-progress")
+                    .help("Never show a progress bar for large inputs"),
+            )
+            .arg(
+                Arg::new("expr-file")
+                    .short('E')
+                    .long("expr-file")
+                    .help("Read the text expression from a file instead, or one expression per file of a directory")
+                    .takes_value(true)
+                    .value_name("PATH")
+                    .value_hint(ValueHint::FilePath),
+            )
+    }
+
+    fn build_input_arg(index: usize) -> Arg<'static> {
+        Arg::new("input")
+            .help("The path to the input file to use")
+            .takes_value(true)
+            .value_name("FILE")
+            .value_hint(ValueHint::FilePath)
+            .index(index)
+    }
+
+    /// `--tag` and `--severity`, which select the patterns of the library
+    fn build_selector_args() -> [Arg<'static>; 2] {
+        [
+            Arg::new("tag")
+                .long("tag")
+                .takes_value(true)
+                .value_name("TAG")
+                .value_hint(ValueHint::Other)
+                .multiple_occurrences(true)
+                .help(
+                    "Only use the named patterns with the given tag, can be given multiple times",
+                ),
+            Arg::new("severity")
+                .long("severity")
+                .takes_value(true)
+                .value_name("SEVERITY")
+                .possible_values(srch::Severity::ALL.map(|severity| severity.name()))
+                .help("Only use the named patterns of at least the given severity"),
+        ]
+    }
+
+    fn build_filter_subcommand(name: &'static str, descr: &'static str) -> App<'static> {
+        build_subcommand(name, descr)
+            .arg(
+                build_input_arg(2)
+                    .help("The paths to the input files to use")
+                    .multiple_values(true),
+            )
+            .arg(
+                Arg::new("first")
+                    .short('f')
+                    .long("first")
+                    .help("Only use first match (same as --head 1)")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::new("last")
+                    .short('l')
+                    .long("last")
+                    .help("Only use last match (same as --tail 1)")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::new("head")
+                    .long("head")
+                    .takes_value(true)
+                    .value_name("n")
+                    .value_hint(ValueHint::Other)
+                    .validator(is_count)
+                    .help("Only use the first n matches")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::new("tail")
+                    .long("tail")
+                    .takes_value(true)
+                    .value_name("n")
+                    .value_hint(ValueHint::Other)
+                    .validator(is_count)
+                    .help("Only use the last n matches")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::new("skip")
+                    .short('s')
+                    .long("skip")
+                    .takes_value(true)
+                    .value_name("n")
+                    .value_hint(ValueHint::Other)
+                    .validator(is_count)
+                    .help("Skip the first n matches")
+                    .display_order(1),
+            )
+            .arg(
+                Arg::new("sort")
+                    .long("sort")
+                    .help("Sort matches before printing them")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("collate")
+                    .long("collate")
+                    .takes_value(true)
+                    .value_name("LOCALE")
+                    .value_hint(ValueHint::Other)
+                    .requires("sort")
+                    .help("Sort matches in the order of the given locale (e.g. de or sv-SE) instead of by their bytes")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("ignore-case")
+                    .short('i')
+                    .long("ignore-case")
+                    .help("Ignore the case of the input and the expression")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("invert-match")
+                    .short('v')
+                    .long("invert-match")
+                    .help("Invert the matches, `for -v` is the same as `not`")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("count")
+                    .short('c')
+                    .long("count")
+                    .help("Print only the number of matches")
+                    .conflicts_with_all(&["exec", "format", "heading"])
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("line-number")
+                    .short('n')
+                    .long("line-number")
+                    .help("Prefix each match with its line number")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("unique")
+                    .short('u')
+                    .long("unique")
+                    .help("Print every distinct match only once")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("squeeze")
+                    .long("squeeze")
+                    .help("Print runs of identical matches once, prefixed with their length")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("stats")
+                    .long("stats")
+                    .help("Print a summary of the search to stderr when done")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("print0")
+                    .long("print0")
+                    .help("Terminate each printed match with a NUL byte instead of a newline")
+                    .conflicts_with_all(&["heading", "exec", "crlf"]),
+            )
+            .arg(
+                Arg::new("clipboard")
+                    .long("clipboard")
+                    .help("Read the input from the system clipboard")
+                    .conflicts_with_all(&["input", "files-from", "files-from0", "follow"]),
+            )
+            .arg(
+                Arg::new("copy")
+                    .long("copy")
+                    .help("Copy the output to the system clipboard instead of printing it")
+                    .conflicts_with("exec"),
+            )
+            .arg(
+                Arg::new("files-from")
+                    .long("files-from")
+                    .help("Read the list of files to search from FILE, one per line, - reads stdin")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .conflicts_with("files-from0"),
+            )
+            .arg(
+                Arg::new("files-from0")
+                    .long("files-from0")
+                    .help("Like --files-from, but the file names are separated by NUL bytes")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath),
+            )
+            .arg(
+                Arg::new("follow")
+                    .short('F')
+                    .long("follow")
+                    .conflicts_with_all(&["sort", "last", "tail", "files-from", "files-from0"])
+                    .help("Keep reading the input file and print new matches as they are appended")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("explain")
+                    .long("explain")
+                    .conflicts_with_all(&[
+                        "count",
+                        "exec",
+                        "format",
+                        "output-format",
+                        "quiet",
+                        "sort",
+                        "unique",
+                    ])
+                    .help("Print why every record matches or not as json instead of filtering"),
+            )
+            .arg(
+                Arg::new("rank")
+                    .long("rank")
+                    .conflicts_with_all(&[
+                        "count",
+                        "exec",
+                        "explain",
+                        "follow",
+                        "format",
+                        "only-matching",
+                        "output-format",
+                        "quiet",
+                        "sort",
+                        "unique",
+                    ])
+                    .help("Print every record with its score, the closest matches first, instead of filtering"),
+            )
+            .arg(
+                Arg::new("matches-to")
+                    .long("matches-to")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .conflicts_with_all(&["copy", "explain"])
+                    .help("Write the printed records to FILE instead of stdout"),
+            )
+            .arg(
+                Arg::new("rest-to")
+                    .long("rest-to")
+                    .takes_value(true)
+                    .value_name("FILE")
+                    .value_hint(ValueHint::FilePath)
+                    .conflicts_with_all(&["explain", "first", "head"])
+                    .help("Write the records which aren't printed to FILE in the same pass"),
+            )
+            .arg(
+                Arg::new("stats-detail")
+                    .long("stats-detail")
+                    .conflicts_with_all(&["follow", "summary", "explain"])
+                    .help("Print how often every query was evaluated and matched to stderr when done")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("summary")
+                    .long("summary")
+                    .conflicts_with("follow")
+                    .help("Print how many records each expression matched to stderr when done")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("only-matching")
+                    .short('o')
+                    .long("only-matching")
+                    .help("Print every part of a record matched by the expression instead of whole records")
+                    .display_order(2),
+            )
+            .arg(
+                Arg::new("max-record-length")
+                    .long("max-record-length")
+                    .takes_value(true)
+                    .value_name("BYTES")
+                    .validator(is_count)
+                    .help("Never read records longer than BYTES into memory, see --overlong"),
+            )
+            .arg(
+                Arg::new("overlong")
+                    .long("overlong")
+                    .takes_value(true)
+                    .value_name("POLICY")
+                    .possible_values(["error", "skip", "truncate"])
+                    .requires("max-record-length")
+                    .help("Whether records longer than --max-record-length fail (the default), are skipped or are truncated and matched"),
+            )
+            .arg(
+                Arg::new("exec")
+                    .short('x')
+                    .long("exec")
+                    .takes_value(true)
+                    .value_name("COMMAND")
+                    .value_hint(ValueHint::CommandString)
+                    .validator(is_command)
+                    .conflicts_with("format")
+                    .help("Run a command for every match instead of printing it")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("heading")
+                    .long("heading")
+                    .help("Print the file name above the matches of each file")
+                    .overrides_with("no-heading"),
+            )
+            .arg(
+                Arg::new("no-heading")
+                    .long("no-heading")
+                    .help("Prefix each match with its file name instead of grouping by file")
+                    .overrides_with("heading"),
+            )
+            .arg(
+                Arg::new("format")
+                    .long("format")
+                    .takes_value(true)
+                    .value_name("TEMPLATE")
+                    .value_hint(ValueHint::Other)
+                    .validator(is_template)
+                    .help("Format matches using {file}, {line} and {text} placeholders")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("output-format")
+                    .long("output-format")
+                    .takes_value(true)
+                    .value_name("FORMAT")
+                    .possible_values(["plain", "json", "jsonl"])
+                    .conflicts_with_all(&["count", "exec", "format", "quiet"])
+                    .help("Print matches as plain text, a json array or one json object per line")
+                    .display_order(3),
+            )
+            .arg(
+                Arg::new("quiet")
+                    .short('q')
+                    .long("quiet")
+                    .conflicts_with_all(&["count", "exec", "format"])
+                    .help("Print nothing, exit with 1 if nothing matched")
+                    .display_order(2),
+            )
+            .group(
+                ArgGroup::new("slice")
+                    .arg("first")
+                    .arg("last")
+                    .arg("head")
+                    .arg("tail"),
+            )
+    }
+
+    App::new(NAME)
+        .version(VERSION)
+        .author(AUTHOR)
+        .about(DESCRIPTION)
+        // `-v` inverts the matches like grep and `-V` prints the version, so
+        // there is deliberately no short flag
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .help("Print what srch is doing to stderr, repeat for more details (no short flag, -v inverts the matches)")
+                .multiple_occurrences(true)
+                .global(true),
+        )
+        .subcommand(build_filter_subcommand(
+            "for",
+            "Filter a text and print matches",
+        ))
+        .subcommand(build_filter_subcommand(
+            "not",
+            "Ignore matches and print the rest",
+        ))
+        .subcommand(
+            build_subcommand("replace", "Replace matches with a given string")
+                .arg(
+                    Arg::new("replacement")
+                        .help("The string used to replace matches")
+                        .takes_value(true)
+                        .value_name("REPLACEMENT")
+                        .value_hint(ValueHint::Other)
+                        .required_unless_present_any(["expr-file", "exprs"])
+                        .index(2),
+                )
+                .arg(
+                    build_input_arg(3)
+                        .help("The paths to the input files to use, multiple files require --patch")
+                        .multiple_values(true),
+                )
+                .arg(
+                    Arg::new("patch")
+                        .long("patch")
+                        .help("Print a unified diff of the changes to all input files instead of the result")
+                        .conflicts_with("in-place")
+                        .display_order(1),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .conflicts_with("in-place")
+                        .help("Write the result (or patch) to the given file instead of stdout")
+                        .display_order(1),
+                )
+                .arg(
+                    Arg::new("in-place")
+                        .short('i')
+                        .long("in-place")
+                        .help("Modify the input file in place")
+                        .display_order(1),
+                )
+                .arg(
+                    Arg::new("references")
+                        .long("references")
+                        .help("Expand $0 to the match and $1, $2, ... to the parts of it which made the expression match, $$ is a literal dollar")
+                        .display_order(1),
+                )
+                .arg(
+                    Arg::new("backup")
+                        .long("backup")
+                        .takes_value(true)
+                        .value_name("SUFFIX")
+                        .value_hint(ValueHint::Other)
+                        .requires("in-place")
+                        .help("Keep a copy of the original file with the given suffix")
+                        .display_order(1),
+                ),
+        )
+        .subcommand(
+            build_subcommand(
+                "highlight",
+                "Print the whole input and highlight the matches of the expression",
+            )
+            .arg(build_input_arg(2))
+            .arg(
+                Arg::new("color")
+                    .long("color")
+                    .help("When to highlight the matches, auto only highlights if stdout is a terminal")
+                    .takes_value(true)
+                    .value_name("WHEN")
+                    .default_value("auto")
+                    .possible_values(["auto", "always", "never"]),
+            ),
+        )
+        .subcommand(
+            App::new("convert")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Convert a text expression into another format")
+                .arg(
+                    Arg::new("expression")
+                        .help("The text expression (or regular expression using --from-regex) to convert")
+                        .takes_value(true)
+                        .value_name("EXPRESSION")
+                        .value_hint(ValueHint::Other)
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("to-regex")
+                        .long("to-regex")
+                        .help("Convert the expression into a regular expression"),
+                )
+                .arg(
+                    Arg::new("from-regex")
+                        .long("from-regex")
+                        .help("Convert a regular expression into a text expression"),
+                )
+                .group(
+                    ArgGroup::new("target")
+                        .args(&["to-regex", "from-regex"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            App::new("describe")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Describe a text expression in plain english")
+                .arg(
+                    Arg::new("expression")
+                        .help("The text expression to describe")
+                        .takes_value(true)
+                        .value_name("EXPRESSION")
+                        .value_hint(ValueHint::Other)
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            App::new("lint")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Check the expressions of an expression file for mistakes")
+                .arg(
+                    Arg::new("input")
+                        .help("The expression file, expressions are separated by blank lines")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            App::new("diff")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Compare two versions of an expression file or rule set")
+                .arg(
+                    Arg::new("old")
+                        .help("The old expression file or rule set")
+                        .takes_value(true)
+                        .value_name("OLD")
+                        .value_hint(ValueHint::FilePath)
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("new")
+                        .help("The new expression file or rule set")
+                        .takes_value(true)
+                        .value_name("NEW")
+                        .value_hint(ValueHint::FilePath)
+                        .required(true)
+                        .index(2),
+                ),
+        )
+        .subcommand(
+            App::new("check")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Run the test cases of an expression test suite")
+                .arg(
+                    Arg::new("input")
+                        .help("The test suite to run")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .required(true)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            App::new("sample")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Sample matching and non-matching records of a file into a test suite")
+                .arg(
+                    Arg::new("expression")
+                        .help("The text expression to sample the records of")
+                        .takes_value(true)
+                        .value_name("EXPRESSION")
+                        .value_hint(ValueHint::Other)
+                        .required(true)
+                        .index(1),
+                )
+                .arg(build_input_arg(2).help("The path to the input file to sample"))
+                .arg(
+                    Arg::new("matched")
+                        .long("matched")
+                        .takes_value(true)
+                        .value_name("n")
+                        .value_hint(ValueHint::Other)
+                        .validator(is_count)
+                        .default_value("20")
+                        .help("The number of matching records to sample"),
+                )
+                .arg(
+                    Arg::new("unmatched")
+                        .long("unmatched")
+                        .takes_value(true)
+                        .value_name("n")
+                        .value_hint(ValueHint::Other)
+                        .validator(is_count)
+                        .default_value("20")
+                        .help("The number of records to sample which don't match"),
+                )
+                .arg(
+                    Arg::new("out")
+                        .long("out")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .help("Write the test suite to the given file instead of stdout"),
+                ),
+        )
+        .subcommand(
+            App::new("wizard")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Build an expression by answering a few questions")
+                .arg(
+                    Arg::new("input")
+                        .help("A sample file to test the expression against")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            App::new("which")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Print which rules match the given text, by default all named patterns are tried")
+                .arg(
+                    Arg::new("text")
+                        .help("The text to test the rules against")
+                        .takes_value(true)
+                        .value_name("TEXT")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("exprs")
+                        .short('e')
+                        .long("expr")
+                        .help("A rule to try, can be given multiple times")
+                        .takes_value(true)
+                        .value_name("EXPRESSION")
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("expr-file")
+                        .short('E')
+                        .long("expr-file")
+                        .help("A file of rules separated by blank lines, can be given multiple times")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .multiple_occurrences(true),
+                )
+                .args(
+                    build_selector_args()
+                        .map(|arg| arg.conflicts_with_all(&["exprs", "expr-file"])),
+                ),
+        )
+        .subcommand(
+            App::new("serve")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Serve a json api to compile and test expressions over http")
+                .arg(
+                    Arg::new("host")
+                        .long("host")
+                        .help("The address to listen on")
+                        .takes_value(true)
+                        .value_name("HOST")
+                        .default_value("127.0.0.1"),
+                )
+                .arg(
+                    Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .help("The port to listen on")
+                        .takes_value(true)
+                        .value_name("PORT")
+                        .default_value("8080")
+                        .validator(is_port),
+                ),
+        )
+        .subcommand(
+            App::new("bench")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Measure the throughput of expressions")
+                .arg(
+                    Arg::new("exprs")
+                        .short('e')
+                        .long("expr")
+                        .help("An expression to measure instead of the standard workloads, can be repeated")
+                        .takes_value(true)
+                        .value_name("EXPRESSION")
+                        .multiple_occurrences(true),
+                )
+                .arg(
+                    Arg::new("input")
+                        .help("A file whose lines the expressions are measured against instead of a generated corpus")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .value_hint(ValueHint::FilePath)
+                        .requires("exprs")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("corpus")
+                        .long("corpus")
+                        .help("The kind of corpus generated for the expressions")
+                        .takes_value(true)
+                        .value_name("KIND")
+                        .possible_values(["logs", "words", "values"])
+                        .default_value("logs"),
+                )
+                .arg(
+                    Arg::new("records")
+                        .long("records")
+                        .help("The number of records of generated corpora")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("100000")
+                        .validator(is_count),
+                )
+                .arg(
+                    Arg::new("iterations")
+                        .long("iterations")
+                        .help("How often every expression is run, the fastest run is reported")
+                        .takes_value(true)
+                        .value_name("N")
+                        .default_value("5")
+                        .validator(is_count),
+                ),
+        )
+        .subcommand(
+            App::new("patterns")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Inspect the named pattern library")
+                .arg_required_else_help(true)
+                .subcommand(
+                    App::new("list")
+                        .about("List all named patterns")
+                        .args(build_selector_args()),
+                ),
+        )
+        .subcommand(
+            App::new("doctor")
+                .version(VERSION)
+                .author(AUTHOR)
+                .about("Check the terminal, locale, catalog and pattern library srch uses"),
+        )
+    // .subcommand(build_subcommand("exec", "Execute a given expression against a test string"))
+}
+
+fn read_expression_sources(
+    matches: &ArgMatches,
+    positionals: &Positionals,
+) -> io::Result<Vec<String>> {
+    let mut sources: Vec<String> = matches
+        .values_of("exprs")
+        .map(|exprs| exprs.map(|expr| expr.to_string()).collect())
+        .unwrap_or_default();
+
+    if let Some(path) = matches.value_of("expr-file") {
+        for file in expression_files(path)? {
+            sources.push(fs::read_to_string(file)?);
+        }
+    }
+
+    if let Some(expression) = positionals.expression {
+        sources.push(expression.to_string());
+    }
+
+    Ok(sources)
+}
+
+/// A version of the rules compared by `srch diff`
+enum DiffInput {
+    Expression(srch::Expression),
+    RuleSet(srch::RuleSet),
+}
+
+/// Reads a file as a rule set if it is a toml table, otherwise as an
+/// expression file
+fn read_diff_input(path: &str) -> io::Result<DiffInput> {
+    let source = fs::read_to_string(path)?;
+
+    if let Ok(toml::Value::Table(_)) = source.parse::<toml::Value>() {
+        return match srch::RuleSet::parse(&source) {
+            Ok(rules) => Ok(DiffInput::RuleSet(rules)),
+            Err(err) => fail(format!("{}: {}", path, err)),
+        };
+    }
+
+    let source = match resolve_patterns(vec![source]) {
+        Ok(sources) => sources.concat(),
+        Err(err) => fail(format!("{}: {}", path, err)),
+    };
+
+    match srch::Expression::new(&source) {
+        Ok(expr) => Ok(DiffInput::Expression(expr)),
+        Err(err) => fail(format!("{}: {}", path, err)),
+    }
+}
+
+/// The files of an `--expr-file`, which is either a single file or a
+/// directory of files each containing one expression, sorted by name
+fn expression_files(path: &str) -> io::Result<Vec<PathBuf>> {
+    let path = Path::new(path);
+
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    files.retain(|file| file.is_file());
+    files.sort();
+
+    verbose!(
+        1,
+        "read {} expression file(s) from {}",
+        files.len(),
+        path.display()
+    );
+
+    Ok(files)
+}
+
+/// Labels the expressions read by `read_expression_sources` in the same
+/// order, expression files are labeled by their path
+fn expression_labels(matches: &ArgMatches, positionals: &Positionals) -> Vec<String> {
+    let mut labels: Vec<String> = matches
+        .values_of("exprs")
+        .map(|exprs| exprs.map(|expr| expr.trim().to_string()).collect())
+        .unwrap_or_default();
+
+    if let Some(path) = matches.value_of("expr-file") {
+        for file in expression_files(path).unwrap_or_default() {
+            labels.push(file.display().to_string());
+        }
+    }
+
+    if let Some(expression) = positionals.expression {
+        labels.push(expression.trim().to_string());
+    }
+
+    labels
+}
+
+fn compile_expression_from_matches(
+    matches: &ArgMatches,
+    positionals: &Positionals,
+    ignore_case: bool,
+) -> io::Result<srch::ExpressionSet> {
+    let mut sources = match resolve_patterns(read_expression_sources(matches, positionals)?) {
+        Ok(sources) => sources,
+        Err(err) => fail(err),
+    };
+
+    // Case is ignored by matching the lowercased input against lowercased
+    // expressions
+    if ignore_case {
+        sources = sources.iter().map(|source| source.to_lowercase()).collect();
+    }
+
+    let policy = match matches.is_present("all") {
+        true => srch::Policy::All,
+        false => srch::Policy::Any,
+    };
+
+    let started = Instant::now();
+
+    match srch::ExpressionSet::compile(sources, policy) {
+        Ok(expr) => {
+            verbose!(
+                1,
+                "compiled {} expression(s) in {:?}",
+                expr.len(),
+                started.elapsed()
+            );
+            Ok(expr)
+        }
+        Err(errors) => {
+            // Every invalid expression is reported, not only the first one
+            let labels = expression_labels(matches, positionals);
+
+            for (index, err) in errors {
+                match labels.len() > 1 {
+                    true => eprintln!("error: {}: {}", labels[index], err),
+                    false => eprintln!("error: {}", err),
+                }
+            }
+
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The expression records are matched against
+trait Matcher {
+    fn matches(&self, text: &str) -> bool;
+
+    /// The parts of the text matched by the expression, see `--only-matching`
+    fn scan(&self, text: &str) -> Vec<std::ops::Range<usize>>;
+}
+
+impl Matcher for srch::ExpressionSet {
+    fn matches(&self, text: &str) -> bool {
+        srch::ExpressionSet::matches(self, text)
+    }
+
+    fn scan(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        srch::ExpressionSet::scan(self, text)
+    }
+}
+
+#[cfg(feature = "notify")]
+impl Matcher for srch::WatchedExpression {
+    fn matches(&self, text: &str) -> bool {
+        srch::WatchedExpression::matches(self, text)
+    }
+
+    fn scan(&self, text: &str) -> Vec<std::ops::Range<usize>> {
+        self.current().scan(text)
+    }
+}
+
+/// With `--follow` a single `--expr-file` is watched and recompiled whenever
+/// it changes, the last valid expression is kept while the file is invalid.
+/// The complement of the expression is watched when ignoring matches
+#[cfg(feature = "notify")]
+fn watch_expression_from_matches(
+    matches: &ArgMatches,
+    positionals: &Positionals,
+    ignore_case: bool,
+    invert_matches: bool,
+) -> Option<Rc<dyn Matcher>> {
+    let path = matches.value_of("expr-file")?;
+
+    if !matches.is_present("follow") || matches.is_present("exprs") {
+        return None;
+    }
+
+    if positionals.expression.is_some() {
+        return None;
+    }
+
+    let options = srch::MatchOptions {
+        ignore_case,
+        ..Default::default()
+    };
+
+    match srch::WatchedExpression::from_path_with(path, options) {
+        Ok(watched) => {
+            verbose!(1, "watching {} for changes", watched.path().display());
+
+            match invert_matches {
+                true => Some(Rc::new(watched.complement())),
+                false => Some(Rc::new(watched)),
+            }
+        }
+        Err(err) => fail(format!("{}: {}", path, err)),
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+fn watch_expression_from_matches(
+    _: &ArgMatches,
+    _: &Positionals,
+    _: bool,
+    _: bool,
+) -> Option<Rc<dyn Matcher>> {
+    None
+}
+
+/// Replaces every record with the parts of it matched by the expression,
+/// which keep the file and line of the record
+fn only_matches(input: Items, expr: Rc<dyn Matcher>, ignore_case: bool) -> Items {
+    Box::new(input.flat_map(move |item| {
+        let record = match item {
+            Ok(record) => record,
+            Err(err) => return vec![Err(err)],
+        };
+
+        let lowercased = match ignore_case {
+            true => record.text.to_lowercase(),
+            false => String::new(),
+        };
+
+        // Lowercasing rarely changes the length of a text, only then the
+        // lowercased parts are printed
+        let text = match ignore_case && lowercased.len() != record.text.len() {
+            true => &lowercased,
+            false => &record.text,
+        };
+
+        let windows = match ignore_case {
+            true => expr.scan(&lowercased),
+            false => expr.scan(&record.text),
+        };
+
+        windows
+            .into_iter()
+            .map(|window| {
+                Ok(Record {
+                    text: text[window].to_string(),
+                    document: None,
+                    ..record.clone()
+                })
+            })
+            .collect()
+    }))
+}
+
+/// Prints the result of matching every record as a line of json, see
+/// `srch::Match`
+fn explain(input: Items, expr: &srch::ExpressionSet, ignore_case: bool) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for item in input {
+        let item = item?;
+
+        let result = match ignore_case {
+            true => expr.evaluate(&item.text.to_lowercase()),
+            false => expr.evaluate(&item.text),
+        };
+
+        writeln!(out, "{}", result.to_json())?;
+    }
+
+    Ok(())
+}
+
+/// Prints every record prefixed by its score, the records closest to matching
+/// first. Records with the same score keep their order
+fn rank(
+    input: Items,
+    expr: &srch::ExpressionSet,
+    ignore_case: bool,
+    slice: Slice,
+) -> io::Result<()> {
+    let mut ranked = input
+        .map(|item| {
+            item.map(|item| {
+                let score = match ignore_case {
+                    true => expr.score(&item.text.to_lowercase()),
+                    false => expr.score(&item.text),
+                };
+
+                (score, item)
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    ranked.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+    let items: Items = Box::new(ranked.into_iter().map(|(score, item)| {
+        Ok(Record {
+            text: format!("{:.2} {}", score, item.text),
+            document: None,
+            ..item
+        })
+    }));
+
+    let stdout = io::stdout();
+    let mut sink = output::Plain::new(Box::new(stdout.lock()), output::LINES);
+
+    for item in slice.apply(items)? {
+        sink.record(&item?)?;
+    }
+
+    sink.finish()
+}
+
+/// The invalid expressions and the findings of `lint` for an expression
+/// file, the text of each record starts with `error:` or `warning:`
+fn lint_records(path: &str, source: &str) -> Vec<Record> {
+    let file: Rc<str> = path.into();
+    let mut records = Vec::new();
+
+    let mut record = |line, text| {
+        records.push(Record {
+            file: file.clone(),
+            line,
+            text,
+            document: None,
+        })
+    };
+
+    for (line, expression) in split_expressions(source) {
+        let expr = match srch::Expression::new(&expression) {
+            Ok(expr) => expr,
+            Err(srch::Error::ParserError(_)) if expression_is_empty(&expression) => continue,
+            Err(err) => {
+                record(line, format!("error: {}", err));
+                continue;
+            }
+        };
+
+        for finding in expr.lint() {
+            record(line, format!("warning: {}", finding));
+        }
+    }
+
+    records
+}
+
+/// The labels of the rules matching `text`, rules are pairs of a label and
+/// an expression
+fn matching_rules(text: &str, rules: Vec<(String, String)>) -> Vec<Record> {
+    let mut records = Vec::new();
+
+    for (label, expression) in rules {
+        let expr = match resolve_patterns(vec![expression]) {
+            Ok(sources) => srch::Expression::new(&sources.concat()).map_err(|err| err.to_string()),
+            Err(err) => Err(err),
+        };
+
+        let expr = match expr {
+            Ok(expr) => expr,
+            Err(err) => fail(format!("{}: {}", label, err)),
+        };
+
+        if expr.matches(text) {
+            records.push(Record::message(label));
+        }
+    }
+
+    records
+}
+
+/// The relation of two compared expressions, followed by the inputs only
+/// one of them matches. `prefix` is printed in front of the relation
+fn comparison_records(
+    prefix: &str,
+    comparison: &srch::Comparison,
+    old_path: &str,
+    new_path: &str,
+) -> Vec<Record> {
+    let mut records = vec![Record::message(format!(
+        "{}{}",
+        prefix, comparison.relation
+    ))];
+
+    if let Some(input) = &comparison.removed {
+        records.push(Record::message(format!("  only {}: {:?}", old_path, input)));
+    }
+
+    if let Some(input) = &comparison.added {
+        records.push(Record::message(format!("  only {}: {:?}", new_path, input)));
+    }
+
+    records
+}
+
+/// The differences `diff` prints between two expression files or two rule
+/// sets together with whether they differ at all, `None` if an expression
+/// file is compared with a rule set
+fn diff_records(
+    old: DiffInput,
+    new: DiffInput,
+    old_path: &str,
+    new_path: &str,
+) -> Option<(Vec<Record>, bool)> {
+    match (old, new) {
+        (DiffInput::Expression(old), DiffInput::Expression(new)) => {
+            let comparison = old.compare(&new);
+            let records = comparison_records("", &comparison, old_path, new_path);
+
+            Some((records, comparison.relation != srch::Relation::Equivalent))
+        }
+        (DiffInput::RuleSet(old), DiffInput::RuleSet(new)) => {
+            let changes = old.compare(&new);
+            let mut records = Vec::new();
+
+            for (name, change) in changes.iter() {
+                match change {
+                    srch::RuleChange::Added => {
+                        records.push(Record::message(format!("{}: added", name)))
+                    }
+                    srch::RuleChange::Removed => {
+                        records.push(Record::message(format!("{}: removed", name)))
+                    }
+                    srch::RuleChange::Changed(comparison) => records.extend(comparison_records(
+                        &format!("{}: ", name),
+                        comparison,
+                        old_path,
+                        new_path,
+                    )),
+                }
+            }
+
+            Some((records, !changes.is_empty()))
+        }
+        _ => None,
+    }
+}
+
+/// A `FAIL` record for every failed expectation of a test suite followed by
+/// the number of passed and failed cases, together with whether any case
+/// failed
+fn check_records(suite: &TestSuite) -> (Vec<Record>, bool) {
+    let mut records = Vec::new();
+    let (mut passed, mut failed) = (0, 0);
+
+    for case in suite.cases.iter() {
+        let name = case.name.as_deref().unwrap_or(&case.expression);
+        // An unknown pattern only fails its own case
+        let expr = match resolve_patterns(vec![case.expression.clone()]) {
+            Ok(sources) => srch::Expression::new(&sources.concat()).map_err(|err| err.to_string()),
+            Err(err) => Err(err),
+        };
+
+        let expr = match expr {
+            Ok(expr) => expr,
+            Err(err) => {
+                failed += 1;
+                records.push(Record::message(format!("FAIL {}: {}", name, err)));
+                continue;
+            }
+        };
+
+        let failures = case
+            .matches
+            .iter()
+            .filter(|input| !expr.matches(input))
+            .map(|input| format!("expected {:?} to match", input))
+            .chain(
+                case.rejects
+                    .iter()
+                    .filter(|input| expr.matches(input))
+                    .map(|input| format!("expected {:?} to be rejected", input)),
+            )
+            .collect::<Vec<_>>();
+
+        match failures.is_empty() {
+            true => passed += 1,
+            false => {
+                failed += 1;
+
+                for failure in failures {
+                    records.push(Record::message(format!("FAIL {}: {}", name, failure)));
+                }
+            }
+        }
+    }
+
+    records.push(Record::message(format!(
+        "{} passed, {} failed",
+        passed, failed
+    )));
+
+    (records, failed > 0)
+}
+
+/// The patterns listed by `patterns list`, the built-in and plugin patterns
+/// are only listed if `patterns` isn't narrowed down by a selector
+fn pattern_records(patterns: &Patterns, selected: bool) -> Vec<Record> {
+    let mut records = Vec::new();
+
+    for pattern in srch::Pattern::ALL.iter() {
+        if patterns.get(pattern.name()).is_none() && !selected {
+            records.push(Record::message(format!(
+                "{}{} = <built-in: {}>",
+                PATTERN_PREFIX,
+                pattern.name(),
+                pattern.description()
+            )));
+        }
+    }
+
+    for (name, description) in srch::registered_queries() {
+        if patterns.get(&name).is_none() && !selected {
+            records.push(Record::message(format!(
+                "{}{} = <plugin: {}>",
+                PATTERN_PREFIX, name, description
+            )));
+        }
+    }
+
+    for rule in patterns.rules.iter() {
+        let mut notes = rule
+            .description()
+            .map(String::from)
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if let Some(severity) = rule.severity() {
+            notes.push(format!("severity: {}", severity));
+        }
+
+        if !rule.tags().is_empty() {
+            notes.push(format!("tags: {}", rule.tags().join(", ")));
+        }
+
+        let text = match notes.is_empty() {
+            true => format!("{}{} = {}", PATTERN_PREFIX, rule.name(), rule.source()),
+            false => format!(
+                "{}{} = {} # {}",
+                PATTERN_PREFIX,
+                rule.name(),
+                rule.source(),
+                notes.join("; ")
+            ),
+        };
+
+        records.push(Record::message(text));
+    }
+
+    records
+}
+
+/// Prints records to stdout through a plain sink
+fn print_plain(
+    records: impl IntoIterator<Item = Record>,
+    layout: output::Layout,
+) -> io::Result<()> {
+    let stdout = io::stdout();
+    let mut sink = output::Plain::new(Box::new(stdout.lock()), layout);
+
+    for record in records {
+        sink.record(&record)?;
+    }
+
+    sink.finish()
+}
+
+/// Splits the contents of an expression file into separate expressions,
+/// expressions are separated by blank lines. Returns the line each
+/// expression starts at together with its source
+fn split_expressions(source: &str) -> Vec<(usize, String)> {
+    let mut expressions = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+
+    for (n, line) in source.lines().enumerate() {
+        match (line.trim().is_empty(), &mut current) {
+            (true, _) => expressions.extend(current.take()),
+            (false, Some((_, expression))) => {
+                expression.push('\n');
+                expression.push_str(line);
+            }
+            (false, None) => current = Some((n + 1, line.to_string())),
+        }
+    }
+
+    expressions.extend(current);
+    expressions
+}
+
+/// Whether an expression source consists of whitespace and comments only
+fn expression_is_empty(source: &str) -> bool {
+    source
+        .lines()
+        .all(|line| line.trim().is_empty() || line.trim().starts_with('#'))
+}
+
+/// Replaces every ascii whitespace separated word in `line` matching `expr`,
+/// while preserving the original whitespace between the words
+fn replace_words(
+    line: &str,
+    tokenizer: srch::Tokenizer,
+    expr: &srch::ExpressionSet,
+    replacement: &Replacement,
+) -> std::result::Result<String, String> {
+    map_matching_words(line, tokenizer, expr, |word| replacement.render(word, expr))
+}
+
+fn highlight_words(line: &str, tokenizer: srch::Tokenizer, expr: &srch::ExpressionSet) -> String {
+    let highlighted = map_matching_words(line, tokenizer, expr, |word| {
+        Ok::<_, std::convert::Infallible>(highlight(word))
+    });
+
+    match highlighted {
+        Ok(line) => line,
+        Err(never) => match never {},
+    }
+}
+
+/// Applies `map` to every word of the line matching the expression, while
+/// keeping the text between the words untouched. Stops at the first error
+fn map_matching_words<F, E>(
+    line: &str,
+    tokenizer: srch::Tokenizer,
+    expr: &srch::ExpressionSet,
+    map: F,
+) -> std::result::Result<String, E>
+where
+    F: Fn(&str) -> std::result::Result<String, E>,
+{
+    let mut mapped = String::with_capacity(line.len());
+    let mut end = 0;
+
+    for span in tokenizer.word_spans(line) {
+        let word = &line[span.clone()];
+        mapped.push_str(&line[end..span.start]);
+
+        match expr.matches(word) {
+            true => mapped.push_str(&map(word)?),
+            false => mapped.push_str(word),
+        }
+
+        end = span.end;
+    }
+
+    mapped.push_str(&line[end..]);
+    Ok(mapped)
+}
+
+/// Wraps the text in the ansi escape codes for bold red
+fn highlight(text: &str) -> String {
+    format!("\x1b[1;31m{}\x1b[0m", text)
+}
+
+/// The number of unchanged lines shown around each change of a patch
+const PATCH_CONTEXT: usize = 3;
+
+/// Splits a line into its text and its line ending, which is `\n`, `\r\n`
+/// or empty for an unterminated last line
+fn split_ending(line: &str) -> (&str, &str) {
+    let text = match line.strip_suffix('\n') {
+        Some(text) => text.strip_suffix('\r').unwrap_or(text),
+        None => line,
+    };
+
+    line.split_at(text.len())
+}
+
+/// Calls `f` with the text and the line ending of every line of `reader`, so
+/// the lines can be written back with their own line ending
+fn for_each_line<R, F>(mut reader: R, mut f: F) -> io::Result<()>
+where
+    R: BufRead,
+    F: FnMut(&str, &str) -> io::Result<()>,
+{
+    let mut line = String::new();
+
+    while reader.read_line(&mut line)? > 0 {
+        let (text, ending) = split_ending(&line);
+        f(text, ending)?;
+        line.clear();
+    }
+
+    Ok(())
+}
+
+/// Prints every line of `reader` mapped by `map` through `sink`, each line
+/// keeps its own line ending
+fn print_lines<R, F>(file: &Rc<str>, reader: R, sink: &mut dyn OutputSink, map: F) -> io::Result<()>
+where
+    R: BufRead,
+    F: Fn(&str) -> io::Result<String>,
+{
+    let mut n = 0;
+
+    for_each_line(reader, |line, ending| {
+        n += 1;
+        sink.record(&Record {
+            file: file.clone(),
+            line: n,
+            text: map(line)? + ending,
+            document: None,
+        })
+    })?;
+
+    sink.finish()
+}
+
+/// Highlights the matches of `expr` in a line, either the whole line or the
+/// matching words
+fn highlight_line(line: &str, mode: &Mode, expr: &srch::ExpressionSet) -> String {
+    match mode {
+        Mode::Word(tokenizer) => highlight_words(line, *tokenizer, expr),
+        _ if expr.matches(line) => highlight(line),
+        _ => line.to_string(),
+    }
+}
+
+/// Creates a unified diff of the changes `replace_line` makes to `source`,
+/// which can be applied using `git apply` or `patch -p1`. Every line keeps
+/// its own line ending
+fn unified_diff<F>(path: &str, source: &str, replace_line: F) -> io::Result<String>
+where
+    F: Fn(&str) -> io::Result<String>,
+{
+    let (old, endings): (Vec<_>, Vec<_>) = source.split_inclusive('\n').map(split_ending).unzip();
+    let new = old
+        .iter()
+        .map(|line| replace_line(line))
+        .collect::<io::Result<Vec<_>>>()?;
+    let changed = (0..old.len())
+        .filter(|&i| old[i] != new[i])
+        .collect::<Vec<_>>();
+
+    if changed.is_empty() {
+        return Ok(String::new());
+    }
+
+    // Changes closer than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = vec![];
+
+    for &i in changed.iter() {
+        let start = i.saturating_sub(PATCH_CONTEXT);
+        let end = (i + 1 + PATCH_CONTEXT).min(old.len());
+
+        match hunks.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let missing_newline = !source.ends_with('\n');
+    let new_lines = |range: std::ops::Range<usize>| -> usize {
+        range.map(|i| new[i].split('\n').count()).sum()
+    };
+
+    let mut patch = format!("--- a/{}\n+++ b/{}\n", path, path);
+
+    for (start, end) in hunks {
+        patch.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start + 1,
+            end - start,
+            new_lines(0..start) + 1,
+            new_lines(start..end)
+        ));
+
+        for i in start..end {
+            let is_last = missing_newline && i + 1 == old.len();
+
+            // The unterminated last line is marked instead
+            let ending = match endings[i] {
+                "" => "\n",
+                ending => ending,
+            };
+
+            if old[i] == new[i] {
+                patch.push_str(&format!(" {}{}", old[i], ending));
+            } else {
+                patch.push_str(&format!("-{}{}", old[i], ending));
+
+                if is_last {
+                    patch.push_str("\\ No newline at end of file\n");
+                }
+
+                for line in new[i].split('\n') {
+                    patch.push_str(&format!("+{}{}", line, ending));
+                }
+            }
+
+            if is_last {
+                patch.push_str("\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    Ok(patch)
+}
+
+/// Writes to a temporary file next to `path` and renames it to `path` once
+/// everything was written, so a failed write never leaves a partial file
+fn write_atomically<F>(path: &Path, backup: Option<&str>, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?
+        .to_string_lossy();
+
+    let tmp_path = path.with_file_name(format!(".{}.srch-{}", name, std::process::id()));
+
+    let result = (|| {
+        let mut tmp = BufWriter::new(File::create(&tmp_path)?);
+        write(&mut tmp)?;
+
+        let tmp = tmp.into_inner().map_err(|err| err.into_error())?;
+        tmp.sync_all()?;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&tmp_path, metadata.permissions())?;
+        }
+
+        if let Some(suffix) = backup {
+            let mut backup_path = path.as_os_str().to_owned();
+            backup_path.push(suffix);
+            fs::copy(path, backup_path)?;
+        }
+
+        fs::rename(&tmp_path, path)
+    })();
+
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+
+    result
+}
+
+/// Answers a request to the json api of `srch serve`
+///
+/// - `POST /compile` with `{"expression"}` checks if an expression is valid
+/// - `POST /match` with `{"expression", "text"}` tests a single text
+/// - `POST /batch` with `{"expression", "texts"}` tests many texts at once
+/// - `POST /explain` with `{"expression", "text"}` returns a `srch::Match`
+#[cfg(feature = "server")]
+fn handle_request(method: &str, url: &str, body: &str) -> (u16, serde_json::Value) {
+    use serde_json::{json, Value};
+
+    fn error(status: u16, message: impl Display) -> (u16, Value) {
+        (status, json!({ "error": message.to_string() }))
+    }
+
+    if method != "POST" {
+        return error(405, "only POST requests are supported");
+    }
+
+    let body: Value = match serde_json::from_str(body) {
+        Ok(body) => body,
+        Err(err) => return error(400, format!("invalid json: {}", err)),
+    };
+
+    let source = match body.get("expression").and_then(Value::as_str) {
+        Some(expression) => match resolve_patterns(vec![expression.to_string()]) {
+            Ok(sources) => sources.concat(),
+            Err(err) => return error(400, err),
+        },
+        None => return error(400, "missing \"expression\""),
+    };
+
+    let expr = srch::Expression::new(&source);
+
+    match (url, expr) {
+        ("/compile", Ok(_)) => (200, json!({ "valid": true })),
+        ("/compile", Err(err)) => (200, json!({ "valid": false, "error": err.to_string() })),
+        ("/match", Ok(expr)) => match body.get("text").and_then(Value::as_str) {
+            Some(text) => (200, json!({ "matches": expr.matches(text) })),
+            None => error(400, "missing \"text\""),
+        },
+        ("/batch", Ok(expr)) => match body.get("texts").and_then(Value::as_array) {
+            Some(texts) => match texts.iter().map(Value::as_str).collect::<Option<Vec<_>>>() {
+                Some(texts) => (
+                    200,
+                    json!({ "matches": texts.iter().map(|text| expr.matches(text)).collect::<Vec<_>>() }),
+                ),
+                None => error(400, "\"texts\" must only contain strings"),
+            },
+            None => error(400, "missing \"texts\""),
+        },
+        ("/explain", Ok(expr)) => match body.get("text").and_then(Value::as_str) {
+            Some(text) => (200, json!(expr.evaluate(text))),
+            None => error(400, "missing \"text\""),
+        },
+        ("/match", Err(err)) | ("/batch", Err(err)) | ("/explain", Err(err)) => error(400, err),
+        _ => error(404, format!("unknown endpoint {}", url)),
+    }
+}
+
+#[cfg(feature = "server")]
+fn serve(host: &str, port: u16) -> io::Result<()> {
+    let server = tiny_http::Server::http((host, port)).map_err(io::Error::other)?;
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .expect("static header is valid");
+
+    eprintln!("listening on http://{}:{}", host, port);
+
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+
+        let (status, response) = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => handle_request(request.method().as_str(), request.url(), &body),
+            Err(err) => (400, serde_json::json!({ "error": err.to_string() })),
+        };
+
+        let response = tiny_http::Response::from_string(response.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+
+        if let Err(err) = request.respond(response) {
+            eprintln!("error: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "server"))]
+fn serve(_: &str, _: u16) -> io::Result<()> {
+    fail("serve requires srch to be built with the \"server\" feature")
+}
+
+#[cfg(feature = "bench")]
+fn bench(submatches: &ArgMatches) -> io::Result<()> {
+    use srch::bench::{self, CorpusKind, Measurement};
+
+    let count = |name| {
+        submatches
+            .value_of(name)
+            .and_then(|n| n.parse::<usize>().ok())
+            .unwrap_or_default()
+    };
+
+    let records = count("records");
+    let iterations = count("iterations");
+    let compile = |source: &str| match srch::Expression::new(source) {
+        Ok(expr) => expr,
+        Err(err) => fail(format!("{}: {}", source, err)),
+    };
+
+    let measurements: Vec<Measurement> = match submatches.values_of("exprs") {
+        Some(sources) => {
+            let corpus = match submatches.value_of("input") {
+                Some(path) => fs::read_to_string(path)?
+                    .lines()
+                    .map(str::to_string)
+                    .collect(),
+                None => {
+                    let kind = submatches.value_of("corpus").unwrap_or_default();
+                    let kind = CorpusKind::from_name(kind).unwrap_or(CorpusKind::Logs);
+                    bench::generate(kind, records, bench::SEED)
+                }
+            };
+
+            sources
+                .map(|source| bench::measure(source, &compile(source), &corpus, iterations))
+                .collect()
+        }
+        None => bench::WORKLOADS
+            .iter()
+            .map(|workload| {
+                let corpus = bench::generate(workload.corpus, records, bench::SEED);
+                bench::measure(
+                    workload.name,
+                    &compile(workload.expression),
+                    &corpus,
+                    iterations,
+                )
+            })
+            .collect(),
+    };
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for measurement in measurements {
+        writeln!(
+            out,
+            "{}: {:.2} MiB/s, {:.0} records/s, {} of {} records matched",
+            measurement.name,
+            measurement.bytes_per_second() / (1024.0 * 1024.0),
+            measurement.records_per_second(),
+            measurement.matches,
+            measurement.records
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(feature = "bench"))]
+fn bench(_: &ArgMatches) -> io::Result<()> {
+    fail("bench requires srch to be built with the \"bench\" feature")
+}
+
+fn main() -> io::Result<()> {
+    let matches = build_cli().get_matches();
+
+    init_verbosity(matches.occurrences_of("verbose"));
+
+    // doctor reports a broken catalog instead of failing because of it
+    if !matches!(matches.subcommand(), Some(("doctor", _))) {
+        init_catalog();
+        init_plugins();
+    }
+
+    fn run_filter_command(submatches: &ArgMatches, invert_matches: bool) -> Result<()> {
+        let positionals = Positionals::from_matches(submatches, FILTER_POSITIONALS);
+        let files = read_files_from_matches(submatches)?;
+
+        let mut inputs = positionals.inputs.clone();
+        inputs.extend(files.iter().flatten().map(String::as_str));
+
+        // An empty file list means there is nothing to search, not stdin
+        if inputs.is_empty() && files.is_some() {
+            return Ok(());
+        }
+
+        if submatches.is_present("follow") && inputs.len() != 1 {
+            build_cli()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "--follow requires a single input <FILE>",
+                )
+                .exit();
+        }
+
+        let started = Instant::now();
+        let stats = Rc::new(Stats::default());
+
+        let ignore_case = submatches.is_present("ignore-case");
+        let invert_matches = invert_matches != submatches.is_present("invert-match");
+
+        if submatches.is_present("explain") {
+            let expr = compile_expression_from_matches(submatches, &positionals, ignore_case)?;
+            let input = read_input_from_matches(submatches, &inputs, stats)?;
+
+            // Ignoring matches explains why the records don't match
+            let expr = match invert_matches {
+                true => expr.complement(),
+                false => expr,
+            };
+
+            return explain(input, &expr, ignore_case);
+        }
+
+        if submatches.is_present("rank") {
+            let expr = compile_expression_from_matches(submatches, &positionals, ignore_case)?;
+            let input = read_input_from_matches(submatches, &inputs, stats)?;
+
+            // Ignoring matches ranks the records closest to not matching first
+            let expr = match invert_matches {
+                true => expr.complement(),
+                false => expr,
+            };
+
+            return rank(input, &expr, ignore_case, Slice::from_matches(submatches));
+        }
+
+        let mut summary = None;
+        let mut query_stats = None;
+        let mut literals = None;
+
+        // Ignoring matches keeps the matches of the complement, the summary
+        // and stats still describe the expression as written
+        let expr: Rc<dyn Matcher> = match watch_expression_from_matches(
+            submatches,
+            &positionals,
+            ignore_case,
+            invert_matches,
+        ) {
+            Some(watched) => watched,
+            None => {
+                let mut expr =
+                    compile_expression_from_matches(submatches, &positionals, ignore_case)?;
+
+                if submatches.is_present("stats-detail") {
+                    expr = expr.with_stats();
+                }
+
+                let expr = Rc::new(expr);
+
+                if submatches.is_present("summary") {
+                    let labels = expression_labels(submatches, &positionals);
+                    summary = Some(Rc::new(Summary::new(labels, expr.clone())));
+                }
+
+                if submatches.is_present("stats-detail") {
+                    query_stats = Some(expr.clone());
+                }
+
+                let expr = match invert_matches {
+                    true => Rc::new(expr.complement()),
+                    false => expr,
+                };
+
+                if !submatches.is_present("stats-detail") {
+                    literals = expr
+                        .literals()
+                        .map(|literals| literals.into_iter().map(String::from).collect::<Vec<_>>());
+                }
+
+                expr
+            }
+        };
+
+        let only_matching = submatches.is_present("only-matching");
+
+        if only_matching && invert_matches {
+            fail("--only-matching can't be used when ignoring matches");
+        }
+
+        let scanner = expr.clone();
+        if let Some(literals) = &literals {
+            verbose!(1, "searching for the literals {:?}", literals);
+        }
+
+        // Expressions which only check for substrings skip the runtime
+        let is_match = move |text: &str| {
+            let text = match ignore_case {
+                true => Cow::Owned(text.to_lowercase()),
+                false => Cow::Borrowed(text),
+            };
+
+            match &literals {
+                Some(literals) => literals
+                    .iter()
+                    .any(|literal| text.contains(literal.as_str())),
+                None => expr.matches(&text),
+            }
+        };
+
+        // grep-searcher only yields the matching lines, they aren't matched
+        // a second time
+        #[cfg(feature = "grep")]
+        let (input, searched) = match searcher::is_supported(submatches, &inputs) {
+            true => {
+                let progress = !submatches.is_present("no-progress");
+                let input = searcher::search(&inputs, progress, is_match.clone(), stats.clone());
+                (input, true)
+            }
+            false => (
+                read_input_from_matches(submatches, &inputs, stats.clone())?,
+                false,
+            ),
+        };
+
+        #[cfg(not(feature = "grep"))]
+        let (input, searched) = (
+            read_input_from_matches(submatches, &inputs, stats.clone())?,
+            false,
+        );
+
+        let input = match only_matching {
+            true => only_matches(input, scanner, ignore_case),
+            false => input,
+        };
+
+        let terminator = match (
+            submatches.is_present("print0"),
+            submatches.is_present("crlf"),
+        ) {
+            (true, _) => "\0",
+            (false, true) => "\r\n",
+            (false, false) => "\n",
+        };
+
+        // With --rest-to the records which aren't printed are written to a
+        // file while filtering, so the input is only read once
+        let rest = match submatches.value_of("rest-to") {
+            Some(path) => Some(Rc::new(RefCell::new(BufWriter::new(File::create(path)?)))),
+            None => None,
+        };
+
+        let counter = stats.clone();
+        let rest_writer = rest.clone();
+        let distribution = summary.clone();
+        let filtered = input.filter_map(move |x| match x {
+            Ok(x) => {
+                if let Some(summary) = &distribution {
+                    match ignore_case {
+                        true => summary.count(&x.text.to_lowercase()),
+                        false => summary.count(&x.text),
+                    }
+                }
+
+                let is_match = searched || is_match(&x.text);
+
+                if is_match {
+                    counter.matches.set(counter.matches.get() + 1);
+                    return Some(Ok(x));
+                }
+
+                let rest = rest_writer.as_ref()?;
+
+                match write!(rest.borrow_mut(), "{}{}", x.text, terminator) {
+                    Ok(()) => None,
+                    Err(err) => Some(Err(err)),
+                }
+            }
+            Err(err) => Some(Err(err)),
+        });
+
+        let mut items: Items = Box::new(filtered);
+
+        if submatches.is_present("unique") {
+            items = unique(items);
+        }
+
+        if submatches.is_present("sort") {
+            items = match submatches.value_of("collate") {
+                Some(locale) => sort_collated(items, locale)?,
+                None => sort(items)?,
+            };
+        }
+
+        if submatches.is_present("squeeze") {
+            items = squeeze(items);
+        }
+
+        let stdout = io::stdout();
+        let multiple_files = inputs.len() > 1;
+
+        let heading = match (
+            submatches.is_present("heading"),
+            submatches.is_present("no-heading"),
+        ) {
+            (true, _) => true,
+            (_, true) => false,
+            _ => multiple_files && stdout.is_terminal(),
+        };
+
+        // With --copy the output is collected and copied to the clipboard
+        let mut copied = Vec::new();
+        let out: Box<dyn Write> = match submatches.value_of("matches-to") {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None if submatches.is_present("copy") => Box::new(&mut copied),
+            None => Box::new(stdout.lock()),
+        };
+
+        let layout = output::Layout {
+            heading,
+            file_names: multiple_files && !heading,
+            line_numbers: submatches.is_present("line-number"),
+            terminator,
+        };
+
+        let mut sink = output::from_matches(submatches, out, layout);
+
+        for item in Slice::from_matches(submatches).apply(items)? {
+            sink.record(&item?)?;
+        }
+
+        sink.finish()?;
+
+        let failed = !sink.succeeded();
+        drop(sink);
+
+        if let Some(rest) = rest {
+            rest.borrow_mut().flush()?;
+        }
+
+        if submatches.is_present("copy") {
+            clipboard::copy(String::from_utf8_lossy(&copied).into_owned())?;
+        }
+
+        verbose!(
+            2,
+            "searched {} line(s) of {} file(s) in {:?}",
+            stats.lines.get(),
+            stats.files.get(),
+            started.elapsed()
+        );
+
+        if submatches.is_present("stats") {
+            stats.print(&mut io::stderr(), started.elapsed())?;
+        }
+
+        if let Some(summary) = summary {
+            summary.print(&mut io::stderr())?;
+        }
+
+        if let Some(expr) = query_stats {
+            let labels = expression_labels(submatches, &positionals);
+            print_query_stats(&mut io::stderr(), &labels, &expr.stats())?;
+        }
+
+        if failed {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn run_replace_command(submatches: &ArgMatches) -> Result<()> {
+        let positionals = Positionals::from_matches(submatches, REPLACE_POSITIONALS);
+        let as_patch = submatches.is_present("patch");
+
+        if (as_patch || submatches.is_present("in-place")) && positionals.inputs.is_empty() {
+            build_cli()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    match as_patch {
+                        true => "--patch requires an input <FILE>",
+                        false => "--in-place requires an input <FILE>",
+                    },
+                )
+                .exit();
+        }
+
+        let replacement = match positionals.replacement {
+            Some(replacement) => replacement,
+            None => build_cli()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "The following required arguments were not provided: <REPLACEMENT>",
+                )
+                .exit(),
+        };
+
+        let replacement = match Replacement::parse(replacement, submatches.is_present("references"))
+        {
+            Ok(replacement) => replacement,
+            Err(err) => fail(err),
+        };
+
+        let mode = Mode::from_matches(submatches);
+
+        if !matches!(mode, Mode::Line | Mode::Word(_)) {
+            fail("replace only supports the line and word modes");
+        }
+
+        let expr = compile_expression_from_matches(submatches, &positionals, false)?;
+
+        let replace_line = |line: &str| -> io::Result<String> {
+            let replaced = match mode {
+                Mode::Word(tokenizer) => replace_words(line, tokenizer, &expr, &replacement),
+                _ if expr.matches(line) => replacement.render(line, &expr),
+                _ => Ok(line.to_string()),
+            };
+
+            replaced.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        };
+
+        if as_patch {
+            let mut patch = String::new();
+
+            for path in positionals.inputs.iter() {
+                let diff = unified_diff(path, &fs::read_to_string(path)?, replace_line);
+
+                match diff {
+                    Ok(diff) => patch.push_str(&diff),
+                    Err(err) if err.kind() == io::ErrorKind::InvalidData => fail(err),
+                    Err(err) => return Err(err),
+                }
+            }
+
+            return match submatches.value_of("output") {
+                Some(path) => fs::write(path, patch),
+                None => io::stdout().lock().write_all(patch.as_bytes()),
+            };
+        }
+
+        let input = positionals.input();
+        let (file, reader) = open_input(input, false, !submatches.is_present("no-progress"))?;
+
+        let replace = |out: &mut dyn Write| -> io::Result<()> {
+            let mut sink = output::Plain::new(Box::new(out), output::RAW);
+            print_lines(&file, reader, &mut sink, replace_line)
+        };
+
+        let destination = match submatches.is_present("in-place") {
+            true => input,
+            false => submatches.value_of("output"),
+        };
+
+        let result = match destination {
+            Some(path) => write_atomically(Path::new(path), submatches.value_of("backup"), replace),
+            None => {
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                replace(&mut out)
+            }
+        };
+
+        // Invalid input and references to missing parts, a file written to
+        // is left untouched
+        match result {
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => fail(err),
+            result => result,
+        }
+    }
+
+    fn run_convert_command(submatches: &ArgMatches) -> Result<()> {
+        let source = submatches.value_of("expression").unwrap_or_default();
+
+        if submatches.is_present("from-regex") {
+            return match srch::regex_to_source(source) {
+                Ok(expression) => print_plain(vec![Record::message(expression)], output::LINES),
+                Err(err) => fail(err),
+            };
+        }
+
+        let sources = match resolve_patterns(vec![source.to_string()]) {
+            Ok(sources) => sources,
+            Err(err) => fail(err),
+        };
+
+        let expr = match srch::Expression::new(&sources.concat()) {
+            Ok(expr) => expr,
+            Err(err) => fail(err),
+        };
+
+        // The `target` group requires either --from-regex or --to-regex
+        print_plain(vec![Record::message(expr.to_regex_string())], output::LINES)
+    }
+
+    fn run_describe_command(submatches: &ArgMatches) -> Result<()> {
+        let source = submatches.value_of("expression").unwrap_or_default();
+        let sources = match resolve_patterns(vec![source.to_string()]) {
+            Ok(sources) => sources,
+            Err(err) => fail(err),
+        };
+
+        let description = match srch::Expression::new(&sources.concat()) {
+            Ok(expr) => expr.describe(),
+            Err(err) => fail(err),
+        };
+
+        let stdout = io::stdout();
+        let mut sink = output::Plain::new(Box::new(stdout.lock()), output::LINES);
+
+        sink.record(&Record::message(description))?;
+        sink.finish()
+    }
+
+    fn run_lint_command(submatches: &ArgMatches) -> Result<()> {
+        let path = submatches.value_of("input").unwrap_or_default();
+        let findings = lint_records(path, &fs::read_to_string(path)?);
+
+        let stdout = io::stdout();
+        let template = Template::parse("{file}:{line}: {text}").expect("the template is valid");
+        let mut sink = output::Templated::new(Box::new(stdout.lock()), template, output::LINES);
+
+        for finding in findings.iter() {
+            sink.record(finding)?;
+        }
+
+        sink.finish()?;
+
+        if !findings.is_empty() {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn run_diff_command(submatches: &ArgMatches) -> Result<()> {
+        let old_path = submatches.value_of("old").unwrap_or_default();
+        let new_path = submatches.value_of("new").unwrap_or_default();
+
+        let old = read_diff_input(old_path)?;
+        let new = read_diff_input(new_path)?;
+
+        let (records, changed) = match diff_records(old, new, old_path, new_path) {
+            Some(diff) => diff,
+            None => fail("an expression file can only be compared with another expression file, and a rule set with another rule set"),
+        };
+
+        print_plain(records, output::LINES)?;
+
+        if changed {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn run_check_command(submatches: &ArgMatches) -> Result<()> {
+        let path = submatches.value_of("input").unwrap_or_default();
+
+        let suite = match TestSuite::parse(&fs::read_to_string(path)?) {
+            Ok(suite) => suite,
+            Err(err) => fail(format!("{}: {}", path, err)),
+        };
+
+        let (records, failed) = check_records(&suite);
+        print_plain(records, output::LINES)?;
+
+        if failed {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn run_wizard_command(submatches: &ArgMatches) -> Result<()> {
+        const PREVIEW: usize = 5;
+
+        let sample = submatches
+            .value_of("input")
+            .map(fs::read_to_string)
+            .transpose()?;
+
+        let stdin = io::stdin();
+        let stderr = io::stderr();
+        let mut wizard = Wizard::new(stdin.lock(), stderr.lock());
+
+        loop {
+            let expression = match wizard.build()? {
+                Some(expression) => expression,
+                None => fail("no question was answered, there is no expression to build"),
+            };
+
+            let sample = match &sample {
+                Some(sample) => sample,
+                None => return print_plain(vec![Record::message(expression)], output::LINES),
+            };
+
+            let expr = match srch::Expression::new(&expression) {
+                Ok(expr) => expr,
+                Err(err) => fail(err),
+            };
+
+            let matches = sample
+                .lines()
+                .filter(|line| expr.matches(line))
+                .collect::<Vec<_>>();
+
+            writeln!(
+                wizard.prompt,
+                "\n{}\nmatches {} of {} lines of the sample:",
+                expression,
+                matches.len(),
+                sample.lines().count()
+            )?;
+
+            for line in matches.iter().take(PREVIEW) {
+                writeln!(wizard.prompt, "  {}", line)?;
+            }
+
+            if matches.len() > PREVIEW {
+                writeln!(wizard.prompt, "  ...")?;
+            }
+
+            if wizard.confirm("Is this what you are looking for? [Y/n]")? {
+                return print_plain(vec![Record::message(expression)], output::LINES);
+            }
+        }
+    }
+
+    fn run_highlight_command(submatches: &ArgMatches) -> Result<()> {
+        let positionals = Positionals::from_matches(submatches, FILTER_POSITIONALS);
+        let mode = Mode::from_matches(submatches);
+
+        if !matches!(mode, Mode::Line | Mode::Word(_)) {
+            fail("highlight only supports the line and word modes");
+        }
+
+        let expr = compile_expression_from_matches(submatches, &positionals, false)?;
+        let (file, reader) = open_input(
+            positionals.input(),
+            false,
+            !submatches.is_present("no-progress"),
+        )?;
+
+        let stdout = io::stdout();
+
+        let color = match submatches.value_of("color") {
+            Some("always") => true,
+            Some("never") => false,
+            _ => stdout.is_terminal(),
+        };
+
+        let mut sink = output::Plain::new(Box::new(stdout.lock()), output::RAW);
+
+        print_lines(&file, reader, &mut sink, |line| match color {
+            true => Ok(highlight_line(line, &mode, &expr)),
+            false => Ok(line.to_string()),
+        })
+    }
+
+    fn run_which_command(submatches: &ArgMatches) -> Result<()> {
+        let text = submatches.value_of("text").unwrap_or_default();
+        let mut rules: Vec<(String, String)> = vec![];
+
+        for expression in submatches.values_of("exprs").into_iter().flatten() {
+            rules.push((expression.to_string(), expression.to_string()));
+        }
+
+        for path in submatches.values_of("expr-file").into_iter().flatten() {
+            for (line, expression) in split_expressions(&fs::read_to_string(path)?) {
+                if !expression_is_empty(&expression) {
+                    rules.push((format!("{}:{}", path, line), expression));
+                }
+            }
+        }
+
+        // Without explicit rules the named pattern library is used, the
+        // built-in patterns have no tags or severity and are never selected
+        if rules.is_empty() {
+            let mut patterns = Patterns::load();
+            let selector = selector_from_matches(submatches);
+
+            for pattern in srch::Pattern::ALL.iter() {
+                if patterns.get(pattern.name()).is_none() && selector.is_none() {
+                    rules.push((
+                        pattern.as_keyword().to_string(),
+                        pattern.as_keyword().to_string(),
+                    ));
+                }
+            }
+
+            if let Some(selector) = &selector {
+                patterns.rules.retain(selector);
+            }
+
+            for (name, expression) in patterns.iter() {
+                rules.push((
+                    format!("{}{}", PATTERN_PREFIX, name),
+                    expression.to_string(),
+                ));
+            }
+        }
+
+        let matched = matching_rules(text, rules);
+
+        let stdout = io::stdout();
+        let mut sink = output::Plain::new(Box::new(stdout.lock()), output::LINES);
+
+        for rule in matched.iter() {
+            sink.record(rule)?;
+        }
+
+        sink.finish()?;
+
+        if matched.is_empty() {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    fn run_serve_command(submatches: &ArgMatches) -> Result<()> {
+        let host = submatches.value_of("host").unwrap_or_default();
+        let port = submatches
+            .value_of("port")
+            .and_then(|port| port.parse().ok())
+            .unwrap_or_default();
+
+        serve(host, port)
+    }
+
+    fn run_bench_command(submatches: &ArgMatches) -> Result<()> {
+        bench(submatches)
+    }
+
+    fn run_patterns_command(submatches: &ArgMatches) -> Result<()> {
+        if let Some(("list", submatches)) = submatches.subcommand() {
+            let mut patterns = Patterns::load();
+            let selector = selector_from_matches(submatches);
+
+            if let Some(selector) = &selector {
+                patterns.rules.retain(selector);
+            }
+
+            print_plain(
+                pattern_records(&patterns, selector.is_some()),
+                output::LINES,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn run_sample_command(submatches: &ArgMatches) -> Result<()> {
+        let source = submatches.value_of("expression").unwrap_or_default();
+        let input = submatches.value_of("input");
+
+        let count = |name| {
+            submatches
+                .value_of(name)
+                .and_then(|n| n.parse().ok())
+                .unwrap_or_default()
+        };
+
+        let sources = match resolve_patterns(vec![source.to_string()]) {
+            Ok(sources) => sources,
+            Err(err) => fail(err),
+        };
+
+        let expr = match srch::Expression::new(&sources.concat()) {
+            Ok(expr) => expr,
+            Err(err) => fail(err),
+        };
+
+        let (_, reader) = open_input(input, false, false)?;
+        let mut matched = Reservoir::new(count("matched"));
+        let mut unmatched = Reservoir::new(count("unmatched"));
+
+        for (position, line) in reader.lines().enumerate() {
+            let line = line?;
+
+            match expr.matches(&line) {
+                true => matched.offer(position, &line),
+                false => unmatched.offer(position, &line),
+            }
+        }
+
+        let suite = TestSuite {
+            cases: vec![TestCase {
+                name: input.map(|input| format!("samples of {}", input)),
+                expression: source.to_string(),
+                matches: matched.into_sample(),
+                rejects: unmatched.into_sample(),
+            }],
+        };
+
+        match submatches.value_of("out") {
+            Some(path) => fs::write(path, suite.to_toml()),
+            None => print_plain(vec![Record::message(suite.to_toml())], output::RAW),
+        }
+    }
+
+    fn run_doctor_command(_: &ArgMatches) -> Result<()> {
+        let diagnoses = diagnose();
+
+        print_plain(
+            diagnoses
+                .iter()
+                .map(|diagnosis| Record::message(diagnosis.to_string())),
+            output::LINES,
+        )?;
+
+        if diagnoses
+            .iter()
+            .any(|diagnosis| diagnosis.health == Health::Error)
+        {
+            std::process::exit(1);
+        }
+
+        Ok(())
+    }
+
+    match matches.subcommand() {
+        Some(("for", submatches)) => run_filter_command(submatches, false)?,
+        Some(("not", submatches)) => run_filter_command(submatches, true)?,
+        Some(("replace", submatches)) => run_replace_command(submatches)?,
+        Some(("convert", submatches)) => run_convert_command(submatches)?,
+        Some(("describe", submatches)) => run_describe_command(submatches)?,
+        Some(("lint", submatches)) => run_lint_command(submatches)?,
+        Some(("diff", submatches)) => run_diff_command(submatches)?,
+        Some(("check", submatches)) => run_check_command(submatches)?,
+        Some(("which", submatches)) => run_which_command(submatches)?,
+        Some(("serve", submatches)) => run_serve_command(submatches)?,
+        Some(("highlight", submatches)) => run_highlight_command(submatches)?,
+        Some(("wizard", submatches)) => run_wizard_command(submatches)?,
+        Some(("bench", submatches)) => run_bench_command(submatches)?,
+        Some(("patterns", submatches)) => run_patterns_command(submatches)?,
+        Some(("doctor", submatches)) => run_doctor_command(submatches)?,
+        Some(("sample", submatches)) => run_sample_command(submatches)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod template {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn record() -> Record {
+            Record {
+                file: "foo.txt".into(),
+                line: 42,
+                text: "bar".to_string(),
+                document: None,
+            }
+        }
+
+        #[test]
+        fn renders_placeholders() {
+            let template = Template::parse("{file}:{line}: {text}").unwrap();
+            assert_eq!(template.render(&record()), "foo.txt:42: bar");
+        }
+
+        #[test]
+        fn renders_escaped_braces() {
+            let template = Template::parse("{{{text}}}").unwrap();
+            assert_eq!(template.render(&record()), "{bar}");
+        }
+
+        #[test]
+        fn rejects_unknown_placeholders() {
+            assert!(Template::parse("{foo}").is_err());
+        }
+
+        #[test]
+        fn rejects_unclosed_placeholders() {
+            assert!(Template::parse("{text").is_err());
+        }
+    }
+
+    mod output {
+        use super::*;
+        use crate::output::{
+            Count, Json, JsonLines, Layout, OutputSink, Plain, Quiet, Templated, LINES, RAW,
+        };
+        use pretty_assertions::assert_eq;
+
+        fn records() -> Vec<Record> {
+            [
+                ("a.txt", 1, "x"),
+                ("a.txt", 3, "say \"y\""),
+                ("b.txt", 2, "z"),
+            ]
+            .iter()
+            .map(|(file, line, text)| Record {
+                file: (*file).into(),
+                line: *line,
+                text: text.to_string(),
+                document: None,
+            })
+            .collect()
+        }
+
+        fn layout() -> Layout {
+            Layout {
+                heading: false,
+                file_names: false,
+                line_numbers: false,
+                terminator: "\n",
+            }
+        }
+
+        fn print<F>(sink: F) -> String
+        where
+            F: for<'a> FnOnce(Box<dyn Write + 'a>) -> Box<dyn OutputSink + 'a>,
+        {
+            print_records(records(), sink)
+        }
+
+        fn print_records<F>(records: Vec<Record>, sink: F) -> String
+        where
+            F: for<'a> FnOnce(Box<dyn Write + 'a>) -> Box<dyn OutputSink + 'a>,
+        {
+            let mut out = Vec::new();
+            let mut sink = sink(Box::new(&mut out));
+
+            for record in records {
+                sink.record(&record).unwrap();
+            }
+
+            sink.finish().unwrap();
+            drop(sink);
+
+            String::from_utf8(out).unwrap()
+        }
+
+        #[test]
+        fn prints_plain_text() {
+            let layout = Layout {
+                file_names: true,
+                line_numbers: true,
+                ..layout()
+            };
+
+            assert_eq!(
+                print(|out| Box::new(Plain::new(out, layout))),
+                "a.txt:1:x\na.txt:3:say \"y\"\nb.txt:2:z\n"
+            );
+        }
+
+        #[test]
+        fn groups_records_under_headings() {
+            let layout = Layout {
+                heading: true,
+                terminator: "\0",
+                ..layout()
+            };
+            let template = Template::parse("{line}: {text}").unwrap();
+
+            assert_eq!(
+                print(|out| Box::new(Templated::new(out, template, layout))),
+                "a.txt\n1: x\u{0}3: say \"y\"\u{0}\nb.txt\n2: z\u{0}"
+            );
+        }
+
+        #[test]
+        fn prints_json() {
+            assert_eq!(
+                print(|out| Box::new(JsonLines::new(out))),
+                concat!(
+                    "{\"file\":\"a.txt\",\"line\":1,\"text\":\"x\"}\n",
+                    "{\"file\":\"a.txt\",\"line\":3,\"text\":\"say \\\"y\\\"\"}\n",
+                    "{\"file\":\"b.txt\",\"line\":2,\"text\":\"z\"}\n",
+                )
+            );
+
+            let json = print(|out| Box::new(Json::new(out)));
+            let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(parsed[1]["text"], "say \"y\"");
+            assert_eq!(parsed.as_array().map(Vec::len), Some(3));
+        }
+
+        #[test]
+        fn counts_records() {
+            assert_eq!(print(|out| Box::new(Count::new(out))), "3\n");
+        }
+
+        #[test]
+        fn prints_lint_findings() {
+            let findings = lint_records(
+                "rules.te",
+                "numeric\n\nlength 3 and length 5\n\nnumeric and\n",
+            );
+            let template = Template::parse("{file}:{line}: {text}").unwrap();
+
+            assert_eq!(
+                print_records(findings, |out| Box::new(Templated::new(out, template, LINES))),
+                concat!(
+                    "rules.te:3: warning: contradiction: `length 3 and length 5` can never match: `length 3` and `length 5` can never match together\n",
+                    "rules.te:5: error: syntax error: expected a query\n",
+                )
+            );
+        }
+
+        #[test]
+        fn prints_matching_rules() {
+            let rules = vec![
+                ("zip".to_string(), "numeric and length 5".to_string()),
+                ("word".to_string(), "alpha".to_string()),
+                ("@ipv4".to_string(), "@ipv4".to_string()),
+            ];
+
+            assert_eq!(
+                print_records(matching_rules("12345", rules), |out| Box::new(Plain::new(
+                    out, LINES
+                ))),
+                "zip\n"
+            );
+        }
+
+        #[test]
+        fn prints_descriptions() {
+            let description = srch::Expression::new("numeric").unwrap().describe();
+
+            assert_eq!(
+                print_records(vec![Record::message(description)], |out| Box::new(
+                    Plain::new(out, LINES)
+                )),
+                "matches strings that consist only of digits\n"
+            );
+        }
+
+        fn print_plain(records: Vec<Record>) -> String {
+            print_records(records, |out| Box::new(Plain::new(out, LINES)))
+        }
+
+        #[test]
+        fn prints_diffs_of_expressions() {
+            let old = DiffInput::Expression(srch::Expression::new("numeric").unwrap());
+            let new = DiffInput::Expression(srch::Expression::new("numeric or alpha").unwrap());
+            let (records, changed) = diff_records(old, new, "old.te", "new.te").unwrap();
+
+            assert_eq!(changed, true);
+            assert_eq!(
+                print_plain(records),
+                "strictly broader\n  only new.te: \"x\"\n"
+            );
+        }
+
+        #[test]
+        fn prints_diffs_of_rule_sets() {
+            let old = srch::RuleSet::parse("zip = 'numeric and length 5'\nid = 'numeric'").unwrap();
+            let new =
+                srch::RuleSet::parse("zip = 'numeric'\nid = 'numeric'\nname = 'alpha'").unwrap();
+            let (records, changed) = diff_records(
+                DiffInput::RuleSet(old),
+                DiffInput::RuleSet(new),
+                "old.toml",
+                "new.toml",
+            )
+            .unwrap();
+
+            assert_eq!(changed, true);
+            assert_eq!(
+                print_plain(records),
+                "name: added\nzip: strictly broader\n  only new.toml: \"1111\"\n"
+            );
+        }
+
+        #[test]
+        fn only_compares_inputs_of_the_same_kind() {
+            let old = DiffInput::Expression(srch::Expression::new("numeric").unwrap());
+            let new = DiffInput::RuleSet(srch::RuleSet::parse("id = 'numeric'").unwrap());
+
+            assert!(diff_records(old, new, "old.te", "new.toml").is_none());
+        }
+
+        #[test]
+        fn prints_check_results() {
+            let suite = TestSuite::parse(
+                "[[case]]\nexpression = 'numeric'\nmatches = ['1']\n\n[[case]]\nname = 'letters'\nexpression = 'alpha'\nmatches = ['a1']\nrejects = ['b']\n\n[[case]]\nexpression = 'alpha and'\n",
+            )
+            .unwrap();
+            let (records, failed) = check_records(&suite);
+
+            assert_eq!(failed, true);
+            assert_eq!(
+                print_plain(records),
+                concat!(
+                    "FAIL letters: expected \"a1\" to match\n",
+                    "FAIL letters: expected \"b\" to be rejected\n",
+                    "FAIL alpha and: syntax error: expected a query\n",
+                    "1 passed, 2 failed\n",
+                )
+            );
+        }
+
+        #[test]
+        fn fails_cases_with_unknown_patterns() {
+            let suite = TestSuite::parse(
+                "[[case]]\nexpression = '@no-such-pattern'\nmatches = ['1']\n\n[[case]]\nexpression = 'numeric'\nmatches = ['1']\n",
+            )
+            .unwrap();
+            let (records, failed) = check_records(&suite);
+
+            assert_eq!(failed, true);
+            assert_eq!(
+                print_plain(records),
+                concat!(
+                    "FAIL @no-such-pattern: unknown pattern \"@no-such-pattern\"\n",
+                    "1 passed, 1 failed\n",
+                )
+            );
+        }
+
+        #[test]
+        fn prints_selected_patterns() {
+            let patterns = Patterns::parse(
+                "version = 1\n[rules.five-digits]\nexpression = 'numeric and length 5'\ntags = ['pii']\n",
+            )
+            .unwrap();
+
+            assert_eq!(
+                print_plain(pattern_records(&patterns, true)),
+                "@five-digits = numeric and length 5 # tags: pii\n"
+            );
+        }
+
+        fn print_mapped<F>(input: &str, map: F) -> String
+        where
+            F: Fn(&str) -> io::Result<String>,
+        {
+            let mut out = Vec::new();
+            let mut sink = Plain::new(Box::new(&mut out), RAW);
+
+            print_lines(&"a.txt".into(), input.as_bytes(), &mut sink, map).unwrap();
+            drop(sink);
+
+            String::from_utf8(out).unwrap()
+        }
+
+        #[test]
+        fn prints_highlighted_lines_with_their_endings() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            let mode = Mode::Word(Default::default());
+
+            assert_eq!(
+                print_mapped("a 1\r\nb\n2", |line| Ok(highlight_line(line, &mode, &expr))),
+                "a \x1b[1;31m1\x1b[0m\r\nb\n\x1b[1;31m2\x1b[0m"
+            );
+        }
+
+        #[test]
+        fn prints_replaced_lines_with_their_endings() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            let replacement = Replacement::parse("N", false).unwrap();
+
+            let replaced = print_mapped("1\r\nb\n2\n", |line| match expr.matches(line) {
+                true => Ok(replacement.render(line, &expr).unwrap()),
+                false => Ok(line.to_string()),
+            });
+
+            assert_eq!(replaced, "N\r\nb\nN\n");
+        }
+
+        #[test]
+        fn succeeds_quietly_if_anything_matched() {
+            let mut quiet = Quiet::default();
+            assert_eq!(quiet.succeeded(), false);
+
+            quiet.record(&records()[0]).unwrap();
+            quiet.finish().unwrap();
+            assert_eq!(quiet.succeeded(), true);
+        }
+    }
+
+    mod squeeze {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn collapses_runs_of_identical_matches() {
+            let items: Items = Box::new(["a", "a", "b", "a", "a", "a"].iter().enumerate().map(
+                |(line, text)| {
+                    Ok(Record {
+                        file: "-".into(),
+                        line: line + 1,
+                        text: text.to_string(),
+                        document: None,
+                    })
+                },
+            ));
+
+            let squeezed = squeeze(items)
+                .map(|item| item.map(|item| (item.line, item.text)))
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+
+            assert_eq!(
+                squeezed,
+                vec![
+                    (1, "      2 a".to_string()),
+                    (3, "      1 b".to_string()),
+                    (4, "      3 a".to_string())
+                ]
+            );
+        }
+    }
+
+    mod only_matches {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn replaces_records_with_their_matches() {
+            let items: Items = Box::new(["id 12 and 345", "none", "ABC"].iter().enumerate().map(
+                |(line, text)| {
+                    Ok(Record {
+                        file: "-".into(),
+                        line: line + 1,
+                        text: text.to_string(),
+                        document: None,
+                    })
+                },
+            ));
+
+            let sources = ["numeric", "equals \"abc\""];
+            let expr = Rc::new(srch::ExpressionSet::new(sources, srch::Policy::Any).unwrap());
+            let matches = only_matches(items, expr, true)
+                .map(|item| item.map(|item| (item.line, item.text)))
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+
+            assert_eq!(
+                matches,
+                vec![
+                    (1, "12".to_string()),
+                    (1, "345".to_string()),
+                    (3, "ABC".to_string())
+                ]
+            );
+        }
+    }
+
+    mod summary {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn counts_matches_per_expression() {
+            let expr = srch::ExpressionSet::new(["numeric", "length 2"], srch::Policy::Any);
+            let labels = vec!["numeric".to_string(), "@short".to_string()];
+            let summary = Summary::new(labels, Rc::new(expr.unwrap()));
+
+            for text in ["1", "12", "ab", "abc"].iter() {
+                summary.count(text);
+            }
+
+            let mut out = Vec::new();
+            summary.print(&mut out).unwrap();
+
+            assert_eq!(String::from_utf8(out).unwrap(), "2 numeric\n2 @short\n");
+        }
+    }
+
+    mod doctor {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn health(diagnoses: Vec<Diagnosis>) -> Vec<Health> {
+            diagnoses
+                .into_iter()
+                .map(|diagnosis| diagnosis.health)
+                .collect()
+        }
+
+        #[test]
+        fn checks_the_terminal() {
+            assert_eq!(diagnose_terminal(true, Some("xterm")).health, Health::Ok);
+            assert_eq!(
+                diagnose_terminal(true, Some("dumb")).health,
+                Health::Warning
+            );
+            assert_eq!(
+                diagnose_terminal(false, Some("xterm")).health,
+                Health::Warning
+            );
+        }
+
+        #[test]
+        fn checks_the_locale() {
+            assert_eq!(
+                health(diagnose_locale(Some("de_DE.UTF-8"))),
+                vec![Health::Ok, Health::Ok]
+            );
+            assert_eq!(
+                health(diagnose_locale(Some("fr_FR.ISO-8859-1"))),
+                vec![Health::Warning, Health::Warning]
+            );
+            assert_eq!(health(diagnose_locale(None)), vec![Health::Warning]);
+        }
+
+        #[test]
+        fn checks_the_pattern_library() {
+            let path = Path::new("patterns.toml");
+            let missing = io::Error::from(io::ErrorKind::NotFound);
+
+            assert_eq!(
+                diagnose_patterns(path, Ok("id = 'numeric'".to_string())).message,
+                "patterns.toml defines 1 pattern(s)"
+            );
+            assert_eq!(
+                diagnose_patterns(path, Ok("id = 'and'".to_string())).health,
+                Health::Error
+            );
+            assert_eq!(diagnose_patterns(path, Err(missing)).health, Health::Ok);
+        }
+
+        #[test]
+        fn checks_the_catalog() {
+            let path = Path::new("catalog.toml");
+
+            assert_eq!(
+                diagnose_catalog(path, Ok("\"error.syntax\" = \"{0}\"".to_string())).health,
+                Health::Ok
+            );
+            assert_eq!(
+                diagnose_catalog(path, Ok("\"error.syntax\" = 1".to_string())).health,
+                Health::Error
+            );
+        }
+    }
+
+    mod query_stats {
+        use super::*;
+        use pretty_assertions::assert_eq;
 
-use std::fs::File;
-use std::io::{self, Read, Result};
+        #[test]
+        fn groups_queries_by_expression() {
+            let expr =
+                srch::ExpressionSet::new(["numeric and length 2", "alpha"], srch::Policy::Any)
+                    .unwrap()
+                    .with_stats();
 
-use clap::{App, Arg, ArgGroup, ArgMatches, ValueHint};
+            for text in ["12", "123", "ab", "a1"].iter() {
+                expr.matches(text);
+            }
 
-const NAME: &'static str = env!("CARGO_PKG_NAME");
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
-const AUTHOR: &'static str = env!("CARGO_PKG_AUTHORS");
-const DESCRIPTION: &'static str = env!("CARGO_PKG_DESCRIPTION");
+            let labels = vec!["numeric and length 2".to_string(), "alpha".to_string()];
+            let mut out = Vec::new();
+            print_query_stats(&mut out, &labels, &expr.stats()).unwrap();
 
-fn read_stdin() -> io::Result<String> {
-    let mut buffer = String::new();
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                concat!(
+                    "numeric and length 2:\n",
+                    "3 evaluated 1 matched  numeric\n",
+                    "4 evaluated 3 matched  length 2\n",
+                    "alpha:\n",
+                    "3 evaluated 1 matched  alpha\n"
+                )
+            );
+        }
+    }
 
-    io::stdin().read_to_string(&mut buffer)?;
+    mod replace_words {
+        use super::*;
+        use pretty_assertions::assert_eq;
 
-    Ok(buffer)
-}
+        fn replacement(source: &str) -> Replacement {
+            Replacement::parse(source, true).unwrap()
+        }
 
-fn read_file(path: &str) -> Result<String> {
-    let mut file = File::open(path)?;
-    let mut contents = String::new();
+        #[test]
+        fn replaces_matching_words_only() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            assert_eq!(
+                replace_words("foo 123 bar", Default::default(), &expr, &replacement("N")).unwrap(),
+                "foo N bar"
+            );
+        }
 
-    file.read_to_string(&mut contents)?;
+        #[test]
+        fn preserves_whitespace() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            assert_eq!(
+                replace_words(
+                    "  1\t foo  2 ",
+                    Default::default(),
+                    &expr,
+                    &replacement("N")
+                )
+                .unwrap(),
+                "  N\t foo  N "
+            );
+        }
 
-    Ok(contents)
-}
+        #[test]
+        fn preserves_stripped_punctuation() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            let tokenizer = srch::Tokenizer {
+                strip_punctuation: true,
+                ..Default::default()
+            };
 
-fn read_input_from_matches(matches: &ArgMatches) -> io::Result<Vec<String>> {
-    let input = match matches.value_of("input") {
-        Some(path) => read_file(path)?,
-        None => read_stdin()?,
-    };
+            assert_eq!(
+                replace_words("(1) foo, 2.", tokenizer, &expr, &replacement("N")).unwrap(),
+                "(N) foo, N."
+            );
+        }
 
-    let items = match matches.value_of("mode") {
-        Some("line") => input.lines().map(|x| x.to_string()).collect(),
-        Some("word") => input
-            .split_ascii_whitespace()
-            .map(|x| x.to_string())
-            .collect(),
-        Some(_) | None => vec![],
-    };
+        #[test]
+        fn renders_references() {
+            let expr =
+                srch::ExpressionSet::new(["starts \"id-\" and ends \"!\""], srch::Policy::Any)
+                    .unwrap();
 
-    Ok(items)
-}
+            assert_eq!(
+                replacement("[$0] $2${1}1 $$").render("id-7!", &expr),
+                Ok("[id-7!] !id-1 $".to_string())
+            );
+        }
 
-// TODO: Add a "split" command to split text at certain chars
+        #[test]
+        fn rejects_references_to_missing_parts() {
+            let expr = srch::ExpressionSet::new(["starts \"id-\""], srch::Policy::Any).unwrap();
 
-fn build_cli() -> App<'static> {
-    fn build_subcommand(name: &'static str, descr: &'static str) -> App<'static> {
-        App::new(name)
-            .version(VERSION)
-            .author(AUTHOR)
-            .about(descr)
-            .arg(
-                Arg::new("mode")
-                    .short('m')
-                    .long("mode")
-                    .help("Sets the operation mode")
-                    .takes_value(true)
-                    .value_name("MODE")
-                    .default_value("line")
-                    .possible_values(&["line", "word"]),
-            )
-            .arg(
-                Arg::new("expression")
-                    .help("The text expression used to determine matches")
-                    .takes_value(true)
-                    .value_name("EXPRESSION")
-                    .value_hint(ValueHint::Other)
-                    .required(true)
-                    .index(1),
-            )
-            .arg(
-                Arg::new("input")
-                    .help("The path to the input file to use")
-                    .takes_value(true)
-                    .value_name("FILE")
-                    .value_hint(ValueHint::FilePath)
-                    .index(2),
-            )
-            .arg(
-                Arg::new("first")
-                    .short('f')
-                    .long("first")
-                    .conflicts_with_all(&["last"])
-                    .help("Only use first match")
-                    .display_order(1),
-            )
-            .arg(
-                Arg::new("skip")
-                    .short('s')
-                    .long("skip")
-                    .takes_value(true)
-                    .value_name("n")
-                    .value_hint(ValueHint::Other)
-                    .conflicts_with_all(&["first", "last"])
-                    .help("Skip n matches first match")
-                    .display_order(1),
+            assert_eq!(
+                replacement("$2").render("id-7", &expr),
+                Err(
+                    "the replacement references $2, but the match \"id-7\" only has 1 parts"
+                        .to_string()
+                )
+            );
+        }
+
+        #[test]
+        fn keeps_dollars_literal_without_references() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            let replacement = Replacement::parse("$5 $ $$", false).unwrap();
+
+            assert_eq!(replacement.render("12", &expr), Ok("$5 $ $$".to_string()));
+        }
+
+        #[test]
+        fn rejects_invalid_references() {
+            assert!(Replacement::parse("$", true).is_err());
+            assert!(Replacement::parse("$a", true).is_err());
+            assert!(Replacement::parse("${1", true).is_err());
+            assert!(Replacement::parse("${}", true).is_err());
+        }
+
+        #[test]
+        fn highlights_matching_words() {
+            let expr = srch::ExpressionSet::new(["numeric"], srch::Policy::Any).unwrap();
+            assert_eq!(
+                highlight_words("foo 1", Default::default(), &expr),
+                "foo \x1b[1;31m1\x1b[0m"
+            );
+        }
+    }
+
+    mod patterns {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn parses_named_expressions() {
+            let patterns = Patterns::parse("five-digits = 'numeric and length 5'").unwrap();
+            assert_eq!(patterns.get("five-digits"), Some("numeric and length 5"));
+            assert_eq!(patterns.get("unknown"), None);
+        }
+
+        #[test]
+        fn rejects_non_string_patterns() {
+            assert!(Patterns::parse("five = 5").is_err());
+        }
+
+        #[test]
+        fn parses_versioned_rule_sets() {
+            let patterns = Patterns::parse(
+                "version = 1\n[rules.five-digits]\nexpression = 'numeric and length 5'",
             )
-            .arg(
-                Arg::new("last")
-                    .short('l')
-                    .long("last")
-                    .conflicts_with_all(&["first"])
-                    .help("Only use last match")
-                    .display_order(1),
+            .unwrap();
+            assert_eq!(patterns.get("five-digits"), Some("numeric and length 5"));
+        }
+
+        #[test]
+        fn rejects_invalid_patterns() {
+            assert!(Patterns::parse("five-digits = 'numeric and'").is_err());
+        }
+
+        #[test]
+        fn finds_references_outside_of_strings_and_comments() {
+            let source = "@a and contains \"@b\" # @c\nor @d-e";
+            let names = pattern_references(source)
+                .into_iter()
+                .map(|name| &source[name])
+                .collect::<Vec<_>>();
+
+            assert_eq!(names, vec!["a", "d-e"]);
+        }
+
+        #[test]
+        fn resolves_references_inside_of_expressions() {
+            let mut patterns = Some(
+                Patterns::parse("test-errs = 'contains \"error\" or contains \"fatal\"'").unwrap(),
+            );
+            let source = resolve_references(
+                "@test-errs and length 5 or @email".to_string(),
+                &mut patterns,
             )
-            .group(
-                ArgGroup::new("advanced")
-                    .arg("first")
-                    .arg("last")
-                    .arg("skip"),
+            .unwrap();
+
+            assert_eq!(source, "@test_errs and length 5 or @email");
+
+            let expr = srch::Expression::new(&source).unwrap();
+            assert_eq!(expr.matches("error"), true);
+            assert_eq!(expr.matches("fatal"), true);
+            assert_eq!(expr.matches("xerror"), false);
+        }
+
+        #[test]
+        fn rejects_patterns_referenced_by_the_same_name() {
+            let mut patterns = Some(
+                Patterns::parse(
+                    "clashing-digits = 'numeric'\nclashing_digits = 'numeric and length 5'\nClashing-Digits = 'alpha'",
+                )
+                .unwrap(),
+            );
+
+            let source =
+                resolve_references("@clashing-digits and length 3".to_string(), &mut patterns);
+            assert_eq!(source, Ok("@clashing_digits and length 3".to_string()));
+
+            for name in ["clashing_digits", "Clashing-Digits"].iter() {
+                let source = resolve_references(format!("@{} and length 3", name), &mut patterns);
+
+                assert_eq!(
+                    source,
+                    Err(format!(
+                        "the patterns \"@clashing-digits\" and \"@{}\" can't both be used inside of an expression, both are referenced as \"clashing_digits\"",
+                        name
+                    ))
+                );
+            }
+        }
+    }
+
+    mod catalog {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn parses_message_templates() {
+            let catalog =
+                parse_catalog("\"parser.expected_query\" = 'a query is missing'").unwrap();
+            assert_eq!(
+                catalog.get("parser.expected_query").map(String::as_str),
+                Some("a query is missing")
+            );
+        }
+
+        #[test]
+        fn rejects_non_string_templates() {
+            assert!(parse_catalog("key = 5").is_err());
+        }
+    }
+
+    mod command {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn splits_at_whitespace() {
+            assert_eq!(
+                Command::split("mv  {text} /tmp/").unwrap(),
+                vec!["mv", "{text}", "/tmp/"]
+            );
+        }
+
+        #[test]
+        fn keeps_quoted_arguments_together() {
+            assert_eq!(
+                Command::split("echo 'a b' \"c d\" ''").unwrap(),
+                vec!["echo", "a b", "c d", ""]
+            );
+        }
+
+        #[test]
+        fn rejects_unclosed_quotes() {
+            assert!(Command::split("echo 'a b").is_err());
+        }
+
+        #[test]
+        fn rejects_empty_commands() {
+            assert!(Command::parse("  ").is_err());
+        }
+    }
+
+    mod split_expressions {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn splits_at_blank_lines() {
+            assert_eq!(
+                split_expressions("numeric\n  or alpha\n\n\n# comment\nlength 5\n"),
+                vec![
+                    (1, "numeric\n  or alpha".to_string()),
+                    (5, "# comment\nlength 5".to_string())
+                ]
+            );
+        }
+    }
+
+    mod test_suite {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn parses_cases() {
+            let suite = TestSuite::parse(
+                "[[case]]\nexpression = 'numeric'\nmatches = ['1']\nrejects = ['a']\n\n[[case]]\nname = 'letters'\nexpression = 'alpha'\n",
             )
+            .unwrap();
+
+            assert_eq!(
+                suite.cases,
+                vec![
+                    TestCase {
+                        name: None,
+                        expression: "numeric".to_string(),
+                        matches: vec!["1".to_string()],
+                        rejects: vec!["a".to_string()],
+                    },
+                    TestCase {
+                        name: Some("letters".to_string()),
+                        expression: "alpha".to_string(),
+                        ..TestCase::default()
+                    }
+                ]
+            );
+        }
+
+        #[test]
+        fn requires_an_expression() {
+            assert!(TestSuite::parse("[[case]]\nmatches = ['1']\n").is_err());
+        }
+
+        #[test]
+        fn writes_parseable_suites() {
+            let suite = TestSuite {
+                cases: vec![
+                    TestCase {
+                        name: Some("quotes".to_string()),
+                        expression: "contains \"'\"".to_string(),
+                        matches: vec!["it's".to_string(), "\"'\"".to_string()],
+                        rejects: vec![],
+                    },
+                    TestCase {
+                        expression: "numeric".to_string(),
+                        rejects: vec!["a\\b".to_string()],
+                        ..TestCase::default()
+                    },
+                ],
+            };
+
+            assert_eq!(TestSuite::parse(&suite.to_toml()).unwrap(), suite);
+        }
     }
 
-    App::new(NAME)
-        .version(VERSION)
-        .author(AUTHOR)
-        .about(DESCRIPTION)
-        .subcommand(build_subcommand("for", "Filter a text and print matches"))
-        .subcommand(build_subcommand("not", "Ignore matches and print the rest"))
-        .subcommand(build_subcommand(
-            "replace",
-            "Replace matches with a given string",
-        ))
-    // .subcommand(build_subcommand("exec", "Execute a given expression against a test string"))
-}
+    mod reservoir {
+        use super::*;
+        use pretty_assertions::assert_eq;
 
-fn main() -> io::Result<()> {
-    let matches = build_cli().get_matches();
+        #[test]
+        fn keeps_everything_below_its_size() {
+            let mut reservoir = Reservoir::new(3);
 
-    fn run_filter_command(submatches: &ArgMatches, invert_matches: bool) -> Result<()> {
-        let expression = submatches.value_of("expression").unwrap_or_default();
-        let input = read_input_from_matches(&submatches)?;
+            for (position, record) in ["a", "b", "a"].iter().enumerate() {
+                reservoir.offer(position, record);
+            }
+
+            assert_eq!(reservoir.into_sample(), vec!["a", "b"]);
+        }
 
-        let expr = match srch::Expression::new(&expression.to_owned()) {
-            Ok(ast) => ast,
-            Err(_) => {
-                println!("Seems like you've provided an invalid text expression!");
-                println!("Please head over to the text expression documentation:");
-                println!("\nhttps://docs.rs/sel/");
-                std::process::exit(1);
+        #[test]
+        fn samples_distinct_records_in_order() {
+            let mut reservoir = Reservoir::new(10);
+
+            for position in 0..1_000 {
+                reservoir.offer(position, &position.to_string());
             }
-        };
 
-        let result = {
-            let iter = input.iter();
-            let filtered = iter.filter(|x| {
-                let is_match = expr.matches(&x.to_string());
+            let sample = reservoir
+                .into_sample()
+                .iter()
+                .map(|record| record.parse::<usize>().unwrap())
+                .collect::<Vec<_>>();
 
-                match invert_matches {
-                    true => !is_match,
-                    false => is_match,
-                }
+            assert_eq!(sample.len(), 10);
+            assert!(sample.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(sample.iter().any(|&position| position >= 500));
+        }
+    }
+
+    mod wizard {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn build(answers: &str) -> Option<String> {
+            Wizard::new(answers.as_bytes(), io::sink()).build().unwrap()
+        }
+
+        #[test]
+        fn builds_expression_from_answers() {
+            assert_eq!(
+                build("foo\n\n\n5\nalpha\n"),
+                Some("starts \"foo\" and length 5 and alpha".to_string())
+            );
+        }
+
+        #[test]
+        fn asks_again_on_invalid_answers() {
+            assert_eq!(
+                build("\n\na\"b\nab\nfive\n5\nletters\nnumeric\n"),
+                Some("contains \"ab\" and length 5 and numeric".to_string())
+            );
+        }
+
+        #[test]
+        fn builds_nothing_without_answers() {
+            assert_eq!(build(""), None);
+        }
+    }
+
+    mod modes {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn record(line: usize, text: &str) -> Record {
+            Record {
+                file: STDIN_NAME.into(),
+                line,
+                text: text.to_string(),
+                document: None,
+            }
+        }
+
+        fn paragraphs(text: &str) -> Vec<Record> {
+            let lines = text
+                .lines()
+                .enumerate()
+                .map(|(n, line)| Ok(record(n + 1, line)))
+                .collect::<Vec<_>>();
+
+            Paragraphs::new(Box::new(lines.into_iter()))
+                .collect::<io::Result<_>>()
+                .unwrap()
+        }
+
+        #[test]
+        fn splits_paragraphs_on_blank_lines() {
+            assert_eq!(
+                paragraphs("\nfoo\nbar\n\n  \nbaz\n"),
+                vec![record(2, "foo\nbar"), record(6, "baz")]
+            );
+        }
+
+        #[test]
+        fn splits_sentences() {
+            assert_eq!(
+                sentences(&record(3, "One. Two is\nlonger! Three? v1.2 is\nout")),
+                vec![
+                    record(3, "One."),
+                    record(3, "Two is longer!"),
+                    record(4, "Three?"),
+                    record(4, "v1.2 is out"),
+                ]
+            );
+        }
+    }
+
+    mod delimited {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn records(input: &str, delimiter: &str) -> Vec<String> {
+            let delimiter = unescape(delimiter).unwrap().into_bytes();
+
+            Delimited::new(input.as_bytes(), delimiter)
+                .collect::<io::Result<_>>()
+                .unwrap()
+        }
+
+        #[test]
+        fn splits_on_delimiter() {
+            assert_eq!(records("a;b;;c\n", ";"), vec!["a", "b", "", "c"]);
+        }
+
+        #[test]
+        fn splits_on_escaped_delimiters() {
+            assert_eq!(records("a\0b\0", "\\0"), vec!["a", "b"]);
+            assert_eq!(records("a\tb", "\\t"), vec!["a", "b"]);
+        }
+
+        #[test]
+        fn splits_on_multi_byte_delimiters() {
+            assert_eq!(records("a||b|c||", "||"), vec!["a", "b|c"]);
+        }
+
+        #[test]
+        fn strips_carriage_returns_of_lines() {
+            assert_eq!(records("a\r\nb\rc\r\n", "\\n"), vec!["a", "b\rc"]);
+            assert_eq!(records("a\r\n", ";"), vec!["a"]);
+            assert_eq!(records("a\r;b", ";"), vec!["a\r", "b"]);
+        }
+
+        #[test]
+        fn rejects_unknown_escapes() {
+            assert!(unescape("\\x").is_err());
+            assert!(unescape("\\").is_err());
+        }
+
+        fn limited(input: &str, max: usize, overlong: Overlong) -> Vec<Option<String>> {
+            Delimited::new(input.as_bytes(), b";".to_vec())
+                .with_limit(Some(Limit { max, overlong }))
+                .map(|record| match record {
+                    Ok(record) => Some(record),
+                    Err(err) => {
+                        assert!(err.get_ref().unwrap().is::<RecordTooLong>());
+                        None
+                    }
+                })
+                .collect()
+        }
+
+        #[test]
+        fn fails_on_overlong_records() {
+            assert_eq!(
+                limited("ab;abcdef;abc", 3, Overlong::Error),
+                vec![Some("ab".to_string()), None, Some("abc".to_string())]
+            );
+        }
+
+        #[test]
+        fn truncates_overlong_records() {
+            assert_eq!(
+                limited("abcdefgh;a;abcd\n", 3, Overlong::Truncate),
+                vec![
+                    Some("abc".to_string()),
+                    Some("a".to_string()),
+                    Some("abc".to_string())
+                ]
+            );
+        }
+
+        #[test]
+        fn truncates_at_character_boundaries() {
+            assert_eq!(
+                limited("aéé;b", 2, Overlong::Truncate),
+                vec![Some("a".to_string()), Some("b".to_string())]
+            );
+        }
+
+        #[test]
+        fn discards_overlong_records_larger_than_a_chunk() {
+            let input = format!("{};b", "a".repeat(DISCARD_CHUNK as usize * 3));
+            assert_eq!(
+                limited(&input, 1, Overlong::Skip),
+                vec![None, Some("b".to_string())]
+            );
+        }
+    }
+
+    mod file_list {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn reads_lines() {
+            assert_eq!(
+                read_file_list("a.txt\r\n\nb c.txt\n".as_bytes(), b'\n').unwrap(),
+                vec!["a.txt", "b c.txt"]
+            );
+        }
+
+        #[test]
+        fn reads_nul_separated() {
+            assert_eq!(
+                read_file_list("a\nb.txt\0c.txt\0".as_bytes(), b'\0').unwrap(),
+                vec!["a\nb.txt", "c.txt"]
+            );
+        }
+    }
+
+    mod expression_files {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn reads_directories_sorted_by_name() {
+            let dir = std::env::temp_dir().join(format!("srch-rules-{}", std::process::id()));
+            fs::create_dir_all(dir.join("nested")).unwrap();
+            fs::write(dir.join("b.te"), "alpha").unwrap();
+            fs::write(dir.join("a.te"), "numeric").unwrap();
+
+            let files = expression_files(dir.to_str().unwrap());
+            fs::remove_dir_all(&dir).unwrap();
+
+            assert_eq!(files.unwrap(), vec![dir.join("a.te"), dir.join("b.te")]);
+            assert_eq!(
+                expression_files("rule.te").unwrap(),
+                vec![PathBuf::from("rule.te")]
+            );
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    mod yaml {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn reads_scalars_at_key() {
+            let source = "a:\n  b: foo\n---\na:\n  b: [1, 2]\n---\nc: bar\n";
+            let texts = |key| {
+                yaml_records(&STDIN_NAME.into(), source, key)
+                    .unwrap()
+                    .into_iter()
+                    .map(|record| (record.line, record.text))
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(texts("a.b"), vec![(1, "foo".to_string())]);
+            assert_eq!(texts("a.b.1"), vec![(2, "2".to_string())]);
+            assert_eq!(texts("c"), vec![(3, "bar".to_string())]);
+        }
+    }
+
+    mod in_place {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        #[test]
+        fn keeps_the_line_endings_of_every_line() {
+            let path = std::env::temp_dir().join(format!("srch-mixed-{}.txt", std::process::id()));
+            fs::write(&path, "x1\r\na\nx2\nx3\r\nx4").unwrap();
+
+            let reader = BufReader::new(File::open(&path).unwrap());
+            let result = write_atomically(&path, None, |out| {
+                for_each_line(reader, |line, ending| {
+                    write!(out, "{}{}", line.to_uppercase(), ending)
+                })
             });
 
-            filtered.map(|s| &**s).collect::<Vec<&str>>().join("\n")
-        };
+            let written = fs::read_to_string(&path);
+            fs::remove_file(&path).unwrap();
 
-        if !result.is_empty() {
-            println!("{}", result);
+            result.unwrap();
+            assert_eq!(written.unwrap(), "X1\r\nA\nX2\nX3\r\nX4");
         }
+    }
 
-        Ok(())
+    mod unified_diff {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        fn upper(line: &str) -> io::Result<String> {
+            match line.starts_with('x') {
+                true => Ok(line.to_uppercase()),
+                false => Ok(line.to_string()),
+            }
+        }
+
+        #[test]
+        fn is_empty_without_changes() {
+            assert_eq!(unified_diff("a.txt", "a\nb\n", upper).unwrap(), "");
+        }
+
+        #[test]
+        fn groups_changes_into_hunks() {
+            let source = "x1\n2\n3\n4\n5\n6\n7\n8\n9\nx10\n";
+
+            assert_eq!(
+                unified_diff("a.txt", source, upper).unwrap(),
+                "--- a/a.txt\n+++ b/a.txt\n\
+                 @@ -1,4 +1,4 @@\n-x1\n+X1\n 2\n 3\n 4\n\
+                 @@ -7,4 +7,4 @@\n 7\n 8\n 9\n-x10\n+X10\n"
+            );
+        }
+
+        #[test]
+        fn keeps_crlf_line_endings() {
+            assert_eq!(
+                unified_diff("a.txt", "a\r\nx\r\n", upper).unwrap(),
+                "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\r\n-x\r\n+X\r\n"
+            );
+        }
+
+        #[test]
+        fn keeps_mixed_line_endings() {
+            assert_eq!(
+                unified_diff("a.txt", "x1\r\nx2\nx3\r\n", upper).unwrap(),
+                "--- a/a.txt\n+++ b/a.txt\n@@ -1,3 +1,3 @@\n-x1\r\n+X1\r\n-x2\n+X2\n-x3\r\n+X3\r\n"
+            );
+        }
+
+        #[test]
+        fn marks_missing_newline_at_end_of_file() {
+            assert_eq!(
+                unified_diff("a.txt", "a\nx", upper).unwrap(),
+                "--- a/a.txt\n+++ b/a.txt\n@@ -1,2 +1,2 @@\n a\n-x\n\\ No newline at end of file\n+X\n\\ No newline at end of file\n"
+            );
+        }
     }
 
-    match matches.subcommand() {
-        Some(("for", submatches)) => run_filter_command(submatches, false)?,
-        Some(("not", submatches)) => run_filter_command(submatches, true)?,
-        Some(("replace", _submatches)) => unimplemented!(),
-        _ => {}
+    #[cfg(feature = "server")]
+    mod server {
+        use super::*;
+        use pretty_assertions::assert_eq;
+        use serde_json::json;
+
+        #[test]
+        fn compiles_expressions() {
+            assert_eq!(
+                handle_request("POST", "/compile", r#"{"expression": "numeric"}"#),
+                (200, json!({ "valid": true }))
+            );
+            assert_eq!(
+                handle_request("POST", "/compile", r#"{"expression": "and"}"#).1["valid"],
+                json!(false)
+            );
+        }
+
+        #[test]
+        fn matches_texts() {
+            assert_eq!(
+                handle_request(
+                    "POST",
+                    "/match",
+                    r#"{"expression": "numeric", "text": "1"}"#
+                ),
+                (200, json!({ "matches": true }))
+            );
+            assert_eq!(
+                handle_request(
+                    "POST",
+                    "/batch",
+                    r#"{"expression": "numeric", "texts": ["1", "a"]}"#
+                ),
+                (200, json!({ "matches": [true, false] }))
+            );
+        }
+
+        #[test]
+        fn explains_matches() {
+            let (status, response) = handle_request(
+                "POST",
+                "/explain",
+                r#"{"expression": "contains \"b\" and length 3", "text": "abc"}"#,
+            );
+
+            assert_eq!(status, 200);
+            assert_eq!(response["matched"], json!(true));
+            assert_eq!(
+                response["root"]["children"][0]["evidence"],
+                json!([{ "start": 1, "end": 2 }])
+            );
+        }
+
+        #[test]
+        fn rejects_invalid_requests() {
+            assert_eq!(handle_request("GET", "/match", "").0, 405);
+            assert_eq!(handle_request("POST", "/match", "{").0, 400);
+            assert_eq!(
+                handle_request("POST", "/match", r#"{"expression": "alpha"}"#).0,
+                400
+            );
+            assert_eq!(
+                handle_request("POST", "/nope", r#"{"expression": "alpha"}"#).0,
+                404
+            );
+        }
     }
 
-    Ok(())
+    #[cfg(feature = "grep")]
+    mod searcher {
+        use super::*;
+        use pretty_assertions::assert_eq;
+
+        /// Searches a temporary file for the lines starting with 1
+        fn search(name: &str, content: &[u8]) -> (Items, Rc<Stats>, PathBuf) {
+            let path =
+                std::env::temp_dir().join(format!("srch-{}-{}.txt", name, std::process::id()));
+            fs::write(&path, content).unwrap();
+
+            let stats = Rc::new(Stats::default());
+            let items = crate::searcher::search(
+                &[path.to_str().unwrap()],
+                false,
+                |text| text.starts_with('1'),
+                stats.clone(),
+            );
+
+            (items, stats, path)
+        }
+
+        #[test]
+        fn searches_one_chunk_at_a_time() {
+            let content = (0..100_000).map(|n| format!("{}\n", n)).collect::<String>();
+            let (mut items, stats, path) = search("chunks", content.as_bytes());
+
+            assert_eq!(items.next().unwrap().unwrap().line, 2);
+            assert!(stats.lines.get() < 100_000);
+
+            let last = items.last().unwrap().unwrap();
+            assert_eq!((last.line, last.text.as_str()), (20_000, "19999"));
+            assert_eq!(stats.lines.get(), 100_000);
+
+            fs::remove_file(path).unwrap();
+        }
+
+        #[test]
+        fn rejects_invalid_utf8_after_the_previous_matches() {
+            let (items, _, path) = search("invalid", b"1\n\xff\n12\n");
+            let items = items.collect::<Vec<_>>();
+            fs::remove_file(path).unwrap();
+
+            assert_eq!(items.len(), 2);
+            assert_eq!(items[0].as_ref().unwrap().text, "1");
+            assert_eq!(
+                items[1].as_ref().unwrap_err().kind(),
+                io::ErrorKind::InvalidData
+            );
+        }
+    }
 }
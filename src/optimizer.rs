@@ -0,0 +1,126 @@
+use crate::parser::AST;
+use crate::query::Query;
+
+/// A rough estimate of how expensive it is to execute a query, queries
+/// which only look at a bounded part of the input are the cheapest
+pub fn query_cost(query: &Query) -> u32 {
+    match query {
+        Query::Length(_) | Query::LengthRange(_) => 1,
+        Query::Starts(_) | Query::Ends(_) | Query::Equals(_) => 2,
+        Query::Numeric
+        | Query::Alpha
+        | Query::Alphanumeric
+        | Query::Uppercase
+        | Query::Lowercase
+        | Query::Whitespace
+        | Query::Special
+        | Query::Value(_)
+        | Query::Words(_)
+        | Query::WordsRange(_) => 4,
+        Query::Contains(_) | Query::ContainsTimes(..) => 5,
+        // The input is lowercased before it is compared
+        Query::IStarts(_) | Query::IEnds(_) | Query::IEquals(_) => 5,
+        Query::IContains(_) => 6,
+        // Custom queries run arbitrary code, so they are assumed to be
+        // as expensive as the named patterns
+        Query::Pattern(_) | Query::Custom(_) => 10,
+    }
+}
+
+/// The estimated cost of evaluating every query of an ast
+pub fn cost(ast: &AST) -> u32 {
+    match ast {
+        AST::Query(query) => query_cost(query),
+        AST::Not(ast) => cost(ast),
+        AST::BinaryExpression { left, right, .. } => cost(left) + cost(right),
+    }
+}
+
+/// Reorders the operands of every operator so the cheaper operand is
+/// evaluated first and can short-circuit the more expensive one. Operands
+/// of the same cost keep their order
+pub fn reorder(ast: AST) -> AST {
+    match ast {
+        AST::Query(query) => AST::Query(query),
+        AST::Not(ast) => AST::Not(Box::new(reorder(*ast))),
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => {
+            let left = reorder(*left);
+            let right = reorder(*right);
+
+            let (left, right) = match cost(&right) < cost(&left) {
+                true => (right, left),
+                false => (left, right),
+            };
+
+            AST::BinaryExpression {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            }
+        }
+    }
+}
+
+/// The positions of the queries of `reorder(ast)` in `ast`, counting from
+/// the left, so results of the reordered queries can be attributed to the
+/// queries as written
+pub fn reordered_positions(ast: &AST) -> Vec<usize> {
+    fn positions(ast: &AST, next: &mut usize) -> Vec<usize> {
+        match ast {
+            AST::Query(_) => {
+                *next += 1;
+                vec![*next - 1]
+            }
+            AST::Not(ast) => positions(ast, next),
+            AST::BinaryExpression { left, right, .. } => {
+                let mut first = positions(left, next);
+                let mut second = positions(right, next);
+
+                // Mirrors `reorder`, reordering doesn't change the costs
+                if cost(right) < cost(left) {
+                    std::mem::swap(&mut first, &mut second);
+                }
+
+                first.extend(second);
+                first
+            }
+        }
+    }
+
+    positions(ast, &mut 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reorder, reordered_positions};
+    use crate::into_ast;
+    use pretty_assertions::assert_eq;
+
+    macro_rules! reorder_tests {
+        ($($name:ident: $value:expr,)*) => {
+            $(
+                #[test]
+                fn $name() {
+                    let (source, expected) = $value;
+                    assert_eq!(reorder(into_ast(source).unwrap()), into_ast(expected).unwrap());
+                }
+            )*
+        }
+    }
+
+    #[test]
+    fn locates_reordered_queries() {
+        let ast = into_ast("@email or contains \"a\" and length 3").unwrap();
+        assert_eq!(reordered_positions(&ast), vec![2, 1, 0]);
+    }
+
+    reorder_tests! {
+        cheaper_operand_first: ("contains \"a\" and length 3", "length 3 and contains \"a\""),
+        keeps_equal_costs: ("starts \"a\" or ends \"b\"", "starts \"a\" or ends \"b\""),
+        nested: ("@email or numeric and length 3", "length 3 and numeric or @email"),
+    }
+}
@@ -0,0 +1,214 @@
+use std::borrow::Cow;
+
+use crate::interner::Interner;
+use crate::parser::AST;
+use crate::query::Query;
+
+/// Options which are compiled into an expression, see
+/// [`Expression::new_with`](struct.Expression.html#method.new_with)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MatchOptions {
+    /// Ignores the case of the input and the literals, the literals are
+    /// lowercased when the expression is compiled
+    pub ignore_case: bool,
+    /// Ignores leading and trailing whitespace of the input
+    pub trim: bool,
+    /// Lets `numeric`, `alpha`, `alphanumeric` and `special` match non-ascii
+    /// characters of their class, e.g. `alpha` matches "Straße"
+    pub unicode: bool,
+    /// Evaluates the operands of `and` and `or` from left to right exactly as
+    /// written, instead of evaluating the cheaper operand first
+    pub strict: bool,
+}
+
+impl MatchOptions {
+    /// Normalizes an input once before the queries are executed
+    pub(crate) fn prepare<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        let input = match self.trim {
+            true => input.trim(),
+            false => input,
+        };
+
+        match self.ignore_case {
+            true => Cow::Owned(input.to_lowercase()),
+            false => Cow::Borrowed(input),
+        }
+    }
+
+    /// Bakes the options into an ast, the folded literals are interned
+    /// again so identical ones still share one allocation
+    pub(crate) fn compile(&self, ast: AST) -> AST {
+        match self.ignore_case {
+            true => fold_case(ast, &mut Interner::default()),
+            false => ast,
+        }
+    }
+}
+
+fn fold_case(ast: AST, interner: &mut Interner) -> AST {
+    match ast {
+        AST::Query(query) => AST::Query(match query {
+            Query::Starts(arg) => Query::Starts(interner.fold(arg)),
+            Query::Ends(arg) => Query::Ends(interner.fold(arg)),
+            Query::Contains(arg) => Query::Contains(interner.fold(arg)),
+            Query::ContainsTimes(arg, times) => Query::ContainsTimes(interner.fold(arg), times),
+            Query::Equals(arg) => Query::Equals(interner.fold(arg)),
+            // The input is already lowercased, so the arguments can be
+            // compared as they are
+            Query::IStarts(arg) => Query::Starts(interner.share(arg)),
+            Query::IEnds(arg) => Query::Ends(interner.share(arg)),
+            Query::IContains(arg) => Query::Contains(interner.share(arg)),
+            Query::IEquals(arg) => Query::Equals(interner.share(arg)),
+            // Ignoring case any letter is accepted
+            Query::Uppercase | Query::Lowercase => Query::Alpha,
+            query => query,
+        }),
+        AST::Not(ast) => AST::Not(Box::new(fold_case(*ast, interner))),
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => AST::BinaryExpression {
+            left: Box::new(fold_case(*left, interner)),
+            operator,
+            right: Box::new(fold_case(*right, interner)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::MatchOptions;
+    use crate::parser::AST;
+    use crate::query::Query;
+    use crate::{into_ast, Error, Expression};
+    use pretty_assertions::assert_eq;
+
+    fn matches(source: &str, options: MatchOptions, input: &str) -> bool {
+        Expression::new_with(source, options)
+            .unwrap()
+            .matches(input)
+    }
+
+    #[test]
+    fn ignores_the_case() {
+        let options = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+
+        assert_eq!(matches("starts \"Foo\"", options, "fOOBAR"), true);
+        assert_eq!(matches("equals \"foo\"", options, "FOO"), true);
+        assert_eq!(matches("equals \"foo\"", options, "FOOO"), false);
+        assert_eq!(
+            matches("starts \"Foo\"", MatchOptions::default(), "foo"),
+            false
+        );
+    }
+
+    #[test]
+    fn keeps_case_insensitive_queries() {
+        let options = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+
+        assert_eq!(matches("iends \"BAR\"", options, "fooBar"), true);
+        assert_eq!(
+            matches("iends \"BAR\"", MatchOptions::default(), "fooBar"),
+            true
+        );
+    }
+
+    #[test]
+    fn accepts_letters_of_either_case() {
+        let options = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+
+        assert_eq!(matches("uppercase", options, "Const"), true);
+        assert_eq!(matches("lowercase", options, "CONST"), true);
+        assert_eq!(matches("uppercase", options, "CONST_1"), false);
+    }
+
+    #[test]
+    fn trims_the_input() {
+        let options = MatchOptions {
+            trim: true,
+            ..MatchOptions::default()
+        };
+
+        assert_eq!(matches("numeric and length 3", options, "  123\t"), true);
+        assert_eq!(matches("numeric", MatchOptions::default(), " 123"), false);
+    }
+
+    #[test]
+    fn matches_unicode_classes() {
+        let options = MatchOptions {
+            unicode: true,
+            ..MatchOptions::default()
+        };
+
+        assert_eq!(matches("alpha", options, "Straße"), true);
+        assert_eq!(matches("alpha", MatchOptions::default(), "Straße"), false);
+        assert_eq!(matches("special", options, "«»"), true);
+        assert_eq!(matches("numeric", options, "١٢٣"), true);
+    }
+
+    #[test]
+    fn folds_literals_before_sharing_them() {
+        let ast = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        }
+        .compile(into_ast("starts \"AB\" or ends \"ab\"").unwrap());
+
+        match ast {
+            AST::BinaryExpression { left, right, .. } => match (*left, *right) {
+                (AST::Query(Query::Starts(left)), AST::Query(Query::Ends(right))) => {
+                    assert_eq!(&*left, "ab");
+                    assert!(Arc::ptr_eq(&left, &right));
+                }
+                ast => panic!("unexpected ast {:?}", ast),
+            },
+            ast => panic!("unexpected ast {:?}", ast),
+        }
+    }
+
+    #[test]
+    fn combines_expressions_with_the_same_options() {
+        let options = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+
+        let expr = Expression::new_with("starts \"a\"", options)
+            .unwrap()
+            .and(Expression::new_with("ends \"B\"", options).unwrap())
+            .unwrap();
+
+        assert_eq!(expr.matches("AxB"), true);
+    }
+
+    #[test]
+    fn rejects_combining_expressions_with_different_options() {
+        let options = MatchOptions {
+            ignore_case: true,
+            ..MatchOptions::default()
+        };
+
+        // The right side would lose its options
+        let right = Expression::new_with("contains \"abc\"", options).unwrap();
+        let result = Expression::new("numeric").unwrap().or(right);
+        assert!(matches!(result, Err(Error::IncompatibleOptions)));
+
+        // The right side would become case-insensitive
+        let result = Expression::new_with("starts \"a\"", options)
+            .unwrap()
+            .and(Expression::new("ends \"B\"").unwrap());
+        assert!(matches!(result, Err(Error::IncompatibleOptions)));
+    }
+}
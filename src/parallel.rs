@@ -0,0 +1,64 @@
+use rayon::iter::ParallelIterator;
+
+use crate::Expression;
+
+/// Adds [`par_srch_filter`](Self::par_srch_filter) to every parallel
+/// iterator over strings
+pub trait ParallelFilterExt: ParallelIterator
+where
+    Self::Item: AsRef<str>,
+{
+    /// Filters the items by an expression on all cores. Expressions are
+    /// immutable once compiled, so matching needs no synchronization
+    ///
+    /// ```rust
+    /// use rayon::prelude::*;
+    /// use srch::ParallelFilterExt;
+    ///
+    /// let expr = srch::Expression::new("numeric").unwrap();
+    /// let corpus = vec!["123", "abc", "456"];
+    ///
+    /// let matches = corpus.into_par_iter().par_srch_filter(&expr).collect::<Vec<_>>();
+    /// assert_eq!(matches, vec!["123", "456"]);
+    /// ```
+    fn par_srch_filter<'e>(
+        self,
+        expression: &'e Expression,
+    ) -> impl ParallelIterator<Item = Self::Item> + 'e
+    where
+        Self: 'e,
+    {
+        self.filter(move |item| expression.matches(item.as_ref()))
+    }
+}
+
+impl<I> ParallelFilterExt for I
+where
+    I: ParallelIterator,
+    I::Item: AsRef<str>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use rayon::prelude::*;
+
+    use super::ParallelFilterExt;
+    use crate::Expression;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn expressions_can_be_shared_between_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Expression>();
+    }
+
+    #[test]
+    fn filters_in_parallel() {
+        let expr = Expression::new("numeric and length 3").unwrap();
+        let corpus = (0..10_000).map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let matches = corpus.par_iter().par_srch_filter(&expr).count();
+        assert_eq!(matches, 900);
+    }
+}
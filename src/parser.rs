@@ -1,3 +1,6 @@
+use std::fmt;
+
+use crate::i18n;
 use crate::lexer::Token;
 use crate::query::Query;
 use crate::logical_operator::LogicalOperator;
@@ -6,16 +9,37 @@ use crate::logical_operator::LogicalOperator;
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Clone, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
 	ExpectedQuery,
 	ExpectedOperator,
 	EmptyExpression,
+	/// The syntax tree would be nested deeper than the bound, which is the
+	/// payload, so matching it could overflow the stack
+	ExpressionTooComplex(usize),
 	InternalError
 }
 
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let message = match self {
+			Self::ExpectedQuery => i18n::message("parser.expected_query", ""),
+			Self::ExpectedOperator => i18n::message("parser.expected_operator", ""),
+			Self::EmptyExpression => i18n::message("parser.empty_expression", ""),
+			Self::ExpressionTooComplex(max) => i18n::message("parser.too_complex", &max.to_string()),
+			Self::InternalError => i18n::message("parser.internal_error", "")
+		};
+
+		write!(f, "{}", message)
+	}
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum ASTNode {
 	Query(Query),
+	Not(Box<ASTNode>),
 	BinaryExpression {
 		left: Box<ASTNode>,
 		operator: LogicalOperator,
@@ -23,8 +47,24 @@ pub enum ASTNode {
 	},
 }
 
+#[allow(clippy::upper_case_acronyms)]
 pub type AST = ASTNode;
 
+/// The maximum nesting depth of a parsed syntax tree. Matching, optimizing
+/// and converting expressions walk the tree recursively, so deeper trees
+/// are rejected instead of risking a stack overflow. Chains like
+/// `a or b or c` are joined into balanced trees, so only `log2` of their
+/// length counts, while every `not` adds another level
+pub const MAX_DEPTH: usize = 1000;
+
+/// A negated or plain query together with its depth, or an operator between
+/// two of them
+#[derive(Clone, Debug)]
+enum Term {
+	Operand(AST, usize),
+	Operator(LogicalOperator)
+}
+
 #[derive(Clone, Debug)]
 pub struct Parser {
 	tokens: Vec<Token>
@@ -38,106 +78,207 @@ impl Parser {
 		}
 	}
 
-	fn expect_query(token: Token) -> Result<Query> {
-		match token {
-			Token::Query(q) => Ok(q),
-			Token::LogicalOperator(_) => Err(Error::ExpectedQuery)
+	/// Groups the tokens into operands, i.e. queries together with the `not`s
+	/// preceding them, and the operators between the operands. `not` binds
+	/// stronger than `and` and `or`, so it only negates the following query.
+	/// Operands are paired with their depth
+	fn terms(&self) -> Result<Vec<Term>> {
+		let mut terms = Vec::new();
+		let mut negated = 0;
+
+		for tkn in self.tokens.iter() {
+			match tkn {
+				Token::Not => negated += 1,
+				Token::Query(_) if negated >= MAX_DEPTH => return Err(Error::ExpressionTooComplex(MAX_DEPTH)),
+				Token::Query(query) => {
+					let operand = (0..negated).fold(AST::Query(query.clone()), |ast, _| AST::Not(Box::new(ast)));
+
+					terms.push(Term::Operand(operand, negated + 1));
+					negated = 0;
+				},
+				Token::LogicalOperator(_) if negated > 0 => return Err(Error::ExpectedQuery),
+				Token::LogicalOperator(op) => terms.push(Term::Operator(*op))
+			}
 		}
-	}
 
-	fn expect_operator(token: Token) -> Result<LogicalOperator> {
-		match token {
-			Token::LogicalOperator(op) => Ok(op),
-			Token::Query(_) => Err(Error::ExpectedOperator)
+		if negated > 0 {
+			return Err(Error::ExpectedQuery);
 		}
+
+		Ok(terms)
 	}
 
-	fn validate_structure(&self) -> Result<()> {
-		if self.tokens.is_empty() {
+	fn validate_structure(terms: &[Term]) -> Result<()> {
+		if terms.is_empty() {
 			return Err(Error::EmptyExpression);
 		}
 
-		match self.tokens.first() {
-			Some(tkn) => { Self::expect_query(tkn.clone())?; },
-			None => {}
-		}
-
-		match self.tokens.last() {
-			Some(tkn) => { Self::expect_query(tkn.clone())?; },
-			None => {}
-		}
-
 		let mut expect_query = true;
 
-		for tkn in self.tokens.clone() {
-			if expect_query {
-				Self::expect_query(tkn)?;
-			} else {
-				Self::expect_operator(tkn)?;
+		for term in terms {
+			match (term, expect_query) {
+				(Term::Operand(..), true) | (Term::Operator(_), false) => {},
+				(Term::Operator(_), true) => return Err(Error::ExpectedQuery),
+				(Term::Operand(..), false) => return Err(Error::ExpectedOperator)
 			}
 
 			expect_query = !expect_query;
 		}
 
+		// a trailing operator
+		if expect_query {
+			return Err(Error::ExpectedQuery);
+		}
+
 		Ok(())
 	}
 
+	/// Parses alternating operands and operators, `and` binds stronger than
+	/// `or` and chains of both are balanced
+	fn parse_terms(terms: Vec<Term>) -> Result<AST> {
+		let mut alternatives = vec![vec![]];
+
+		for term in terms {
+			match term {
+				Term::Operand(ast, depth) => alternatives.last_mut().ok_or(Error::InternalError)?.push((ast, depth)),
+				Term::Operator(LogicalOperator::Or) => alternatives.push(vec![]),
+				Term::Operator(LogicalOperator::And) => {}
+			}
+		}
+
+		let alternatives = alternatives
+			.into_iter()
+			.map(|operands| balance(operands, LogicalOperator::And))
+			.collect::<Result<Vec<_>>>()?;
+
+		Ok(balance(alternatives, LogicalOperator::Or)?.0)
+	}
+
 	pub fn parse(&mut self) -> Result<AST> {
-		self.validate_structure()?;
+		let terms = self.terms()?;
+		Self::validate_structure(&terms)?;
+		Self::parse_terms(terms)
+	}
 
-		if self.tokens.len() == 1 {
-			let query = Self::expect_query(self.tokens[0].clone())?;
-			return Ok(AST::Query(query));
-		} else if self.tokens.len() > 2 {
-			let next_operator = Self::expect_operator(self.tokens[1].clone())?;
+}
 
-			if next_operator == LogicalOperator::And {
-				let mut tkns = self.tokens.clone();
+pub fn parse(tokens: Vec<Token>) -> Result<AST> {
+	let mut parser = Parser::new(tokens);
 
-				if let Some(index) = tkns.iter().position(|tkn| tkn == &Token::LogicalOperator(LogicalOperator::Or)) {
-					let right_tokens = tkns.split_off(index)[1..].to_vec();
+	parser.parse()
+}
 
-					let left = Box::new(parse(tkns)?);
-					let right = Box::new(parse(right_tokens)?);
+/// The nesting depth of an ast, a single query is one level deep
+pub fn depth(ast: &AST) -> usize {
+	match ast {
+		AST::Query(_) => 1,
+		AST::Not(ast) => 1 + depth(ast),
+		AST::BinaryExpression { left, right, .. } => 1 + depth(left).max(depth(right))
+	}
+}
 
-					return Ok(ASTNode::BinaryExpression {
-						left,
-						operator: LogicalOperator::Or,
-						right
-					});
-				}
-			}
+/// Joins operands paired with their depth into a balanced tree, so a chain
+/// of `n` operands is only nested about `log2(n)` levels deeper than its
+/// deepest operand. The operands keep their order, so they are still
+/// evaluated from left to right, and three operands associate to the right
+/// like `a or (b or c)`. The depth is counted while joining, the tree is
+/// never walked
+fn balance(mut operands: Vec<(AST, usize)>, operator: LogicalOperator) -> Result<(AST, usize)> {
+	if operands.len() <= 1 {
+		return operands.pop().ok_or(Error::InternalError);
+	}
 
-			let left = Box::new(
-				AST::Query(
-					Self::expect_query(self.tokens[0].clone())?
-				)
-			);
-	
-			let right = Box::new(parse(self.tokens.clone()[2..].to_vec())?);
-	
-			return Ok(ASTNode::BinaryExpression {
-				left,
-				operator: next_operator,
-				right
-			});
-		}
+	let depths = operands.iter().map(|(_, depth)| *depth).collect::<Vec<_>>();
+	let right = operands.split_off(split(&depths));
+	let (left, left_depth) = balance(operands, operator)?;
+	let (right, right_depth) = balance(right, operator)?;
+	let depth = 1 + left_depth.max(right_depth);
 
-		Err(Error::InternalError)
+	if depth > MAX_DEPTH {
+		return Err(Error::ExpressionTooComplex(MAX_DEPTH));
 	}
 
+	Ok((
+		ASTNode::BinaryExpression {
+			left: Box::new(left),
+			operator,
+			right: Box::new(right)
+		},
+		depth
+	))
 }
 
-pub fn parse(tokens: Vec<Token>) -> Result<AST> {
-	let mut parser = Parser::new(tokens);
+/// Where to split operands so both halves weigh about the same. An operand
+/// nested `d` levels deep weighs `2^d`, so deeper operands end up closer to
+/// the root and the joined tree is about as shallow as possible. Operands of
+/// the same depth are split in the middle, an odd one out goes to the right
+pub(crate) fn split(depths: &[usize]) -> usize {
+	let deepest = depths.iter().copied().max().unwrap_or(0);
+	let weights = depths
+		.iter()
+		.map(|depth| 0.5_f64.powi((deepest - depth).min(i32::MAX as usize) as i32))
+		.collect::<Vec<_>>();
+
+	let half = weights.iter().sum::<f64>() / 2.0;
+	let mut prefix = 0.0;
+
+	// The first split with at least half of the weight on the left, or the
+	// one before it if that is closer to the middle
+	for (i, weight) in weights[..weights.len() - 1].iter().enumerate() {
+		let previous = prefix;
+		prefix += weight;
+
+		if prefix >= half {
+			return match i > 0 && half - previous <= prefix - half {
+				true => i,
+				false => i + 1
+			};
+		}
+	}
 
-	parser.parse()
+	weights.len() - 1
+}
+
+/// Rebalances the chains of `and` and `or` of an ast, e.g. after combining
+/// expressions one at a time nested it one level deeper every time. Fails
+/// if the ast is still nested deeper than [`MAX_DEPTH`], e.g. because of
+/// many `not`s
+pub fn rebalance(ast: AST) -> Result<(AST, usize)> {
+	/// Collects the operands of a chain of the same operator, from left to right
+	fn operands(ast: AST, operator: LogicalOperator, found: &mut Vec<AST>) {
+		match ast {
+			AST::BinaryExpression { left, operator: op, right } if op == operator => {
+				operands(*left, operator, found);
+				operands(*right, operator, found);
+			},
+			ast => found.push(ast)
+		}
+	}
+
+	match ast {
+		AST::Query(query) => Ok((AST::Query(query), 1)),
+		AST::Not(ast) => {
+			let (ast, depth) = rebalance(*ast)?;
+
+			match depth < MAX_DEPTH {
+				true => Ok((AST::Not(Box::new(ast)), depth + 1)),
+				false => Err(Error::ExpressionTooComplex(MAX_DEPTH))
+			}
+		},
+		AST::BinaryExpression { operator, .. } => {
+			let mut found = Vec::new();
+			operands(ast, operator, &mut found);
+
+			let operands = found.into_iter().map(rebalance).collect::<Result<Vec<_>>>()?;
+			balance(operands, operator)
+		}
+	}
 }
 
 
 #[cfg(test)]
 mod tests {
-	use super::{parse, AST, ASTNode};
+	use super::{parse, AST, ASTNode, Error};
 	use crate::lexer::Token;
 	use crate::logical_operator::LogicalOperator;
 	use crate::query::Query;
@@ -294,20 +435,20 @@ mod tests {
 					Token::Query(Query::Special),
 				],
 				AST::BinaryExpression {
-					left: Box::new(ASTNode::Query(Query::Numeric)),
+					left: Box::new(ASTNode::BinaryExpression {
+						left: Box::new(ASTNode::Query(Query::Numeric)),
+						operator: LogicalOperator::Or,
+						right: Box::new(ASTNode::Query(Query::Alpha)),
+					}),
 					operator: LogicalOperator::Or,
 					right: Box::new(ASTNode::BinaryExpression {
-						left: Box::new(ASTNode::Query(Query::Alpha)),
-						operator: LogicalOperator::Or,
-						right: Box::new(ASTNode::BinaryExpression {
-							left: Box::new(ASTNode::BinaryExpression {
-								left: Box::new(ASTNode::Query(Query::Alphanumeric)),
-								operator: LogicalOperator::And,
-								right: Box::new(ASTNode::Query(Query::Length(100))),
-							}),
-							operator: LogicalOperator::Or,
-							right: Box::new(ASTNode::Query(Query::Special))
+						left: Box::new(ASTNode::BinaryExpression {
+							left: Box::new(ASTNode::Query(Query::Alphanumeric)),
+							operator: LogicalOperator::And,
+							right: Box::new(ASTNode::Query(Query::Length(100))),
 						}),
+						operator: LogicalOperator::Or,
+						right: Box::new(ASTNode::Query(Query::Special))
 					}),
 				}
 			),
@@ -343,4 +484,190 @@ mod tests {
 			),
 		}
 	}
+
+	mod it_parses_negations {
+		use super::*;
+
+		fn not(query: Query) -> Box<ASTNode> {
+			Box::new(ASTNode::Not(Box::new(ASTNode::Query(query))))
+		}
+
+		parser_tests! {
+			not_contains: (
+				vec![
+					Token::Not,
+					Token::Query(Query::Contains("error".into()))
+				],
+				*not(Query::Contains("error".into()))
+			),
+			numeric_and_not_length_or_not_special: (
+				vec![
+					Token::Query(Query::Numeric),
+					Token::LogicalOperator(LogicalOperator::And),
+					Token::Not,
+					Token::Query(Query::Length(5)),
+					Token::LogicalOperator(LogicalOperator::Or),
+					Token::Not,
+					Token::Query(Query::Special),
+				],
+				AST::BinaryExpression {
+					left: Box::new(ASTNode::BinaryExpression {
+						left: Box::new(ASTNode::Query(Query::Numeric)),
+						operator: LogicalOperator::And,
+						right: not(Query::Length(5)),
+					}),
+					operator: LogicalOperator::Or,
+					right: not(Query::Special),
+				}
+			),
+			not_not_alpha: (
+				vec![
+					Token::Not,
+					Token::Not,
+					Token::Query(Query::Alpha)
+				],
+				ASTNode::Not(not(Query::Alpha))
+			),
+		}
+
+		#[test]
+		fn requires_a_query_after_not() {
+			assert!(matches!(parse(vec![Token::Not]), Err(Error::ExpectedQuery)));
+			assert!(matches!(
+				parse(vec![Token::Query(Query::Alpha), Token::LogicalOperator(LogicalOperator::And), Token::Not]),
+				Err(Error::ExpectedQuery)
+			));
+			assert!(matches!(
+				parse(vec![Token::Not, Token::LogicalOperator(LogicalOperator::Or), Token::Query(Query::Alpha)]),
+				Err(Error::ExpectedQuery)
+			));
+			assert!(matches!(parse(vec![Token::Query(Query::Alpha), Token::Not, Token::Query(Query::Alpha)]), Err(Error::ExpectedOperator)));
+		}
+	}
+
+	mod it_bounds_the_depth {
+		use super::*;
+		use crate::parser::depth;
+		use crate::{into_ast, Error as CompileError, Expression, Limits, MAX_DEPTH};
+
+		fn join(queries: impl Iterator<Item = String>, operator: &str) -> String {
+			queries.collect::<Vec<_>>().join(operator)
+		}
+
+		fn equals_chain(n: usize) -> String {
+			join((0..n).map(|i| format!("equals \"{}\"", i)), " or ")
+		}
+
+		fn contains_chain(n: usize) -> String {
+			join((0..n).map(|_| "contains \"a\"".to_string()), " and ")
+		}
+
+		fn not_chain(n: usize) -> String {
+			format!("{}numeric", "not ".repeat(n))
+		}
+
+		/// Alternatives of two negated queries, every alternative is two levels deep
+		fn mixed_chain(n: usize) -> String {
+			join((0..n).map(|_| "not alpha and not special".to_string()), " or ")
+		}
+
+		fn is_too_complex(source: &str) -> bool {
+			matches!(into_ast(source), Err(CompileError::ParserError(Error::ExpressionTooComplex(MAX_DEPTH))))
+		}
+
+		#[test]
+		fn accepts_expressions_at_the_bound() {
+			let source = not_chain(MAX_DEPTH - 1);
+			pretty_assertions::assert_eq!(depth(&into_ast(&source).unwrap()), MAX_DEPTH);
+
+			// The deepest operand of a chain is joined right below the root
+			let source = format!("{} or {}", not_chain(MAX_DEPTH - 2), equals_chain(1_000));
+			pretty_assertions::assert_eq!(depth(&into_ast(&source).unwrap()), MAX_DEPTH);
+
+			let source = format!("{} or {}", equals_chain(1_000), not_chain(MAX_DEPTH - 2));
+			pretty_assertions::assert_eq!(depth(&into_ast(&source).unwrap()), MAX_DEPTH);
+		}
+
+		#[test]
+		fn balances_flat_chains() {
+			for (source, expected) in [
+				(equals_chain(100_000), 18),
+				(contains_chain(100_000), 18),
+				(mixed_chain(100_000), 20),
+			]
+			.iter()
+			{
+				pretty_assertions::assert_eq!(depth(&into_ast(source).unwrap()), *expected);
+			}
+		}
+
+		#[test]
+		fn rejects_expressions_beyond_the_bound() {
+			for n in [MAX_DEPTH + 1, 5_000, 100_000].iter() {
+				assert!(is_too_complex(&not_chain(*n - 1)));
+				assert!(is_too_complex(&format!("{} or alpha", not_chain(*n - 2))));
+			}
+		}
+
+		#[test]
+		fn evaluates_expressions_at_the_bound() {
+			let equals = Expression::new(&equals_chain(100_000)).unwrap();
+			assert!(equals.matches("99999"));
+			assert!(!equals.matches("x"));
+
+			let contains = Expression::new(&contains_chain(100_000)).unwrap();
+			assert!(contains.matches("a"));
+			assert!(!contains.matches("b"));
+
+			let not = Expression::new(&not_chain(MAX_DEPTH - 1)).unwrap();
+			assert_eq!(not.matches("1"), (MAX_DEPTH - 1).is_multiple_of(2));
+
+			let mixed = Expression::new(&mixed_chain(100_000)).unwrap();
+			assert!(mixed.matches("1"));
+			assert!(!mixed.matches("a"));
+		}
+
+		#[test]
+		fn rebalances_folded_expressions() {
+			let expr = (1..=100_001).fold(Expression::new("equals \"0\"").unwrap(), |expr, i| {
+				expr.or(Expression::new(&format!("equals \"{}\"", i)).unwrap()).unwrap()
+			});
+
+			assert!(expr.matches("0"));
+			assert!(expr.matches("100001"));
+			assert!(!expr.matches("x"));
+			assert!(expr.complement().matches("x"));
+
+			let negated = (0..MAX_DEPTH).fold(Expression::new("numeric").unwrap(), |expr, _| expr.negate());
+			assert!(negated.or(Expression::new("alpha").unwrap()).is_ok());
+
+			let too_deep = Expression::new(&not_chain(MAX_DEPTH - 1)).unwrap();
+			assert!(matches!(
+				too_deep.and(Expression::new("alpha").unwrap()),
+				Err(CompileError::ParserError(Error::ExpressionTooComplex(MAX_DEPTH)))
+			));
+		}
+
+		#[test]
+		fn evaluates_untrusted_expressions_at_the_bound() {
+			let limits = Limits {
+				max_source_length: usize::MAX,
+				max_queries: usize::MAX,
+				max_depth: MAX_DEPTH,
+				fuel: None,
+				..Limits::default()
+			};
+
+			let expr = Expression::new_untrusted(&equals_chain(MAX_DEPTH), limits).unwrap();
+			assert_eq!(expr.try_matches("x"), Ok(false));
+
+			let limits = Limits {
+				fuel: Some(u64::MAX),
+				..limits
+			};
+
+			let expr = Expression::new_untrusted(&contains_chain(MAX_DEPTH), limits).unwrap();
+			assert_eq!(expr.try_matches("a"), Ok(true));
+		}
+	}
 }
@@ -0,0 +1,222 @@
+/// Built-in named patterns which can be used like any other query by
+/// prefixing their name with `@`, e.g. `@email and ends ".org"`
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Pattern {
+	Email,
+	Url,
+	Uuid,
+	Ipv4
+}
+
+impl Pattern {
+
+	pub const ALL: [Pattern; 4] = [Self::Email, Self::Url, Self::Uuid, Self::Ipv4];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL.iter().copied().find(|pattern| pattern.name() == name)
+	}
+
+	pub fn name(&self) -> &'static str {
+		match self {
+			Self::Email => "email",
+			Self::Url => "url",
+			Self::Uuid => "uuid",
+			Self::Ipv4 => "ipv4"
+		}
+	}
+
+	pub fn as_keyword(&self) -> &'static str {
+		match self {
+			Self::Email => "@email",
+			Self::Url => "@url",
+			Self::Uuid => "@uuid",
+			Self::Ipv4 => "@ipv4"
+		}
+	}
+
+	pub fn description(&self) -> &'static str {
+		match self {
+			Self::Email => "an email address like \"jane@example.com\"",
+			Self::Url => "an url with a scheme like \"https://example.com/path\"",
+			Self::Uuid => "a uuid like \"123e4567-e89b-12d3-a456-426614174000\"",
+			Self::Ipv4 => "an ipv4 address like \"192.168.0.1\""
+		}
+	}
+
+	/// A string matching the pattern
+	pub fn example(&self) -> &'static str {
+		match self {
+			Self::Email => "jane@example.com",
+			Self::Url => "https://example.com/path",
+			Self::Uuid => "123e4567-e89b-12d3-a456-426614174000",
+			Self::Ipv4 => "192.168.0.1"
+		}
+	}
+
+	pub fn exec(&self, tested_string: &str) -> bool {
+		match self {
+			Self::Email => is_email(tested_string),
+			Self::Url => is_url(tested_string),
+			Self::Uuid => is_uuid(tested_string),
+			Self::Ipv4 => is_ipv4(tested_string)
+		}
+	}
+
+}
+
+fn is_domain(domain: &str) -> bool {
+	let labels = domain.split('.').collect::<Vec<_>>();
+
+	let valid_labels = labels.iter().all(|label| {
+		!label.is_empty()
+			&& label.len() <= 63
+			&& !label.starts_with('-')
+			&& !label.ends_with('-')
+			&& label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+	});
+
+	let valid_tld = match labels.last() {
+		Some(tld) => tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()),
+		None => false
+	};
+
+	labels.len() >= 2 && valid_labels && valid_tld
+}
+
+fn is_email(s: &str) -> bool {
+	let (local, domain) = match s.split_once('@') {
+		Some(parts) => parts,
+		None => return false
+	};
+
+	let valid_local = !local.is_empty()
+		&& !local.starts_with('.')
+		&& !local.ends_with('.')
+		&& !local.contains("..")
+		&& local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c));
+
+	valid_local && is_domain(domain)
+}
+
+fn is_url(s: &str) -> bool {
+	let (scheme, rest) = match s.split_once("://") {
+		Some(parts) => parts,
+		None => return false
+	};
+
+	let valid_scheme = match scheme.chars().next() {
+		Some(c) => c.is_ascii_alphabetic() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c)),
+		None => false
+	};
+
+	let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+
+	valid_scheme
+		&& !authority.is_empty()
+		&& !s.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn is_uuid(s: &str) -> bool {
+	let groups = s.split('-').collect::<Vec<_>>();
+
+	groups.len() == 5
+		&& groups.iter().zip([8, 4, 4, 4, 12].iter()).all(|(group, len)| {
+			group.len() == *len && group.chars().all(|c| c.is_ascii_hexdigit())
+		})
+}
+
+fn is_ipv4(s: &str) -> bool {
+	let octets = s.split('.').collect::<Vec<_>>();
+
+	octets.len() == 4
+		&& octets.iter().all(|octet| {
+			!octet.is_empty()
+				&& octet.len() <= 3
+				&& octet.chars().all(|c| c.is_ascii_digit())
+				&& (octet.len() == 1 || !octet.starts_with('0'))
+				&& octet.parse::<u8>().is_ok()
+		})
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::Pattern;
+
+	macro_rules! pattern_tests {
+		($($name:ident: $value:expr,)*) => {
+			$(
+				#[test]
+				fn $name() {
+					let (pattern, input, expected): (Pattern, &str, bool) = $value;
+					pretty_assertions::assert_eq!(pattern.exec(input), expected);
+				}
+			)*
+		}
+	}
+
+	mod email {
+		use super::*;
+
+		pattern_tests! {
+			simple: (Pattern::Email, "foo@baz.com", true),
+			with_subdomain_and_plus: (Pattern::Email, "foo.bar+spam@mail.baz.co", true),
+			missing_at: (Pattern::Email, "foo.baz.com", false),
+			missing_local_part: (Pattern::Email, "@baz.com", false),
+			missing_tld: (Pattern::Email, "foo@baz", false),
+			double_at: (Pattern::Email, "foo@bar@baz.com", false),
+			consecutive_dots: (Pattern::Email, "foo..bar@baz.com", false),
+			whitespace: (Pattern::Email, "foo bar@baz.com", false),
+		}
+	}
+
+	mod url {
+		use super::*;
+
+		pattern_tests! {
+			https: (Pattern::Url, "https://example.com", true),
+			with_path_and_query: (Pattern::Url, "http://example.com:8080/a/b?c=d#e", true),
+			other_scheme: (Pattern::Url, "ftp://files.example.com", true),
+			missing_scheme: (Pattern::Url, "example.com/path", false),
+			missing_host: (Pattern::Url, "https:///path", false),
+			whitespace: (Pattern::Url, "https://exa mple.com", false),
+		}
+	}
+
+	mod uuid {
+		use super::*;
+
+		pattern_tests! {
+			lowercase: (Pattern::Uuid, "123e4567-e89b-12d3-a456-426614174000", true),
+			uppercase: (Pattern::Uuid, "123E4567-E89B-12D3-A456-426614174000", true),
+			missing_group: (Pattern::Uuid, "123e4567-e89b-12d3-426614174000", false),
+			wrong_group_length: (Pattern::Uuid, "123e4567-e89b-12d3-a456-42661417400", false),
+			non_hex: (Pattern::Uuid, "123e4567-e89b-12d3-a456-42661417400g", false),
+		}
+	}
+
+	mod ipv4 {
+		use super::*;
+
+		pattern_tests! {
+			local: (Pattern::Ipv4, "192.168.0.1", true),
+			zeros: (Pattern::Ipv4, "0.0.0.0", true),
+			broadcast: (Pattern::Ipv4, "255.255.255.255", true),
+			octet_out_of_range: (Pattern::Ipv4, "256.1.1.1", false),
+			leading_zero: (Pattern::Ipv4, "01.1.1.1", false),
+			too_few_octets: (Pattern::Ipv4, "1.1.1", false),
+			empty_octet: (Pattern::Ipv4, "1..1.1", false),
+		}
+	}
+
+	#[test]
+	fn resolves_names() {
+		for pattern in Pattern::ALL.iter() {
+			pretty_assertions::assert_eq!(Pattern::from_name(pattern.name()), Some(*pattern));
+		}
+
+		pretty_assertions::assert_eq!(Pattern::from_name("unknown"), None);
+	}
+}
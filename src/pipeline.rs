@@ -0,0 +1,447 @@
+//! The pipeline of the `srch` cli as a library, so other programs (e.g. a
+//! log viewer) can read, split, filter and print records exactly like `srch
+//! for` does without running the binary
+//!
+//! ```rust
+//! use srch::pipeline::{Mode, Pipeline};
+//!
+//! let expr = srch::Expression::new("numeric").unwrap();
+//! let mut out = Vec::new();
+//!
+//! let matches = Pipeline::new(expr)
+//!     .mode(Mode::Word(srch::Tokenizer::default()))
+//!     .text("ids.txt", "id 12\nname jane\nid 345\n")
+//!     .line_numbers(true)
+//!     .run(&mut out)
+//!     .unwrap();
+//!
+//! assert_eq!(matches, 2);
+//! assert_eq!(String::from_utf8(out).unwrap(), "1:12\n3:345\n");
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Write};
+use std::path::PathBuf;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Document, Expression, Tokenizer};
+
+/// The name of the standard input in the output, like in the cli
+pub const STDIN_NAME: &str = "<stdin>";
+
+/// The unit records are matched as, like the `--mode` of the cli
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Mode {
+    #[default]
+    Line,
+    Word(Tokenizer),
+    /// Blocks of lines separated by blank lines
+    Paragraph,
+    /// Single graphemes
+    Char,
+    /// Records separated by a delimiter instead of line breaks
+    Delimited(String),
+}
+
+/// A record of an input, the line is the line it starts at counting from 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub input: String,
+    pub line: usize,
+    pub text: String,
+}
+
+enum Source {
+    Path(PathBuf),
+    Reader(Box<dyn BufRead>),
+}
+
+/// The records of all inputs of a pipeline
+pub type Records = Box<dyn Iterator<Item = io::Result<Record>>>;
+
+/// Reads the records of a reader separated by a delimiter, the delimiter is
+/// removed like the line terminators of [`BufRead::lines`]
+struct Delimited<R> {
+    reader: R,
+    delimiter: Vec<u8>,
+}
+
+impl<R: BufRead> Iterator for Delimited<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = *self.delimiter.last()?;
+        let mut record = Vec::new();
+
+        loop {
+            match self.reader.read_until(last, &mut record) {
+                Ok(0) if record.is_empty() => return None,
+                Ok(0) => break,
+                Ok(_) if record.ends_with(&self.delimiter) => {
+                    record.truncate(record.len() - self.delimiter.len());
+                    break;
+                }
+                Ok(_) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        Some(
+            String::from_utf8(record)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+        )
+    }
+}
+
+/// Groups lines into paragraphs, which keep the line number of their first
+/// line
+struct Paragraphs {
+    lines: Records,
+}
+
+impl Iterator for Paragraphs {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut paragraph: Option<Record> = None;
+
+        for line in self.lines.by_ref() {
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+
+            match (&mut paragraph, line.text.trim().is_empty()) {
+                (None, true) => continue,
+                (None, false) => paragraph = Some(line),
+                (Some(_), true) => break,
+                (Some(paragraph), false) => {
+                    paragraph.text.push('\n');
+                    paragraph.text.push_str(&line.text);
+                }
+            }
+        }
+
+        paragraph.map(Ok)
+    }
+}
+
+/// Splits every record into several records sharing its input and line
+fn split_records<F>(records: Records, split: F) -> Records
+where
+    F: Fn(&str) -> Vec<String> + 'static,
+{
+    Box::new(records.flat_map(move |record| {
+        match record {
+            Ok(record) => split(&record.text)
+                .into_iter()
+                .map(|text| {
+                    Ok(Record {
+                        text,
+                        ..record.clone()
+                    })
+                })
+                .collect::<Vec<_>>(),
+            Err(err) => vec![Err(err)],
+        }
+    }))
+}
+
+/// Reads and filters the records of inputs by an expression and prints the
+/// matches, see the [module documentation](self)
+pub struct Pipeline {
+    expression: Expression,
+    inputs: Vec<(String, Source)>,
+    mode: Mode,
+    invert: bool,
+    unique: bool,
+    limit: Option<usize>,
+    file_names: Option<bool>,
+    line_numbers: bool,
+    terminator: String,
+}
+
+impl Pipeline {
+    pub fn new(expression: Expression) -> Self {
+        Self {
+            expression,
+            inputs: vec![],
+            mode: Mode::default(),
+            invert: false,
+            unique: false,
+            limit: None,
+            file_names: None,
+            line_numbers: false,
+            terminator: "\n".to_string(),
+        }
+    }
+
+    /// Adds a file which is opened when the records are read
+    pub fn file(mut self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.inputs
+            .push((path.display().to_string(), Source::Path(path)));
+        self
+    }
+
+    /// Adds a reader, e.g. a socket or the output of a process
+    pub fn reader(mut self, name: impl Into<String>, reader: impl BufRead + 'static) -> Self {
+        self.inputs
+            .push((name.into(), Source::Reader(Box::new(reader))));
+        self
+    }
+
+    /// Adds a text which is already in memory
+    pub fn text(self, name: impl Into<String>, text: impl Into<String>) -> Self {
+        self.reader(name, Cursor::new(text.into()))
+    }
+
+    /// Adds the standard input, named [`STDIN_NAME`]
+    pub fn stdin(self) -> Self {
+        self.reader(STDIN_NAME, BufReader::new(io::stdin()))
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Keeps the records which don't match instead, like `srch not`
+    pub fn invert(mut self, invert: bool) -> Self {
+        self.invert = invert;
+        self
+    }
+
+    /// Keeps only the first occurrence of every matching record, like
+    /// `--unique`
+    pub fn unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    /// Stops after `limit` matching records
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Prefixes every match with the name of its input. By default names are
+    /// printed if there is more than one input, like in the cli
+    pub fn file_names(mut self, file_names: bool) -> Self {
+        self.file_names = Some(file_names);
+        self
+    }
+
+    /// Prefixes every match with its line number, like `--line-number`
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// The terminator written after every match, `\n` by default
+    pub fn terminator(mut self, terminator: impl Into<String>) -> Self {
+        self.terminator = terminator.into();
+        self
+    }
+
+    fn read(name: String, source: Source, mode: &Mode) -> io::Result<Records> {
+        let reader: Box<dyn BufRead> = match source {
+            Source::Path(path) => Box::new(BufReader::new(File::open(path)?)),
+            Source::Reader(reader) => reader,
+        };
+
+        let lines: Box<dyn Iterator<Item = io::Result<String>>> = match mode {
+            Mode::Delimited(delimiter) => Box::new(Delimited {
+                reader,
+                delimiter: delimiter.as_bytes().to_vec(),
+            }),
+            _ => Box::new(reader.lines()),
+        };
+
+        let records: Records = Box::new(lines.enumerate().map(move |(n, text)| {
+            text.map(|text| Record {
+                input: name.clone(),
+                line: n + 1,
+                text,
+            })
+        }));
+
+        Ok(match mode.clone() {
+            Mode::Line | Mode::Delimited(_) => records,
+            Mode::Paragraph => Box::new(Paragraphs { lines: records }),
+            Mode::Char => split_records(records, |text| {
+                text.graphemes(true).map(String::from).collect()
+            }),
+            Mode::Word(tokenizer) => split_records(records, move |text| {
+                Document::new(text)
+                    .words_with(tokenizer)
+                    .map(String::from)
+                    .collect()
+            }),
+        })
+    }
+
+    /// The matching records of all inputs in order, for applications which
+    /// present the matches themselves. Files are opened up front, so a
+    /// missing file is reported before any record is read
+    pub fn records(self) -> io::Result<Records> {
+        let mut records: Records = Box::new(std::iter::empty());
+
+        for (name, source) in self.inputs {
+            records = Box::new(records.chain(Self::read(name, source, &self.mode)?));
+        }
+
+        let expression = match self.invert {
+            true => self.expression.complement(),
+            false => self.expression,
+        };
+
+        let mut records: Records = Box::new(records.filter(move |record| match record {
+            Ok(record) => expression.matches(&record.text),
+            Err(_) => true,
+        }));
+
+        if self.unique {
+            let mut seen = HashSet::new();
+            records = Box::new(records.filter(move |record| match record {
+                Ok(record) => seen.insert(record.text.clone()),
+                Err(_) => true,
+            }));
+        }
+
+        if let Some(limit) = self.limit {
+            records = Box::new(records.take(limit));
+        }
+
+        Ok(records)
+    }
+
+    /// Writes the matching records to `out` and returns their number
+    pub fn run(self, mut out: impl Write) -> io::Result<usize> {
+        let file_names = self.file_names.unwrap_or(self.inputs.len() > 1);
+        let line_numbers = self.line_numbers;
+        let terminator = self.terminator.clone();
+
+        let mut matches = 0;
+
+        for record in self.records()? {
+            let record = record?;
+
+            if file_names {
+                write!(out, "{}:", record.input)?;
+            }
+
+            if line_numbers {
+                write!(out, "{}:", record.line)?;
+            }
+
+            write!(out, "{}{}", record.text, terminator)?;
+            matches += 1;
+        }
+
+        out.flush()?;
+
+        Ok(matches)
+    }
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field(
+                "inputs",
+                &self.inputs.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("mode", &self.mode)
+            .field("invert", &self.invert)
+            .field("unique", &self.unique)
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mode, Pipeline, Record};
+    use crate::{Expression, Tokenizer};
+    use pretty_assertions::assert_eq;
+
+    fn pipeline(source: &str) -> Pipeline {
+        Pipeline::new(Expression::new(source).unwrap())
+    }
+
+    fn output(pipeline: Pipeline) -> String {
+        let mut out = Vec::new();
+        pipeline.run(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn prefixes_matches_of_multiple_inputs() {
+        let pipeline = pipeline("numeric")
+            .text("a.txt", "1\nx\n")
+            .text("b.txt", "y\n2\n");
+
+        assert_eq!(output(pipeline), "a.txt:1\nb.txt:2\n");
+    }
+
+    #[test]
+    fn splits_records_by_mode() {
+        let paragraphs = pipeline("contains \"b\"")
+            .mode(Mode::Paragraph)
+            .text("a", "a\nb\n\n\nc\n")
+            .line_numbers(true);
+
+        assert_eq!(output(paragraphs), "1:a\nb\n");
+
+        let records = pipeline("numeric")
+            .mode(Mode::Delimited("||".to_string()))
+            .text("a", "1||a|b||22")
+            .terminator(";");
+
+        assert_eq!(output(records), "1;22;");
+    }
+
+    #[test]
+    fn inverts_deduplicates_and_limits() {
+        let pipeline = pipeline("numeric")
+            .mode(Mode::Word(Tokenizer::default()))
+            .text("a", "a 1 b a\nc 2 d\n")
+            .invert(true)
+            .unique(true)
+            .limit(3);
+
+        assert_eq!(output(pipeline), "a\nb\nc\n");
+    }
+
+    #[test]
+    fn yields_records_with_their_origin() {
+        let records = pipeline("alpha")
+            .mode(Mode::Char)
+            .text("a", "1\n2b\n")
+            .records()
+            .unwrap()
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            records,
+            vec![Record {
+                input: "a".to_string(),
+                line: 2,
+                text: "b".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        assert!(pipeline("numeric")
+            .file("does/not/exist.txt")
+            .records()
+            .is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::ffi::{c_char, c_void, CStr};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use libloading::{Library, Symbol};
+
+use crate::i18n;
+use crate::registry::{register_query, RegistryError};
+
+/// The version of the plugin abi, plugins built against another version are
+/// rejected instead of being called with a different layout
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// The function of a custom query exported by a plugin, it is passed the
+/// utf-8 bytes of the tested string and returns whether it matches
+pub type PluginQuery = extern "C" fn(input: *const u8, len: usize) -> bool;
+
+/// Passed to the `srch_plugin_register` entry point of a plugin, which calls
+/// `register_query` once for every query it provides. The name and
+/// description are nul-terminated utf-8 strings which are copied,
+/// `register_query` returns whether the query was registered
+#[repr(C)]
+pub struct Registrar {
+    context: *mut c_void,
+    pub register_query: extern "C" fn(
+        registrar: *mut Registrar,
+        name: *const c_char,
+        description: *const c_char,
+        query: PluginQuery,
+    ) -> bool,
+}
+
+/// The queries registered by the plugin being loaded
+#[derive(Default)]
+struct Registration {
+    queries: Vec<String>,
+    error: Option<RegistryError>,
+}
+
+extern "C" fn register(
+    registrar: *mut Registrar,
+    name: *const c_char,
+    description: *const c_char,
+    query: PluginQuery,
+) -> bool {
+    // Safety: the registrar is the one passed to the plugin, which outlives
+    // the entry point, the strings are nul-terminated as required by the abi
+    let (registration, name, description) = unsafe {
+        (
+            &mut *((*registrar).context as *mut Registration),
+            CStr::from_ptr(name).to_string_lossy(),
+            CStr::from_ptr(description).to_string_lossy(),
+        )
+    };
+
+    match register_query(&name, &description, move |input| {
+        query(input.as_ptr(), input.len())
+    }) {
+        Ok(()) => {
+            registration.queries.push(name.into_owned());
+            true
+        }
+        Err(err) => {
+            registration.error.get_or_insert(err);
+            false
+        }
+    }
+}
+
+/// The error of a plugin which can't be loaded
+#[derive(Debug)]
+pub enum PluginError {
+    /// The plugin directory can't be read
+    Io(io::Error),
+    /// The file isn't a library or lacks an entry point
+    Library(libloading::Error),
+    /// The plugin was built against another version of the abi
+    Abi(u32),
+    /// A query of the plugin can't be registered
+    Registry(RegistryError),
+    /// The entry point of the plugin reported an error
+    Failed,
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PluginError::Io(err) => write!(f, "{}", err),
+            PluginError::Library(err) => write!(f, "{}", err),
+            PluginError::Abi(version) => {
+                write!(f, "{}", i18n::message("plugin.abi", &version.to_string()))
+            }
+            PluginError::Registry(err) => write!(f, "{}", err),
+            PluginError::Failed => write!(f, "{}", i18n::message("plugin.failed", "")),
+        }
+    }
+}
+
+impl std::error::Error for PluginError {}
+
+impl From<io::Error> for PluginError {
+    fn from(err: io::Error) -> Self {
+        PluginError::Io(err)
+    }
+}
+
+impl From<libloading::Error> for PluginError {
+    fn from(err: libloading::Error) -> Self {
+        PluginError::Library(err)
+    }
+}
+
+/// A shared library providing custom queries, e.g. validators of internal
+/// id formats, which are registered like queries of
+/// [`register_query`](crate::register_query) when it is loaded.
+///
+/// A plugin exports two functions using the C abi:
+///
+/// ```c
+/// uint32_t srch_plugin_abi_version(void);
+/// bool srch_plugin_register(Registrar *registrar);
+/// ```
+///
+/// The first returns the [`PLUGIN_ABI_VERSION`] the plugin was built
+/// against, the second registers the queries of the plugin using the
+/// [`Registrar`] and returns whether it succeeded. Loaded libraries are
+/// never unloaded, since their queries can be used at any time
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plugin {
+    path: PathBuf,
+    queries: Vec<String>,
+}
+
+impl Plugin {
+    /// Loads a plugin and registers its queries
+    ///
+    /// # Safety
+    ///
+    /// The library runs arbitrary code when it is loaded and its queries
+    /// are called, it has to be trusted and follow the plugin abi
+    pub unsafe fn load(path: impl AsRef<Path>) -> Result<Self, PluginError> {
+        let path = path.as_ref();
+        let library = Library::new(path)?;
+
+        let version: Symbol<extern "C" fn() -> u32> = library.get(b"srch_plugin_abi_version")?;
+
+        if version() != PLUGIN_ABI_VERSION {
+            return Err(PluginError::Abi(version()));
+        }
+
+        let entry: Symbol<extern "C" fn(*mut Registrar) -> bool> =
+            library.get(b"srch_plugin_register")?;
+
+        let mut registration = Registration::default();
+        let mut registrar = Registrar {
+            context: &mut registration as *mut Registration as *mut c_void,
+            register_query: register,
+        };
+
+        let succeeded = entry(&mut registrar);
+
+        // Queries registered before a failure still point into the library
+        std::mem::forget(library);
+
+        match (succeeded, registration.error) {
+            (_, Some(err)) => Err(PluginError::Registry(err)),
+            (false, None) => Err(PluginError::Failed),
+            (true, None) => Ok(Self {
+                path: path.to_path_buf(),
+                queries: registration.queries,
+            }),
+        }
+    }
+
+    /// Loads every shared library in a directory in the order of their
+    /// names, a missing directory contains no plugins
+    ///
+    /// # Safety
+    ///
+    /// Every library of the directory is loaded, see [`Plugin::load`]
+    pub unsafe fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, (PathBuf, PluginError)> {
+        let dir = dir.as_ref();
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err((dir.to_path_buf(), err.into())),
+        };
+
+        let mut paths = entries
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()
+            .map_err(|err| (dir.to_path_buf(), err.into()))?;
+
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+        });
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| Self::load(&path).map_err(|err| (path, err)))
+            .collect()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The names of the queries the plugin registered
+    pub fn queries(&self) -> &[String] {
+        &self.queries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register, Plugin, PluginError, Registrar, Registration};
+    use crate::{registry::RegistryError, Expression};
+    use pretty_assertions::assert_eq;
+    use std::ffi::{c_void, CString};
+    use std::fs;
+
+    extern "C" fn is_plugin_id(input: *const u8, len: usize) -> bool {
+        let input = unsafe { std::slice::from_raw_parts(input, len) };
+        input.starts_with(b"ID-")
+    }
+
+    #[test]
+    fn registers_queries_through_the_registrar() {
+        let mut registration = Registration::default();
+        let mut registrar = Registrar {
+            context: &mut registration as *mut Registration as *mut c_void,
+            register_query: register,
+        };
+
+        let mut register = |name: &str, description: &str| {
+            let name = CString::new(name).unwrap();
+            let description = CString::new(description).unwrap();
+
+            (registrar.register_query)(
+                &mut registrar,
+                name.as_ptr(),
+                description.as_ptr(),
+                is_plugin_id,
+            )
+        };
+
+        assert!(register("test_plugin_id", "an internal id"));
+        assert!(!register("email", ""));
+        assert_eq!(registration.queries, vec!["test_plugin_id".to_string()]);
+        assert_eq!(
+            registration.error,
+            Some(RegistryError::Taken("email".to_string()))
+        );
+
+        let expr = Expression::new("@test_plugin_id").unwrap();
+        assert_eq!(expr.matches("ID-42"), true);
+        assert_eq!(expr.matches("42"), false);
+    }
+
+    #[test]
+    fn rejects_files_which_arent_plugins() {
+        let dir = std::env::temp_dir().join(format!("srch-plugins-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join(format!("broken.{}", std::env::consts::DLL_EXTENSION));
+        fs::write(&path, "not a library").unwrap();
+
+        assert!(matches!(
+            unsafe { Plugin::load(&path) },
+            Err(PluginError::Library(_))
+        ));
+        assert!(matches!(
+            unsafe { Plugin::load_dir(&dir) },
+            Err((failed, PluginError::Library(_))) if failed == path
+        ));
+        assert_eq!(
+            unsafe { Plugin::load_dir(dir.join("missing")) }.unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use polars::prelude::*;
+
+/// Adds [`srch`](Self::srch) to polars expressions
+pub trait SrchExprExt {
+    /// Evaluates to a boolean column which is true for the strings matching
+    /// the text expression `source`, nulls stay null. An invalid `source` is
+    /// reported when the query is executed
+    ///
+    /// ```rust
+    /// use polars::prelude::*;
+    /// use srch::SrchExprExt;
+    ///
+    /// let df = df!("msg" => ["an error occured", "ok", "error"]).unwrap();
+    ///
+    /// let errors = df
+    ///     .lazy()
+    ///     .filter(col("msg").srch("contains \"error\" and length 16"))
+    ///     .collect()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(errors.height(), 1);
+    /// ```
+    fn srch(self, source: &str) -> Expr;
+}
+
+impl SrchExprExt for Expr {
+    fn srch(self, source: &str) -> Expr {
+        let expression = crate::Expression::new(source)
+            .map(Arc::new)
+            .map_err(|err| err.to_string());
+
+        self.map(
+            move |column| {
+                let expression = match &expression {
+                    Ok(expression) => expression,
+                    Err(err) => polars_bail!(ComputeError: "invalid text expression: {}", err),
+                };
+
+                let strings = column.str()?;
+                let mask = strings
+                    .into_iter()
+                    .map(|value| value.map(|value| expression.matches(value)))
+                    .collect::<BooleanChunked>()
+                    .with_name(strings.name().clone());
+
+                Ok(mask.into_column())
+            },
+            |_, field| Ok(Field::new(field.name().clone(), DataType::Boolean)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use polars::prelude::*;
+
+    use super::SrchExprExt;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn keeps_nulls() {
+        let df = df!("value" => [Some("123"), None, Some("abc")]).unwrap();
+
+        let mask = df
+            .lazy()
+            .select([col("value").srch("numeric")])
+            .collect()
+            .unwrap();
+
+        let mask = mask.column("value").unwrap().bool().unwrap();
+        assert_eq!(
+            mask.into_iter().collect::<Vec<_>>(),
+            vec![Some(true), None, Some(false)]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_expressions_on_execution() {
+        let df = df!("value" => ["123"]).unwrap();
+        let result = df.lazy().filter(col("value").srch("numeric and")).collect();
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::Expression;
+
+/// A compiled text expression
+#[pyclass(name = "Expression", frozen)]
+struct PyExpression {
+	expression: Expression
+}
+
+#[pymethods]
+impl PyExpression {
+
+	#[new]
+	fn new(source: &str) -> PyResult<Self> {
+		let expression = Expression::new(source).map_err(|err| PyValueError::new_err(err.to_string()))?;
+		Ok(Self { expression })
+	}
+
+	fn matches(&self, input: &str) -> bool {
+		self.expression.matches(input)
+	}
+
+	fn explain(&self) -> String {
+		self.expression.explain()
+	}
+
+	/// Matches every input without holding the GIL, the result can be used
+	/// as a boolean mask, e.g. `df[expr.matches_all(df["col"].tolist())]`
+	fn matches_all(&self, py: Python<'_>, inputs: Vec<String>) -> Vec<bool> {
+		py.allow_threads(|| inputs.iter().map(|input| self.expression.matches(input)).collect())
+	}
+
+	/// Returns the matching inputs, matching happens without holding the GIL
+	fn filter(&self, py: Python<'_>, inputs: Vec<String>) -> Vec<String> {
+		py.allow_threads(|| inputs.into_iter().filter(|input| self.expression.matches(input)).collect())
+	}
+
+}
+
+#[pymodule]
+fn srch(module: &Bound<'_, PyModule>) -> PyResult<()> {
+	module.add_class::<PyExpression>()
+}
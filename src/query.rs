@@ -1,48 +1,355 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::pattern::Pattern;
+use crate::registry;
+
+/// A range of lengths in bytes, written after `length`, e.g. `length over 5`
+/// or `length between 2 and 8`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum LengthRange {
+	Over(u64),
+	Under(u64),
+	AtLeast(u64),
+	AtMost(u64),
+	/// Both bounds are included
+	Between(u64, u64)
+}
+
+impl LengthRange {
+
+	/// The shortest and the longest length in the range, both included. The
+	/// range is unbounded above if there is no longest length
+	pub fn bounds(&self) -> (u64, Option<u64>) {
+		match *self {
+			Self::Over(n) => (n.saturating_add(1), None),
+			Self::Under(n) => (0, Some(n.saturating_sub(1))),
+			Self::AtLeast(n) => (n, None),
+			Self::AtMost(n) => (0, Some(n)),
+			Self::Between(min, max) => (min, Some(max))
+		}
+	}
+
+	/// Whether no length is in the range, e.g. `under 0` or `between 5 and 2`
+	pub fn is_empty(&self) -> bool {
+		match *self {
+			Self::Over(n) => n == u64::MAX,
+			Self::Under(n) => n == 0,
+			Self::Between(min, max) => min > max,
+			Self::AtLeast(_) | Self::AtMost(_) => false
+		}
+	}
+
+	pub fn contains(&self, len: u64) -> bool {
+		match *self {
+			Self::Over(n) => len > n,
+			Self::Under(n) => len < n,
+			Self::AtLeast(n) => len >= n,
+			Self::AtMost(n) => len <= n,
+			Self::Between(min, max) => min <= len && len <= max
+		}
+	}
+
+}
+
+impl fmt::Display for LengthRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Over(n) => write!(f, "over {}", n),
+			Self::Under(n) => write!(f, "under {}", n),
+			Self::AtLeast(n) => write!(f, "at least {}", n),
+			Self::AtMost(n) => write!(f, "at most {}", n),
+			Self::Between(min, max) => write!(f, "between {} and {}", min, max)
+		}
+	}
+}
+
+/// A comparison of the number an input is with an integer, written after
+/// `value`, e.g. `value over 100`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum ValueRange {
+	Over(u64),
+	Under(u64),
+	Equals(u64)
+}
+
+/// Compares a decimal number like `-12.50` with an integer. The comparison
+/// is exact for numbers of any length, inputs which aren't decimal numbers
+/// can't be compared
+fn compare_value(input: &str, n: u64) -> Option<Ordering> {
+	let (negative, number) = match input.strip_prefix('-') {
+		Some(number) => (true, number),
+		None => (false, input)
+	};
+
+	let (integer, fraction) = number.split_once('.').unwrap_or((number, "0"));
+
+	if integer.is_empty() || fraction.is_empty() || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+		return None;
+	}
+
+	let integer = integer.trim_start_matches('0');
+	let is_fractional = fraction.bytes().any(|b| b != b'0');
+
+	if negative && (!integer.is_empty() || is_fractional) {
+		return Some(Ordering::Less);
+	}
+
+	let n = match n {
+		0 => String::new(),
+		n => n.to_string()
+	};
+
+	let ordering = integer.len().cmp(&n.len()).then_with(|| integer.cmp(n.as_str()));
+
+	match (ordering, is_fractional) {
+		(Ordering::Equal, true) => Some(Ordering::Greater),
+		(ordering, _) => Some(ordering)
+	}
+}
+
+impl ValueRange {
+
+	/// The integer the value is compared with
+	pub fn bound(&self) -> u64 {
+		match *self {
+			Self::Over(n) | Self::Under(n) | Self::Equals(n) => n
+		}
+	}
+
+	pub fn contains(&self, input: &str) -> bool {
+		match compare_value(input, self.bound()) {
+			Some(ordering) => match self {
+				Self::Over(_) => ordering == Ordering::Greater,
+				Self::Under(_) => ordering == Ordering::Less,
+				Self::Equals(_) => ordering == Ordering::Equal
+			},
+			None => false
+		}
+	}
+
+}
+
+impl fmt::Display for ValueRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Over(n) => write!(f, "over {}", n),
+			Self::Under(n) => write!(f, "under {}", n),
+			Self::Equals(n) => write!(f, "equals {}", n)
+		}
+	}
+}
+
+/// How often the argument of a quantified `contains` occurs without
+/// overlapping, e.g. `contains "," exactly 2 times`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub enum Times {
+	AtLeast(u64),
+	AtMost(u64),
+	Exactly(u64)
+}
+
+impl Times {
+
+	/// The fewest and the most occurrences, both included. There is no most
+	/// if any number of occurrences is enough
+	pub fn bounds(&self) -> (u64, Option<u64>) {
+		match *self {
+			Self::AtLeast(n) => (n, None),
+			Self::AtMost(n) => (0, Some(n)),
+			Self::Exactly(n) => (n, Some(n))
+		}
+	}
+
+	/// Whether `arg` occurs in `input` as often as required, occurrences are
+	/// only counted until the result is known
+	pub fn count_in(&self, input: &str, arg: &str) -> bool {
+		let (min, max) = self.bounds();
+		let limit = max.unwrap_or(min).saturating_add(1);
+		let count = input.matches(arg).take(limit.min(usize::MAX as u64) as usize).count() as u64;
+
+		min <= count && max.is_none_or(|max| count <= max)
+	}
+
+}
+
+impl fmt::Display for Times {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let (keyword, n) = match *self {
+			Self::AtLeast(n) => ("at least", n),
+			Self::AtMost(n) => ("at most", n),
+			Self::Exactly(n) => ("exactly", n)
+		};
+
+		match n {
+			1 => write!(f, "{} 1 time", keyword),
+			n => write!(f, "{} {} times", keyword, n)
+		}
+	}
+}
+
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Query {
-	Starts(String),
-	Ends(String),
-	Contains(String),
-	Equals(String),
+	Starts(Arc<str>),
+	Ends(Arc<str>),
+	Contains(Arc<str>),
+	/// A `contains` counting the occurrences of its non-empty argument
+	ContainsTimes(Arc<str>, Times),
+	Equals(Arc<str>),
+	/// The case-insensitive variants of the string queries, their argument
+	/// is lowercased when the expression is lexed
+	IStarts(Arc<str>),
+	IEnds(Arc<str>),
+	IContains(Arc<str>),
+	IEquals(Arc<str>),
 	Length(u64),
+	LengthRange(LengthRange),
+	/// The number of words separated by whitespace, compared like lengths,
+	/// e.g. `words 3` or `words over 5`
+	Words(u64),
+	WordsRange(LengthRange),
+	/// Compares the input parsed as a decimal number, inputs which aren't
+	/// numbers don't match
+	Value(ValueRange),
 	Numeric,
 	Alpha,
 	Alphanumeric,
-	Special
+	Uppercase,
+	Lowercase,
+	Whitespace,
+	Special,
+	Pattern(Pattern),
+	/// A query registered using [`register_query`](crate::register_query),
+	/// written `@name` like a pattern
+	Custom(Arc<str>)
 }
 
 impl Query {
 
-	pub fn into_keyword(&self) -> &str {
+	pub fn as_keyword(&self) -> &str {
 		match self {
 			Self::Starts(_) => "starts",
 			Self::Ends(_) => "ends",
-			Self::Contains(_) => "contains",
+			Self::Contains(_) | Self::ContainsTimes(..) => "contains",
 			Self::Equals(_) => "equals",
-			Self::Length(_) => "length",
+			Self::IStarts(_) => "istarts",
+			Self::IEnds(_) => "iends",
+			Self::IContains(_) => "icontains",
+			Self::IEquals(_) => "iequals",
+			Self::Length(_) | Self::LengthRange(_) => "length",
+			Self::Words(_) | Self::WordsRange(_) => "words",
+			Self::Value(_) => "value",
 			Self::Numeric => "numeric",
 			Self::Alpha => "alpha",
 			Self::Alphanumeric => "alphanumeric",
-			Self::Special => "special"
+			Self::Uppercase => "uppercase",
+			Self::Lowercase => "lowercase",
+			Self::Whitespace => "whitespace",
+			Self::Special => "special",
+			Self::Pattern(pattern) => pattern.as_keyword(),
+			Self::Custom(name) => name
 		}
 	}
 
-	pub fn exec(&self, tested_string: &String) -> bool {
+	/// The shortest and the longest length matched by a `length` query, both
+	/// included
+	pub fn length_bounds(&self) -> Option<(u64, Option<u64>)> {
 		match self {
-			Self::Starts(arg) => tested_string.starts_with(arg),
-			Self::Ends(arg) => tested_string.ends_with(arg),
-			Self::Contains(arg) => tested_string.contains(arg),
-			Self::Equals(arg) => &tested_string == &arg,
+			Self::Length(len) => Some((*len, Some(*len))),
+			Self::LengthRange(range) => Some(range.bounds()),
+			_ => None
+		}
+	}
+
+	/// The fewest and the most words matched by a `words` query, both
+	/// included
+	pub fn word_bounds(&self) -> Option<(u64, Option<u64>)> {
+		match self {
+			Self::Words(words) => Some((*words, Some(*words))),
+			Self::WordsRange(range) => Some(range.bounds()),
+			_ => None
+		}
+	}
+
+	pub fn exec(&self, tested_string: &str) -> bool {
+		self.exec_with(tested_string, false)
+	}
+
+	/// Executes the query, `unicode` lets the classes match non-ascii
+	/// characters of the class as well
+	pub fn exec_with(&self, tested_string: &str, unicode: bool) -> bool {
+		match self {
+			Self::Numeric if unicode => tested_string.chars().all(char::is_numeric),
+			Self::Alpha if unicode => tested_string.chars().all(char::is_alphabetic),
+			Self::Alphanumeric if unicode => tested_string.chars().all(char::is_alphanumeric),
+			Self::Uppercase if unicode => tested_string.chars().all(char::is_uppercase),
+			Self::Lowercase if unicode => tested_string.chars().all(char::is_lowercase),
+			Self::Whitespace if unicode => tested_string.chars().all(char::is_whitespace),
+			Self::Special if unicode => tested_string.chars().all(|c| {
+				c.is_ascii_punctuation() || !c.is_ascii() && !c.is_alphanumeric() && !c.is_whitespace() && !c.is_control()
+			}),
+			Self::Starts(arg) => tested_string.starts_with(&**arg),
+			Self::Ends(arg) => tested_string.ends_with(&**arg),
+			Self::Contains(arg) => tested_string.contains(&**arg),
+			Self::ContainsTimes(arg, times) => times.count_in(tested_string, arg),
+			Self::Equals(arg) => tested_string == &**arg,
+			Self::IStarts(arg) => tested_string.to_lowercase().starts_with(&**arg),
+			Self::IEnds(arg) => tested_string.to_lowercase().ends_with(&**arg),
+			Self::IContains(arg) => tested_string.to_lowercase().contains(&**arg),
+			Self::IEquals(arg) => tested_string.to_lowercase() == **arg,
 			Self::Length(len) => tested_string.len() == *len as usize,
+			Self::LengthRange(range) => range.contains(tested_string.len() as u64),
+			Self::Words(_) | Self::WordsRange(_) => {
+				let words = match unicode {
+					true => tested_string.split_whitespace().count() as u64,
+					false => tested_string.split_ascii_whitespace().count() as u64
+				};
+
+				self.word_bounds().is_some_and(|(min, max)| min <= words && max.is_none_or(|max| words <= max))
+			},
+			Self::Value(range) => range.contains(tested_string),
 			Self::Numeric => tested_string.chars().all(|c| c.is_ascii_digit()),
 			Self::Alpha => tested_string.chars().all(|c| c.is_ascii_alphabetic()),
 			Self::Alphanumeric => tested_string.chars().all(|c| c.is_ascii_alphanumeric()),
-			Self::Special => tested_string.chars().all(|c| c.is_ascii_punctuation())
+			Self::Uppercase => tested_string.chars().all(|c| c.is_ascii_uppercase()),
+			Self::Lowercase => tested_string.chars().all(|c| c.is_ascii_lowercase()),
+			Self::Whitespace => tested_string.chars().all(|c| c.is_ascii_whitespace()),
+			Self::Special => tested_string.chars().all(|c| c.is_ascii_punctuation()),
+			Self::Pattern(pattern) => pattern.exec(tested_string),
+			Self::Custom(name) => registry::exec(name, tested_string)
 		}
 	}
 
 }
 
+impl fmt::Display for Query {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Starts(arg) | Self::Ends(arg) | Self::Contains(arg) | Self::Equals(arg)
+			| Self::IStarts(arg) | Self::IEnds(arg) | Self::IContains(arg) | Self::IEquals(arg) => {
+				write!(f, "{} \"{}\"", self.as_keyword(), arg)
+			},
+			Self::ContainsTimes(arg, times) => write!(f, "{} \"{}\" {}", self.as_keyword(), arg, times),
+			Self::Length(len) => write!(f, "{} {}", self.as_keyword(), len),
+			Self::LengthRange(range) => write!(f, "{} {}", self.as_keyword(), range),
+			Self::Words(words) => write!(f, "{} {}", self.as_keyword(), words),
+			Self::WordsRange(range) => write!(f, "{} {}", self.as_keyword(), range),
+			Self::Value(range) => write!(f, "{} {}", self.as_keyword(), range),
+			Self::Custom(name) => write!(f, "@{}", name),
+			_ => write!(f, "{}", self.as_keyword())
+		}
+	}
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -55,7 +362,7 @@ mod tests {
 		#[test]
 		fn correct() {
 			assert_eq!(
-				Query::Starts("foo".to_string()).exec(&"foobar".to_string()),
+				Query::Starts("foo".into()).exec("foobar"),
 				true
 			);
 		}
@@ -63,7 +370,7 @@ mod tests {
 		#[test]
 		fn correct_but_with_space() {
 			assert_eq!(
-				Query::Starts("foo".to_string()).exec(&" foobar".to_string()),
+				Query::Starts("foo".into()).exec(" foobar"),
 				false
 			);
 		}
@@ -71,7 +378,7 @@ mod tests {
 		#[test]
 		fn incorrect() {
 			assert_eq!(
-				Query::Starts("foo".to_string()).exec(&"barfoo".to_string()),
+				Query::Starts("foo".into()).exec("barfoo"),
 				false
 			);
 		}
@@ -84,7 +391,7 @@ mod tests {
 		#[test]
 		fn correct() {
 			assert_eq!(
-				Query::Ends("baz".to_string()).exec(&"foobaz".to_string()),
+				Query::Ends("baz".into()).exec("foobaz"),
 				true
 			);
 		}
@@ -92,7 +399,7 @@ mod tests {
 		#[test]
 		fn correct_but_with_space() {
 			assert_eq!(
-				Query::Ends("baz".to_string()).exec(&"baz ".to_string()),
+				Query::Ends("baz".into()).exec("baz "),
 				false
 			);
 		}
@@ -100,7 +407,7 @@ mod tests {
 		#[test]
 		fn incorrect() {
 			assert_eq!(
-				Query::Ends("baz".to_string()).exec(&"bazfoo".to_string()),
+				Query::Ends("baz".into()).exec("bazfoo"),
 				false
 			);
 		}
@@ -113,7 +420,7 @@ mod tests {
 		#[test]
 		fn at_start() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"bazfoo".to_string()),
+				Query::Contains("baz".into()).exec("bazfoo"),
 				true
 			);
 		}
@@ -121,7 +428,7 @@ mod tests {
 		#[test]
 		fn at_start_with_space() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&" bazfoo".to_string()),
+				Query::Contains("baz".into()).exec(" bazfoo"),
 				true
 			);
 		}
@@ -129,7 +436,7 @@ mod tests {
 		#[test]
 		fn at_start_with_one_char_infront() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"Xbazfoo".to_string()),
+				Query::Contains("baz".into()).exec("Xbazfoo"),
 				true
 			);
 		}
@@ -137,7 +444,7 @@ mod tests {
 		#[test]
 		fn somewhere_in_string() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"ewfnorbaz2dewf1!".to_string()),
+				Query::Contains("baz".into()).exec("ewfnorbaz2dewf1!"),
 				true
 			);
 		}
@@ -145,7 +452,7 @@ mod tests {
 		#[test]
 		fn at_end() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"foobaz".to_string()),
+				Query::Contains("baz".into()).exec("foobaz"),
 				true
 			);
 		}
@@ -153,7 +460,7 @@ mod tests {
 		#[test]
 		fn at_end_with_space() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"bazfoo ".to_string()),
+				Query::Contains("baz".into()).exec("bazfoo "),
 				true
 			);
 		}
@@ -161,7 +468,7 @@ mod tests {
 		#[test]
 		fn at_end_with_one_char_behind() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"foobazX".to_string()),
+				Query::Contains("baz".into()).exec("foobazX"),
 				true
 			);
 		}
@@ -169,7 +476,7 @@ mod tests {
 		#[test]
 		fn does_not_contain() {
 			assert_eq!(
-				Query::Contains("baz".to_string()).exec(&"foobar".to_string()),
+				Query::Contains("baz".into()).exec("foobar"),
 				false
 			);
 		}
@@ -182,7 +489,7 @@ mod tests {
 		#[test]
 		fn correct() {
 			assert_eq!(
-				Query::Equals("foo".to_string()).exec(&"foo".to_string()),
+				Query::Equals("foo".into()).exec("foo"),
 				true
 			);
 		}
@@ -190,7 +497,7 @@ mod tests {
 		#[test]
 		fn correct_but_with_space() {
 			assert_eq!(
-				Query::Equals("foo".to_string()).exec(&" foo".to_string()),
+				Query::Equals("foo".into()).exec(" foo"),
 				false
 			);
 		}
@@ -198,7 +505,7 @@ mod tests {
 		#[test]
 		fn close_to_correct() {
 			assert_eq!(
-				Query::Equals("foo".to_string()).exec(&"fooo".to_string()),
+				Query::Equals("foo".into()).exec("fooo"),
 				false
 			);
 		}
@@ -206,12 +513,31 @@ mod tests {
 		#[test]
 		fn incorrect() {
 			assert_eq!(
-				Query::Equals("foo".to_string()).exec(&"bar".to_string()),
+				Query::Equals("foo".into()).exec("bar"),
 				false
 			);
 		}
 	}
 
+	mod ignoring_case {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn folds_the_input() {
+			assert_eq!(Query::IStarts("foo".into()).exec("FOObar"), true);
+			assert_eq!(Query::IEnds("bar".into()).exec("fooBaR"), true);
+			assert_eq!(Query::IContains("error".into()).exec("fatal ERROR: disk full"), true);
+			assert_eq!(Query::IEquals("straße".into()).exec("STRAßE"), true);
+		}
+
+		#[test]
+		fn still_compares_the_letters() {
+			assert_eq!(Query::IStarts("foo".into()).exec("barFOO"), false);
+			assert_eq!(Query::IEquals("foo".into()).exec("FOOO"), false);
+		}
+	}
+
 	mod length {
 		use super::*;
 		use pretty_assertions::assert_eq;
@@ -219,7 +545,7 @@ mod tests {
 		#[test]
 		fn correct() {
 			assert_eq!(
-				Query::Length(3).exec(&"foo".to_string()),
+				Query::Length(3).exec("foo"),
 				true
 			);
 		}
@@ -227,7 +553,7 @@ mod tests {
 		#[test]
 		fn one_char_to_short() {
 			assert_eq!(
-				Query::Length(3).exec(&"fo".to_string()),
+				Query::Length(3).exec("fo"),
 				false
 			);
 		}
@@ -235,7 +561,7 @@ mod tests {
 		#[test]
 		fn one_char_to_long() {
 			assert_eq!(
-				Query::Length(3).exec(&"fooo".to_string()),
+				Query::Length(3).exec("fooo"),
 				false
 			);
 		}
@@ -243,12 +569,134 @@ mod tests {
 		#[test]
 		fn completly_wrong_length() {
 			assert_eq!(
-				Query::Length(3).exec(&"foobarbaz".to_string()),
+				Query::Length(3).exec("foobarbaz"),
 				false
 			);
 		}
 	}
 
+	mod length_range {
+		use super::*;
+		use crate::query::LengthRange;
+		use pretty_assertions::assert_eq;
+
+		fn lengths(range: LengthRange) -> Vec<usize> {
+			(0..8).filter(|len| Query::LengthRange(range).exec(&"a".repeat(*len))).collect()
+		}
+
+		#[test]
+		fn excludes_the_bound() {
+			assert_eq!(lengths(LengthRange::Over(5)), vec![6, 7]);
+			assert_eq!(lengths(LengthRange::Under(2)), vec![0, 1]);
+		}
+
+		#[test]
+		fn includes_the_bound() {
+			assert_eq!(lengths(LengthRange::AtLeast(6)), vec![6, 7]);
+			assert_eq!(lengths(LengthRange::AtMost(1)), vec![0, 1]);
+			assert_eq!(lengths(LengthRange::Between(2, 4)), vec![2, 3, 4]);
+		}
+
+		#[test]
+		fn counts_bytes() {
+			assert_eq!(Query::LengthRange(LengthRange::Over(1)).exec("ä"), true);
+		}
+
+		#[test]
+		fn has_bounds() {
+			assert_eq!(LengthRange::Over(5).bounds(), (6, None));
+			assert_eq!(LengthRange::Under(5).bounds(), (0, Some(4)));
+			assert_eq!(LengthRange::Between(2, 4).bounds(), (2, Some(4)));
+			assert_eq!(LengthRange::Under(0).is_empty(), true);
+			assert_eq!(LengthRange::Between(4, 2).is_empty(), true);
+			assert_eq!(LengthRange::AtMost(0).is_empty(), false);
+		}
+	}
+
+	mod value {
+		use super::*;
+		use crate::query::ValueRange;
+		use pretty_assertions::assert_eq;
+
+		fn matching(range: ValueRange) -> Vec<&'static str> {
+			["-3", "-0", "0", "0.5", "9", "10", "010", "10.00", "10.01", "11", "123456789012345678901234567890"]
+				.iter()
+				.copied()
+				.filter(|input| Query::Value(range).exec(input))
+				.collect()
+		}
+
+		#[test]
+		fn compares_numbers() {
+			assert_eq!(matching(ValueRange::Over(10)), vec!["10.01", "11", "123456789012345678901234567890"]);
+			assert_eq!(matching(ValueRange::Under(1)), vec!["-3", "-0", "0", "0.5"]);
+			assert_eq!(matching(ValueRange::Equals(10)), vec!["10", "010", "10.00"]);
+			assert_eq!(matching(ValueRange::Equals(0)), vec!["-0", "0"]);
+		}
+
+		#[test]
+		fn doesnt_match_other_inputs() {
+			for input in ["", "-", "1.", ".5", "1e3", " 1", "+1", "0x10", "1,000"].iter() {
+				assert_eq!(Query::Value(ValueRange::Under(u64::MAX)).exec(input), false, "{}", input);
+			}
+		}
+	}
+
+	mod contains_times {
+		use super::*;
+		use crate::query::Times;
+		use pretty_assertions::assert_eq;
+
+		fn count_matches(times: Times) -> Vec<&'static str> {
+			["", "a", "a,b", "a,b,c", ",,,", "a,,b,c"]
+				.iter()
+				.copied()
+				.filter(|input| Query::ContainsTimes(",".into(), times).exec(input))
+				.collect()
+		}
+
+		#[test]
+		fn counts_occurrences() {
+			assert_eq!(count_matches(Times::Exactly(2)), vec!["a,b,c"]);
+			assert_eq!(count_matches(Times::AtLeast(3)), vec![",,,", "a,,b,c"]);
+			assert_eq!(count_matches(Times::AtMost(1)), vec!["", "a", "a,b"]);
+			assert_eq!(count_matches(Times::Exactly(0)), vec!["", "a"]);
+		}
+
+		#[test]
+		fn doesnt_count_overlapping_occurrences() {
+			assert_eq!(Query::ContainsTimes("aa".into(), Times::Exactly(2)).exec("aaaa"), true);
+			assert_eq!(Query::ContainsTimes("aa".into(), Times::Exactly(2)).exec("aaa"), false);
+		}
+	}
+
+	mod words {
+		use super::*;
+		use crate::query::LengthRange;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn counts_words() {
+			assert_eq!(Query::Words(3).exec("the quick fox"), true);
+			assert_eq!(Query::Words(3).exec("  the\tquick\n fox  "), true);
+			assert_eq!(Query::Words(3).exec("the quick brown fox"), false);
+			assert_eq!(Query::Words(0).exec(" \t"), true);
+		}
+
+		#[test]
+		fn compares_word_counts() {
+			assert_eq!(Query::WordsRange(LengthRange::Over(2)).exec("a b c"), true);
+			assert_eq!(Query::WordsRange(LengthRange::Over(2)).exec("a b"), false);
+			assert_eq!(Query::WordsRange(LengthRange::Between(1, 2)).exec("a-b"), true);
+		}
+
+		#[test]
+		fn splits_at_unicode_whitespace_if_enabled() {
+			assert_eq!(Query::Words(2).exec_with("a\u{a0}b", false), false);
+			assert_eq!(Query::Words(2).exec_with("a\u{a0}b", true), true);
+		}
+	}
+
 	mod numeric {
 		use super::*;
 		use pretty_assertions::assert_eq;
@@ -256,7 +704,7 @@ mod tests {
 		#[test]
 		fn only_digits() {
 			assert_eq!(
-				Query::Numeric.exec(&"123456789".to_string()),
+				Query::Numeric.exec("123456789"),
 				true
 			);
 		}
@@ -264,7 +712,7 @@ mod tests {
 		#[test]
 		fn digits_and_spaces() {
 			assert_eq!(
-				Query::Numeric.exec(&"123 213124 2".to_string()),
+				Query::Numeric.exec("123 213124 2"),
 				false
 			);
 		}
@@ -272,7 +720,7 @@ mod tests {
 		#[test]
 		fn digits_and_alpha() {
 			assert_eq!(
-				Query::Numeric.exec(&"123e".to_string()),
+				Query::Numeric.exec("123e"),
 				false
 			);
 		}
@@ -280,7 +728,7 @@ mod tests {
 		#[test]
 		fn digits_and_punctuation() {
 			assert_eq!(
-				Query::Numeric.exec(&"123.2".to_string()),
+				Query::Numeric.exec("123.2"),
 				false
 			);
 		}
@@ -288,7 +736,7 @@ mod tests {
 		#[test]
 		fn empty() {
 			assert_eq!(
-				Query::Numeric.exec(&"".to_string()),
+				Query::Numeric.exec(""),
 				true
 			);
 		}
@@ -301,7 +749,7 @@ mod tests {
 		#[test]
 		fn only_alpha() {
 			assert_eq!(
-				Query::Alpha.exec(&"abc".to_string()),
+				Query::Alpha.exec("abc"),
 				true
 			);
 		}
@@ -309,7 +757,7 @@ mod tests {
 		#[test]
 		fn alpha_and_spaces() {
 			assert_eq!(
-				Query::Alpha.exec(&"abc def ghij k".to_string()),
+				Query::Alpha.exec("abc def ghij k"),
 				false
 			);
 		}
@@ -317,7 +765,7 @@ mod tests {
 		#[test]
 		fn alpha_and_digits() {
 			assert_eq!(
-				Query::Alpha.exec(&"ABC1".to_string()),
+				Query::Alpha.exec("ABC1"),
 				false
 			);
 		}
@@ -325,7 +773,7 @@ mod tests {
 		#[test]
 		fn alpha_and_punctuation() {
 			assert_eq!(
-				Query::Alpha.exec(&"abc.com".to_string()),
+				Query::Alpha.exec("abc.com"),
 				false
 			);
 		}
@@ -333,10 +781,91 @@ mod tests {
 		#[test]
 		fn empty() {
 			assert_eq!(
-				Query::Alpha.exec(&"".to_string()),
+				Query::Alpha.exec(""),
+				true
+			);
+		}
+	}
+
+	mod uppercase {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn only_uppercase() {
+			assert_eq!(
+				Query::Uppercase.exec("MAXVALUE"),
+				true
+			);
+		}
+
+		#[test]
+		fn mixed_case() {
+			assert_eq!(
+				Query::Uppercase.exec("Max"),
+				false
+			);
+		}
+
+		#[test]
+		fn non_ascii_with_unicode() {
+			assert_eq!(Query::Uppercase.exec("ÄÖÜ"), false);
+			assert_eq!(Query::Uppercase.exec_with("ÄÖÜ", true), true);
+		}
+	}
+
+	mod lowercase {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn only_lowercase() {
+			assert_eq!(
+				Query::Lowercase.exec("abc"),
+				true
+			);
+		}
+
+		#[test]
+		fn lowercase_and_digits() {
+			assert_eq!(
+				Query::Lowercase.exec("abc1"),
+				false
+			);
+		}
+
+		#[test]
+		fn non_ascii_with_unicode() {
+			assert_eq!(Query::Lowercase.exec("straße"), false);
+			assert_eq!(Query::Lowercase.exec_with("straße", true), true);
+		}
+	}
+
+	mod whitespace {
+		use super::*;
+		use pretty_assertions::assert_eq;
+
+		#[test]
+		fn indentation() {
+			assert_eq!(
+				Query::Whitespace.exec(" \t  \r"),
 				true
 			);
 		}
+
+		#[test]
+		fn indented_text() {
+			assert_eq!(
+				Query::Whitespace.exec("  foo"),
+				false
+			);
+		}
+
+		#[test]
+		fn non_breaking_space_with_unicode() {
+			assert_eq!(Query::Whitespace.exec("\u{a0}"), false);
+			assert_eq!(Query::Whitespace.exec_with("\u{a0}", true), true);
+		}
 	}
 
 }
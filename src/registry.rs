@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::i18n;
+use crate::pattern::Pattern;
+
+type Matcher = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+struct Custom {
+    description: String,
+    matches: Matcher,
+}
+
+static QUERIES: RwLock<BTreeMap<String, Custom>> = RwLock::new(BTreeMap::new());
+
+/// The error of a custom query which can't be registered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RegistryError {
+    /// The name isn't a lowercase letter followed by lowercase letters,
+    /// digits and underscores
+    InvalidName(String),
+    /// The name is taken by a built-in pattern or another custom query
+    Taken(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            Self::InvalidName(name) => i18n::message("registry.invalid_name", name),
+            Self::Taken(name) => i18n::message("registry.taken", name),
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars.next().is_some_and(|c| c.is_ascii_lowercase())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Registers a custom query, which can be used like a built-in pattern by
+/// prefixing its name with `@`, e.g. an internal id format. Expressions are
+/// checked against the queries registered when they are compiled. The name
+/// can't be used by another query, so names should be specific
+///
+/// ```rust
+/// srch::register_query("ticket", "a ticket id like \"OPS-123\"", |input| {
+///     input.split_once('-').is_some_and(|(project, id)| {
+///         project.chars().all(|c| c.is_ascii_uppercase()) && id.parse::<u32>().is_ok()
+///     })
+/// })
+/// .unwrap();
+///
+/// let expr = srch::Expression::new("@ticket and starts \"OPS\"").unwrap();
+/// assert!(expr.matches("OPS-123"));
+/// assert!(!expr.matches("OPS-abc"));
+/// ```
+pub fn register_query<F>(name: &str, description: &str, matches: F) -> Result<(), RegistryError>
+where
+    F: Fn(&str) -> bool + Send + Sync + 'static,
+{
+    if !is_valid_name(name) {
+        return Err(RegistryError::InvalidName(name.to_string()));
+    }
+
+    let mut queries = QUERIES.write().unwrap_or_else(|err| err.into_inner());
+
+    if Pattern::from_name(name).is_some() || queries.contains_key(name) {
+        return Err(RegistryError::Taken(name.to_string()));
+    }
+
+    queries.insert(
+        name.to_string(),
+        Custom {
+            description: description.to_string(),
+            matches: Arc::new(matches),
+        },
+    );
+
+    Ok(())
+}
+
+/// The names and descriptions of the registered custom queries, ordered by
+/// name
+pub fn registered_queries() -> Vec<(String, String)> {
+    QUERIES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .iter()
+        .map(|(name, custom)| (name.clone(), custom.description.clone()))
+        .collect()
+}
+
+pub(crate) fn is_registered(name: &str) -> bool {
+    QUERIES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .contains_key(name)
+}
+
+pub(crate) fn description(name: &str) -> Option<String> {
+    QUERIES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(name)
+        .map(|custom| custom.description.clone())
+}
+
+/// Runs a custom query, queries which aren't registered (e.g. of a
+/// deserialized expression) don't match
+pub(crate) fn exec(name: &str, input: &str) -> bool {
+    let matches = QUERIES
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .get(name)
+        .map(|custom| custom.matches.clone());
+
+    matches.is_some_and(|matches| matches(input))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{register_query, registered_queries, RegistryError};
+    use crate::{Expression, SqlDialect};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn registers_queries_usable_in_expressions() {
+        register_query("test_even", "an even number", |input| {
+            input.parse::<u64>().is_ok_and(|n| n.is_multiple_of(2))
+        })
+        .unwrap();
+
+        let expr = Expression::new("@test_even and length 2").unwrap();
+
+        assert_eq!(expr.matches("42"), true);
+        assert_eq!(expr.matches("43"), false);
+        assert_eq!(
+            expr.to_sql_predicate("id", SqlDialect::Postgres),
+            "(test_even(id) AND octet_length(id) = 2)"
+        );
+        assert!(
+            registered_queries().contains(&("test_even".to_string(), "an even number".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_and_taken_names() {
+        assert_eq!(
+            register_query("Test-Id", "", |_| true),
+            Err(RegistryError::InvalidName("Test-Id".to_string()))
+        );
+        assert_eq!(
+            register_query("email", "", |_| true),
+            Err(RegistryError::Taken("email".to_string()))
+        );
+
+        register_query("test_taken", "", |_| true).unwrap();
+
+        assert_eq!(
+            register_query("test_taken", "", |_| false),
+            Err(RegistryError::Taken("test_taken".to_string()))
+        );
+    }
+}
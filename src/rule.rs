@@ -0,0 +1,21 @@
+/// A named expression values can be checked against at the type level,
+/// usually defined using [`rule!`](macro.rule.html)
+pub trait Rule {
+    const EXPRESSION: &'static str;
+}
+
+/// Defines a [`Rule`] type for an expression
+///
+/// ```rust
+/// srch::rule!(ZipCode, "numeric and length 5");
+/// ```
+#[macro_export]
+macro_rules! rule {
+    ($vis:vis $name:ident, $expression:expr) => {
+        $vis struct $name;
+
+        impl $crate::Rule for $name {
+            const EXPRESSION: &'static str = $expression;
+        }
+    };
+}
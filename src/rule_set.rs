@@ -0,0 +1,735 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::{Comparison, Error, Expression, Relation};
+
+/// How a rule differs between two versions of a rule set, see
+/// [`RuleSet::compare`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuleChange {
+    Added,
+    Removed,
+    /// The expression of the rule changed
+    Changed(Comparison),
+}
+
+/// An error loading a [`RuleSet`]
+#[derive(Clone, Debug)]
+pub enum RuleSetError {
+    /// The file isn't valid toml or doesn't have the expected layout
+    Format(String),
+    /// The file was written by a newer version of srch
+    UnsupportedVersion(u64),
+    /// The expressions of one or more rules are invalid, ordered by name
+    InvalidRules(Vec<InvalidRule>),
+}
+
+/// A rule of a [`RuleSet`] with an invalid expression
+#[derive(Clone, Debug)]
+pub struct InvalidRule {
+    pub name: String,
+    pub error: Error,
+}
+
+impl fmt::Display for InvalidRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rule \"{}\": {}", self.name, self.error)
+    }
+}
+
+impl fmt::Display for RuleSetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleSetError::Format(err) => write!(f, "{}", err),
+            RuleSetError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported version {}, expected at most {}",
+                version,
+                RuleSet::VERSION
+            ),
+            RuleSetError::InvalidRules(rules) => {
+                let rules = rules.iter().map(InvalidRule::to_string).collect::<Vec<_>>();
+                write!(f, "{}", rules.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for RuleSetError {}
+
+type Hook = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Hooks rewriting the expressions of older rule sets, each hook migrates
+/// the syntax of one version to the next
+#[derive(Default)]
+pub struct Migrations {
+    hooks: BTreeMap<u64, Hook>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a hook migrating expressions of version `from` to `from + 1`,
+    /// versions without a hook are migrated unchanged
+    pub fn add(mut self, from: u64, hook: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.hooks.insert(from, Box::new(hook));
+        self
+    }
+
+    fn migrate(&self, mut source: String, from: u64) -> String {
+        for (_, hook) in self.hooks.range(from..RuleSet::VERSION) {
+            source = hook(&source);
+        }
+
+        source
+    }
+}
+
+/// How serious a match of a rule is, ordered from the least to the most
+/// serious
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 4] = [
+        Severity::Info,
+        Severity::Warning,
+        Severity::Error,
+        Severity::Critical,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|severity| severity.name() == name)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Selects rules of a [`RuleSet`] by their metadata, an empty selector
+/// selects every rule
+///
+/// ```rust
+/// use srch::{RuleSet, Selector, Severity};
+///
+/// let rules = RuleSet::parse(r#"
+///     version = 1
+///
+///     [rules.api-key]
+///     expression = 'starts "sk_" and length 32'
+///     severity = "critical"
+///     tags = ["secrets"]
+///
+///     [rules.todo]
+///     expression = 'contains "TODO"'
+///     severity = "info"
+/// "#).unwrap();
+///
+/// let selector = Selector::new().min_severity(Severity::Warning);
+/// let names = rules.select(&selector).map(|rule| rule.name()).collect::<Vec<_>>();
+///
+/// assert_eq!(names, vec!["api-key"]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selector {
+    /// Selects the rules with at least one of the tags
+    pub tags: Vec<String>,
+    /// Selects the rules at least this serious, rules without a severity
+    /// aren't selected
+    pub min_severity: Option<Severity>,
+}
+
+impl Selector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn min_severity(mut self, severity: Severity) -> Self {
+        self.min_severity = Some(severity);
+        self
+    }
+
+    pub fn matches(&self, rule: &NamedRule) -> bool {
+        let tagged = self.tags.is_empty() || self.tags.iter().any(|tag| rule.has_tag(tag));
+
+        let serious = match self.min_severity {
+            Some(min) => rule.severity.is_some_and(|severity| severity >= min),
+            None => true,
+        };
+
+        tagged && serious
+    }
+}
+
+/// A named expression of a [`RuleSet`]
+pub struct NamedRule {
+    name: String,
+    source: String,
+    description: Option<String>,
+    severity: Option<Severity>,
+    tags: Vec<String>,
+    expression: Expression,
+}
+
+impl NamedRule {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The (migrated) source of the expression
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn severity(&self) -> Option<Severity> {
+        self.severity
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| own == tag)
+    }
+
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+}
+
+/// A file of named and documented expressions, which are all validated when
+/// the file is loaded. Besides a description rules can have a severity and
+/// tags, which are used to apply only some rules of large rule sets, see
+/// [`RuleSet::select`]
+///
+/// ```rust
+/// let rules = srch::RuleSet::parse(r#"
+///     version = 1
+///
+///     [rules]
+///     https-errors = 'contains "https" and contains "error"'
+///
+///     [rules.zip-code]
+///     expression = "numeric and length 5"
+///     description = "a german zip code"
+///     severity = "warning"
+///     tags = ["pii", "address"]
+/// "#).unwrap();
+///
+/// let rule = rules.get("zip-code").unwrap();
+/// assert_eq!(rule.description(), Some("a german zip code"));
+/// assert_eq!(rule.severity(), Some(srch::Severity::Warning));
+/// assert!(rule.has_tag("pii"));
+/// assert!(rule.expression().matches("12345"));
+/// ```
+///
+/// Files without a `version` are version 0, which is the layout of the
+/// pattern library: a table mapping names directly to expressions. Older
+/// versions are migrated to [`RuleSet::VERSION`] when loaded, see
+/// [`RuleSet::parse_with`]
+pub struct RuleSet {
+    version: u64,
+    rules: BTreeMap<String, NamedRule>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            rules: BTreeMap::new(),
+        }
+    }
+}
+
+impl RuleSet {
+    /// The current version of the rule set format
+    pub const VERSION: u64 = 1;
+
+    pub fn parse(source: &str) -> Result<Self, RuleSetError> {
+        Self::parse_with(source, &Migrations::default())
+    }
+
+    /// Parses a rule set, migrating the expressions of older versions using
+    /// the given hooks
+    ///
+    /// ```rust
+    /// use srch::{Migrations, RuleSet};
+    ///
+    /// let migrations = Migrations::new().add(0, |source| source.replace("digits", "numeric"));
+    /// let rules = RuleSet::parse_with("zip-code = 'digits and length 5'", &migrations).unwrap();
+    ///
+    /// assert_eq!(rules.version(), 0);
+    /// assert_eq!(rules.get("zip-code").unwrap().source(), "numeric and length 5");
+    /// ```
+    pub fn parse_with(source: &str, migrations: &Migrations) -> Result<Self, RuleSetError> {
+        let mut table = match source.parse::<toml::Value>() {
+            Ok(toml::Value::Table(table)) => table,
+            Ok(_) => return Err(RuleSetError::Format("expected a table".to_string())),
+            Err(err) => return Err(RuleSetError::Format(err.to_string())),
+        };
+
+        let (version, entries) = match table.remove("version") {
+            None => (0, table),
+            Some(toml::Value::Integer(version)) if version >= 0 => {
+                let version = version as u64;
+
+                if version > Self::VERSION {
+                    return Err(RuleSetError::UnsupportedVersion(version));
+                }
+
+                match table.remove("rules") {
+                    Some(toml::Value::Table(rules)) => (version, rules),
+                    Some(_) => {
+                        return Err(RuleSetError::Format("`rules` is not a table".to_string()))
+                    }
+                    None => (version, toml::value::Table::new()),
+                }
+            }
+            Some(_) => {
+                return Err(RuleSetError::Format(
+                    "`version` is not a positive integer".to_string(),
+                ))
+            }
+        };
+
+        // The expressions are compiled together once every entry is read
+        let mut sources = vec![];
+        let mut metadata = vec![];
+
+        for (name, entry) in entries {
+            let (source, description, severity, tags) = match entry {
+                toml::Value::String(source) => (source, None, None, vec![]),
+                toml::Value::Table(mut entry) if version > 0 => {
+                    let source = match entry.remove("expression") {
+                        Some(toml::Value::String(source)) => source,
+                        _ => {
+                            return Err(RuleSetError::Format(format!(
+                                "rule \"{}\" has no expression",
+                                name
+                            )))
+                        }
+                    };
+
+                    let description = match entry.remove("description") {
+                        Some(toml::Value::String(description)) => Some(description),
+                        None => None,
+                        Some(_) => {
+                            return Err(RuleSetError::Format(format!(
+                                "the description of rule \"{}\" is not a string",
+                                name
+                            )))
+                        }
+                    };
+
+                    let severity = match entry.remove("severity") {
+                        Some(toml::Value::String(severity)) => {
+                            match Severity::from_name(&severity) {
+                                Some(severity) => Some(severity),
+                                None => {
+                                    return Err(RuleSetError::Format(format!(
+                                        "rule \"{}\" has the unknown severity \"{}\"",
+                                        name, severity
+                                    )))
+                                }
+                            }
+                        }
+                        None => None,
+                        Some(_) => {
+                            return Err(RuleSetError::Format(format!(
+                                "the severity of rule \"{}\" is not a string",
+                                name
+                            )))
+                        }
+                    };
+
+                    let tags = match entry.remove("tags") {
+                        Some(toml::Value::Array(tags)) => tags
+                            .into_iter()
+                            .map(|tag| match tag {
+                                toml::Value::String(tag) => Ok(tag),
+                                _ => Err(RuleSetError::Format(format!(
+                                    "the tags of rule \"{}\" are not strings",
+                                    name
+                                ))),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                        None => vec![],
+                        Some(_) => {
+                            return Err(RuleSetError::Format(format!(
+                                "the tags of rule \"{}\" are not an array",
+                                name
+                            )))
+                        }
+                    };
+
+                    (source, description, severity, tags)
+                }
+                _ => {
+                    return Err(RuleSetError::Format(format!(
+                        "rule \"{}\" is not a string",
+                        name
+                    )))
+                }
+            };
+
+            sources.push(migrations.migrate(source, version));
+            metadata.push((name, description, severity, tags));
+        }
+
+        let mut rules = BTreeMap::new();
+        let mut invalid = vec![];
+
+        let compiled = crate::compile_all(&sources);
+
+        for ((source, compiled), (name, description, severity, tags)) in
+            sources.into_iter().zip(compiled).zip(metadata)
+        {
+            match compiled {
+                Ok(expression) => {
+                    rules.insert(
+                        name.clone(),
+                        NamedRule {
+                            name,
+                            source,
+                            description,
+                            severity,
+                            tags,
+                            expression,
+                        },
+                    );
+                }
+                Err(error) => invalid.push(InvalidRule { name, error }),
+            }
+        }
+
+        match invalid.is_empty() {
+            true => Ok(Self { version, rules }),
+            false => Err(RuleSetError::InvalidRules(invalid)),
+        }
+    }
+
+    /// The version the rule set was loaded from
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NamedRule> {
+        self.rules.get(name)
+    }
+
+    /// Iterates over the rules ordered by name
+    pub fn iter(&self) -> impl Iterator<Item = &NamedRule> {
+        self.rules.values()
+    }
+
+    /// Iterates over the rules selected by `selector` ordered by name
+    pub fn select<'a, 'b>(
+        &'a self,
+        selector: &'b Selector,
+    ) -> impl Iterator<Item = &'a NamedRule> + 'b
+    where
+        'a: 'b,
+    {
+        self.iter().filter(move |rule| selector.matches(rule))
+    }
+
+    /// Compares the rules with a new version of the rule set by name, rules
+    /// whose expressions are equivalent are left out
+    ///
+    /// ```rust
+    /// use srch::{Relation, RuleChange, RuleSet};
+    ///
+    /// let old = RuleSet::parse("zip = 'numeric and length 5'\nid = 'numeric'").unwrap();
+    /// let new = RuleSet::parse("zip = 'numeric'\nid = 'numeric'\nname = 'alpha'").unwrap();
+    ///
+    /// let changes = old.compare(&new);
+    /// assert_eq!(changes[0], ("name".to_string(), RuleChange::Added));
+    /// assert!(matches!(&changes[1].1, RuleChange::Changed(c) if c.relation == Relation::Broader));
+    /// ```
+    pub fn compare(&self, new: &RuleSet) -> Vec<(String, RuleChange)> {
+        let mut names = self
+            .rules
+            .keys()
+            .chain(new.rules.keys())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let change = match (self.get(name), new.get(name)) {
+                    (Some(_), None) => RuleChange::Removed,
+                    (None, Some(_)) => RuleChange::Added,
+                    (Some(old), Some(new)) => match old.expression().compare(new.expression()) {
+                        comparison if comparison.relation == Relation::Equivalent => return None,
+                        comparison => RuleChange::Changed(comparison),
+                    },
+                    (None, None) => return None,
+                };
+
+                Some((name.clone(), change))
+            })
+            .collect()
+    }
+
+    /// Removes every rule which isn't selected by `selector`
+    pub fn retain(&mut self, selector: &Selector) {
+        self.rules.retain(|_, rule| selector.matches(rule));
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Writes the rule set in the current version, e.g. to persist migrated
+    /// rules
+    pub fn to_toml(&self) -> String {
+        let rules = self
+            .rules
+            .values()
+            .map(|rule| {
+                let mut entry = toml::value::Table::new();
+                entry.insert(
+                    "expression".to_string(),
+                    toml::Value::String(rule.source.clone()),
+                );
+
+                if let Some(description) = &rule.description {
+                    entry.insert(
+                        "description".to_string(),
+                        toml::Value::String(description.clone()),
+                    );
+                }
+
+                if let Some(severity) = rule.severity {
+                    entry.insert(
+                        "severity".to_string(),
+                        toml::Value::String(severity.name().to_string()),
+                    );
+                }
+
+                if !rule.tags.is_empty() {
+                    entry.insert(
+                        "tags".to_string(),
+                        toml::Value::Array(
+                            rule.tags.iter().cloned().map(toml::Value::String).collect(),
+                        ),
+                    );
+                }
+
+                (rule.name.clone(), toml::Value::Table(entry))
+            })
+            .collect();
+
+        let mut table = toml::value::Table::new();
+        table.insert(
+            "version".to_string(),
+            toml::Value::Integer(Self::VERSION as i64),
+        );
+        table.insert("rules".to_string(), toml::Value::Table(rules));
+
+        toml::Value::Table(table).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Migrations, RuleChange, RuleSet, RuleSetError, Selector, Severity};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_unversioned_pattern_libraries() {
+        let rules = RuleSet::parse("five-digits = 'numeric and length 5'").unwrap();
+
+        assert_eq!(rules.version(), 0);
+        assert_eq!(rules.len(), 1);
+        assert_eq!(
+            rules.get("five-digits").map(|rule| rule.source()),
+            Some("numeric and length 5")
+        );
+    }
+
+    #[test]
+    fn validates_all_rules_at_load() {
+        let err = RuleSet::parse(
+            "version = 1\n[rules]\nvalid = 'numeric'\ninvalid = 'length'\nalso-invalid = 'and'",
+        )
+        .err()
+        .unwrap();
+
+        match err {
+            RuleSetError::InvalidRules(rules) => assert_eq!(
+                rules
+                    .iter()
+                    .map(|rule| rule.name.as_str())
+                    .collect::<Vec<_>>(),
+                vec!["also-invalid", "invalid"]
+            ),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn compares_rules_by_name() {
+        let old = RuleSet::parse("a = 'numeric'\nb = 'alpha'\nc = 'starts \"x\"'").unwrap();
+        let new = RuleSet::parse("a = 'numeric'\nc = 'starts \"xy\"'\nd = 'alpha'").unwrap();
+
+        let changes = old
+            .compare(&new)
+            .into_iter()
+            .map(|(name, change)| match change {
+                RuleChange::Changed(comparison) => (name, comparison.relation.to_string()),
+                change => (name, format!("{:?}", change)),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            changes,
+            vec![
+                ("b".to_string(), "Removed".to_string()),
+                ("c".to_string(), "strictly narrower".to_string()),
+                ("d".to_string(), "Added".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let err = RuleSet::parse("version = 2").err().unwrap();
+        assert!(matches!(err, RuleSetError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn rejects_documented_rules_in_unversioned_files() {
+        let err = RuleSet::parse("[zip]\nexpression = 'numeric'")
+            .err()
+            .unwrap();
+        assert!(matches!(err, RuleSetError::Format(_)));
+    }
+
+    #[test]
+    fn migrates_older_versions_only() {
+        let migrations = Migrations::new().add(0, |source| source.replace("digits", "numeric"));
+
+        let old = RuleSet::parse_with("zip = 'digits'", &migrations).unwrap();
+        assert_eq!(old.get("zip").unwrap().source(), "numeric");
+
+        let current = RuleSet::parse_with("version = 1\n[rules]\nzip = 'digits'", &migrations);
+        assert!(current.is_err());
+    }
+
+    const TAGGED: &str = r#"
+        version = 1
+
+        [rules.api-key]
+        expression = 'starts "sk_"'
+        severity = "critical"
+        tags = ["secrets", "security"]
+
+        [rules.email]
+        expression = '@email'
+        severity = "warning"
+        tags = ["pii"]
+
+        [rules.todo]
+        expression = 'contains "TODO"'
+    "#;
+
+    fn selected(rules: &RuleSet, selector: Selector) -> Vec<&str> {
+        rules.select(&selector).map(|rule| rule.name()).collect()
+    }
+
+    #[test]
+    fn selects_rules_by_tag_and_severity() {
+        let rules = RuleSet::parse(TAGGED).unwrap();
+
+        assert_eq!(
+            selected(&rules, Selector::new()),
+            vec!["api-key", "email", "todo"]
+        );
+        assert_eq!(
+            selected(&rules, Selector::new().tag("pii").tag("secrets")),
+            vec!["api-key", "email"]
+        );
+        assert_eq!(
+            selected(&rules, Selector::new().min_severity(Severity::Error)),
+            vec!["api-key"]
+        );
+        assert!(selected(
+            &rules,
+            Selector::new().tag("pii").min_severity(Severity::Error)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_severities() {
+        let err =
+            RuleSet::parse("version = 1\n[rules.x]\nexpression = 'numeric'\nseverity = 'fatal'")
+                .err()
+                .unwrap();
+        assert!(matches!(err, RuleSetError::Format(_)));
+    }
+
+    #[test]
+    fn writes_metadata() {
+        let mut rules = RuleSet::parse(TAGGED).unwrap();
+        rules.retain(&Selector::new().tag("security"));
+
+        let written = RuleSet::parse(&rules.to_toml()).unwrap();
+        let rule = written.get("api-key").unwrap();
+
+        assert_eq!(written.len(), 1);
+        assert_eq!(rule.severity(), Some(Severity::Critical));
+        assert_eq!(rule.tags(), ["secrets", "security"]);
+    }
+
+    #[test]
+    fn writes_the_current_version() {
+        let rules = RuleSet::parse("zip = 'numeric and length 5'").unwrap();
+        let written = RuleSet::parse(&rules.to_toml()).unwrap();
+
+        assert_eq!(written.version(), RuleSet::VERSION);
+        assert_eq!(
+            written.get("zip").map(|rule| rule.source()),
+            Some("numeric and length 5")
+        );
+    }
+}
@@ -1,44 +1,550 @@
+use std::sync::Arc;
+
+use crate::limits::OutOfFuel;
 use crate::logical_operator::LogicalOperator;
-use crate::parser::AST;
+use crate::optimizer;
+use crate::options::MatchOptions;
+use crate::parser::{self, AST, MAX_DEPTH};
+use crate::stats::{self, Counters, QueryStats};
 
+/// Evaluates an ast, clones share the ast.
+///
+/// `and` and `or` short-circuit: the second operand is only evaluated if
+/// the first one doesn't decide the result. By default the operands are
+/// reordered so the cheaper one is evaluated first, with
+/// [`MatchOptions::strict`] they are evaluated from left to right exactly
+/// as written
+#[derive(Clone)]
 pub struct Runtime {
+    /// The ast as written
+    ast: Arc<AST>,
+    /// The ast which is evaluated
+    program: Arc<AST>,
+    options: MatchOptions,
+    /// The nesting depth of the ast, see [`MAX_DEPTH`]
+    depth: usize,
+    /// The estimated cost of the ast, see [`optimizer::cost`]
+    cost: u32,
+    /// The operands joined by [`Runtime::combine`] since the ast was last
+    /// balanced
+    spine: Spine,
+    /// Counts how often every query is evaluated, shared by clones
+    counters: Option<Arc<Counters>>,
+    /// The fuel of a single run, see [`Limits::fuel`](crate::Limits::fuel)
+    fuel: Option<u64>,
+}
+
+/// The operands joined one at a time by [`Runtime::combine`] since the ast
+/// was last balanced, each of them is the right operand of the left spine of
+/// the ast
+#[derive(Clone, Default)]
+struct Spine {
+    operator: Option<LogicalOperator>,
+    /// The depth and cost of the ast the operands were joined to
+    base: (usize, u32),
+    /// From the first to the last joined operand
+    joined: Vec<Joined>,
+}
+
+#[derive(Clone, Copy)]
+struct Joined {
+    depth: usize,
+    cost: u32,
+    /// Whether the operand is the left operand of the program
+    swapped: bool,
+}
+
+/// An operand which is rebalanced, together with its program and its cost
+/// unless it is evaluated as written
+struct Operand {
     ast: AST,
+    program: Option<(AST, u32)>,
+    depth: usize,
 }
 
 impl Runtime {
     pub fn new(ast: AST) -> Self {
-        Self { ast }
+        Self::with_options(ast, MatchOptions::default())
     }
 
-    pub fn run(&self, input: impl AsRef<str>) -> bool {
-        let input = &input.as_ref().to_owned();
-
-        match &self.ast {
-            AST::Query(query) => query.exec(input),
-            AST::BinaryExpression {
-                left,
-                operator,
-                right,
-            } => match operator {
-                LogicalOperator::And => {
-                    run((**left).clone(), input) && run((**right).clone(), input)
-                }
-                LogicalOperator::Or => {
-                    run((**left).clone(), input) || run((**right).clone(), input)
-                }
+    /// Compiles the options into the ast, so they don't have to be applied
+    /// to every query
+    pub fn with_options(ast: AST, options: MatchOptions) -> Self {
+        Self::compiled(options.compile(ast), options)
+    }
+
+    /// The runtime of an ast the options are already compiled into
+    fn compiled(ast: AST, options: MatchOptions) -> Self {
+        let depth = parser::depth(&ast);
+        let cost = optimizer::cost(&ast);
+        let ast = Arc::new(ast);
+
+        let program = match options.strict {
+            true => ast.clone(),
+            false => Arc::new(optimizer::reorder((*ast).clone())),
+        };
+
+        Self {
+            ast,
+            program,
+            options,
+            depth,
+            cost,
+            spine: Spine::default(),
+            counters: None,
+            fuel: None,
+        }
+    }
+
+    /// Joins two runtimes with the same options using an operator, without
+    /// walking their asts. Combining expressions one at a time nests them one
+    /// level deeper every time, so once the joined ast is nested deeper than
+    /// [`MAX_DEPTH`] the operands joined since it was last balanced are
+    /// rebalanced. The fuel and counters aren't kept
+    pub(crate) fn combine(
+        mut self,
+        operator: LogicalOperator,
+        other: Self,
+    ) -> Result<Self, parser::Error> {
+        let options = self.options;
+        let depth = 1 + self.depth.max(other.depth);
+        let cost = self.cost.saturating_add(other.cost);
+
+        let mut spine = match self.spine.operator == Some(operator) {
+            true => std::mem::take(&mut self.spine),
+            false => Spine {
+                operator: Some(operator),
+                base: (self.depth, self.cost),
+                joined: Vec::new(),
             },
+        };
+
+        let joined = (other.depth, other.cost);
+        let (left, left_program) = self.into_parts();
+        let (right, right_program) = other.into_parts();
+
+        let ast = AST::BinaryExpression {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        };
+
+        // The programs are already reordered, only the operands of the new
+        // operator are left
+        let (program, swapped) = match (left_program, right_program) {
+            (Some(left), Some(right)) => {
+                let ((program, _), swapped) = join(left, operator, right);
+                (Some(program), swapped)
+            }
+            _ => (None, false),
+        };
+
+        spine.joined.push(Joined {
+            depth: joined.0,
+            cost: joined.1,
+            swapped,
+        });
+
+        let runtime = Self::joined(ast, program, options, depth, cost, spine);
+
+        match depth > MAX_DEPTH {
+            true => runtime.rebalance(),
+            false => Ok(runtime),
+        }
+    }
+
+    /// The runtime of an ast the options are already compiled into, its
+    /// chains of `and` and `or` are rebalanced if it is nested deeper than
+    /// [`MAX_DEPTH`]
+    fn checked(ast: AST, options: MatchOptions) -> Result<Self, parser::Error> {
+        match parser::depth(&ast) > MAX_DEPTH {
+            true => Ok(Self::compiled(parser::rebalance(ast)?.0, options)),
+            false => Ok(Self::compiled(ast, options)),
         }
     }
+
+    /// The runtime of a joined ast and its program, the program is `None` if
+    /// the ast is evaluated as written
+    fn joined(
+        ast: AST,
+        program: Option<AST>,
+        options: MatchOptions,
+        depth: usize,
+        cost: u32,
+        spine: Spine,
+    ) -> Self {
+        let ast = Arc::new(ast);
+
+        Self {
+            program: program.map_or_else(|| ast.clone(), Arc::new),
+            ast,
+            options,
+            depth,
+            cost,
+            spine,
+            counters: None,
+            fuel: None,
+        }
+    }
+
+    /// Rebalances only the operands joined since the ast was last balanced,
+    /// so combining expressions one at a time doesn't walk the whole ast
+    /// every [`MAX_DEPTH`] operands. The rest of the ast is kept as one
+    /// operand, the whole ast is only rebalanced if that is nested too deep
+    fn rebalance(self) -> Result<Self, parser::Error> {
+        let options = self.options;
+        let cost = self.cost;
+        let spine = self.spine.clone();
+        let operator = spine.operator.ok_or(parser::Error::InternalError)?;
+        let (mut ast, program) = self.into_parts();
+        let mut program = program.map(|(program, _)| program);
+        let mut operands = Vec::with_capacity(spine.joined.len() + 1);
+
+        // Every joined operand is the right operand of the left spine of the
+        // ast, and the left or right one of the program if it was swapped
+        for joined in spine.joined.iter().rev() {
+            let (left, right) = match ast {
+                AST::BinaryExpression { left, right, .. } => (*left, *right),
+                _ => return Err(parser::Error::InternalError),
+            };
+
+            let (rest, operand) = match program {
+                Some(AST::BinaryExpression { left, right, .. }) => match joined.swapped {
+                    true => (Some(*right), Some(*left)),
+                    false => (Some(*left), Some(*right)),
+                },
+                Some(_) => return Err(parser::Error::InternalError),
+                None => (None, None),
+            };
+
+            operands.push(Operand {
+                ast: right,
+                program: operand.map(|program| (program, joined.cost)),
+                depth: joined.depth,
+            });
+
+            ast = left;
+            program = rest;
+        }
+
+        operands.push(Operand {
+            ast,
+            program: program.map(|program| (program, spine.base.1)),
+            depth: spine.base.0,
+        });
+        operands.reverse();
+
+        let Operand {
+            ast,
+            program,
+            depth,
+        } = balance(operands, operator);
+
+        match depth > MAX_DEPTH {
+            true => Self::checked(ast, options),
+            false => Ok(Self::joined(
+                ast,
+                program.map(|(program, _)| program),
+                options,
+                depth,
+                cost,
+                Spine::default(),
+            )),
+        }
+    }
+
+    /// The ast and, unless it is evaluated as written, the program together
+    /// with its cost
+    fn into_parts(self) -> (AST, Option<(AST, u32)>) {
+        let unwrap = |ast: Arc<AST>| Arc::try_unwrap(ast).unwrap_or_else(|ast| (*ast).clone());
+        let Self {
+            ast,
+            program,
+            options,
+            cost,
+            ..
+        } = self;
+
+        let program = match options.strict {
+            // The program shares the ast, which can only be unwrapped alone
+            true => {
+                drop(program);
+                None
+            }
+            false => Some((unwrap(program), cost)),
+        };
+
+        (unwrap(ast), program)
+    }
+
+    /// The runtime matching exactly the inputs this one doesn't match, a
+    /// negated ast is unwrapped instead of negated twice. The options, fuel
+    /// and counters are kept, negating doesn't change the queries.
+    ///
+    /// The complement is nested at most one level deeper than [`MAX_DEPTH`],
+    /// combining it checks the depth again and its complement unwraps it
+    pub fn complement(&self) -> Self {
+        let negate = |ast: &AST| match ast {
+            AST::Not(ast) => (**ast).clone(),
+            ast => AST::Not(Box::new(ast.clone())),
+        };
+
+        let depth = match &*self.ast {
+            AST::Not(_) => self.depth - 1,
+            _ => self.depth + 1,
+        };
+
+        Self {
+            ast: Arc::new(negate(&self.ast)),
+            program: Arc::new(negate(&self.program)),
+            depth,
+            spine: Spine::default(),
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn with_fuel(mut self, fuel: Option<u64>) -> Self {
+        self.fuel = fuel;
+        self
+    }
+
+    /// Counts how often every query is evaluated and matches from now on
+    pub(crate) fn count(&mut self) {
+        let positions = match self.options.strict {
+            true => (0..stats::count(&self.ast)).collect(),
+            false => optimizer::reordered_positions(&self.ast),
+        };
+
+        self.counters = Some(Arc::new(Counters::new(positions)));
+    }
+
+    pub(crate) fn stats(&self) -> Vec<QueryStats> {
+        match &self.counters {
+            Some(counters) => counters.stats(&self.ast),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn reset_stats(&self) {
+        if let Some(counters) = &self.counters {
+            counters.reset();
+        }
+    }
+
+    pub(crate) fn ast(&self) -> &AST {
+        &self.ast
+    }
+
+    pub(crate) fn options(&self) -> MatchOptions {
+        self.options
+    }
+
+    #[cfg(test)]
+    pub(crate) fn program(&self) -> &AST {
+        &self.program
+    }
+
+    pub fn run(&self, input: impl AsRef<str>) -> bool {
+        self.try_run(input).unwrap_or(false)
+    }
+
+    /// Runs the program unless it runs out of fuel, programs without fuel
+    /// always finish. The queries of programs with fuel aren't counted
+    pub fn try_run(&self, input: impl AsRef<str>) -> Result<bool, OutOfFuel> {
+        let input = self.options.prepare(input.as_ref());
+
+        if let Some(fuel) = self.fuel {
+            return run_fueled(&self.program, &input, self.options.unicode, &mut { fuel });
+        }
+
+        Ok(match &self.counters {
+            Some(counters) => stats::run(&self.program, &input, self.options.unicode, counters, 0),
+            None => run(&self.program, &input, self.options.unicode),
+        })
+    }
+}
+
+/// Joins two programs and their costs using an operator, the cheaper one is
+/// evaluated first like [`optimizer::reorder`] does. Returns whether they
+/// were swapped
+fn join(left: (AST, u32), operator: LogicalOperator, right: (AST, u32)) -> ((AST, u32), bool) {
+    let cost = left.1.saturating_add(right.1);
+    let swapped = right.1 < left.1;
+
+    let (left, right) = match swapped {
+        true => (right.0, left.0),
+        false => (left.0, right.0),
+    };
+
+    let program = AST::BinaryExpression {
+        left: Box::new(left),
+        operator,
+        right: Box::new(right),
+    };
+
+    ((program, cost), swapped)
+}
+
+/// Joins operands into a balanced tree like the parser joins chains, see
+/// [`parser::split`]
+fn balance(mut operands: Vec<Operand>, operator: LogicalOperator) -> Operand {
+    if operands.len() == 1 {
+        return operands.remove(0);
+    }
+
+    let depths = operands
+        .iter()
+        .map(|operand| operand.depth)
+        .collect::<Vec<_>>();
+    let right = operands.split_off(parser::split(&depths));
+    let left = balance(operands, operator);
+    let right = balance(right, operator);
+
+    let program = match (left.program, right.program) {
+        (Some(left), Some(right)) => Some(join(left, operator, right).0),
+        _ => None,
+    };
+
+    Operand {
+        ast: AST::BinaryExpression {
+            left: Box::new(left.ast),
+            operator,
+            right: Box::new(right.ast),
+        },
+        program,
+        depth: 1 + left.depth.max(right.depth),
+    }
+}
+
+/// Evaluates an ast, borrowing both the ast and the input
+pub fn run(ast: &AST, input: &str, unicode: bool) -> bool {
+    match ast {
+        AST::Query(query) => query.exec_with(input, unicode),
+        AST::Not(ast) => !run(ast, input, unicode),
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => match operator {
+            LogicalOperator::And => run(left, input, unicode) && run(right, input, unicode),
+            LogicalOperator::Or => run(left, input, unicode) || run(right, input, unicode),
+        },
+    }
 }
 
-pub fn run(ast: AST, input: impl AsRef<str>) -> bool {
-    Runtime::new(ast).run(input)
+/// Evaluates an ast like [`run`], every query uses up its estimated cost for
+/// every started 64 bytes of the input
+fn run_fueled(ast: &AST, input: &str, unicode: bool, fuel: &mut u64) -> Result<bool, OutOfFuel> {
+    match ast {
+        AST::Query(query) => {
+            let blocks = input.len().div_ceil(64).max(1) as u64;
+            let cost = optimizer::query_cost(query) as u64 * blocks;
+
+            *fuel = fuel.checked_sub(cost).ok_or(OutOfFuel)?;
+            Ok(query.exec_with(input, unicode))
+        }
+        AST::Not(ast) => Ok(!run_fueled(ast, input, unicode, fuel)?),
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => {
+            let left = run_fueled(left, input, unicode, fuel)?;
+
+            match (operator, left) {
+                (LogicalOperator::And, false) => Ok(false),
+                (LogicalOperator::Or, true) => Ok(true),
+                _ => run_fueled(right, input, unicode, fuel),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::run;
-    use crate::into_ast;
+    use super::{run, Runtime, AST};
+    use crate::logical_operator::LogicalOperator;
+    use crate::options::MatchOptions;
+    use crate::{into_ast, optimizer, parser};
+
+    #[test]
+    fn reorders_operands_unless_strict() {
+        let ast = into_ast("contains \"a\" and length 3").unwrap();
+
+        let runtime = Runtime::new(ast.clone());
+        pretty_assertions::assert_eq!(
+            runtime.program(),
+            &into_ast("length 3 and contains \"a\"").unwrap()
+        );
+        pretty_assertions::assert_eq!(runtime.ast(), &ast);
+
+        let strict = MatchOptions {
+            strict: true,
+            ..MatchOptions::default()
+        };
+
+        let runtime = Runtime::with_options(ast.clone(), strict);
+        pretty_assertions::assert_eq!(runtime.program(), &ast);
+    }
+
+    #[test]
+    fn complements_the_program() {
+        let ast = into_ast("contains \"a\" and length 3").unwrap();
+        let runtime = Runtime::new(ast.clone()).complement();
+
+        pretty_assertions::assert_eq!(
+            runtime.program(),
+            &AST::Not(Box::new(into_ast("length 3 and contains \"a\"").unwrap()))
+        );
+        pretty_assertions::assert_eq!(runtime.run("abc"), false);
+        pretty_assertions::assert_eq!(runtime.run("xyz"), true);
+        pretty_assertions::assert_eq!(runtime.complement().ast(), &ast);
+    }
+
+    #[test]
+    fn combines_the_reordered_programs() {
+        let left = Runtime::new(into_ast("@email or alpha").unwrap());
+        let right = Runtime::new(into_ast("contains \"a\" and length 3").unwrap());
+        let runtime = left.combine(LogicalOperator::And, right).unwrap();
+
+        pretty_assertions::assert_eq!(
+            runtime.program(),
+            &optimizer::reorder(runtime.ast().clone())
+        );
+        pretty_assertions::assert_eq!(runtime.depth, parser::depth(runtime.ast()));
+        pretty_assertions::assert_eq!(runtime.run("abc"), true);
+        pretty_assertions::assert_eq!(runtime.run("abcd"), false);
+    }
+
+    #[test]
+    fn rebalances_only_the_joined_operands() {
+        let sources = ["@email", "length 3", "contains \"a\" and numeric"];
+
+        for strict in [false, true] {
+            let options = MatchOptions {
+                strict,
+                ..MatchOptions::default()
+            };
+
+            let runtime = (0..3000).fold(
+                Runtime::with_options(into_ast("alpha").unwrap(), options),
+                |runtime, i| {
+                    let other = Runtime::with_options(into_ast(sources[i % 3]).unwrap(), options);
+                    runtime.combine(LogicalOperator::Or, other).unwrap()
+                },
+            );
+
+            let program = match strict {
+                true => runtime.ast().clone(),
+                false => optimizer::reorder(runtime.ast().clone()),
+            };
+
+            pretty_assertions::assert_eq!(runtime.program(), &program);
+            pretty_assertions::assert_eq!(runtime.depth, parser::depth(runtime.ast()));
+            pretty_assertions::assert_eq!(runtime.depth <= parser::MAX_DEPTH, true);
+            pretty_assertions::assert_eq!(runtime.run("abcd"), true);
+        }
+    }
 
     macro_rules! runtime_test {
 		($($name:ident: $value:expr,)*) => {
@@ -46,7 +552,7 @@ mod tests {
 				#[test]
 				fn $name() {
 					let (query_source, test_string, result) = $value;
-					pretty_assertions::assert_eq!(run(into_ast(&query_source.to_string()).unwrap(), &test_string.to_string()), result);
+					pretty_assertions::assert_eq!(run(&into_ast(query_source).unwrap(), test_string, false), result);
 				}
 			)*
 		}
@@ -94,6 +600,38 @@ mod tests {
         }
     }
 
+    mod it_handles_negations {
+        use super::*;
+
+        runtime_test! {
+            not_contains: (
+                "not contains \"error\"",
+                "all good",
+                true
+            ),
+            not_contains_with_matching_input: (
+                "not contains \"error\"",
+                "an error",
+                false
+            ),
+            numeric_and_not_length: (
+                "numeric and not length 5",
+                "1234",
+                true
+            ),
+            numeric_and_not_length_with_excluded_length: (
+                "numeric and not length 5",
+                "12345",
+                false
+            ),
+            not_binds_stronger_than_or: (
+                "not numeric or length 3",
+                "123",
+                true
+            ),
+        }
+    }
+
     mod it_handles_single_or_expressions {
         use super::*;
 
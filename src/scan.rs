@@ -0,0 +1,278 @@
+use std::ops::Range;
+
+use crate::analysis::{self, Literal};
+use crate::parser::AST;
+use crate::query::Query;
+
+/// The longest window which can match an ast, known if every clause bounds
+/// the length
+pub fn max_window(ast: &AST) -> Option<usize> {
+    analysis::clauses(ast)?
+        .iter()
+        .map(|clause| {
+            clause
+                .iter()
+                .filter(|literal| !literal.negated)
+                .filter_map(|literal| literal.query.length_bounds()?.1)
+                .min()
+                .map(|len| len as usize)
+        })
+        .try_fold(0, |max, len| len.map(|len| max.max(len)))
+}
+
+/// Where a literal has to occur in a window
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Position {
+    Anywhere,
+    Start,
+    End,
+    Whole,
+}
+
+/// A literal every window matching a clause has to contain, together with
+/// all of its (possibly overlapping) occurrences in the input
+#[derive(Debug)]
+pub struct Anchor {
+    position: Position,
+    len: usize,
+    occurrences: Vec<usize>,
+}
+
+impl Anchor {
+    fn new(literal: &Literal, input: &str) -> Option<Self> {
+        if literal.negated {
+            return None;
+        }
+
+        let (position, arg) = match literal.query {
+            Query::Contains(arg) => (Position::Anywhere, arg),
+            Query::ContainsTimes(arg, times) if times.bounds().0 > 0 => (Position::Anywhere, arg),
+            Query::Starts(arg) => (Position::Start, arg),
+            Query::Ends(arg) => (Position::End, arg),
+            Query::Equals(arg) => (Position::Whole, arg),
+            _ => return None,
+        };
+
+        let mut occurrences = Vec::new();
+        let mut from = 0;
+
+        while let Some(i) = input[from..].find(&**arg) {
+            occurrences.push(from + i);
+
+            match input[from + i..].chars().next() {
+                Some(c) => from += i + c.len_utf8(),
+                None => break,
+            }
+        }
+
+        Some(Self {
+            position,
+            len: arg.len(),
+            occurrences,
+        })
+    }
+
+    /// Whether an occurrence starts at or after `start`
+    fn reachable(&self, start: usize) -> bool {
+        self.occurrences.last().is_some_and(|&last| last >= start)
+    }
+
+    /// Adds the ranges of ends up to `last` (both included) of the windows
+    /// starting at `start` which contain an occurrence where required
+    fn ends(&self, start: usize, last: usize, ends: &mut Vec<(usize, usize)>) {
+        let occurs_at = |i: usize| self.occurrences.binary_search(&i).is_ok();
+        let after = &self.occurrences[self.occurrences.partition_point(|&i| i < start)..];
+
+        match self.position {
+            Position::Anywhere => ends.extend(after.first().map(|&i| (i + self.len, last))),
+            Position::Start if occurs_at(start) => ends.push((start + self.len, last)),
+            Position::End => ends.extend(
+                after
+                    .iter()
+                    .map(|&i| (i + self.len, i + self.len))
+                    .take_while(|&(end, _)| end <= last),
+            ),
+            Position::Whole if occurs_at(start) => ends.push((start + self.len, start + self.len)),
+            Position::Start | Position::Whole => {}
+        }
+
+        ends.retain(|&(first, end)| first <= end && end <= last);
+    }
+}
+
+/// The most restrictive anchor of every clause of an ast, `None` if a clause
+/// has no anchor and any window could match it. The anchors assume the
+/// windows are matched as they are, i.e. neither trimmed nor lowercased
+pub fn anchors(ast: &AST, input: &str) -> Option<Vec<Anchor>> {
+    analysis::clauses(ast)?
+        .iter()
+        .map(|clause| {
+            clause
+                .iter()
+                .filter_map(|literal| Anchor::new(literal, input))
+                .max_by_key(|anchor| (anchor.position, anchor.len))
+        })
+        .collect()
+}
+
+/// Finds the non-empty windows of `input` accepted by `matches`, from left to
+/// right. The longest window starting at a position is taken and the search
+/// continues after it, so windows never overlap. `max` bounds the length of
+/// the windows which are tried, `anchors` skips the windows which can't
+/// contain a required literal. Without either every window is tried
+pub fn scan<F>(
+    input: &str,
+    max: Option<usize>,
+    anchors: Option<&[Anchor]>,
+    matches: F,
+) -> Vec<Range<usize>>
+where
+    F: Fn(&str) -> bool,
+{
+    let bounds = input
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(input.len()))
+        .collect::<Vec<_>>();
+
+    let max = max.unwrap_or(input.len());
+    let mut windows = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < bounds.len() {
+        let start = bounds[i];
+
+        let last = input.len().min(start + max);
+
+        // The byte ranges the end of a window can be in, in ascending order
+        let ends = match anchors {
+            Some(anchors) => {
+                // No window starting here or later contains a required literal
+                if !anchors.iter().any(|anchor| anchor.reachable(start)) {
+                    break;
+                }
+
+                let mut ends = Vec::new();
+                for anchor in anchors {
+                    anchor.ends(start, last, &mut ends);
+                }
+
+                merge(ends)
+            }
+            None => vec![(start, last)],
+        };
+
+        let end = ends
+            .iter()
+            .rev()
+            .flat_map(|&(first, end)| {
+                let first = bounds.partition_point(|&bound| bound < first).max(i + 1);
+                let end = bounds.partition_point(|&bound| bound <= end);
+                (first..end).rev()
+            })
+            .find(|&j| matches(&input[start..bounds[j]]));
+
+        match end {
+            Some(j) => {
+                windows.push(start..bounds[j]);
+                i = j;
+            }
+            None => i += 1,
+        }
+    }
+
+    windows
+}
+
+/// Sorts ranges and merges the overlapping or adjacent ones
+fn merge(mut ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+
+    for (first, last) in ranges {
+        match merged.last_mut() {
+            Some(previous) if first <= previous.1 + 1 => previous.1 = previous.1.max(last),
+            _ => merged.push((first, last)),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::max_window;
+    use crate::{into_ast, Expression};
+    use pretty_assertions::assert_eq;
+
+    fn windows(source: &str, input: &str) -> Vec<String> {
+        let expr = Expression::new(source).unwrap();
+
+        expr.scan(input)
+            .into_iter()
+            .map(|window| input[window].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_embedded_runs() {
+        assert_eq!(
+            windows("numeric and length 5", "id 12345, zip 987654 or 1234"),
+            vec!["12345", "98765"]
+        );
+    }
+
+    #[test]
+    fn takes_the_longest_window() {
+        assert_eq!(windows("numeric", "a12b3"), vec!["12", "3"]);
+        assert_eq!(
+            windows("starts \"a\" and ends \"b\"", "xab ab"),
+            vec!["ab ab"]
+        );
+    }
+
+    #[test]
+    fn handles_multibyte_characters() {
+        assert_eq!(windows("alpha", "über"), vec!["ber"]);
+    }
+
+    #[test]
+    fn skips_long_lines_without_the_required_literals() {
+        let line = "x".repeat(100_000);
+
+        assert_eq!(windows("contains \"error\"", &line), Vec::<String>::new());
+        assert_eq!(
+            windows("starts \"a\" or ends \"b\" and numeric", &line),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn anchors_windows_at_the_literals() {
+        let line = format!("{}error{}", "x".repeat(5_000), "y".repeat(5_000));
+
+        assert_eq!(windows("contains \"error\"", &line), vec![line.clone()]);
+        assert_eq!(
+            windows("ends \"error\" and length 7", &line),
+            vec!["xxerror"]
+        );
+        assert_eq!(windows("equals \"error\"", &line), vec!["error"]);
+    }
+
+    #[test]
+    fn finds_overlapping_literals() {
+        assert_eq!(
+            windows("starts \"b\" and ends \"aa\"", "baaa"),
+            vec!["baaa"]
+        );
+    }
+
+    #[test]
+    fn bounds_windows_by_length() {
+        let max = |source| max_window(&into_ast(source).unwrap());
+
+        assert_eq!(max("numeric and length 5 or length 3"), Some(5));
+        assert_eq!(max("numeric and length 5 or alpha"), None);
+    }
+}
@@ -0,0 +1,99 @@
+//! (De)serializes expressions as their syntax tree, so external systems can
+//! store and inspect rules without parsing the expression language
+//!
+//! ```rust
+//! let expr = srch::Expression::new("numeric and length 5").unwrap();
+//!
+//! let json = serde_json::to_string(&expr).unwrap();
+//! assert_eq!(
+//!     json,
+//!     r#"{"binary_expression":{"left":{"query":"numeric"},"operator":"and","right":{"query":{"length":5}}}}"#
+//! );
+//!
+//! let expr: srch::Expression = serde_json::from_str(&json).unwrap();
+//! assert!(expr.matches("12345"));
+//! ```
+
+use serde::de::{Deserialize, Deserializer};
+use serde::ser::{Serialize, Serializer};
+
+use crate::parser::AST;
+use crate::{Expression, Runtime};
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.runtime.ast().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ast = AST::deserialize(deserializer)?;
+
+        Ok(Self {
+            runtime: Runtime::new(ast),
+            source: None,
+        })
+    }
+}
+
+/// The JSON Schema of the document format is generated using
+/// `schemars::schema_for!(srch::Expression)`
+#[cfg(feature = "schemars")]
+impl schemars::JsonSchema for Expression {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Expression".into()
+    }
+
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        AST::json_schema(generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Expression;
+
+    fn round_trip(source: &str) -> Expression {
+        let expr = Expression::new(source).unwrap();
+        let json = serde_json::to_string(&expr).unwrap();
+
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn it_round_trips_expressions() {
+        let expr = round_trip("starts \"a\" or @email and ends \".org\"");
+
+        assert!(expr.matches("abc"));
+        assert!(expr.matches("foo@bar.org"));
+        assert!(!expr.matches("foo@bar.com"));
+    }
+
+    #[test]
+    fn it_serializes_patterns() {
+        let expr = Expression::new("@uuid").unwrap();
+
+        pretty_assertions::assert_eq!(
+            serde_json::to_string(&expr).unwrap(),
+            r#"{"query":{"pattern":"uuid"}}"#
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_queries() {
+        let json = r#"{"query":{"matches":"a"}}"#;
+
+        assert!(serde_json::from_str::<Expression>(json).is_err());
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn it_generates_a_json_schema() {
+        let schema = serde_json::to_value(schemars::schema_for!(Expression)).unwrap();
+
+        pretty_assertions::assert_eq!(schema["title"], "Expression");
+        assert!(schema["$defs"]["Query"]["oneOf"].is_array());
+        assert!(schema["$defs"]["Pattern"].is_object());
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::logical_operator::LogicalOperator;
+use crate::parser::AST;
+
+/// How often a query of an expression was evaluated and how often it
+/// matched, see [`Expression::stats`](crate::Expression::stats)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueryStats {
+    /// The query as written
+    pub query: String,
+    /// The position of the query in the expression, counting from the left
+    pub index: usize,
+    pub evaluated: u64,
+    pub matched: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counter {
+    evaluated: AtomicU64,
+    matched: AtomicU64,
+}
+
+/// The counters of the queries of a program, in the order the queries
+/// appear in the program
+#[derive(Debug)]
+pub struct Counters {
+    counters: Vec<Counter>,
+    /// The position of every query of the program in the ast as written
+    positions: Vec<usize>,
+}
+
+impl Counters {
+    pub fn new(positions: Vec<usize>) -> Self {
+        Self {
+            counters: positions.iter().map(|_| Counter::default()).collect(),
+            positions,
+        }
+    }
+
+    /// The stats of the queries of `ast`, the ast the program was created
+    /// from, in the order they are written
+    pub fn stats(&self, ast: &AST) -> Vec<QueryStats> {
+        let mut stats = queries(ast)
+            .into_iter()
+            .enumerate()
+            .map(|(index, query)| QueryStats {
+                query,
+                index,
+                evaluated: 0,
+                matched: 0,
+            })
+            .collect::<Vec<_>>();
+
+        for (counter, position) in self.counters.iter().zip(self.positions.iter()) {
+            stats[*position].evaluated = counter.evaluated.load(Ordering::Relaxed);
+            stats[*position].matched = counter.matched.load(Ordering::Relaxed);
+        }
+
+        stats
+    }
+
+    pub fn reset(&self) {
+        for counter in self.counters.iter() {
+            counter.evaluated.store(0, Ordering::Relaxed);
+            counter.matched.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The queries of an ast from left to right
+fn queries(ast: &AST) -> Vec<String> {
+    match ast {
+        AST::Query(query) => vec![query.to_string()],
+        AST::Not(ast) => queries(ast),
+        AST::BinaryExpression { left, right, .. } => {
+            let mut written = queries(left);
+            written.extend(queries(right));
+            written
+        }
+    }
+}
+
+/// The number of queries of an ast
+pub fn count(ast: &AST) -> usize {
+    match ast {
+        AST::Query(_) => 1,
+        AST::Not(ast) => count(ast),
+        AST::BinaryExpression { left, right, .. } => count(left) + count(right),
+    }
+}
+
+/// Evaluates a program like [`runtime::run`](crate::runtime::run) and counts
+/// every evaluated query, `first` is the position of the first query of
+/// `ast` in the program
+pub fn run(ast: &AST, input: &str, unicode: bool, counters: &Counters, first: usize) -> bool {
+    match ast {
+        AST::Query(query) => {
+            let matched = query.exec_with(input, unicode);
+            let counter = &counters.counters[first];
+
+            counter.evaluated.fetch_add(1, Ordering::Relaxed);
+
+            if matched {
+                counter.matched.fetch_add(1, Ordering::Relaxed);
+            }
+
+            matched
+        }
+        AST::Not(ast) => !run(ast, input, unicode, counters, first),
+        AST::BinaryExpression {
+            left,
+            operator,
+            right,
+        } => {
+            let second = first + count(left);
+            let left = || run(left, input, unicode, counters, first);
+            let right = || run(right, input, unicode, counters, second);
+
+            match operator {
+                LogicalOperator::And => left() && right(),
+                LogicalOperator::Or => left() || right(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Expression, MatchOptions};
+    use pretty_assertions::assert_eq;
+
+    fn counts(expr: &Expression) -> Vec<(String, u64, u64)> {
+        expr.stats()
+            .into_iter()
+            .map(|stats| (stats.query, stats.evaluated, stats.matched))
+            .collect()
+    }
+
+    #[test]
+    fn counts_evaluated_queries_in_written_order() {
+        let expr = Expression::new("contains \"a\" and length 3 or numeric")
+            .unwrap()
+            .with_stats();
+
+        for input in ["abc", "abcd", "xyz", "12"].iter() {
+            expr.matches(input);
+        }
+
+        assert_eq!(
+            counts(&expr),
+            vec![
+                ("contains \"a\"".to_string(), 2, 1),
+                ("length 3".to_string(), 3, 2),
+                ("numeric".to_string(), 4, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_the_written_order_if_strict() {
+        let options = MatchOptions {
+            strict: true,
+            ..MatchOptions::default()
+        };
+
+        let expr = Expression::new_with("contains \"a\" and length 3", options)
+            .unwrap()
+            .with_stats();
+
+        expr.matches("xyz");
+
+        assert_eq!(
+            counts(&expr),
+            vec![
+                ("contains \"a\"".to_string(), 1, 0),
+                ("length 3".to_string(), 0, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn shares_counters_between_clones() {
+        let expr = Expression::new("numeric").unwrap().with_stats();
+        expr.clone().matches("1");
+
+        assert_eq!(expr.stats()[0].evaluated, 1);
+
+        expr.reset_stats();
+        assert_eq!(expr.stats()[0].evaluated, 0);
+    }
+
+    #[test]
+    fn has_no_stats_unless_enabled() {
+        let expr = Expression::new("numeric").unwrap();
+        expr.matches("1");
+
+        assert!(expr.stats().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! [`proptest`](https://crates.io/crates/proptest) strategies generating
+//! valid expressions and inputs which do or don't match an expression
+//!
+//! ```rust
+//! use proptest::test_runner::TestRunner;
+//!
+//! let zip_codes = srch::strategies::matching("numeric and length 5");
+//!
+//! TestRunner::default()
+//!     .run(&zip_codes, |zip| {
+//!         assert!(zip.len() == 5 && zip.chars().all(|c| c.is_ascii_digit()));
+//!         Ok(())
+//!     })
+//!     .unwrap();
+//! ```
+
+use std::sync::Arc;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::analysis::{clauses, example, Fillers};
+use crate::pattern::Pattern;
+use crate::{into_ast, Expression};
+
+const LITERAL: &str = "[a-zA-Z0-9 .@#-]{0,6}";
+
+fn literal_query() -> impl Strategy<Value = String> {
+    let keyword = prop_oneof![
+        Just("starts"),
+        Just("ends"),
+        Just("contains"),
+        Just("equals"),
+        Just("istarts"),
+        Just("iends"),
+        Just("icontains"),
+        Just("iequals")
+    ];
+
+    (keyword, LITERAL).prop_map(|(keyword, literal)| format!("{} \"{}\"", keyword, literal))
+}
+
+fn length_range() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (0u64..20).prop_map(|n| format!("length over {}", n)),
+        (1u64..20).prop_map(|n| format!("length under {}", n)),
+        (0u64..20).prop_map(|n| format!("length at least {}", n)),
+        (0u64..20).prop_map(|n| format!("length at most {}", n)),
+        (0u64..10, 0u64..10)
+            .prop_map(|(a, b)| { format!("length between {} and {}", a.min(b), a.max(b)) }),
+    ]
+}
+
+fn words() -> impl Strategy<Value = String> {
+    prop_oneof![
+        (0u64..6).prop_map(|n| format!("words {}", n)),
+        (0u64..6).prop_map(|n| format!("words over {}", n)),
+        (1u64..6).prop_map(|n| format!("words under {}", n)),
+        (0u64..6).prop_map(|n| format!("words at most {}", n)),
+    ]
+}
+
+fn contains_times() -> impl Strategy<Value = String> {
+    let quantifier = prop_oneof![Just("at least"), Just("at most"), Just("exactly")];
+
+    ("[a-zA-Z0-9 .@#-]{1,3}", quantifier, 0u64..5).prop_map(|(literal, quantifier, n)| {
+        format!("contains \"{}\" {} {} times", literal, quantifier, n)
+    })
+}
+
+fn value() -> impl Strategy<Value = String> {
+    let comparison = prop_oneof![Just("over"), Just("under"), Just("equals")];
+
+    (comparison, 0u64..2000).prop_map(|(comparison, n)| format!("value {} {}", comparison, n))
+}
+
+/// Generates the source of a single random query
+pub fn query() -> impl Strategy<Value = String> {
+    prop_oneof![
+        literal_query(),
+        (0u64..20).prop_map(|n| format!("length {}", n)),
+        length_range(),
+        contains_times(),
+        words(),
+        value(),
+        Just("numeric".to_string()),
+        Just("alpha".to_string()),
+        Just("alphanumeric".to_string()),
+        Just("uppercase".to_string()),
+        Just("lowercase".to_string()),
+        Just("whitespace".to_string()),
+        Just("special".to_string()),
+        prop::sample::select(Pattern::ALL.to_vec()).prop_map(|p| p.as_keyword().to_string()),
+    ]
+}
+
+/// Generates the source of a random valid expression of up to 6 queries
+pub fn expression() -> impl Strategy<Value = String> {
+    let operator = prop_oneof![Just(" and "), Just(" or ")];
+
+    (query(), prop::collection::vec((operator, query()), 0..6)).prop_map(|(first, rest)| {
+        rest.into_iter().fold(first, |source, (operator, query)| {
+            source + operator + &query
+        })
+    })
+}
+
+/// Random parts used to build a candidate input for a clause
+#[derive(Clone, Debug)]
+struct Seed {
+    clause: Index,
+    fillers: Fillers,
+}
+
+fn seed() -> impl Strategy<Value = Seed> {
+    (
+        any::<Index>(),
+        "[a-zA-Z0-9 ]{0,8}",
+        "[0-9]{0,8}",
+        "[a-zA-Z]{0,8}",
+        "[!-/:-@\\[-`{-~]{0,8}",
+    )
+        .prop_map(|(clause, filler, digits, letters, special)| Seed {
+            clause,
+            fillers: Fillers {
+                filler,
+                digits,
+                letters,
+                special,
+            },
+        })
+}
+
+fn candidates(source: &str) -> (Arc<Expression>, impl Strategy<Value = String>) {
+    let expression = Arc::new(
+        Expression::new(source)
+            .unwrap_or_else(|err| panic!("invalid expression `{}`: {}", source, err)),
+    );
+
+    let ast = into_ast(source).expect("the expression was compiled before");
+    // Expressions with too many clauses are only matched against the fillers
+    let owned = clauses(&ast)
+        .unwrap_or_else(|| vec![Vec::new()])
+        .into_iter()
+        .map(|clause| {
+            clause
+                .into_iter()
+                .filter(|literal| !literal.negated)
+                .map(|literal| literal.query.clone())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let strategy = seed().prop_map(move |seed| {
+        let clause = seed.clause.get(&owned).iter().collect::<Vec<_>>();
+        example(&clause, &seed.fillers)
+    });
+
+    (expression, strategy)
+}
+
+/// Generates inputs matching an expression. Fails with too many rejects if
+/// the expression can't match or only matches rare inputs
+///
+/// # Panics
+///
+/// If `source` isn't a valid expression
+pub fn matching(source: &str) -> impl Strategy<Value = String> {
+    let (expression, candidates) = candidates(source);
+
+    candidates.prop_filter("input must match the expression", move |input| {
+        expression.matches(input)
+    })
+}
+
+/// Generates inputs which don't match an expression, mostly inputs close to
+/// matching ones. Fails with too many rejects if the expression matches
+/// nearly every input
+///
+/// # Panics
+///
+/// If `source` isn't a valid expression
+pub fn rejecting(source: &str) -> impl Strategy<Value = String> {
+    let (expression, candidates) = candidates(source);
+
+    let near_misses =
+        (candidates, "[a-zA-Z0-9!-/]", any::<bool>()).prop_map(|(candidate, extra, prepend)| {
+            match prepend {
+                true => extra + &candidate,
+                false => candidate + &extra,
+            }
+        });
+
+    prop_oneof![any::<String>(), near_misses]
+        .prop_filter("input must not match the expression", move |input| {
+            !expression.matches(input)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::{expression, matching, rejecting};
+    use crate::{into_ast, Expression};
+
+    proptest! {
+        #[test]
+        fn generated_expressions_compile(source in expression()) {
+            prop_assert!(into_ast(&source).is_ok());
+        }
+
+        #[test]
+        fn evaluation_is_consistent_with_the_clauses(source in expression(), input in "[a-z0-9 .@]{0,10}") {
+            let expr = Expression::new(&source).unwrap();
+            let traced = expr.trace(&input).lines().any(|clause| clause.starts_with('+'));
+
+            prop_assert_eq!(expr.matches(&input), traced);
+        }
+
+        #[test]
+        fn matching_inputs_match(input in matching("starts \"a\" and length 5 or numeric and length 3")) {
+            prop_assert!(input.starts_with('a') && input.len() == 5 || input.len() == 3 && input.chars().all(|c| c.is_ascii_digit()));
+        }
+
+        #[test]
+        fn rejecting_inputs_are_rejected(input in rejecting("alpha and ends \"x\"")) {
+            prop_assert!(!Expression::new("alpha and ends \"x\"").unwrap().matches(&input));
+        }
+    }
+}
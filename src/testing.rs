@@ -0,0 +1,4 @@
+//! Tools for testing srch itself, they are public so the tests of the
+//! workspace and fuzzers can use them but aren't part of the stable api
+
+pub mod differential;
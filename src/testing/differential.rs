@@ -0,0 +1,584 @@
+//! Differential testing of the matchers of an expression against a naive
+//! reference interpreter. Random expressions and inputs are generated from a
+//! seed, so every divergence can be reproduced by running the same config
+//! again
+//!
+//! ```rust
+//! use srch::testing::differential::{self, Config};
+//!
+//! let report = differential::run(&Config {
+//!     cases: 200,
+//!     ..Config::default()
+//! });
+//!
+//! assert_eq!(report.cases, 200);
+//! assert!(report.divergences.is_empty(), "{}", report.divergences[0]);
+//! ```
+
+use std::fmt;
+
+use crate::rng::Rng;
+use crate::Expression;
+
+/// The seed of the default config
+pub const SEED: u64 = 0xd1ff;
+
+/// The characters of arguments and inputs, including tabs and characters
+/// whose lowercase form is longer. Quotes only appear in inputs, since the
+/// strings of the lexer have no escapes
+const CHARS: &[char] = &[
+    'a', 'b', 'A', 'B', '1', '2', ' ', '\t', '.', '-', '"', 'é', 'Ä', 'İ',
+];
+
+/// The alphabets of inputs which consist of a single character class
+const CLASSES: &[&[u8]] = &[
+    b"0123456789",
+    b"abcxyz",
+    b"ABCXYZ",
+    b"!#$%&*+-./:;=?@_~",
+    b" \t\n\r",
+];
+
+/// How many and how large expressions are generated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The same seed always generates the same expressions and inputs
+    pub seed: u64,
+    /// The number of generated expressions
+    pub cases: usize,
+    /// The number of inputs every expression is matched against
+    pub inputs: usize,
+    /// The maximum number of `or` separated alternatives of an expression
+    pub max_alternatives: usize,
+    /// The maximum number of `and` separated queries of an alternative
+    pub max_queries: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            seed: SEED,
+            cases: 1_000,
+            inputs: 16,
+            max_alternatives: 3,
+            max_queries: 3,
+        }
+    }
+}
+
+/// The part of srch which disagreed with the reference interpreter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Engine {
+    /// The generated expression was rejected when it was compiled
+    Parser,
+    /// [`Expression::matches`], the optimized runtime
+    Runtime,
+    /// [`Expression::complement`], which has to match exactly the inputs the
+    /// expression doesn't match
+    Complement,
+    /// [`Expression::evaluate`], which evaluates every node
+    Evaluation,
+}
+
+impl Engine {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Engine::Parser => "parser",
+            Engine::Runtime => "runtime",
+            Engine::Complement => "complement",
+            Engine::Evaluation => "evaluation",
+        }
+    }
+}
+
+/// An input on which an engine returned another result than the reference
+/// interpreter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    pub source: String,
+    /// The input, empty if the parser rejected the expression
+    pub input: String,
+    pub engine: Engine,
+    /// The result of the reference interpreter, the engine returned the
+    /// opposite
+    pub expected: bool,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.engine {
+            Engine::Parser => write!(f, "the parser rejected {:?}", self.source),
+            engine => write!(
+                f,
+                "the {} returned {} instead of {} for {:?} on {:?}",
+                engine.name(),
+                !self.expected,
+                self.expected,
+                self.source,
+                self.input
+            ),
+        }
+    }
+}
+
+/// The result of a differential test run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    /// The number of generated expressions
+    pub cases: usize,
+    /// The number of inputs matched against an expression
+    pub inputs: usize,
+    /// The number of inputs the reference interpreter matched, runs which
+    /// (almost) never or always match test little
+    pub matched: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// A query with its naive semantics, independent of the implementation of
+/// the runtime
+#[derive(Clone, Debug)]
+enum Reference {
+    Starts(String),
+    Ends(String),
+    Contains(String),
+    ContainsTimes(String, u64, Option<u64>),
+    Equals(String),
+    IStarts(String),
+    IEnds(String),
+    IContains(String),
+    IEquals(String),
+    Length(u64),
+    Over(u64),
+    Under(u64),
+    AtLeast(u64),
+    AtMost(u64),
+    Between(u64, u64),
+    Words(u64),
+    WordsOver(u64),
+    ValueOver(u64),
+    ValueUnder(u64),
+    ValueEquals(u64),
+    Numeric,
+    Alpha,
+    Alphanumeric,
+    Uppercase,
+    Lowercase,
+    Whitespace,
+    Special,
+}
+
+fn starts(input: &str, prefix: &str) -> bool {
+    input.get(..prefix.len()) == Some(prefix)
+}
+
+fn ends(input: &str, suffix: &str) -> bool {
+    input
+        .len()
+        .checked_sub(suffix.len())
+        .and_then(|start| input.get(start..))
+        == Some(suffix)
+}
+
+fn contains(input: &str, infix: &str) -> bool {
+    (0..=input.len()).any(|start| input.get(start..).is_some_and(|rest| starts(rest, infix)))
+}
+
+/// The number of non-overlapping occurrences of a non-empty infix
+fn count(input: &str, infix: &str) -> u64 {
+    let mut count = 0;
+    let mut start = 0;
+
+    while start < input.len() {
+        match input.get(start..).is_some_and(|rest| starts(rest, infix)) {
+            true => {
+                count += 1;
+                start += infix.len();
+            }
+            false => start += 1,
+        }
+    }
+
+    count
+}
+
+/// The number of runs of characters which aren't ascii whitespace
+fn words(input: &str) -> u64 {
+    let mut words = 0;
+    let mut in_word = false;
+
+    for b in input.bytes() {
+        let is_space = b.is_ascii_whitespace();
+        words += (!is_space && !in_word) as u64;
+        in_word = !is_space;
+    }
+
+    words
+}
+
+/// The number of an input like `-12.5`, inputs with other syntax (e.g.
+/// exponents) aren't numbers
+fn number(input: &str) -> Option<f64> {
+    let digits = input.strip_prefix('-').unwrap_or(input);
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    let is_digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    match is_digits(integer) && is_digits(fraction) {
+        true => input.parse().ok(),
+        false => None,
+    }
+}
+
+impl Reference {
+    fn generate(rng: &mut Rng, arguments: &mut Vec<String>) -> Self {
+        let mut argument = |rng: &mut Rng| {
+            let len = rng.below(4);
+            let argument = (0..len)
+                .map(|_| *rng.pick(CHARS))
+                .filter(|c| *c != '"')
+                .collect::<String>();
+
+            arguments.push(argument.clone());
+            argument
+        };
+
+        let value = |rng: &mut Rng| rng.below(100) as u64;
+
+        let reference = match rng.below(27) {
+            0 => Self::Starts(argument(rng)),
+            1 => Self::Ends(argument(rng)),
+            2 => Self::Contains(argument(rng)),
+            3 => Self::Equals(argument(rng)),
+            4 => Self::IStarts(argument(rng)),
+            5 => Self::IEnds(argument(rng)),
+            6 => Self::IContains(argument(rng)),
+            7 => Self::IEquals(argument(rng)),
+            8 => Self::Length(rng.below(8) as u64),
+            9 => Self::Over(rng.below(8) as u64),
+            // `length under 0` is rejected since no length is in the range
+            10 => Self::Under(rng.below(8) as u64 + 1),
+            11 => Self::AtLeast(rng.below(8) as u64),
+            12 => Self::AtMost(rng.below(8) as u64),
+            13 => {
+                let min = rng.below(8) as u64;
+                Self::Between(min, min + rng.below(4) as u64)
+            }
+            14 => Self::ValueOver(value(rng)),
+            15 => Self::ValueUnder(value(rng)),
+            16 => Self::ValueEquals(value(rng)),
+            17 => {
+                // Occurrences of an empty string can't be counted
+                let arg = std::iter::repeat_with(|| argument(rng))
+                    .find(|arg| !arg.is_empty())
+                    .unwrap_or_default();
+                let n = rng.below(4) as u64;
+
+                match rng.below(3) {
+                    0 => Self::ContainsTimes(arg, n, None),
+                    1 => Self::ContainsTimes(arg, 0, Some(n)),
+                    _ => Self::ContainsTimes(arg, n, Some(n)),
+                }
+            }
+            24 => Self::Numeric,
+            25 => Self::Words(rng.below(4) as u64),
+            26 => Self::WordsOver(rng.below(4) as u64),
+            18 => Self::Alpha,
+            19 => Self::Alphanumeric,
+            20 => Self::Uppercase,
+            21 => Self::Lowercase,
+            22 => Self::Whitespace,
+            _ => Self::Special,
+        };
+
+        // Numbers are arguments as well, so inputs equal to them are likely
+        if let Self::ValueOver(n) | Self::ValueUnder(n) | Self::ValueEquals(n) = reference {
+            arguments.push(n.to_string());
+        }
+
+        reference
+    }
+
+    fn source(&self) -> String {
+        match self {
+            Self::Starts(arg) => format!("starts \"{}\"", arg),
+            Self::Ends(arg) => format!("ends \"{}\"", arg),
+            Self::Contains(arg) => format!("contains \"{}\"", arg),
+            Self::ContainsTimes(arg, min, None) => {
+                format!("contains \"{}\" at least {} times", arg, min)
+            }
+            Self::ContainsTimes(arg, 0, Some(max)) => {
+                format!("contains \"{}\" at most {} times", arg, max)
+            }
+            Self::ContainsTimes(arg, n, Some(_)) => {
+                format!("contains \"{}\" exactly {} times", arg, n)
+            }
+            Self::Equals(arg) => format!("equals \"{}\"", arg),
+            Self::IStarts(arg) => format!("istarts \"{}\"", arg),
+            Self::IEnds(arg) => format!("iends \"{}\"", arg),
+            Self::IContains(arg) => format!("icontains \"{}\"", arg),
+            Self::IEquals(arg) => format!("iequals \"{}\"", arg),
+            Self::Length(len) => format!("length {}", len),
+            Self::Over(len) => format!("length over {}", len),
+            Self::Under(len) => format!("length under {}", len),
+            Self::AtLeast(len) => format!("length at least {}", len),
+            Self::AtMost(len) => format!("length at most {}", len),
+            Self::Between(min, max) => format!("length between {} and {}", min, max),
+            Self::Words(n) => format!("words {}", n),
+            Self::WordsOver(n) => format!("words over {}", n),
+            Self::ValueOver(n) => format!("value over {}", n),
+            Self::ValueUnder(n) => format!("value under {}", n),
+            Self::ValueEquals(n) => format!("value equals {}", n),
+            Self::Numeric => "numeric".to_string(),
+            Self::Alpha => "alpha".to_string(),
+            Self::Alphanumeric => "alphanumeric".to_string(),
+            Self::Uppercase => "uppercase".to_string(),
+            Self::Lowercase => "lowercase".to_string(),
+            Self::Whitespace => "whitespace".to_string(),
+            Self::Special => "special".to_string(),
+        }
+    }
+
+    fn matches(&self, input: &str) -> bool {
+        let len = input.len() as u64;
+        let folded = || input.to_lowercase();
+
+        match self {
+            Self::Starts(arg) => starts(input, arg),
+            Self::Ends(arg) => ends(input, arg),
+            Self::Contains(arg) => contains(input, arg),
+            Self::ContainsTimes(arg, min, max) => {
+                let count = count(input, arg);
+                *min <= count && max.is_none_or(|max| count <= max)
+            }
+            Self::Equals(arg) => input == arg,
+            Self::IStarts(arg) => starts(&folded(), &arg.to_lowercase()),
+            Self::IEnds(arg) => ends(&folded(), &arg.to_lowercase()),
+            Self::IContains(arg) => contains(&folded(), &arg.to_lowercase()),
+            Self::IEquals(arg) => folded() == arg.to_lowercase(),
+            Self::Length(n) => len == *n,
+            Self::Over(n) => len > *n,
+            Self::Under(n) => len < *n,
+            Self::AtLeast(n) => len >= *n,
+            Self::AtMost(n) => len <= *n,
+            Self::Between(min, max) => *min <= len && len <= *max,
+            Self::Words(n) => words(input) == *n,
+            Self::WordsOver(n) => words(input) > *n,
+            Self::ValueOver(n) => number(input).is_some_and(|value| value > *n as f64),
+            Self::ValueUnder(n) => number(input).is_some_and(|value| value < *n as f64),
+            Self::ValueEquals(n) => number(input) == Some(*n as f64),
+            Self::Numeric => input.bytes().all(|b| b.is_ascii_digit()),
+            Self::Alpha => input.bytes().all(|b| b.is_ascii_alphabetic()),
+            Self::Alphanumeric => input.bytes().all(|b| b.is_ascii_alphanumeric()),
+            Self::Uppercase => input.bytes().all(|b| b.is_ascii_uppercase()),
+            Self::Lowercase => input.bytes().all(|b| b.is_ascii_lowercase()),
+            Self::Whitespace => input.bytes().all(|b| b.is_ascii_whitespace()),
+            Self::Special => input.bytes().all(|b| b.is_ascii_punctuation()),
+        }
+    }
+}
+
+/// A generated expression in disjunctive normal form, which is how sources
+/// without parentheses are parsed: `or` binds weaker than `and`, which binds
+/// weaker than `not`
+struct Case {
+    /// The queries of every alternative with the number of `not`s before
+    /// them
+    alternatives: Vec<Vec<(usize, Reference)>>,
+    /// The string arguments of the queries, inputs are built from them so
+    /// the queries on strings match as well
+    arguments: Vec<String>,
+}
+
+impl Case {
+    fn generate(rng: &mut Rng, config: &Config) -> Self {
+        let mut arguments = Vec::new();
+
+        let alternatives = (0..rng.below(config.max_alternatives.max(1)) + 1)
+            .map(|_| {
+                (0..rng.below(config.max_queries.max(1)) + 1)
+                    .map(|_| {
+                        let negations = match rng.below(4) {
+                            0 => 1,
+                            1 if rng.below(4) == 0 => 2,
+                            _ => 0,
+                        };
+
+                        (negations, Reference::generate(rng, &mut arguments))
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            alternatives,
+            arguments,
+        }
+    }
+
+    fn source(&self) -> String {
+        self.alternatives
+            .iter()
+            .map(|queries| {
+                queries
+                    .iter()
+                    .map(|(negations, query)| {
+                        format!("{}{}", "not ".repeat(*negations), query.source())
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" and ")
+            })
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
+
+    fn matches(&self, input: &str) -> bool {
+        self.alternatives.iter().any(|queries| {
+            queries
+                .iter()
+                .all(|(negations, query)| query.matches(input) != (negations % 2 == 1))
+        })
+    }
+
+    fn input(&self, rng: &mut Rng) -> String {
+        let len = rng.below(6);
+
+        match rng.below(3) {
+            0 => {
+                let class = *rng.pick(CLASSES);
+                rng.string(class, len)
+            }
+            _ => (0..len)
+                .map(|_| match self.arguments.is_empty() || rng.below(2) == 0 {
+                    true => rng.pick(CHARS).to_string(),
+                    false => rng.pick(&self.arguments).clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Matches an expression against inputs using every engine and records
+/// where they disagree with the reference
+fn check<F>(source: &str, reference: F, inputs: &[String], report: &mut Report)
+where
+    F: Fn(&str) -> bool,
+{
+    report.cases += 1;
+
+    let expr = match Expression::new(source) {
+        Ok(expr) => expr,
+        Err(_) => {
+            report.divergences.push(Divergence {
+                source: source.to_string(),
+                input: String::new(),
+                engine: Engine::Parser,
+                expected: true,
+            });
+            return;
+        }
+    };
+
+    let complement = expr.complement();
+
+    for input in inputs {
+        let expected = reference(input);
+
+        report.inputs += 1;
+        report.matched += expected as usize;
+
+        let results = [
+            (Engine::Runtime, expr.matches(input)),
+            (Engine::Complement, !complement.matches(input)),
+            (Engine::Evaluation, expr.evaluate(input).matched),
+        ];
+
+        for (engine, actual) in results.iter() {
+            if *actual != expected {
+                report.divergences.push(Divergence {
+                    source: source.to_string(),
+                    input: input.clone(),
+                    engine: *engine,
+                    expected,
+                });
+            }
+        }
+    }
+}
+
+/// Generates expressions and inputs and matches them using the runtime and
+/// the reference interpreter, see the [module documentation](self)
+pub fn run(config: &Config) -> Report {
+    let mut rng = Rng::new(config.seed);
+    let mut report = Report::default();
+
+    for _ in 0..config.cases {
+        let case = Case::generate(&mut rng, config);
+        let inputs = (0..config.inputs)
+            .map(|_| case.input(&mut rng))
+            .collect::<Vec<_>>();
+
+        check(
+            &case.source(),
+            |input| case.matches(input),
+            &inputs,
+            &mut report,
+        );
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, run, Config, Divergence, Engine, Report};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn agrees_with_the_reference() {
+        let report = run(&Config::default());
+
+        assert_eq!(report.divergences, vec![]);
+        assert_eq!(report.inputs, 16_000);
+        assert!(report.matched > report.inputs / 10);
+        assert!(report.matched < report.inputs * 9 / 10);
+    }
+
+    #[test]
+    fn generates_the_same_cases_for_a_seed() {
+        let config = Config {
+            seed: 7,
+            cases: 50,
+            ..Config::default()
+        };
+
+        assert_eq!(run(&config), run(&config));
+        assert_ne!(
+            run(&config).matched,
+            run(&Config { seed: 8, ..config }).matched
+        );
+    }
+
+    #[test]
+    fn reports_divergences() {
+        let mut report = Report::default();
+        let inputs = vec!["12".to_string(), "ab".to_string()];
+
+        check("numeric", |input| input.is_empty(), &inputs, &mut report);
+        check("numeric or", |_| true, &inputs, &mut report);
+
+        assert_eq!(report.cases, 2);
+        assert_eq!(report.divergences.len(), 4);
+        assert_eq!(
+            report.divergences[0].to_string(),
+            "the runtime returned true instead of false for \"numeric\" on \"12\""
+        );
+        assert_eq!(
+            report.divergences[3],
+            Divergence {
+                source: "numeric or".to_string(),
+                input: String::new(),
+                engine: Engine::Parser,
+                expected: true,
+            }
+        );
+    }
+}
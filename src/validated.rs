@@ -0,0 +1,137 @@
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+
+use serde::de::{Deserialize, Deserializer, Error};
+use serde::ser::{Serialize, Serializer};
+
+use crate::{Expression, Rule};
+
+/// A string which is checked against the expression of `R` when it is
+/// deserialized, so invalid input is rejected like a type error
+///
+/// ```rust
+/// use serde::Deserialize;
+/// use srch::Validated;
+///
+/// srch::rule!(ZipCode, "numeric and length 5");
+///
+/// #[derive(Deserialize)]
+/// struct Address {
+///     zip: Validated<ZipCode>,
+/// }
+///
+/// let address: Address = serde_json::from_str(r#"{ "zip": "12345" }"#).unwrap();
+/// assert_eq!(&*address.zip, "12345");
+///
+/// assert!(serde_json::from_str::<Address>(r#"{ "zip": "1234a" }"#).is_err());
+/// ```
+pub struct Validated<R, T = String> {
+    value: T,
+    rule: PhantomData<R>,
+}
+
+impl<R, T> Validated<R, T> {
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<R: Rule, T: AsRef<str>> Validated<R, T> {
+    /// Checks `value` against the expression of `R`, returns the value back
+    /// if it doesn't match
+    pub fn new(value: T) -> Result<Self, T> {
+        let expression = Expression::new(R::EXPRESSION)
+            .unwrap_or_else(|err| panic!("invalid expression `{}`: {}", R::EXPRESSION, err));
+
+        match expression.matches(value.as_ref()) {
+            true => Ok(Self {
+                value,
+                rule: PhantomData,
+            }),
+            false => Err(value),
+        }
+    }
+}
+
+impl<R, T> Deref for Validated<R, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<R, T: Clone> Clone for Validated<R, T> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            rule: PhantomData,
+        }
+    }
+}
+
+impl<R, T: fmt::Debug> fmt::Debug for Validated<R, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<R, T: fmt::Display> fmt::Display for Validated<R, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl<R, T: PartialEq> PartialEq for Validated<R, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<'de, R: Rule, T: Deserialize<'de> + AsRef<str>> Deserialize<'de> for Validated<R, T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Self::new(T::deserialize(deserializer)?).map_err(|value| {
+            D::Error::custom(format!(
+                "`{}` doesn't match `{}`",
+                value.as_ref(),
+                R::EXPRESSION
+            ))
+        })
+    }
+}
+
+impl<R, T: Serialize> Serialize for Validated<R, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Validated;
+    use pretty_assertions::assert_eq;
+
+    crate::rule!(Numeric, "numeric and length 3");
+
+    #[test]
+    fn accepts_matching_values() {
+        let value: Validated<Numeric> = serde_json::from_str("\"123\"").unwrap();
+        assert_eq!(value.into_inner(), "123");
+    }
+
+    #[test]
+    fn rejects_other_values() {
+        let err = serde_json::from_str::<Validated<Numeric>>("\"12a\"").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`12a` doesn't match `numeric and length 3`"
+        );
+    }
+
+    #[test]
+    fn serializes_transparently() {
+        let value = Validated::<Numeric>::new("123".to_string()).unwrap();
+        assert_eq!(serde_json::to_string(&value).unwrap(), "\"123\"");
+    }
+}
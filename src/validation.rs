@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+
+use validator::ValidationError;
+
+use crate::{Expression, Rule};
+
+/// Checks `value` against an expression, the error has the code `srch` and
+/// the expression as `expression` parameter
+pub fn validate_with(
+    expression: &Expression,
+    source: &str,
+    value: &str,
+) -> Result<(), ValidationError> {
+    if expression.matches(value) {
+        return Ok(());
+    }
+
+    let mut err = ValidationError::new("srch")
+        .with_message(Cow::Owned(format!("doesn't match `{}`", source)));
+    err.add_param(Cow::Borrowed("expression"), &source);
+    err.add_param(Cow::Borrowed("value"), &value);
+
+    Err(err)
+}
+
+/// Checks `value` against the expression of `R`, can be used as a custom
+/// validation function of the `validator` crate
+///
+/// ```rust
+/// use validator::Validate;
+///
+/// srch::rule!(ZipCode, "numeric and length 5");
+///
+/// #[derive(Validate)]
+/// struct Address {
+///     #[validate(custom(function = "srch::validate::<ZipCode>"))]
+///     zip: String,
+/// }
+///
+/// assert!(Address { zip: "12345".into() }.validate().is_ok());
+/// assert!(Address { zip: "1234a".into() }.validate().is_err());
+/// ```
+pub fn validate<R: Rule>(value: &str) -> Result<(), ValidationError> {
+    let expression = Expression::new(R::EXPRESSION)
+        .unwrap_or_else(|err| panic!("invalid expression `{}`: {}", R::EXPRESSION, err));
+
+    validate_with(&expression, R::EXPRESSION, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate;
+    use pretty_assertions::assert_eq;
+
+    crate::rule!(Numeric, "numeric and length 3");
+
+    #[test]
+    fn accepts_matching_values() {
+        assert_eq!(validate::<Numeric>("123"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_other_values() {
+        let err = validate::<Numeric>("12a").unwrap_err();
+        assert_eq!(err.code, "srch");
+        assert_eq!(err.to_string(), "doesn't match `numeric and length 3`");
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{Error, Expression, MatchOptions};
+
+/// An error watching an expression file
+#[derive(Debug)]
+pub enum WatchError {
+    /// The file can't be read
+    Io(io::Error),
+    /// The file can't be watched
+    Notify(notify::Error),
+    /// The file doesn't contain a valid expression
+    Expression(Error),
+}
+
+impl fmt::Display for WatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WatchError::Io(err) => write!(f, "{}", err),
+            WatchError::Notify(err) => write!(f, "{}", err),
+            WatchError::Expression(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for WatchError {}
+
+impl From<io::Error> for WatchError {
+    fn from(err: io::Error) -> Self {
+        WatchError::Io(err)
+    }
+}
+
+impl From<notify::Error> for WatchError {
+    fn from(err: notify::Error) -> Self {
+        WatchError::Notify(err)
+    }
+}
+
+impl From<Error> for WatchError {
+    fn from(err: Error) -> Self {
+        WatchError::Expression(err)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// The error of the last reload, cleared by the next successful one
+    error: Option<WatchError>,
+    /// Whether the complement of the file's expression is matched
+    complement: bool,
+}
+
+struct Shared {
+    path: PathBuf,
+    options: MatchOptions,
+    current: RwLock<Arc<Expression>>,
+    state: Mutex<State>,
+}
+
+impl Shared {
+    fn compile(path: &Path, options: MatchOptions) -> Result<Expression, WatchError> {
+        let source = fs::read_to_string(path)?;
+        Ok(Expression::new_with(source.trim(), options)?)
+    }
+
+    fn reload(&self) -> bool {
+        // Reloads are serialized so an older version of the file can't
+        // replace a newer one
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+
+        match Self::compile(&self.path, self.options) {
+            Ok(expression) => {
+                let expression = match state.complement {
+                    true => expression.complement(),
+                    false => expression,
+                };
+
+                let mut current = self.current.write().unwrap_or_else(|err| err.into_inner());
+                *current = Arc::new(expression);
+                state.error = None;
+                true
+            }
+            Err(err) => {
+                state.error = Some(err);
+                false
+            }
+        }
+    }
+}
+
+/// An expression compiled from a file, which is recompiled whenever the file
+/// changes. Long running filters pick up edits of their rules without a
+/// restart, if the file doesn't contain a valid expression the last valid one
+/// is kept
+///
+/// ```rust,no_run
+/// let expr = srch::WatchedExpression::from_path("rules.srch").unwrap();
+///
+/// for line in std::io::stdin().lines() {
+///     let line = line.unwrap();
+///
+///     if expr.matches(&line) {
+///         println!("{}", line);
+///     }
+/// }
+/// ```
+pub struct WatchedExpression {
+    shared: Arc<Shared>,
+    _watcher: RecommendedWatcher,
+}
+
+impl WatchedExpression {
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, WatchError> {
+        Self::from_path_with(path, MatchOptions::default())
+    }
+
+    pub fn from_path_with(
+        path: impl AsRef<Path>,
+        options: MatchOptions,
+    ) -> Result<Self, WatchError> {
+        let path = fs::canonicalize(path)?;
+        let expression = Shared::compile(&path, options)?;
+
+        let shared = Arc::new(Shared {
+            path: path.clone(),
+            options,
+            current: RwLock::new(Arc::new(expression)),
+            state: Mutex::new(State::default()),
+        });
+
+        let handler = Arc::clone(&shared);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if !event.kind.is_access() && event.paths.contains(&handler.path) {
+                        handler.reload();
+                    }
+                }
+            })?;
+
+        // Editors often replace a file instead of writing to it, which only
+        // shows up as an event of its directory
+        let directory = path.parent().unwrap_or(&path);
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+        Ok(Self {
+            shared,
+            _watcher: watcher,
+        })
+    }
+
+    /// The currently compiled expression, which isn't affected by later
+    /// changes of the file
+    pub fn current(&self) -> Arc<Expression> {
+        self.shared
+            .current
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    pub fn matches(&self, input: impl AsRef<str>) -> bool {
+        self.current().matches(input)
+    }
+
+    /// Matches exactly the inputs the expression of the file doesn't match
+    /// instead, also after the file is reloaded
+    pub fn complement(self) -> Self {
+        {
+            let mut state = self
+                .shared
+                .state
+                .lock()
+                .unwrap_or_else(|err| err.into_inner());
+            let mut current = self
+                .shared
+                .current
+                .write()
+                .unwrap_or_else(|err| err.into_inner());
+
+            state.complement = !state.complement;
+            *current = Arc::new(current.complement());
+        }
+
+        self
+    }
+
+    /// Recompiles the file without waiting for a change, returns whether the
+    /// expression was replaced
+    pub fn reload(&self) -> bool {
+        self.shared.reload()
+    }
+
+    /// The error of the last reload if it failed
+    pub fn last_error(&self) -> Option<String> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .error
+            .as_ref()
+            .map(|err| err.to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.shared.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WatchedExpression;
+    use pretty_assertions::assert_eq;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn temp_file(name: &str, contents: &str) -> PathBuf {
+        let dir = format!("srch-watch-{}-{}", name, std::process::id());
+        let dir = std::env::temp_dir().join(dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let path = dir.join("rules.srch");
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn eventually(condition: impl Fn() -> bool) -> bool {
+        let started = Instant::now();
+
+        while started.elapsed() < Duration::from_secs(5) {
+            if condition() {
+                return true;
+            }
+
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        false
+    }
+
+    #[test]
+    fn recompiles_changed_files() {
+        let path = temp_file("changes", "numeric\n");
+        let expr = WatchedExpression::from_path(&path).unwrap();
+
+        assert_eq!(expr.matches("123"), true);
+
+        fs::write(&path, "starts \"a\"\n").unwrap();
+
+        assert!(eventually(|| expr.matches("abc")));
+        assert_eq!(expr.matches("123"), false);
+    }
+
+    #[test]
+    fn keeps_the_last_valid_expression() {
+        let path = temp_file("invalid", "numeric");
+        let expr = WatchedExpression::from_path(&path).unwrap();
+
+        fs::write(&path, "numeric and").unwrap();
+
+        assert!(eventually(|| expr.last_error().is_some()));
+        assert_eq!(expr.matches("123"), true);
+
+        fs::write(&path, "length 2").unwrap();
+
+        assert_eq!(expr.reload(), true);
+        assert_eq!(expr.last_error(), None);
+        assert_eq!(expr.matches("12"), true);
+    }
+
+    #[test]
+    fn keeps_the_complement_after_reloads() {
+        let path = temp_file("complement", "numeric");
+        let expr = WatchedExpression::from_path(&path).unwrap().complement();
+
+        assert_eq!(expr.matches("123"), false);
+
+        fs::write(&path, "alpha").unwrap();
+
+        assert_eq!(expr.reload(), true);
+        assert_eq!(expr.matches("123"), true);
+        assert_eq!(expr.matches("abc"), false);
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let path = temp_file("rejects", "contains");
+        assert!(WatchedExpression::from_path(&path).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs srch with `args`, feeding `input` to stdin, and returns its stdout
+fn srch(args: &[&str], input: &str) -> String {
+    String::from_utf8(run(args, input).stdout).unwrap()
+}
+
+/// Runs srch with `args`, feeding `input` to stdin
+fn run(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_srch"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+mod rank {
+    use super::srch;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn ranks_matches_first() {
+        assert_eq!(
+            srch(&["for", "--rank", "numeric"], "abc\n123\n"),
+            "1.00 123\n0.00 abc\n"
+        );
+    }
+
+    #[test]
+    fn ranks_the_complement_when_ignoring_matches() {
+        let expected = "1.00 abc\n0.00 123\n";
+
+        assert_eq!(srch(&["not", "--rank", "numeric"], "123\nabc\n"), expected);
+        assert_eq!(
+            srch(&["for", "-v", "--rank", "numeric"], "123\nabc\n"),
+            expected
+        );
+    }
+}
+
+mod explain {
+    use super::srch;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn explains_the_complement_when_ignoring_matches() {
+        let expected = concat!(
+            r#"{"input":"abc","matched":true,"root":{"node":"not","matched":true,"evidence":[],"#,
+            r#""children":[{"node":"numeric","matched":false,"evidence":[],"children":[]}]}}"#,
+            "\n"
+        );
+
+        assert_eq!(srch(&["not", "--explain", "numeric"], "abc\n"), expected);
+        assert_eq!(
+            srch(&["for", "-v", "--explain", "numeric"], "abc\n"),
+            expected
+        );
+    }
+}
+
+mod convert {
+    use super::{run, srch};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn converts_expressions_into_regular_expressions() {
+        assert_eq!(
+            srch(&["convert", "--to-regex", "starts \"a\""], ""),
+            "^(?:a.*)$\n"
+        );
+    }
+
+    #[test]
+    fn prints_why_an_expression_is_invalid() {
+        let output = run(&["convert", "--to-regex", "numeric and"], "");
+
+        assert_eq!(output.status.success(), false);
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            "error: syntax error: expected a query\n"
+        );
+    }
+
+    #[test]
+    fn requires_a_target() {
+        let output = run(&["convert", "numeric"], "");
+
+        assert_eq!(output.status.success(), false);
+        assert_eq!(output.stdout, b"");
+    }
+}
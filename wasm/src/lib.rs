@@ -0,0 +1,26 @@
+use wasm_bindgen::prelude::*;
+
+use srch::Expression;
+
+/// A compiled expression which can be passed to [`matches`] and [`explain`]
+#[wasm_bindgen]
+pub struct Handle {
+	expression: Expression
+}
+
+/// Compiles an expression, throws if the source is invalid
+#[wasm_bindgen]
+pub fn compile(source: &str) -> Result<Handle, JsError> {
+	let expression = Expression::new(source).map_err(|err| JsError::new(&err.to_string()))?;
+	Ok(Handle { expression })
+}
+
+#[wasm_bindgen]
+pub fn matches(handle: &Handle, input: &str) -> bool {
+	handle.expression.matches(input)
+}
+
+#[wasm_bindgen]
+pub fn explain(handle: &Handle) -> String {
+	handle.expression.explain()
+}